@@ -0,0 +1,312 @@
+//! Automatic differentiation
+//!
+//! Problems which implement [`GenericCost`] get exact derivatives for free:
+//! [`AutoDiff`] wraps them and provides `CostFunction`, `Gradient` and
+//! `Hessian`, computed by evaluating the generic cost function on dual
+//! numbers ([`Dual`], forward mode) or on tape variables ([`Var`], reverse
+//! mode). Unlike finite differences, there is no step size to tune and no
+//! truncation error.
+//!
+//! The modes differ in cost, for `n` parameters:
+//!
+//! | mode      | gradient          | Hessian            | Hessian-vector product |
+//! |-----------|-------------------|--------------------|------------------------|
+//! | `forward` | `n` evaluations   | `n (n + 1) / 2`    | `n`                    |
+//! | `reverse` | 1 sweep           | `n` sweeps         | 1 sweep                |
+//!
+//! A reverse sweep records every operation, so it takes a few times as long
+//! as an evaluation and memory proportional to it. Forward mode is therefore
+//! preferable for few parameters, reverse mode for many.
+//!
+//! Hessian-vector products ([`hessian_product`]) let Newton-CG run without
+//! ever forming the Hessian, see [`crate::backend::HessianProduct`].
+
+use crate::scalar::{Dual, GenericCost, Var};
+use argmin::core::{CostFunction, Error, Gradient, Hessian};
+use num_traits::{One, Zero};
+use serde::{Deserialize, Serialize};
+
+/// Differentiation modes which can be selected by name
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Mode {
+    /// Dual numbers, one directional derivative per evaluation
+    Forward,
+    /// Tape of the evaluation swept backwards, the whole gradient at once
+    #[default]
+    Reverse,
+}
+
+impl Mode {
+    /// All modes, in the order in which they are listed
+    pub const ALL: &'static [Mode] = &[Mode::Forward, Mode::Reverse];
+
+    /// Name used to select the mode
+    pub fn name(self) -> &'static str {
+        match self {
+            Mode::Forward => "forward",
+            Mode::Reverse => "reverse",
+        }
+    }
+
+    /// Look up a mode by its name
+    pub fn from_name(name: &str) -> Option<Mode> {
+        Mode::ALL.iter().copied().find(|m| m.name() == name)
+    }
+}
+
+/// A cost function which can be evaluated on the number types of automatic
+/// differentiation
+///
+/// Implemented for every [`GenericCost`]; type-erased problems implement it
+/// directly.
+pub trait AdCost {
+    /// Evaluate the cost function at real arguments
+    fn cost_f64(&self, p: &[f64]) -> Result<f64, Error>;
+    /// Evaluate the cost function at dual numbers
+    fn cost_dual(&self, p: &[Dual]) -> Result<Dual, Error>;
+    /// Evaluate the cost function at nested dual numbers
+    fn cost_dual2(&self, p: &[Dual<Dual>]) -> Result<Dual<Dual>, Error>;
+    /// Evaluate the cost function on the tape
+    fn cost_var(&self, p: &[Var]) -> Result<Var, Error>;
+    /// Evaluate the cost function on the tape of dual numbers
+    fn cost_var_dual(&self, p: &[Var<Dual>]) -> Result<Var<Dual>, Error>;
+}
+
+impl<O: GenericCost> AdCost for O {
+    fn cost_f64(&self, p: &[f64]) -> Result<f64, Error> {
+        self.cost_generic(p)
+    }
+
+    fn cost_dual(&self, p: &[Dual]) -> Result<Dual, Error> {
+        self.cost_generic(p)
+    }
+
+    fn cost_dual2(&self, p: &[Dual<Dual>]) -> Result<Dual<Dual>, Error> {
+        self.cost_generic(p)
+    }
+
+    fn cost_var(&self, p: &[Var]) -> Result<Var, Error> {
+        self.cost_generic(p)
+    }
+
+    fn cost_var_dual(&self, p: &[Var<Dual>]) -> Result<Var<Dual>, Error> {
+        self.cost_generic(p)
+    }
+}
+
+/// Gradient of `problem` at `x`
+pub fn gradient<O: AdCost + ?Sized>(problem: &O, mode: Mode, x: &[f64]) -> Result<Vec<f64>, Error> {
+    match mode {
+        Mode::Forward => {
+            let mut p: Vec<Dual> = x.iter().copied().map(Dual::real).collect();
+            let mut gradient = Vec::with_capacity(x.len());
+            for i in 0..x.len() {
+                p[i].eps = 1.0;
+                gradient.push(problem.cost_dual(&p)?.eps);
+                p[i].eps = 0.0;
+            }
+            Ok(gradient)
+        }
+        Mode::Reverse => Ok(Var::gradient(x, |p| problem.cost_var(p))?.1),
+    }
+}
+
+/// Hessian of `problem` at `x`
+pub fn hessian<O: AdCost + ?Sized>(
+    problem: &O,
+    mode: Mode,
+    x: &[f64],
+) -> Result<Vec<Vec<f64>>, Error> {
+    let n = x.len();
+    let mut hessian = vec![vec![0.0; n]; n];
+    match mode {
+        Mode::Forward => {
+            // The inner and outer derivative directions select one entry
+            let mut p: Vec<Dual<Dual>> = x.iter().map(|&xi| Dual::real(Dual::real(xi))).collect();
+            for i in 0..n {
+                p[i].eps.re = 1.0;
+                for j in i..n {
+                    p[j].re.eps = 1.0;
+                    let h = problem.cost_dual2(&p)?.eps.eps;
+                    hessian[i][j] = h;
+                    hessian[j][i] = h;
+                    p[j].re.eps = 0.0;
+                }
+                p[i].eps.re = 0.0;
+            }
+        }
+        Mode::Reverse => {
+            let mut v = vec![0.0; n];
+            for i in 0..n {
+                v[i] = 1.0;
+                let column = hessian_product(problem, mode, x, &v)?;
+                for (j, h) in column.into_iter().enumerate() {
+                    hessian[j][i] = h;
+                }
+                v[i] = 0.0;
+            }
+        }
+    }
+    Ok(hessian)
+}
+
+/// Product of the Hessian of `problem` at `x` with `v`
+pub fn hessian_product<O: AdCost + ?Sized>(
+    problem: &O,
+    mode: Mode,
+    x: &[f64],
+    v: &[f64],
+) -> Result<Vec<f64>, Error> {
+    match mode {
+        Mode::Forward => {
+            // The inner direction is `v`, the outer one selects a component
+            let mut p: Vec<Dual<Dual>> = x
+                .iter()
+                .zip(v)
+                .map(|(&xi, &vi)| Dual::real(Dual::new(xi, vi)))
+                .collect();
+            let mut product = Vec::with_capacity(x.len());
+            for i in 0..x.len() {
+                p[i].eps = Dual::one();
+                product.push(problem.cost_dual2(&p)?.eps.eps);
+                p[i].eps = Dual::zero();
+            }
+            Ok(product)
+        }
+        Mode::Reverse => {
+            // The gradient at `x + v ε` is `∇f(x) + H(x) v ε`
+            let p: Vec<Dual> = x
+                .iter()
+                .zip(v)
+                .map(|(&xi, &vi)| Dual::new(xi, vi))
+                .collect();
+            let (_, gradient) = Var::gradient(&p, |p| problem.cost_var_dual(p))?;
+            Ok(gradient.into_iter().map(|g| g.eps).collect())
+        }
+    }
+}
+
+/// A cost function with automatically differentiated gradient and Hessian
+///
+/// Only the cost function needs to be implemented, generically over the
+/// scalar type ([`GenericCost`]).
+#[derive(Clone, Debug)]
+pub struct AutoDiff<O> {
+    /// The wrapped cost function
+    pub problem: O,
+    /// Differentiation mode
+    pub mode: Mode,
+}
+
+impl<O> AutoDiff<O> {
+    /// Differentiate `problem` automatically in `mode`
+    pub fn new(problem: O, mode: Mode) -> Self {
+        AutoDiff { problem, mode }
+    }
+}
+
+impl<O: AdCost> AutoDiff<O> {
+    /// Product of the Hessian at `p` with `v`
+    pub fn hessian_product(&self, p: &[f64], v: &[f64]) -> Result<Vec<f64>, Error> {
+        hessian_product(&self.problem, self.mode, p, v)
+    }
+}
+
+impl<O: AdCost> CostFunction for AutoDiff<O> {
+    type Param = Vec<f64>;
+    type Output = f64;
+
+    fn cost(&self, p: &Self::Param) -> Result<Self::Output, Error> {
+        self.problem.cost_f64(p)
+    }
+}
+
+impl<O: AdCost> Gradient for AutoDiff<O> {
+    type Param = Vec<f64>;
+    type Gradient = Vec<f64>;
+
+    fn gradient(&self, p: &Self::Param) -> Result<Self::Gradient, Error> {
+        gradient(&self.problem, self.mode, p)
+    }
+}
+
+impl<O: AdCost> Hessian for AutoDiff<O> {
+    type Param = Vec<f64>;
+    type Hessian = Vec<Vec<f64>>;
+
+    fn hessian(&self, p: &Self::Param) -> Result<Self::Hessian, Error> {
+        hessian(&self.problem, self.mode, p)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::problem::{self, Rosenbrock, RosenbrockNd};
+    use crate::solver::{solve, Method, SolverOptions};
+    use crate::RunConfig;
+
+    const MODES: [Mode; 2] = [Mode::Forward, Mode::Reverse];
+
+    fn assert_close(a: &[f64], b: &[f64]) {
+        for (x, y) in a.iter().zip(b) {
+            assert!((x - y).abs() <= 1e-10 * y.abs().max(1.0), "{:?} != {:?}", a, b);
+        }
+    }
+
+    #[test]
+    fn names_round_trip() {
+        for mode in MODES {
+            assert_eq!(Mode::from_name(mode.name()), Some(mode));
+        }
+        assert_eq!(Mode::from_name("mixed"), None);
+    }
+
+    #[test]
+    fn derivatives_match_the_analytic_derivatives() {
+        let problem = Rosenbrock::default();
+        let x = vec![-1.2, 1.0];
+        let exact_hessian = problem.hessian(&x).unwrap();
+        for mode in MODES {
+            assert_close(
+                &gradient(&problem, mode, &x).unwrap(),
+                &problem.gradient(&x).unwrap(),
+            );
+            let hessian = hessian(&problem, mode, &x).unwrap();
+            for (row, exact) in hessian.iter().zip(&exact_hessian) {
+                assert_close(row, exact);
+            }
+        }
+    }
+
+    #[test]
+    fn hessian_products_match_the_hessian() {
+        let problem = RosenbrockNd::default();
+        let x = vec![-1.2, 1.0, 0.5, 2.0];
+        let v = vec![1.0, -2.0, 0.5, 3.0];
+        let exact = problem.hessian(&x).unwrap().dot(&v);
+        for mode in MODES {
+            assert_close(&hessian_product(&problem, mode, &x, &v).unwrap(), &exact);
+        }
+    }
+
+    #[test]
+    fn hessian_products_require_a_hessian() {
+        let options = SolverOptions {
+            hessian_products: true,
+            ..SolverOptions::new(Method::NewtonCg)
+        };
+        let ackley = problem::build("ackley", None).unwrap();
+        let config = RunConfig {
+            init_param: ackley.info().default_start.clone(),
+            ..RunConfig::default()
+        };
+        assert!(!ackley.has_hessian());
+        assert!(solve(ackley.clone(), &options, &config).is_err());
+
+        let ackley = ackley.autodiff(Mode::Reverse).unwrap();
+        let report = solve(ackley, &options, &config).unwrap();
+        assert!(report.cost < 1e-6, "cost {}", report.cost);
+    }
+}
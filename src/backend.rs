@@ -0,0 +1,343 @@
+//! Math backends
+//!
+//! argmin is generic over the types of parameter vectors and matrices, and
+//! argmin-math implements its math traits for `Vec<f64>`, ndarray and
+//! nalgebra. The problems of this crate are written against `Vec<f64>`;
+//! [`OnBackend`] makes them available for the other backends by converting
+//! parameters, gradients and Hessians at the boundary.
+//!
+//! Every backend, including `vec`, goes through the same conversion, so that
+//! timings of different backends remain comparable: the difference is the
+//! linear algebra done by the solver.
+//!
+//! The problems themselves are not generic over the backend. Every
+//! evaluation copies the parameter vector into a `Vec<f64>` and the result
+//! back, and Hessians are assembled as a `Vec` of rows before they are
+//! converted. Some solvers also fall back to `Vec`: Newton's method on
+//! ndarray solves the Newton system on a copy of the Hessian as
+//! `Vec<Vec<f64>>` (see [`SolveLinear`](crate::solver::newton::SolveLinear)).
+//! Timings of cheap problems therefore include a conversion overhead
+//! proportional to the dimension (or its square, for Hessians), which the
+//! backends share.
+//!
+//! argmin-math does not implement every operation for every backend, so some
+//! solvers are unavailable on some backends
+//! ([`Method::supports`](crate::solver::Method::supports)): BFGS, DFP and
+//! SR1 on nalgebra, and particle swarm optimization on ndarray and nalgebra.
+//! `opt list` shows these combinations.
+//!
+//! Problems with a tridiagonal Hessian
+//! ([`DynProblem::has_tridiagonal_hessian`]) can hand it to Newton's method
+//! as a [`Tridiagonal`] on every backend, which avoids the dense matrix.
+//!
+//! Newton-CG only multiplies the Hessian with vectors. With
+//! [`HessianProduct`] as its matrix type, it never forms the Hessian but
+//! asks the problem for Hessian-vector products
+//! ([`DynProblem::hessian_product`]), which automatically differentiated
+//! problems compute at the price of a gradient.
+
+use crate::problem::{DynProblem, Tridiagonal};
+use argmin::core::{ArgminError, CostFunction, Error, Gradient, Hessian};
+use argmin_math::ArgminDot;
+use nalgebra::{DMatrix, DVector};
+use ndarray::{Array1, Array2};
+use serde::{Deserialize, Serialize};
+use std::marker::PhantomData;
+
+/// Math backends which can be selected by name
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Backend {
+    /// `Vec<f64>` and `Vec<Vec<f64>>`
+    #[default]
+    Vec,
+    /// `ndarray::Array1<f64>` and `ndarray::Array2<f64>`
+    Ndarray,
+    /// `nalgebra::DVector<f64>` and `nalgebra::DMatrix<f64>`
+    Nalgebra,
+}
+
+impl Backend {
+    /// All backends, in the order in which they are listed
+    pub const ALL: &'static [Backend] = &[Backend::Vec, Backend::Ndarray, Backend::Nalgebra];
+
+    /// Name used to select the backend
+    pub fn name(self) -> &'static str {
+        match self {
+            Backend::Vec => "vec",
+            Backend::Ndarray => "ndarray",
+            Backend::Nalgebra => "nalgebra",
+        }
+    }
+
+    /// One line description
+    pub fn description(self) -> &'static str {
+        match self {
+            Backend::Vec => "Vec<f64> vectors, Vec<Vec<f64>> matrices",
+            Backend::Ndarray => "ndarray Array1<f64> vectors, Array2<f64> matrices",
+            Backend::Nalgebra => "nalgebra DVector<f64> vectors, DMatrix<f64> matrices",
+        }
+    }
+
+    /// Look up a backend by its name
+    pub fn from_name(name: &str) -> Option<Backend> {
+        Backend::ALL.iter().copied().find(|b| b.name() == name)
+    }
+}
+
+/// Parameter vector of a backend
+pub trait Vector: Clone {
+    /// Convert from a `Vec<f64>`
+    fn from_vec(v: Vec<f64>) -> Self;
+    /// Convert to a `Vec<f64>`
+    fn to_vec(&self) -> Vec<f64>;
+}
+
+impl Vector for Vec<f64> {
+    fn from_vec(v: Vec<f64>) -> Self {
+        v
+    }
+
+    fn to_vec(&self) -> Vec<f64> {
+        self.clone()
+    }
+}
+
+impl Vector for Array1<f64> {
+    fn from_vec(v: Vec<f64>) -> Self {
+        Array1::from(v)
+    }
+
+    fn to_vec(&self) -> Vec<f64> {
+        self.iter().copied().collect()
+    }
+}
+
+impl Vector for DVector<f64> {
+    fn from_vec(v: Vec<f64>) -> Self {
+        DVector::from_vec(v)
+    }
+
+    fn to_vec(&self) -> Vec<f64> {
+        self.iter().copied().collect()
+    }
+}
+
+/// Square matrix (Hessian) of a backend
+pub trait Matrix {
+    /// Convert from a `Vec` of rows
+    fn from_rows(rows: Vec<Vec<f64>>) -> Self;
+}
+
+impl Matrix for Vec<Vec<f64>> {
+    fn from_rows(rows: Vec<Vec<f64>>) -> Self {
+        rows
+    }
+}
+
+impl Matrix for Array2<f64> {
+    fn from_rows(rows: Vec<Vec<f64>>) -> Self {
+        let n = rows.len();
+        Array2::from_shape_fn((n, n), |(i, j)| rows[i][j])
+    }
+}
+
+impl Matrix for DMatrix<f64> {
+    fn from_rows(rows: Vec<Vec<f64>>) -> Self {
+        let n = rows.len();
+        DMatrix::from_fn(n, n, |i, j| rows[i][j])
+    }
+}
+
+/// A problem with parameter vectors of type `P` and Hessians of type `H`
+pub struct OnBackend<P, H> {
+    problem: DynProblem,
+    backend: PhantomData<fn() -> (P, H)>,
+}
+
+impl<P, H> OnBackend<P, H> {
+    /// Make `problem` available to solvers working on `P` and `H`
+    pub fn new(problem: DynProblem) -> Self {
+        OnBackend {
+            problem,
+            backend: PhantomData,
+        }
+    }
+
+    /// The wrapped problem
+    pub fn problem(&self) -> &DynProblem {
+        &self.problem
+    }
+}
+
+impl<P: Vector, H> CostFunction for OnBackend<P, H> {
+    type Param = P;
+    type Output = f64;
+
+    fn cost(&self, p: &Self::Param) -> Result<Self::Output, Error> {
+        self.problem.cost(&p.to_vec())
+    }
+}
+
+impl<P: Vector, H> Gradient for OnBackend<P, H> {
+    type Param = P;
+    type Gradient = P;
+
+    fn gradient(&self, p: &Self::Param) -> Result<Self::Gradient, Error> {
+        self.problem.gradient(&p.to_vec()).map(P::from_vec)
+    }
+}
+
+impl<P: Vector, H: Matrix> Hessian for OnBackend<P, H> {
+    type Param = P;
+    type Hessian = H;
+
+    fn hessian(&self, p: &Self::Param) -> Result<Self::Hessian, Error> {
+        self.problem.hessian(&p.to_vec()).map(H::from_rows)
+    }
+}
+
+impl<P: Vector> Hessian for OnBackend<P, Tridiagonal> {
+    type Param = P;
+    type Hessian = Tridiagonal;
+
+    fn hessian(&self, p: &Self::Param) -> Result<Self::Hessian, Error> {
+        self.problem.hessian_tridiagonal(&p.to_vec())
+    }
+}
+
+/// The Hessian at a point as a linear operator, for any backend
+///
+/// Only the point is serialized; an operator restored from a checkpoint
+/// cannot be applied. Newton-CG, the only solver using it, does not keep the
+/// Hessian in its state, so this never happens.
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct HessianProduct {
+    /// Point at which the Hessian is taken
+    pub x: Vec<f64>,
+    #[serde(skip)]
+    problem: Option<DynProblem>,
+}
+
+impl HessianProduct {
+    /// The Hessian of `problem` at `x`
+    pub fn new(problem: DynProblem, x: Vec<f64>) -> Self {
+        HessianProduct {
+            x,
+            problem: Some(problem),
+        }
+    }
+}
+
+impl<P: Vector> ArgminDot<P, P> for HessianProduct {
+    fn dot(&self, v: &P) -> P {
+        let problem = self
+            .problem
+            .as_ref()
+            .expect("Hessian-vector product of an operator restored from a checkpoint");
+        // `ArgminDot` cannot fail. `hessian` has checked that the problem
+        // provides Hessian-vector products, and Newton-CG has evaluated
+        // cost and gradient at `x` successfully before it multiplies.
+        let product = problem
+            .hessian_product(&self.x, &v.to_vec())
+            .expect("Hessian-vector product");
+        P::from_vec(product)
+    }
+}
+
+impl<P: Vector> Hessian for OnBackend<P, HessianProduct> {
+    type Param = P;
+    type Hessian = HessianProduct;
+
+    fn hessian(&self, p: &Self::Param) -> Result<Self::Hessian, Error> {
+        if !self.problem.has_hessian() {
+            return Err(ArgminError::NotImplemented {
+                text: "This problem does not provide Hessian-vector products".to_string(),
+            }
+            .into());
+        }
+        Ok(HessianProduct::new(self.problem.clone(), p.to_vec()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::problem::{self, Rosenbrock};
+    use crate::solver::{solve, Method, SolverOptions};
+    use crate::{RunConfig, StopCriteria};
+
+    fn options(method: Method, backend: Backend) -> SolverOptions {
+        SolverOptions {
+            backend,
+            seed: Some(1),
+            ..SolverOptions::new(method)
+        }
+    }
+
+    #[test]
+    fn names_round_trip() {
+        for &backend in Backend::ALL {
+            assert_eq!(Backend::from_name(backend.name()), Some(backend));
+        }
+        assert_eq!(Backend::from_name("faer"), None);
+    }
+
+    #[test]
+    fn conversions_round_trip() {
+        let v = vec![1.0, -2.0, 3.5];
+        assert_eq!(<Array1<f64> as Vector>::from_vec(v.clone()).to_vec(), v);
+        assert_eq!(<DVector<f64> as Vector>::from_vec(v.clone()).to_vec(), v);
+        let rows = vec![vec![1.0, 2.0], vec![3.0, 4.0]];
+        assert_eq!(<Array2<f64> as Matrix>::from_rows(rows.clone())[(1, 0)], 3.0);
+        assert_eq!(<DMatrix<f64> as Matrix>::from_rows(rows)[(1, 0)], 3.0);
+    }
+
+    #[test]
+    fn unsupported_combinations_are_rejected() {
+        let problem = problem::build("sphere", Some(2)).unwrap();
+        let config = RunConfig {
+            init_param: vec![1.0, -2.0],
+            stop: StopCriteria {
+                max_iters: 3,
+                ..StopCriteria::default()
+            },
+            ..RunConfig::default()
+        };
+        for &backend in Backend::ALL {
+            for &method in Method::ALL {
+                let res = solve(problem.clone(), &options(method, backend), &config);
+                assert_eq!(
+                    res.is_ok(),
+                    method.supports(backend),
+                    "{} on {}: {:?}",
+                    method.name(),
+                    backend.name(),
+                    res.err()
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn backends_agree() {
+        let config = RunConfig {
+            init_param: vec![-1.2, 1.0],
+            ..RunConfig::default()
+        };
+        for method in [Method::Newton, Method::Lbfgs, Method::NelderMead] {
+            let run = |backend| {
+                let problem = Rosenbrock::default().into_dyn();
+                solve(problem, &options(method, backend), &config).unwrap()
+            };
+            let reference = run(Backend::Vec);
+            for backend in [Backend::Ndarray, Backend::Nalgebra] {
+                let report = run(backend);
+                assert_eq!(report.iterations, reference.iterations, "{}", method.name());
+                for (a, b) in report.param.iter().zip(&reference.param) {
+                    assert!((a - b).abs() < 1e-8, "{} on {}", method.name(), backend.name());
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,721 @@
+//! Benchmarks comparing solvers across problems
+//!
+//! [`bench`] runs every solver of [`BenchOptions`] on every problem, from
+//! the default starting point of the problem and from points drawn
+//! uniformly from its domain. All solvers start from the same points. Each
+//! run is recorded as a [`BenchRun`] with its iterations, evaluation counts,
+//! wall time and distance to the known optimum, and the runs of each pair of
+//! problem and solver are aggregated into a [`BenchSummary`].
+//!
+//! A run solves the problem if its cost function value is within
+//! `success_tol * max(1, |f*|)` of the known minimum `f*`. Runs which fail
+//! with an error, for instance Newton's method on a problem without
+//! Hessian, count as unsolved but do not stop the benchmark.
+//!
+//! The results can be printed as Markdown or CSV tables ([`BenchFormat`]),
+//! either one row per pair of problem and solver or one row per run.
+
+use crate::parallel;
+use crate::problem::{ProblemConfig, ProblemInfo};
+use crate::report::Report;
+use crate::solver::{self, derivative_free, SolverOptions};
+use crate::stopping::StopCriteria;
+use crate::trace::{History, TracePoint};
+use crate::RunConfig;
+use argmin::core::{ArgminError, CostFunction, Error};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::time::Instant;
+
+/// What is benchmarked
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct BenchOptions {
+    /// Problems to solve
+    pub problems: Vec<ProblemConfig>,
+    /// Solvers to compare
+    pub solvers: Vec<SolverOptions>,
+    /// Number of random starting points per problem, in addition to the
+    /// default starting point
+    pub random_starts: usize,
+    /// Seed of the random starting points (drawn from entropy if `None`)
+    pub seed: Option<u64>,
+    /// When each run stops
+    pub stop: StopCriteria,
+    /// A run solves the problem if its cost function value is within
+    /// `success_tol * max(1, |f*|)` of the known minimum `f*`
+    pub success_tol: f64,
+}
+
+/// One run of a benchmark
+#[derive(Clone, Debug, Serialize)]
+pub struct BenchRun {
+    /// Name of the problem
+    pub problem: String,
+    /// Name of the solver
+    pub solver: String,
+    /// Index of the starting point; 0 is the default starting point of the
+    /// problem, the others are random
+    pub start: usize,
+    /// Starting point
+    pub x0: Vec<f64>,
+    /// Outcome of the run (`None` if it failed)
+    pub report: Option<Report>,
+    /// Why the run failed
+    pub error: Option<String>,
+    /// Wall-clock time of the run, including setting up the solver
+    pub wall_time_secs: f64,
+    /// Final cost function value minus the known minimum
+    pub cost_error: Option<f64>,
+    /// Distance of the final parameter vector to the nearest known minimizer
+    pub param_error: Option<f64>,
+    /// Whether the run solved the problem (`None` if the minimum is unknown)
+    pub solved: Option<bool>,
+    /// Cost function value at `x0`
+    pub start_cost: f64,
+    /// Iterations which improved the best cost function value, also of
+    /// runs which failed later
+    pub progress: Vec<Progress>,
+    /// Every iteration of the run, for convergence plots; not serialized
+    #[serde(skip)]
+    pub trace: Vec<TracePoint>,
+}
+
+/// Best cost function value of a run after some iteration, with the
+/// evaluations spent until then
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Progress {
+    /// Iteration number
+    pub iter: u64,
+    /// Cost function evaluations since the start of the run
+    pub cost_evals: u64,
+    /// Gradient evaluations since the start of the run
+    pub gradient_evals: u64,
+    /// Hessian evaluations since the start of the run
+    pub hessian_evals: u64,
+    /// Best cost function value after the iteration
+    pub best_cost: f64,
+}
+
+impl BenchRun {
+    /// Run of `solver` from `x0` which failed with `err`
+    fn failed(
+        info: &ProblemInfo,
+        solver: &SolverOptions,
+        start: usize,
+        x0: Vec<f64>,
+        err: Error,
+    ) -> Self {
+        BenchRun {
+            problem: info.name.clone(),
+            solver: solver.method.name().to_string(),
+            start,
+            x0,
+            report: None,
+            error: Some(err.to_string()),
+            wall_time_secs: 0.0,
+            cost_error: None,
+            param_error: None,
+            solved: info.min_cost.map(|_| false),
+            start_cost: f64::NAN,
+            progress: Vec::new(),
+            trace: Vec::new(),
+        }
+    }
+
+    /// Number of evaluations of `counter` (`cost_count`, ...), zero if the
+    /// run failed
+    pub fn count(&self, counter: &str) -> u64 {
+        self.report
+            .as_ref()
+            .map_or(0, |report| report.count(counter))
+    }
+}
+
+/// Runs of one solver on one problem, aggregated
+///
+/// Means are taken over the runs which did not fail; they are NaN if all
+/// runs failed.
+#[derive(Clone, Debug, Serialize)]
+pub struct BenchSummary {
+    /// Name of the problem
+    pub problem: String,
+    /// Name of the solver
+    pub solver: String,
+    /// Number of runs
+    pub runs: usize,
+    /// Number of runs which failed with an error
+    pub failures: usize,
+    /// Number of runs which solved the problem (`None` if the minimum is
+    /// unknown)
+    pub solved: Option<usize>,
+    /// Mean number of iterations
+    pub mean_iterations: f64,
+    /// Mean number of cost function evaluations
+    pub mean_cost_evals: f64,
+    /// Mean number of gradient evaluations
+    pub mean_gradient_evals: f64,
+    /// Mean number of Hessian evaluations
+    pub mean_hessian_evals: f64,
+    /// Mean wall-clock time
+    pub mean_wall_time_secs: f64,
+    /// Median of the final cost errors
+    pub median_cost_error: Option<f64>,
+}
+
+impl BenchSummary {
+    fn new(runs: &[BenchRun]) -> Self {
+        let completed: Vec<&BenchRun> = runs.iter().filter(|run| run.report.is_some()).collect();
+        let mean = |value: &dyn Fn(&BenchRun) -> f64| {
+            completed.iter().map(|run| value(run)).sum::<f64>() / completed.len() as f64
+        };
+        let solved = runs
+            .iter()
+            .map(|run| run.solved.map(usize::from))
+            .sum::<Option<usize>>();
+        let mut errors: Vec<f64> = runs.iter().filter_map(|run| run.cost_error).collect();
+        errors.sort_by(f64::total_cmp);
+        BenchSummary {
+            problem: runs[0].problem.clone(),
+            solver: runs[0].solver.clone(),
+            runs: runs.len(),
+            failures: runs.len() - completed.len(),
+            solved,
+            mean_iterations: mean(&|run| run.report.as_ref().map_or(0, |r| r.iterations) as f64),
+            mean_cost_evals: mean(&|run| run.count("cost_count") as f64),
+            mean_gradient_evals: mean(&|run| run.count("gradient_count") as f64),
+            mean_hessian_evals: mean(&|run| run.count("hessian_count") as f64),
+            mean_wall_time_secs: mean(&|run| run.wall_time_secs),
+            median_cost_error: median(&errors),
+        }
+    }
+
+    /// Fraction of the runs which solved the problem
+    pub fn success_rate(&self) -> Option<f64> {
+        self.solved.map(|solved| solved as f64 / self.runs as f64)
+    }
+}
+
+fn median(sorted: &[f64]) -> Option<f64> {
+    let n = sorted.len();
+    match n {
+        0 => None,
+        _ if n % 2 == 1 => Some(sorted[n / 2]),
+        _ => Some((sorted[n / 2 - 1] + sorted[n / 2]) / 2.0),
+    }
+}
+
+/// All runs of a benchmark and their summary
+#[derive(Clone, Debug, Serialize)]
+pub struct BenchReport {
+    /// Tolerance which decided whether a run solved its problem
+    pub success_tol: f64,
+    /// Every run, ordered by problem, solver and starting point
+    pub runs: Vec<BenchRun>,
+    /// One entry per pair of problem and solver, in the same order
+    pub summary: Vec<BenchSummary>,
+}
+
+/// How the results of a benchmark are printed
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum BenchFormat {
+    /// Markdown table
+    #[default]
+    Markdown,
+    /// Comma separated values with a header line
+    Csv,
+    /// All runs and the summary as JSON
+    Json,
+}
+
+impl BenchFormat {
+    /// All formats
+    pub const ALL: [BenchFormat; 3] = [BenchFormat::Markdown, BenchFormat::Csv, BenchFormat::Json];
+
+    /// Name of the format on the command line
+    pub fn name(self) -> &'static str {
+        match self {
+            BenchFormat::Markdown => "markdown",
+            BenchFormat::Csv => "csv",
+            BenchFormat::Json => "json",
+        }
+    }
+
+    /// Format called `name`
+    pub fn from_name(name: &str) -> Option<Self> {
+        BenchFormat::ALL.into_iter().find(|f| f.name() == name)
+    }
+}
+
+impl BenchReport {
+    /// Print the report in `format`, with one row per run if `per_run` and
+    /// one row per pair of problem and solver otherwise
+    pub fn render(&self, format: BenchFormat, per_run: bool) -> String {
+        let table = if per_run {
+            self.runs_table()
+        } else {
+            self.summary_table()
+        };
+        match format {
+            BenchFormat::Markdown => table.markdown(),
+            BenchFormat::Csv => table.csv(),
+            // Serializing plain data into a `String` cannot fail
+            BenchFormat::Json => {
+                serde_json::to_string_pretty(self).expect("report is serializable") + "\n"
+            }
+        }
+    }
+
+    /// One row per pair of problem and solver
+    pub fn summary_table(&self) -> Table {
+        let mut table = Table::new(&[
+            "problem",
+            "solver",
+            "runs",
+            "failures",
+            "solved",
+            "success_rate",
+            "iterations",
+            "cost_evals",
+            "gradient_evals",
+            "hessian_evals",
+            "time_ms",
+            "median_cost_error",
+        ]);
+        for summary in &self.summary {
+            table.push(vec![
+                summary.problem.clone(),
+                summary.solver.clone(),
+                summary.runs.to_string(),
+                summary.failures.to_string(),
+                optional(summary.solved, |n| n.to_string()),
+                optional(summary.success_rate(), |r| format!("{:.2}", r)),
+                mean(summary.mean_iterations, 1),
+                mean(summary.mean_cost_evals, 1),
+                mean(summary.mean_gradient_evals, 1),
+                mean(summary.mean_hessian_evals, 1),
+                mean(summary.mean_wall_time_secs * 1e3, 3),
+                optional(summary.median_cost_error, |e| format!("{:.3e}", e)),
+            ]);
+        }
+        table
+    }
+
+    /// One row per run
+    pub fn runs_table(&self) -> Table {
+        let mut table = Table::new(&[
+            "problem",
+            "solver",
+            "start",
+            "iterations",
+            "cost_evals",
+            "gradient_evals",
+            "hessian_evals",
+            "time_ms",
+            "cost",
+            "cost_error",
+            "param_error",
+            "solved",
+            "termination",
+        ]);
+        for run in &self.runs {
+            let report = run.report.as_ref();
+            table.push(vec![
+                run.problem.clone(),
+                run.solver.clone(),
+                run.start.to_string(),
+                optional(report, |r| r.iterations.to_string()),
+                run.count("cost_count").to_string(),
+                run.count("gradient_count").to_string(),
+                run.count("hessian_count").to_string(),
+                format!("{:.3}", run.wall_time_secs * 1e3),
+                optional(report, |r| format!("{:e}", r.cost)),
+                optional(run.cost_error, |e| format!("{:.3e}", e)),
+                optional(run.param_error, |e| format!("{:.3e}", e)),
+                optional(run.solved, |s| s.to_string()),
+                match (report, &run.error) {
+                    (_, Some(error)) => format!("error: {}", error),
+                    (Some(report), None) => report.termination.clone(),
+                    (None, None) => String::new(),
+                },
+            ]);
+        }
+        table
+    }
+}
+
+/// Mean with `precision` decimals, `-` if there was nothing to average
+fn mean(value: f64, precision: usize) -> String {
+    optional((!value.is_nan()).then_some(value), |v| {
+        format!("{:.*}", precision, v)
+    })
+}
+
+/// `-` for missing values
+fn optional<T>(value: Option<T>, f: impl FnOnce(T) -> String) -> String {
+    value.map_or_else(|| "-".to_string(), f)
+}
+
+/// Table of strings which can be printed as Markdown or CSV
+#[derive(Clone, Debug)]
+pub struct Table {
+    header: Vec<String>,
+    rows: Vec<Vec<String>>,
+}
+
+impl Table {
+    /// Empty table with the columns `header`
+    pub fn new(header: &[&str]) -> Self {
+        Table {
+            header: header.iter().map(|h| h.to_string()).collect(),
+            rows: Vec::new(),
+        }
+    }
+
+    /// Append a row with one cell per column
+    pub fn push(&mut self, row: Vec<String>) {
+        debug_assert_eq!(row.len(), self.header.len());
+        self.rows.push(row);
+    }
+
+    /// Markdown table with aligned columns
+    pub fn markdown(&self) -> String {
+        let escape = |cell: &str| cell.replace('|', "\\|");
+        let mut widths: Vec<usize> = self.header.iter().map(|h| h.len().max(3)).collect();
+        for row in &self.rows {
+            for (width, cell) in widths.iter_mut().zip(row) {
+                *width = (*width).max(escape(cell).chars().count());
+            }
+        }
+        let line = |cells: Vec<String>| {
+            let cells: Vec<String> = cells
+                .iter()
+                .zip(&widths)
+                .map(|(cell, &width)| format!("{:<width$}", cell, width = width))
+                .collect();
+            format!("| {} |\n", cells.join(" | "))
+        };
+        let mut out = line(self.header.clone());
+        out.push_str(&line(widths.iter().map(|&w| "-".repeat(w)).collect()));
+        for row in &self.rows {
+            out.push_str(&line(row.iter().map(|cell| escape(cell)).collect()));
+        }
+        out
+    }
+
+    /// Comma separated values with a header line
+    pub fn csv(&self) -> String {
+        let escape = |cell: &String| {
+            if cell.contains([',', '"', '\n']) {
+                format!("\"{}\"", cell.replace('"', "\"\""))
+            } else {
+                cell.clone()
+            }
+        };
+        let mut out = String::new();
+        for row in std::iter::once(&self.header).chain(&self.rows) {
+            let cells: Vec<String> = row.iter().map(escape).collect();
+            out.push_str(&cells.join(","));
+            out.push('\n');
+        }
+        out
+    }
+}
+
+/// Run the benchmark described by `options` on `jobs` threads
+///
+/// Fails only if the options are invalid or a problem cannot be built;
+/// failing runs are recorded in the report. The runs are executed by
+/// [`parallel::map`], so the report lists them in the same order whatever
+/// the number of jobs; only the wall times are affected, since concurrent
+/// runs compete for the cores.
+pub fn bench(options: &BenchOptions, jobs: usize) -> Result<BenchReport, Error> {
+    let invalid = |text: &str| -> Error {
+        ArgminError::InvalidParameter {
+            text: format!("bench: {}", text),
+        }
+        .into()
+    };
+    if options.problems.is_empty() || options.solvers.is_empty() {
+        return Err(invalid("at least one problem and one solver are required"));
+    }
+    if !(options.success_tol.is_finite() && options.success_tol > 0.0) {
+        return Err(invalid("success_tol must be positive"));
+    }
+    options.stop.validate()?;
+    for solver in &options.solvers {
+        options.stop.validate_for(solver.method)?;
+    }
+
+    // Starting points are drawn up front, so that they do not depend on the
+    // order in which the runs are executed
+    let mut rng = derivative_free::rng(options.seed);
+    let mut tasks = Vec::new();
+    let mut groups = Vec::new();
+    for config in &options.problems {
+        let info = config.build()?.info().clone();
+        let mut starts = vec![info.default_start.clone()];
+        starts.extend((0..options.random_starts).map(|_| {
+            info.bounds
+                .iter()
+                .map(|&(lo, hi)| rng.gen_range(lo..=hi))
+                .collect::<Vec<f64>>()
+        }));
+        for solver in &options.solvers {
+            for (start, x0) in starts.iter().enumerate() {
+                tasks.push((config, info.clone(), solver, start, x0.clone()));
+            }
+            groups.push(starts.len());
+        }
+    }
+
+    let outcomes = parallel::map(tasks.clone(), jobs, |(config, info, solver, start, x0)| {
+        bench_run(config, &info, solver, start, &x0, options)
+    });
+    // Runs which could not even be set up, or panicked, count as failed
+    let runs: Vec<BenchRun> = tasks
+        .into_iter()
+        .zip(outcomes)
+        .map(|((_, info, solver, start, x0), outcome)| {
+            outcome.unwrap_or_else(|err| BenchRun::failed(&info, solver, start, x0, err))
+        })
+        .collect();
+    let mut summary = Vec::new();
+    let mut first = 0;
+    for size in groups {
+        summary.push(BenchSummary::new(&runs[first..first + size]));
+        first += size;
+    }
+    Ok(BenchReport {
+        success_tol: options.success_tol,
+        runs,
+        summary,
+    })
+}
+
+/// Run `solver` on the problem `config` from `x0`
+fn bench_run(
+    config: &ProblemConfig,
+    info: &ProblemInfo,
+    solver: &SolverOptions,
+    start: usize,
+    x0: &[f64],
+    options: &BenchOptions,
+) -> Result<BenchRun, Error> {
+    let start_cost = config.build()?.cost(&x0.to_vec())?;
+    let problem = config.build()?;
+    let history = History::new();
+    let run = RunConfig {
+        init_param: x0.to_vec(),
+        stop: options.stop.clone(),
+        trace: None,
+        checkpoint: None,
+        contour: None,
+        convergence: None,
+        multistart: None,
+        outer: None,
+        history: Some(history.clone()),
+    };
+    let time = Instant::now();
+    let result = solver::solve(problem, solver, &run);
+    let wall_time_secs = time.elapsed().as_secs_f64();
+
+    let trace = history.points();
+    let mut progress: Vec<Progress> = Vec::new();
+    for point in &trace {
+        let best = progress.last().map_or(start_cost, |p| p.best_cost);
+        // `best_cost` also improves from NaN to a number
+        if point.best_cost < best || (best.is_nan() && !point.best_cost.is_nan()) {
+            progress.push(Progress {
+                iter: point.iter,
+                cost_evals: point.cost_evals,
+                gradient_evals: point.gradient_evals,
+                hessian_evals: point.hessian_evals,
+                best_cost: point.best_cost,
+            });
+        }
+    }
+
+    let (report, error) = match result {
+        Ok(report) => (Some(report), None),
+        Err(err) => (None, Some(err.to_string())),
+    };
+    let cost_error = report
+        .as_ref()
+        .zip(info.min_cost)
+        .map(|(report, min_cost)| report.cost - min_cost);
+    let param_error = report.as_ref().and_then(|report| {
+        info.minima
+            .iter()
+            .map(|minimum| distance(&report.param, minimum))
+            .min_by(f64::total_cmp)
+    });
+    let solved = info.min_cost.map(|min_cost| {
+        // NaN errors never solve the problem
+        cost_error.is_some_and(|e| e <= options.success_tol * min_cost.abs().max(1.0))
+    });
+    Ok(BenchRun {
+        problem: info.name.clone(),
+        solver: solver.method.name().to_string(),
+        start,
+        x0: x0.to_vec(),
+        report,
+        error,
+        wall_time_secs,
+        cost_error,
+        param_error,
+        solved,
+        start_cost,
+        progress,
+        trace,
+    })
+}
+
+fn distance(a: &[f64], b: &[f64]) -> f64 {
+    a.iter()
+        .zip(b)
+        .map(|(x, y)| (x - y).powi(2))
+        .sum::<f64>()
+        .sqrt()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::solver::Method;
+    use serde_json::json;
+
+    fn options(problems: &[&str], methods: &[Method]) -> BenchOptions {
+        BenchOptions {
+            problems: problems
+                .iter()
+                .map(|name| serde_json::from_value(json!({ "name": name })).unwrap())
+                .collect(),
+            solvers: methods.iter().map(|&m| SolverOptions::new(m)).collect(),
+            random_starts: 2,
+            seed: Some(7),
+            stop: StopCriteria {
+                max_iters: 200,
+                ..StopCriteria::default()
+            },
+            success_tol: 1e-6,
+        }
+    }
+
+    #[test]
+    fn format_names_round_trip() {
+        for format in BenchFormat::ALL {
+            assert_eq!(BenchFormat::from_name(format.name()), Some(format));
+        }
+        assert_eq!(BenchFormat::from_name("xml"), None);
+    }
+
+    #[test]
+    fn invalid_options_are_rejected() {
+        let mut empty = options(&["sphere"], &[]);
+        assert!(bench(&empty, 1).is_err());
+        empty.solvers.push(SolverOptions::new(Method::Lbfgs));
+        empty.success_tol = 0.0;
+        assert!(bench(&empty, 1).is_err());
+    }
+
+    #[test]
+    fn runs_are_ordered_and_summarized() {
+        let options = options(&["booth", "ackley"], &[Method::Lbfgs, Method::Newton]);
+        let report = bench(&options, 1).unwrap();
+        assert_eq!(report.runs.len(), 2 * 2 * 3);
+        assert_eq!(report.summary.len(), 4);
+        let order: Vec<(&str, &str, usize)> = report
+            .runs
+            .iter()
+            .map(|run| (run.problem.as_str(), run.solver.as_str(), run.start))
+            .collect();
+        assert_eq!(
+            order[..4],
+            [
+                ("booth", "lbfgs", 0),
+                ("booth", "lbfgs", 1),
+                ("booth", "lbfgs", 2),
+                ("booth", "newton", 0),
+            ]
+        );
+        // All solvers start from the same points
+        assert_eq!(report.runs[1].x0, report.runs[4].x0);
+        assert_ne!(report.runs[1].x0, report.runs[2].x0);
+
+        let booth = &report.summary[0];
+        assert_eq!((booth.runs, booth.failures, booth.solved), (3, 0, Some(3)));
+        assert_eq!(booth.success_rate(), Some(1.0));
+        // Ackley has no Hessian: Newton's method fails without stopping the
+        // benchmark
+        let newton = &report.summary[3];
+        assert_eq!((newton.problem.as_str(), newton.solver.as_str()), ("ackley", "newton"));
+        assert_eq!((newton.failures, newton.solved), (3, Some(0)));
+        assert!(newton.mean_iterations.is_nan());
+        let failed = report.runs.last().unwrap();
+        assert!(failed.error.as_deref().unwrap().contains("Hessian"));
+    }
+
+    #[test]
+    fn negative_minima_are_reached() {
+        // A target cost of 0 would stop every run at its starting point
+        let problems = ["easom", "picheny", "styblinski-tang"];
+        let options = BenchOptions {
+            random_starts: 0,
+            ..options(&problems, &[Method::Lbfgs])
+        };
+        let report = bench(&options, 1).unwrap();
+        for summary in &report.summary {
+            assert!(summary.mean_iterations > 0.0, "{}", summary.problem);
+            assert_eq!(summary.solved, Some(1), "{}", summary.problem);
+        }
+        assert!(report.runs.iter().all(|run| run.report.as_ref().unwrap().cost < 0.0));
+    }
+
+    #[test]
+    fn progress_improves_the_best_cost() {
+        let report = bench(&options(&["rosenbrock"], &[Method::SteepestDescent]), 1).unwrap();
+        for run in &report.runs {
+            assert!(!run.progress.is_empty());
+            let mut best = run.start_cost;
+            for progress in &run.progress {
+                assert!(progress.best_cost < best);
+                best = progress.best_cost;
+            }
+            assert_eq!(best, run.report.as_ref().unwrap().cost);
+        }
+    }
+
+    #[test]
+    fn results_do_not_depend_on_the_number_of_jobs() {
+        let options = options(&["himmelblau", "beale"], &[Method::Bfgs, Method::NelderMead]);
+        let serial = bench(&options, 1).unwrap();
+        let parallel = bench(&options, 4).unwrap();
+        let table = |report: &BenchReport| {
+            let mut table = report.runs_table();
+            for row in &mut table.rows {
+                row[7].clear();
+            }
+            table.csv()
+        };
+        assert_eq!(table(&serial), table(&parallel));
+    }
+
+    #[test]
+    fn tables_escape_their_cells() {
+        let mut table = Table::new(&["a", "b"]);
+        table.push(vec!["x|y".to_string(), "1,\"2\"".to_string()]);
+        assert_eq!(table.csv(), "a,b\nx|y,\"1,\"\"2\"\"\"\n");
+        assert_eq!(
+            table.markdown(),
+            "| a    | b     |\n| ---- | ----- |\n| x\\|y | 1,\"2\" |\n"
+        );
+    }
+
+    #[test]
+    fn median_of_odd_and_even_lengths() {
+        assert_eq!(median(&[]), None);
+        assert_eq!(median(&[1.0, 2.0, 10.0]), Some(2.0));
+        assert_eq!(median(&[1.0, 2.0, 4.0, 10.0]), Some(3.0));
+    }
+}
@@ -0,0 +1,398 @@
+//! Verification of hand-written derivatives
+//!
+//! [`check`] compares the analytic gradient and Hessian of a problem with
+//! finite differences ([`crate::finitediff`]) at random points of the
+//! problem's domain and at user-given points. For every component it
+//! reports the largest absolute and relative error over all points; a NaN
+//! error at any point is reported as NaN and fails the check. Points at
+//! which the cost function is not finite (e.g. outside the domain of an
+//! expression) are skipped and listed separately.
+//!
+//! The reference derivatives are as accurate as possible: problems which can
+//! be evaluated at complex arguments are differentiated by complex steps, the
+//! others by central differences. Hessians are compared against second
+//! differences of the cost function, so that a wrong Hessian is caught even
+//! if it is consistent with a wrong gradient.
+
+use crate::finitediff::{FiniteDiff, Scheme};
+use crate::problem::DynProblem;
+use crate::solver::derivative_free;
+use argmin::core::{ArgminError, CostFunction, Error, Gradient, Hessian};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Where and how strictly derivatives are checked
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CheckOptions {
+    /// Number of random points drawn from the domain of the problem
+    pub random_points: usize,
+    /// Additional points at which derivatives are checked
+    pub points: Vec<Vec<f64>>,
+    /// Seed of the random points (drawn from entropy if `None`)
+    pub seed: Option<u64>,
+    /// Finite differences used as reference; complex steps if available,
+    /// central differences otherwise if `None`
+    pub diff: Option<FiniteDiff>,
+    /// A component passes if its error is at most
+    /// `tolerance * max(1, |reference|)`
+    pub tolerance: f64,
+}
+
+impl Default for CheckOptions {
+    fn default() -> Self {
+        CheckOptions {
+            random_points: 5,
+            points: Vec::new(),
+            seed: None,
+            diff: None,
+            tolerance: 1e-5,
+        }
+    }
+}
+
+/// Largest errors of one component of a derivative over all points
+#[derive(Clone, Debug, Serialize)]
+pub struct ComponentError {
+    /// Index of the component: `[i]` for gradients, `[i, j]` for Hessians
+    pub index: Vec<usize>,
+    /// Largest absolute error `|analytic - reference|`
+    pub max_abs_error: f64,
+    /// Largest relative error `|analytic - reference| / max(1, |reference|)`
+    pub max_rel_error: f64,
+    /// Point at which the relative error is largest
+    pub worst_point: Vec<f64>,
+    /// Whether the relative error stays within the tolerance
+    pub passed: bool,
+}
+
+/// Comparison of one analytic derivative with its reference
+#[derive(Clone, Debug, Serialize)]
+pub struct DerivativeCheck {
+    /// Errors per component
+    pub components: Vec<ComponentError>,
+    /// Whether all components passed
+    pub passed: bool,
+}
+
+impl DerivativeCheck {
+    fn new(components: Vec<ComponentError>) -> Self {
+        let passed = components.iter().all(|c| c.passed);
+        DerivativeCheck { components, passed }
+    }
+}
+
+/// Outcome of [`check`]
+#[derive(Clone, Debug, Serialize)]
+pub struct CheckReport {
+    /// Name of the problem
+    pub problem: String,
+    /// Finite differences used as reference
+    pub diff: FiniteDiff,
+    /// Tolerance on the relative error
+    pub tolerance: f64,
+    /// Points at which the derivatives were compared
+    pub points: Vec<Vec<f64>>,
+    /// Points which were skipped since the cost function is not finite there
+    pub skipped: Vec<Vec<f64>>,
+    /// Gradient check (`None` if the problem has no analytic gradient)
+    pub gradient: Option<DerivativeCheck>,
+    /// Hessian check (`None` if the problem has no analytic Hessian)
+    pub hessian: Option<DerivativeCheck>,
+    /// Whether every available derivative passed
+    pub passed: bool,
+}
+
+/// Compare the analytic derivatives of `problem` with finite differences
+pub fn check(problem: &DynProblem, options: &CheckOptions) -> Result<CheckReport, Error> {
+    let info = problem.info();
+    if !(options.tolerance.is_finite() && options.tolerance > 0.0) {
+        return Err(ArgminError::InvalidParameter {
+            text: "check: tolerance must be positive".to_string(),
+        }
+        .into());
+    }
+    let diff = match options.diff {
+        Some(diff) if diff.scheme == Scheme::ComplexStep && !problem.is_generic() => {
+            return Err(ArgminError::InvalidParameter {
+                text: format!("{} cannot be evaluated at complex arguments", info.name),
+            }
+            .into())
+        }
+        Some(diff) => diff,
+        None if problem.is_generic() => FiniteDiff::new(Scheme::ComplexStep),
+        None => FiniteDiff::new(Scheme::Central),
+    };
+    diff.validate()?;
+
+    let mut rng = derivative_free::rng(options.seed);
+    let mut candidates: Vec<Vec<f64>> = (0..options.random_points)
+        .map(|_| info.bounds.iter().map(|&(lo, hi)| rng.gen_range(lo..=hi)).collect())
+        .collect();
+    candidates.extend(options.points.iter().cloned());
+    if candidates.is_empty() {
+        return Err(ArgminError::InvalidParameter {
+            text: "check: no points to check derivatives at".to_string(),
+        }
+        .into());
+    }
+    let mut points = Vec::new();
+    let mut skipped = Vec::new();
+    for x in candidates {
+        if problem.cost(&x)?.is_finite() {
+            points.push(x);
+        } else {
+            skipped.push(x);
+        }
+    }
+    if points.is_empty() {
+        return Err(ArgminError::InvalidParameter {
+            text: format!(
+                "check: the cost function of {} is not finite at any of the points",
+                info.name
+            ),
+        }
+        .into());
+    }
+
+    let mut gradients = Vec::new();
+    let mut hessians = Vec::new();
+    for x in &points {
+        if let Some(analytic) = available(problem.gradient(x))? {
+            let reference = match diff.scheme {
+                Scheme::ComplexStep => diff.complex_gradient(problem, x)?,
+                Scheme::Forward | Scheme::Central => diff.gradient(problem, x)?,
+            };
+            gradients.push((analytic, reference));
+        }
+        if let Some(analytic) = available(problem.hessian(x))? {
+            let reference = match diff.scheme {
+                // Central differences of the exact complex step gradient
+                Scheme::ComplexStep => {
+                    diff.hessian_from_gradient(|p| diff.complex_gradient(problem, p), x)?
+                }
+                Scheme::Forward | Scheme::Central => diff.hessian(problem, x)?,
+            };
+            hessians.push((analytic.concat(), reference.concat()));
+        }
+    }
+
+    let n = info.dim;
+    let gradient = (!gradients.is_empty())
+        .then(|| compare(&gradients, &points, options.tolerance, |k| vec![k]));
+    let hessian = (!hessians.is_empty())
+        .then(|| compare(&hessians, &points, options.tolerance, |k| vec![k / n, k % n]));
+    let passed = gradient.iter().chain(&hessian).all(|c| c.passed);
+    Ok(CheckReport {
+        problem: info.name.clone(),
+        diff,
+        tolerance: options.tolerance,
+        points,
+        skipped,
+        gradient,
+        hessian,
+        passed,
+    })
+}
+
+/// `None` if the problem does not provide the derivative
+fn available<T>(result: Result<T, Error>) -> Result<Option<T>, Error> {
+    match result {
+        Ok(value) => Ok(Some(value)),
+        Err(err) => match err.downcast_ref::<ArgminError>() {
+            Some(ArgminError::NotImplemented { .. }) => Ok(None),
+            _ => Err(err),
+        },
+    }
+}
+
+/// Largest errors per component of pairs of flattened `(analytic, reference)`
+/// derivatives
+fn compare<F>(
+    pairs: &[(Vec<f64>, Vec<f64>)],
+    points: &[Vec<f64>],
+    tolerance: f64,
+    index: F,
+) -> DerivativeCheck
+where
+    F: Fn(usize) -> Vec<usize>,
+{
+    let len = pairs[0].0.len();
+    let components = (0..len)
+        .map(|k| {
+            let mut component = ComponentError {
+                index: index(k),
+                max_abs_error: 0.0,
+                max_rel_error: 0.0,
+                worst_point: points[0].clone(),
+                passed: true,
+            };
+            for ((analytic, reference), x) in pairs.iter().zip(points) {
+                let abs = (analytic[k] - reference[k]).abs();
+                let rel = abs / reference[k].abs().max(1.0);
+                // NaN errors are kept (`x > NaN` is false) and always fail
+                if abs.is_nan() || abs > component.max_abs_error {
+                    component.max_abs_error = abs;
+                }
+                if rel.is_nan() || rel > component.max_rel_error {
+                    component.max_rel_error = rel;
+                    component.worst_point = x.clone();
+                }
+            }
+            component.passed = component.max_rel_error <= tolerance;
+            component
+        })
+        .collect();
+    DerivativeCheck::new(components)
+}
+
+impl fmt::Display for CheckReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "DerivativeCheck:")?;
+        writeln!(f, "    Problem:        {}", self.problem)?;
+        writeln!(f, "    reference:      {}", self.diff.scheme.name())?;
+        writeln!(f, "    points:         {}", self.points.len())?;
+        if !self.skipped.is_empty() {
+            writeln!(
+                f,
+                "    skipped:        {} (cost not finite)",
+                self.skipped.len()
+            )?;
+            for x in &self.skipped {
+                writeln!(f, "                    {:?}", x)?;
+            }
+        }
+        writeln!(f, "    tolerance:      {:e}", self.tolerance)?;
+        for (name, check) in [("gradient", &self.gradient), ("hessian", &self.hessian)] {
+            let check = match check {
+                Some(check) => check,
+                None => {
+                    writeln!(f, "    {:<15} not provided", format!("{}:", name))?;
+                    continue;
+                }
+            };
+            writeln!(f, "    {:<15} {}", format!("{}:", name), verdict(check.passed))?;
+            for c in &check.components {
+                let index: Vec<String> = c.index.iter().map(usize::to_string).collect();
+                writeln!(
+                    f,
+                    "        [{:<5}] abs {:<10.3e} rel {:<10.3e} {}",
+                    index.join(","),
+                    c.max_abs_error,
+                    c.max_rel_error,
+                    verdict(c.passed)
+                )?;
+                if !c.passed {
+                    writeln!(f, "                worst at {:?}", c.worst_point)?;
+                }
+            }
+        }
+        writeln!(f, "    result:         {}", verdict(self.passed))
+    }
+}
+
+fn verdict(passed: bool) -> &'static str {
+    if passed {
+        "PASS"
+    } else {
+        "FAIL"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::problem::{self, ProblemConfig, ProblemInfo};
+
+    /// `x^2` with the gradient `grad`
+    struct Parabola {
+        grad: fn(f64) -> f64,
+    }
+
+    impl CostFunction for Parabola {
+        type Param = Vec<f64>;
+        type Output = f64;
+
+        fn cost(&self, p: &Self::Param) -> Result<f64, Error> {
+            Ok(p[0] * p[0])
+        }
+    }
+
+    impl Gradient for Parabola {
+        type Param = Vec<f64>;
+        type Gradient = Vec<f64>;
+
+        fn gradient(&self, p: &Self::Param) -> Result<Vec<f64>, Error> {
+            Ok(vec![(self.grad)(p[0])])
+        }
+    }
+
+    fn parabola(grad: fn(f64) -> f64) -> DynProblem {
+        let info = ProblemInfo {
+            name: "parabola".to_string(),
+            dim: 1,
+            default_start: vec![1.0],
+            minima: vec![vec![0.0]],
+            min_cost: Some(0.0),
+            bounds: vec![(-1.0, 1.0)],
+            bounded: false,
+        };
+        DynProblem::new(Parabola { grad }, info)
+    }
+
+    fn at(points: &[&[f64]]) -> CheckOptions {
+        CheckOptions {
+            random_points: 0,
+            points: points.iter().map(|p| p.to_vec()).collect(),
+            ..CheckOptions::default()
+        }
+    }
+
+    #[test]
+    fn correct_derivatives_pass() {
+        let options = CheckOptions {
+            seed: Some(1),
+            ..CheckOptions::default()
+        };
+        let report = check(&problem::build("rosenbrock", None).unwrap(), &options).unwrap();
+        assert!(report.passed);
+        assert_eq!(report.points.len(), 5);
+        assert!(report.hessian.is_some());
+    }
+
+    #[test]
+    fn wrong_gradient_fails_at_the_worst_point() {
+        let report = check(&parabola(|x| 3.0 * x), &at(&[&[0.1], &[-0.5], &[0.2]])).unwrap();
+        assert!(!report.passed);
+        assert!(report.hessian.is_none());
+        let component = &report.gradient.unwrap().components[0];
+        assert!((component.max_abs_error - 0.5).abs() < 1e-9);
+        assert_eq!(component.worst_point, [-0.5]);
+    }
+
+    #[test]
+    fn nan_errors_are_kept_and_fail() {
+        let grad = |x: f64| if x > 0.0 { f64::NAN } else { 2.0 * x };
+        let report = check(&parabola(grad), &at(&[&[0.5], &[-0.5]])).unwrap();
+        let component = &report.gradient.unwrap().components[0];
+        assert!(component.max_abs_error.is_nan());
+        assert!(component.max_rel_error.is_nan());
+        assert_eq!(component.worst_point, [0.5]);
+        assert!(!report.passed);
+    }
+
+    #[test]
+    fn points_with_non_finite_cost_are_skipped() {
+        let config = ProblemConfig {
+            expression: Some("x^y".to_string()),
+            ..serde_json::from_value(serde_json::json!({"name": "power"})).unwrap()
+        };
+        let problem = config.build().unwrap();
+        let report = check(&problem, &at(&[&[-2.0, 0.5], &[2.0, 0.5]])).unwrap();
+        assert_eq!(report.points, [[2.0, 0.5]]);
+        assert_eq!(report.skipped, [[-2.0, 0.5]]);
+        assert!(report.passed);
+
+        assert!(check(&problem, &at(&[&[-2.0, 0.5]])).is_err());
+    }
+}
@@ -0,0 +1,180 @@
+//! Checkpointing and resuming of runs
+//!
+//! A run with [`RunConfig::checkpoint`](crate::RunConfig) set saves solver
+//! and state to a checkpoint directory every few iterations, using argmin's
+//! `FileCheckpoint`. Next to the checkpoint, [`prepare`] stores the
+//! description of the run ([`RunSpec`]) in `run.json`. [`load`] reads it
+//! back, so that a preempted run can be rebuilt and continued: when a run is
+//! started with an existing checkpoint, argmin picks up solver and state from
+//! the checkpoint instead of starting from the configured initial state.
+//!
+//! Only solver and state are saved. Problems are rebuilt from their
+//! description, and random number generators outside the solver (those
+//! drawing neighbours for simulated annealing) start a fresh stream.
+
+use crate::problem::ProblemConfig;
+use crate::solver::SolverOptions;
+use crate::RunConfig;
+use argmin::core::checkpointing::{CheckpointingFrequency, FileCheckpoint};
+use argmin::core::{ArgminError, Error};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Name of the checkpoint; argmin appends the extension `.arg`
+const CHECKPOINT_NAME: &str = "checkpoint";
+
+/// Name of the file holding solver and state
+const CHECKPOINT_FILE: &str = "checkpoint.arg";
+
+/// Name of the file holding the [`RunSpec`]
+const SPEC_FILE: &str = "run.json";
+
+/// Where and how often checkpoints are written
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CheckpointOptions {
+    /// Directory holding checkpoint and run description
+    pub directory: PathBuf,
+    /// Save a checkpoint every this many iterations
+    pub every: u64,
+}
+
+impl CheckpointOptions {
+    /// argmin checkpoint described by these options
+    pub fn file_checkpoint(&self) -> Result<FileCheckpoint, Error> {
+        if self.every == 0 {
+            return Err(ArgminError::InvalidParameter {
+                text: "checkpoint: frequency must be at least 1".to_string(),
+            }
+            .into());
+        }
+        let directory = self.directory.to_string_lossy();
+        Ok(FileCheckpoint::new(
+            &*directory,
+            CHECKPOINT_NAME,
+            CheckpointingFrequency::Every(self.every),
+        ))
+    }
+}
+
+/// Everything needed to rebuild a run
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RunSpec {
+    /// Problem to solve
+    pub problem: ProblemConfig,
+    /// Solver and its tuning parameters
+    pub solver: SolverOptions,
+    /// Starting point, stopping criteria and outputs
+    pub run: RunConfig,
+}
+
+/// Start a new checkpointed run in the directory of `spec.run.checkpoint`
+///
+/// Creates the directory, removes the checkpoint of an earlier run (which
+/// argmin would otherwise resume) and writes `run.json`.
+pub fn prepare(spec: &RunSpec) -> Result<(), Error> {
+    let options = spec.run.checkpoint.as_ref().ok_or_else(|| ArgminError::InvalidParameter {
+        text: "checkpoint: the run has no checkpoint directory".to_string(),
+    })?;
+    fs::create_dir_all(&options.directory)?;
+    let checkpoint = options.directory.join(CHECKPOINT_FILE);
+    if checkpoint.exists() {
+        fs::remove_file(&checkpoint)?;
+    }
+    fs::write(
+        options.directory.join(SPEC_FILE),
+        serde_json::to_string_pretty(spec)?,
+    )?;
+    Ok(())
+}
+
+/// Read the description of the run checkpointed in `directory`
+pub fn load(directory: &Path) -> Result<RunSpec, Error> {
+    let path = directory.join(SPEC_FILE);
+    let text = fs::read_to_string(&path).map_err(|err| ArgminError::InvalidParameter {
+        text: format!("cannot read {}: {}", path.display(), err),
+    })?;
+    let mut spec: RunSpec = serde_json::from_str(&text).map_err(|err| ArgminError::InvalidParameter {
+        text: format!("invalid {}: {}", path.display(), err),
+    })?;
+    if !directory.join(CHECKPOINT_FILE).exists() {
+        return Err(ArgminError::InvalidParameter {
+            text: format!("no checkpoint has been saved in {} yet", directory.display()),
+        }
+        .into());
+    }
+    // The directory may have been moved since the run was started
+    if let Some(options) = spec.run.checkpoint.as_mut() {
+        options.directory = directory.to_path_buf();
+    }
+    Ok(spec)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::solver::{solve, Method};
+    use crate::trace::History;
+    use crate::StopCriteria;
+
+    fn spec(directory: &Path) -> RunSpec {
+        let problem: ProblemConfig =
+            serde_json::from_value(serde_json::json!({"name": "rosenbrock"})).unwrap();
+        RunSpec {
+            problem,
+            solver: SolverOptions::new(Method::Lbfgs),
+            run: RunConfig {
+                stop: StopCriteria {
+                    max_iters: 10,
+                    ..StopCriteria::default()
+                },
+                checkpoint: Some(CheckpointOptions {
+                    directory: directory.to_path_buf(),
+                    every: 4,
+                }),
+                ..RunConfig::default()
+            },
+        }
+    }
+
+    fn temp_dir(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("opt-{}-{}", name, std::process::id()))
+    }
+
+    #[test]
+    fn spec_is_only_loaded_with_a_checkpoint() {
+        let directory = temp_dir("spec");
+        let spec = spec(&directory);
+        prepare(&spec).unwrap();
+        assert!(load(&directory).is_err());
+        fs::write(directory.join(CHECKPOINT_FILE), "").unwrap();
+        let loaded = load(&directory).unwrap();
+        fs::remove_dir_all(&directory).unwrap();
+        assert_eq!(loaded.problem.name, "rosenbrock");
+        assert_eq!(loaded.solver.method, Method::Lbfgs);
+        assert_eq!(loaded.run.stop.max_iters, 10);
+    }
+
+    #[test]
+    fn resumed_run_ends_where_the_original_run_ended() {
+        let directory = temp_dir("resume");
+        let spec = spec(&directory);
+        prepare(&spec).unwrap();
+        let problem = spec.problem.build().unwrap();
+        let original = solve(problem.clone(), &spec.solver, &spec.run).unwrap();
+        // The checkpoint of iteration 8 is picked up by the next run
+        let spec = load(&directory).unwrap();
+        let history = History::new();
+        let run = RunConfig {
+            history: Some(history.clone()),
+            ..spec.run
+        };
+        let resumed = solve(problem, &spec.solver, &run).unwrap();
+        fs::remove_dir_all(&directory).unwrap();
+        let iters: Vec<u64> = history.points().iter().map(|p| p.iter).collect();
+        assert_eq!(iters, [8, 9]);
+        assert_eq!(resumed.iterations, 10);
+        assert_eq!(resumed.param, original.param);
+        assert_eq!(resumed.cost, original.cost);
+    }
+}
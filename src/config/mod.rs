@@ -0,0 +1,632 @@
+//! Experiment configuration files
+//!
+//! An experiment file describes a complete run: the problem and its
+//! parameters, the starting point, the solver with its line search, the
+//! stopping criteria and the output. `opt run --config exp.toml` runs it, with
+//! the same result as passing the equivalent flags on the command line, so
+//! that experiments can be kept under version control and repeated. For
+//! example
+//!
+//! ```toml
+//! x0 = [-1.2, 1.0]
+//!
+//! [problem]
+//! name = "rosenbrock"
+//! parameters = { a = 1.0, b = 100.0 }
+//! derivatives = "analytic"
+//!
+//! [solver]
+//! method = "lbfgs"
+//! lbfgs_memory = 10
+//!
+//! [solver.linesearch]
+//! method = "hager-zhang"
+//! c1 = 1e-4
+//!
+//! [stop]
+//! max_iters = 500
+//! target_cost = -inf
+//! gradient_tol = 1e-8
+//!
+//! [output]
+//! format = "json"
+//! trace = "lbfgs.csv"
+//! contour = "lbfgs.svg"
+//! contour_bounds = [-2.0, 2.0, -1.0, 3.0]
+//! convergence = "lbfgs-convergence.svg"
+//! ```
+//!
+//! The sections are
+//!
+//! * `x0`: the starting point (defaults to the one of the problem),
+//! * `[problem]`: see [`ProblemSection`],
+//! * `[solver]`: the fields of [`SolverOptions`], with the tables
+//!   `linesearch` ([`LineSearchOptions`](crate::solver::LineSearchOptions)),
+//!   `nelder_mead`, `swarm` and `annealing`,
+//! * `[stop]`: the fields of [`StopCriteria`],
+//! * `[output]`: see [`OutputSection`],
+//! * `[multistart]` (optional): the fields of [`MultiStartOptions`]; if
+//!   present, the solver runs from many starting points instead of `x0`,
+//!   and
+//! * `[outer]` (optional): the fields of [`OuterOptions`], the outer loop
+//!   which solves problems with `constraints` in `[problem]`.
+//!
+//! Omitted keys take the defaults of the corresponding command line flags.
+//! Names of methods and other choices are the ones `opt list` shows.
+//!
+//! Files are read as TOML, YAML or JSON depending on their extension
+//! ([`FileFormat`]), all with the same layout. Infinite numbers are written
+//! `inf` in TOML, `.inf` in YAML and cannot be written in JSON.
+//!
+//! Every key is checked. Syntax errors, unknown keys and values of the wrong
+//! type are reported with line and column, e.g. ``unknown field `c3`,
+//! expected one of `method`, `c1`, ... at line 7 column 1``; settings which
+//! are inconsistent with each other with the path of the key, e.g.
+//! ``output.trace_every: must be at least 1``.
+
+use crate::checkpoint::{CheckpointOptions, RunSpec};
+use crate::constraint::OuterOptions;
+use crate::multistart::MultiStartOptions;
+use crate::plot::contour::{ContourOptions, LevelScale};
+use crate::problem::{DynProblem, ProblemConfig};
+use crate::report::OutputFormat;
+use crate::solver::SolverOptions;
+use crate::stopping::StopCriteria;
+use crate::trace::{TraceFormat, TraceFrequency, TraceOptions};
+use crate::RunConfig;
+use argmin::core::{ArgminError, Error};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Syntax of an experiment file
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FileFormat {
+    /// TOML (`.toml`)
+    Toml,
+    /// YAML (`.yaml`, `.yml`)
+    Yaml,
+    /// JSON (`.json`)
+    Json,
+}
+
+impl FileFormat {
+    /// Format implied by the extension of `path`, if it is a known one
+    pub fn from_path(path: &Path) -> Option<FileFormat> {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("toml") => Some(FileFormat::Toml),
+            Some("yaml") | Some("yml") => Some(FileFormat::Yaml),
+            Some("json") => Some(FileFormat::Json),
+            _ => None,
+        }
+    }
+}
+
+/// Description of a complete run, as read from an experiment file
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Experiment {
+    /// Starting point; defaults to the starting point of the problem
+    #[serde(default)]
+    pub x0: Option<Vec<f64>>,
+    /// Problem to solve
+    pub problem: ProblemSection,
+    /// Solver and its tuning parameters
+    #[serde(default)]
+    pub solver: SolverOptions,
+    /// Stopping criteria
+    #[serde(default)]
+    pub stop: StopCriteria,
+    /// What is written where
+    #[serde(default)]
+    pub output: OutputSection,
+    /// Run the solver from many starting points instead of `x0`
+    #[serde(default)]
+    pub multistart: Option<MultiStartOptions>,
+    /// Outer loop solving problems with constraints
+    #[serde(default)]
+    pub outer: Option<OuterOptions>,
+}
+
+/// The `[problem]` section
+///
+/// Either `name` selects a registered problem or `expression` gives the
+/// cost function (see [`Expression`](crate::problem::Expression)); an
+/// expression is named after itself unless `name` is given too.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ProblemSection {
+    /// Name of the registered problem
+    pub name: Option<String>,
+    /// Cost function as an expression
+    pub expression: Option<String>,
+    /// Order of the variables of `expression` (by name if not given)
+    pub variables: Option<Vec<String>>,
+    /// Dimension of problems which are defined for any dimension
+    pub dim: Option<usize>,
+    /// Parameters of the problem, e.g. `a` and `b` of Rosenbrock, or
+    /// constants substituted into `expression`
+    pub parameters: BTreeMap<String, f64>,
+    /// Source of the derivatives, one of
+    /// [`DERIVATIVES`](crate::problem::DERIVATIVES)
+    pub derivatives: String,
+    /// Relative step of finite-difference gradients
+    pub fd_step: Option<f64>,
+    /// Relative step of finite-difference Hessians
+    pub fd_hessian_step: Option<f64>,
+    /// Constrain the parameters to the domain of the problem
+    pub bounded: bool,
+    /// Lower bounds of the parameters, a list of one value for all of them
+    /// or of one value per parameter; implies `bounded`
+    pub lower: Option<Vec<f64>>,
+    /// Upper bounds of the parameters, like `lower`
+    pub upper: Option<Vec<f64>>,
+    /// Equality and inequality constraints, e.g. `["x1 + x2 = 1"]`; see
+    /// [`ProblemConfig::constraints`]
+    pub constraints: Vec<String>,
+}
+
+impl Default for ProblemSection {
+    fn default() -> Self {
+        ProblemSection {
+            name: None,
+            expression: None,
+            variables: None,
+            dim: None,
+            parameters: BTreeMap::new(),
+            derivatives: "analytic".to_string(),
+            fd_step: None,
+            fd_hessian_step: None,
+            bounded: false,
+            lower: None,
+            upper: None,
+            constraints: Vec::new(),
+        }
+    }
+}
+
+impl ProblemSection {
+    /// Description of the problem
+    pub fn config(&self) -> Result<ProblemConfig, Error> {
+        let name = match (&self.name, &self.expression) {
+            (Some(name), _) => name.clone(),
+            (None, Some(expression)) => expression.clone(),
+            (None, None) => return Err(invalid("either `name` or `expression` is required")),
+        };
+        let config = ProblemConfig {
+            name,
+            dim: self.dim,
+            parameters: self.parameters.clone(),
+            numeric: None,
+            autodiff: None,
+            expression: self.expression.clone(),
+            variables: self.variables.clone(),
+            bounded: self.bounded,
+            lower: self.lower.clone(),
+            upper: self.upper.clone(),
+            constraints: self.constraints.clone(),
+        };
+        config.with_derivatives(&self.derivatives, self.fd_step, self.fd_hessian_step)
+    }
+}
+
+/// The `[output]` section
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct OutputSection {
+    /// How the result is printed
+    pub format: OutputFormat,
+    /// Write a per-iteration trace to this file
+    pub trace: Option<PathBuf>,
+    /// Format of the trace; implied by the extension of `trace` if not given
+    pub trace_format: Option<TraceFormat>,
+    /// Only trace every this many iterations
+    pub trace_every: Option<u64>,
+    /// Only trace iterations which find a new best point
+    pub trace_best: bool,
+    /// Save checkpoints to this directory
+    pub checkpoint_dir: Option<PathBuf>,
+    /// Save a checkpoint every this many iterations
+    pub checkpoint_every: Option<u64>,
+    /// Plot the iterates over a contour plot of the cost function to this
+    /// file (problems with two parameters only)
+    pub contour: Option<PathBuf>,
+    /// Box `[x_lo, x_hi, y_lo, y_hi]` of the contour plot
+    pub contour_bounds: Option<[f64; 4]>,
+    /// Number of contour lines
+    pub contour_levels: Option<usize>,
+    /// Number of grid points along each axis of the contour plot
+    pub contour_resolution: Option<usize>,
+    /// Spacing of colors and contour lines
+    pub contour_scale: Option<LevelScale>,
+    /// Write convergence plots of the run to this SVG file
+    pub convergence: Option<PathBuf>,
+}
+
+impl Default for OutputSection {
+    fn default() -> Self {
+        OutputSection {
+            format: OutputFormat::Text,
+            trace: None,
+            trace_format: None,
+            trace_every: None,
+            trace_best: false,
+            checkpoint_dir: None,
+            checkpoint_every: None,
+            contour: None,
+            contour_bounds: None,
+            contour_levels: None,
+            contour_resolution: None,
+            contour_scale: None,
+            convergence: None,
+        }
+    }
+}
+
+impl OutputSection {
+    /// Trace requested by the section
+    fn trace(&self) -> Result<Option<TraceOptions>, Error> {
+        let Some(path) = self.trace.clone() else {
+            if self.trace_format.is_some() || self.trace_every.is_some() || self.trace_best {
+                return Err(invalid("output: trace settings require `trace`"));
+            }
+            return Ok(None);
+        };
+        let frequency = match (self.trace_every, self.trace_best) {
+            (Some(_), true) => {
+                return Err(invalid(
+                    "output: `trace_every` and `trace_best` are exclusive",
+                ))
+            }
+            (Some(0), false) => return Err(invalid("output.trace_every: must be at least 1")),
+            (Some(n), false) => TraceFrequency::Every(n),
+            (None, false) => TraceFrequency::Every(1),
+            (None, true) => TraceFrequency::NewBest,
+        };
+        Ok(Some(TraceOptions {
+            format: self
+                .trace_format
+                .unwrap_or_else(|| TraceFormat::from_path(&path)),
+            path,
+            frequency,
+            append: false,
+        }))
+    }
+
+    /// Checkpointing requested by the section
+    fn checkpoint(&self) -> Result<Option<CheckpointOptions>, Error> {
+        let Some(directory) = self.checkpoint_dir.clone() else {
+            if self.checkpoint_every.is_some() {
+                return Err(invalid(
+                    "output: `checkpoint_every` requires `checkpoint_dir`",
+                ));
+            }
+            return Ok(None);
+        };
+        let every = self.checkpoint_every.unwrap_or(100);
+        if every == 0 {
+            return Err(invalid("output.checkpoint_every: must be at least 1"));
+        }
+        Ok(Some(CheckpointOptions { directory, every }))
+    }
+
+    /// Contour plot requested by the section, for a problem with `dim`
+    /// parameters
+    fn contour(&self, dim: usize) -> Result<Option<ContourOptions>, Error> {
+        let Some(path) = self.contour.clone() else {
+            if self.contour_bounds.is_some()
+                || self.contour_levels.is_some()
+                || self.contour_resolution.is_some()
+                || self.contour_scale.is_some()
+            {
+                return Err(invalid("output: contour settings require `contour`"));
+            }
+            return Ok(None);
+        };
+        if dim != 2 {
+            return Err(invalid(&format!(
+                "output.contour: needs a problem with 2 parameters, not {}",
+                dim
+            )));
+        }
+        let defaults = ContourOptions::new(path);
+        let options = ContourOptions {
+            bounds: self.contour_bounds,
+            levels: self.contour_levels.unwrap_or(defaults.levels),
+            resolution: self.contour_resolution.unwrap_or(defaults.resolution),
+            scale: self.contour_scale.unwrap_or(defaults.scale),
+            ..defaults
+        };
+        // The messages start with the name of the field
+        options.validate().map_err(|err| {
+            let text = message(&err);
+            let (field, rest) = text.split_once(' ').unwrap_or((&text, ""));
+            invalid(&format!("output.contour_{}: {}", field, rest))
+        })?;
+        Ok(Some(options))
+    }
+}
+
+impl Experiment {
+    /// Read the experiment file at `path`, whose format is given by its
+    /// extension
+    pub fn load(path: &Path) -> Result<Experiment, Error> {
+        let format = FileFormat::from_path(path).ok_or_else(|| {
+            invalid(&format!(
+                "{}: unknown file type, expected `.toml`, `.yaml`, `.yml` or `.json`",
+                path.display()
+            ))
+        })?;
+        let text = fs::read_to_string(path)
+            .map_err(|err| invalid(&format!("cannot read {}: {}", path.display(), err)))?;
+        Experiment::read(&text, format)
+            .map_err(|err| invalid(&format!("{}: {}", path.display(), err)))
+    }
+
+    /// Read an experiment from `text` in `format`
+    pub fn parse(text: &str, format: FileFormat) -> Result<Experiment, Error> {
+        Experiment::read(text, format).map_err(|err| invalid(&err))
+    }
+
+    fn read(text: &str, format: FileFormat) -> Result<Experiment, String> {
+        match format {
+            FileFormat::Toml => toml::from_str(text).map_err(|err| toml_error(text, &err)),
+            FileFormat::Yaml => serde_yaml::from_str(text).map_err(|err| err.to_string()),
+            FileFormat::Json => serde_json::from_str(text).map_err(|err| err.to_string()),
+        }
+    }
+
+    /// Build the problem and the description of the run
+    ///
+    /// Besides building the problem, this checks the settings which the
+    /// file format cannot express, like the length of `x0`.
+    pub fn build(&self) -> Result<(RunSpec, DynProblem), Error> {
+        let prefix = |err: Error| invalid(&format!("problem: {}", message(&err)));
+        let config = self.problem.config().map_err(prefix)?;
+        let problem = config.build().map_err(prefix)?;
+        let dim = problem.info().dim;
+        let init_param = match &self.x0 {
+            Some(x0) if x0.len() != dim => {
+                return Err(invalid(&format!(
+                    "x0: has {} components, but the problem expects {}",
+                    x0.len(),
+                    dim
+                )))
+            }
+            Some(x0) if x0.iter().any(|x| !x.is_finite()) => {
+                return Err(invalid("x0: must only contain finite values"))
+            }
+            Some(x0) => x0.clone(),
+            None => problem.info().default_start.clone(),
+        };
+        self.stop
+            .validate_for(self.solver.method)
+            .map_err(|err| invalid(&format!("stop: {}", message(&err))))?;
+        if let Some(multistart) = &self.multistart {
+            multistart
+                .validate(dim)
+                .map_err(|err| invalid(&format!("multistart: {}", message(&err))))?;
+            let exclusive = [
+                ("x0", self.x0.is_some()),
+                ("output.trace", self.output.trace.is_some()),
+                ("output.checkpoint_dir", self.output.checkpoint_dir.is_some()),
+                ("output.contour", self.output.contour.is_some()),
+            ];
+            if let Some((key, _)) = exclusive.iter().find(|(_, set)| *set) {
+                return Err(invalid(&format!(
+                    "{}: cannot be combined with `multistart`",
+                    key
+                )));
+            }
+        }
+        let constraints = config.build_constraints(&problem).map_err(prefix)?;
+        if let Some(outer) = &self.outer {
+            outer
+                .validate()
+                .map_err(|err| invalid(&format!("outer: {}", message(&err))))?;
+            if constraints.is_empty() {
+                return Err(invalid("outer: only used by problems with `constraints`"));
+            }
+        }
+        if !constraints.is_empty() {
+            let exclusive = [
+                ("output.trace", self.output.trace.is_some()),
+                ("output.checkpoint_dir", self.output.checkpoint_dir.is_some()),
+                ("output.convergence", self.output.convergence.is_some()),
+                ("multistart", self.multistart.is_some()),
+            ];
+            if let Some((key, _)) = exclusive.iter().find(|(_, set)| *set) {
+                return Err(invalid(&format!(
+                    "{}: cannot be combined with `problem.constraints`",
+                    key
+                )));
+            }
+        }
+        let spec = RunSpec {
+            // Record the dimension actually used rather than the one requested
+            problem: ProblemConfig {
+                dim: Some(dim),
+                ..config
+            },
+            solver: self.solver.clone(),
+            run: RunConfig {
+                init_param,
+                stop: self.stop.clone(),
+                trace: self.output.trace()?,
+                checkpoint: self.output.checkpoint()?,
+                contour: self.output.contour(dim)?,
+                convergence: self.output.convergence.clone(),
+                multistart: self.multistart.clone(),
+                outer: self.outer.clone(),
+                history: None,
+            },
+        };
+        Ok((spec, problem))
+    }
+}
+
+/// Read the experiment file at `path` and build the run it describes
+///
+/// Returns the description of the run, the problem and how the result is
+/// printed. Errors name the file.
+pub fn load(path: &Path) -> Result<(RunSpec, DynProblem, OutputFormat), Error> {
+    let experiment = Experiment::load(path)?;
+    let (spec, problem) = experiment
+        .build()
+        .map_err(|err| invalid(&format!("{}: {}", path.display(), message(&err))))?;
+    Ok((spec, problem, experiment.output.format))
+}
+
+/// Message of a TOML error with its position, in the style of the errors
+/// of `serde_yaml` and `serde_json`
+fn toml_error(text: &str, err: &::toml::de::Error) -> String {
+    let message = err.message().trim_end();
+    let Some(span) = err.span() else {
+        return message.to_string();
+    };
+    let before = &text[..span.start.min(text.len())];
+    let line = before.matches('\n').count() + 1;
+    let column = before.rsplit('\n').next().unwrap_or("").chars().count() + 1;
+    format!("{} at line {} column {}", message, line, column)
+}
+
+fn invalid(text: &str) -> Error {
+    ArgminError::InvalidParameter {
+        text: text.to_string(),
+    }
+    .into()
+}
+
+/// Text of `err` without argmin's "Invalid parameter" wrapper
+fn message(err: &Error) -> String {
+    match err.downcast_ref::<ArgminError>() {
+        Some(ArgminError::InvalidParameter { text }) => text.clone(),
+        _ => err.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::solver::Method;
+
+    const TOML: &str = r#"
+x0 = [-1.2, 1.0]
+
+[problem]
+name = "rosenbrock"
+parameters = { a = 1.0, b = 100 }
+
+[solver]
+method = "lbfgs"
+lbfgs_memory = 5
+
+[stop]
+max_iters = 200
+target_cost = -inf
+"#;
+
+    const YAML: &str = "
+x0: [-1.2, 1.0]
+problem:
+  name: rosenbrock
+  parameters: {a: 1.0, b: 100}
+solver:
+  method: lbfgs
+  lbfgs_memory: 5
+stop:
+  max_iters: 200
+  target_cost: -.inf
+";
+
+    fn error(text: &str, format: FileFormat) -> String {
+        match Experiment::parse(text, format) {
+            Ok(_) => panic!("{:?} was accepted:\n{}", format, text),
+            Err(err) => message(&err),
+        }
+    }
+
+    #[test]
+    fn formats_are_implied_by_the_extension() {
+        assert_eq!(FileFormat::from_path(Path::new("a.toml")), Some(FileFormat::Toml));
+        assert_eq!(FileFormat::from_path(Path::new("a.yml")), Some(FileFormat::Yaml));
+        assert_eq!(FileFormat::from_path(Path::new("a.yaml")), Some(FileFormat::Yaml));
+        assert_eq!(FileFormat::from_path(Path::new("a.json")), Some(FileFormat::Json));
+        assert_eq!(FileFormat::from_path(Path::new("a.txt")), None);
+    }
+
+    #[test]
+    fn all_formats_describe_the_same_run() {
+        let json = r#"{
+            "x0": [-1.2, 1.0],
+            "problem": {"name": "rosenbrock", "parameters": {"a": 1.0, "b": 100}},
+            "solver": {"method": "lbfgs", "lbfgs_memory": 5},
+            "stop": {"max_iters": 200}
+        }"#;
+        let mut specs = Vec::new();
+        for (text, format) in [
+            (TOML, FileFormat::Toml),
+            (YAML, FileFormat::Yaml),
+            (json, FileFormat::Json),
+        ] {
+            let experiment = Experiment::parse(text, format).unwrap();
+            assert_eq!(experiment.solver.method, Method::Lbfgs);
+            let (spec, problem) = experiment.build().unwrap();
+            assert_eq!(problem.info().dim, 2);
+            assert_eq!(spec.run.init_param, [-1.2, 1.0]);
+            assert_eq!(spec.run.stop.max_iters, 200);
+            specs.push(serde_json::to_value(&spec.problem).unwrap());
+        }
+        assert!(specs.iter().all(|spec| *spec == specs[0]));
+        let toml = Experiment::parse(TOML, FileFormat::Toml).unwrap();
+        let yaml = Experiment::parse(YAML, FileFormat::Yaml).unwrap();
+        assert_eq!(toml.stop.target_cost, Some(f64::NEG_INFINITY));
+        assert_eq!(yaml.stop.target_cost, Some(f64::NEG_INFINITY));
+    }
+
+    #[test]
+    fn syntax_errors_carry_line_and_column() {
+        let text = "[problem]\nname = \"rosenbrock\"\ndim = = 3\n";
+        let err = error(text, FileFormat::Toml);
+        assert!(err.ends_with("at line 3 column 7"), "{}", err);
+        let text = "problem:\n  name: rosenbrock\n dim: 3\n";
+        let err = error(text, FileFormat::Yaml);
+        assert!(err.contains("at line 3 column 2"), "{}", err);
+    }
+
+    #[test]
+    fn unknown_keys_and_wrong_types_are_located() {
+        let text = "[problem]\nname = \"rosenbrock\"\n\n[solver.linesearch]\nc3 = 1.0\n";
+        let err = error(text, FileFormat::Toml);
+        assert!(err.starts_with("unknown field `c3`"), "{}", err);
+        assert!(err.ends_with("at line 5 column 1"), "{}", err);
+        let text = "problem:\n  name: rosenbrock\nstop:\n  max_iters: many\n";
+        let err = error(text, FileFormat::Yaml);
+        assert!(err.starts_with("stop.max_iters: invalid type"), "{}", err);
+        assert!(err.contains("line 4 column 14"), "{}", err);
+    }
+
+    #[test]
+    fn inconsistent_settings_name_the_key() {
+        let err = |text: &str| {
+            let experiment = Experiment::parse(text, FileFormat::Toml).unwrap();
+            match experiment.build() {
+                Ok(_) => panic!("{} was accepted", text),
+                Err(err) => message(&err),
+            }
+        };
+        assert_eq!(
+            err("x0 = [1.0]\n[problem]\nname = \"rosenbrock\"\n"),
+            "x0: has 1 components, but the problem expects 2"
+        );
+        assert_eq!(
+            err("[problem]\nname = \"rosenbrock\"\n[output]\ntrace_every = 2\n"),
+            "output: trace settings require `trace`"
+        );
+        assert!(err("[problem]\nname = \"nope\"\n").starts_with("problem: "));
+        for format in [FileFormat::Toml, FileFormat::Yaml] {
+            assert!(error("", format).starts_with("missing field `problem`"));
+        }
+    }
+}
@@ -0,0 +1,991 @@
+//! Problems with equality and inequality constraints
+//!
+//! A constrained problem minimizes a cost function `f(x)` subject to
+//! equality constraints `h_j(x) = 0` and inequality constraints
+//! `g_i(x) <= 0`. The constraints are described by the [`Constraints`]
+//! trait, the counterpart of argmin's `CostFunction`. [`ConstraintSet`]
+//! implements it for constraints given as expressions, e.g.
+//! `x1^2 + x2^2 <= 1` or `x1 + x2 = 1` (see [`Constraint::parse`]).
+//!
+//! [`solve_constrained`] turns the constrained problem into a sequence of
+//! unconstrained ones, each solved by one of the solvers of
+//! [`crate::solver`] from the solution of the previous one (the outer
+//! loop). The [`PenaltyMethod`] decides how:
+//!
+//! * `quadratic-penalty` minimizes
+//!   `f + mu/2 (sum h_j^2 + sum max(0, g_i)^2)` for growing `mu`; the
+//!   solutions approach the feasible set from outside,
+//! * `log-barrier` minimizes `f - r sum ln(-g_i) + 1/(2r) sum h_j^2` for
+//!   shrinking `r`, which keeps the iterates strictly inside the
+//!   inequality constraints, and needs a starting point there, and
+//! * `augmented-lagrangian` (Powell-Hestenes-Rockafellar) adds estimates of
+//!   the Lagrange multipliers to the quadratic penalty and updates them
+//!   after every subproblem, so that `mu` can stay moderate; it only grows
+//!   when the constraint violation does not shrink fast enough.
+//!
+//! The outer loop stops when the constraint violation (the largest of
+//! `|h_j|` and `max(0, g_i)`) is at most `constraint_tol`, for the log
+//! barrier also the duality gap `r m` of its `m` inequality constraints.
+//! The [`ConstrainedReport`] lists the value, violation and multiplier of
+//! every constraint, with multipliers of the Lagrangian
+//! `f + sum lambda_j h_j + sum nu_i g_i`, `nu_i >= 0`.
+//!
+//! The subproblems only provide a gradient, so solvers which need the
+//! Hessian cannot solve them. The log barrier is infinite outside the
+//! interior, where More-Thuente and Hager-Zhang cannot step back from; its
+//! subproblems are therefore solved with a backtracking line search
+//! instead (see [`barrier_solver`]).
+
+use crate::bench::Table;
+use crate::problem::{DynProblem, Expression};
+use crate::report::{self, Report};
+use crate::solver::{self, LineSearchMethod, SolverOptions};
+use crate::RunConfig;
+use argmin::core::{ArgminError, CostFunction, Error, Gradient};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Whether a constraint is an equality or an inequality
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ConstraintKind {
+    /// `h(x) = 0`
+    Equality,
+    /// `g(x) <= 0`
+    Inequality,
+}
+
+impl ConstraintKind {
+    /// Name in reports
+    pub fn name(self) -> &'static str {
+        match self {
+            ConstraintKind::Equality => "equality",
+            ConstraintKind::Inequality => "inequality",
+        }
+    }
+}
+
+/// Equality constraints `h(x) = 0` and inequality constraints `g(x) <= 0`
+///
+/// This is the counterpart of argmin's `CostFunction` and `Gradient` for
+/// the constraints of a problem: the values of all constraints of a kind at
+/// once, and their gradients.
+pub trait Constraints {
+    /// Type of the parameter vector
+    type Param;
+
+    /// Values `h_j(x)` of the equality constraints
+    fn equalities(&self, p: &Self::Param) -> Result<Vec<f64>, Error>;
+
+    /// Values `g_i(x)` of the inequality constraints
+    fn inequalities(&self, p: &Self::Param) -> Result<Vec<f64>, Error>;
+
+    /// Gradients of the equality constraints, one per constraint
+    fn equality_gradients(&self, p: &Self::Param) -> Result<Vec<Self::Param>, Error>;
+
+    /// Gradients of the inequality constraints, one per constraint
+    fn inequality_gradients(&self, p: &Self::Param) -> Result<Vec<Self::Param>, Error>;
+
+    /// Name of the `index`th constraint of `kind` in reports, `h0`, `g1`,
+    /// ... unless overridden
+    fn constraint_name(&self, kind: ConstraintKind, index: usize) -> String {
+        match kind {
+            ConstraintKind::Equality => format!("h{}", index),
+            ConstraintKind::Inequality => format!("g{}", index),
+        }
+    }
+}
+
+/// A constraint given as a relation between two expressions
+#[derive(Clone)]
+pub struct Constraint {
+    /// The relation as given, e.g. `x1 + x2 >= 1`
+    pub source: String,
+    /// Equality or inequality
+    pub kind: ConstraintKind,
+    /// `h` or `g` of the constraint, `x1 + x2 >= 1` becomes
+    /// `g = 1 - (x1 + x2)`
+    function: DynProblem,
+}
+
+impl Constraint {
+    /// Parse `lhs = rhs`, `lhs <= rhs` or `lhs >= rhs`
+    ///
+    /// Both sides are [`Expression`]s in `variables`, the parameters of the
+    /// problem in order; identifiers in `parameters` are replaced by their
+    /// values. `==` is accepted for `=`.
+    pub fn parse(
+        source: &str,
+        variables: &[String],
+        parameters: &BTreeMap<String, f64>,
+    ) -> Result<Constraint, Error> {
+        let invalid = |text: String| -> Error { ArgminError::InvalidParameter { text }.into() };
+        let relations: Vec<(usize, &str)> = ["<=", ">=", "=="]
+            .into_iter()
+            .flat_map(|op| source.match_indices(op))
+            .chain(
+                // A single `=` which is not part of the other relations
+                source.match_indices('=').filter(|&(i, _)| {
+                    let before = source[..i].chars().next_back();
+                    let after = source[i + 1..].chars().next();
+                    !matches!(before, Some('<' | '>' | '=')) && !matches!(after, Some('='))
+                }),
+            )
+            .collect();
+        let [(at, op)] = relations[..] else {
+            return Err(invalid(format!(
+                "constraint `{}` needs exactly one of `=`, `<=` and `>=`",
+                source
+            )));
+        };
+        let (lhs, rhs) = (source[..at].trim(), source[at + op.len()..].trim());
+        for side in [lhs, rhs] {
+            if side.is_empty() {
+                return Err(invalid(format!(
+                    "constraint `{}` has an empty side",
+                    source
+                )));
+            }
+            // Report syntax errors with columns of the side they are in
+            Expression::parse(side, Some(variables), parameters)?;
+        }
+        let (kind, function) = match op {
+            "<=" => (ConstraintKind::Inequality, format!("({}) - ({})", lhs, rhs)),
+            ">=" => (ConstraintKind::Inequality, format!("({}) - ({})", rhs, lhs)),
+            _ => (ConstraintKind::Equality, format!("({}) - ({})", lhs, rhs)),
+        };
+        let function = Expression::parse(&function, Some(variables), parameters)?;
+        Ok(Constraint {
+            source: source.to_string(),
+            kind,
+            function: function.into_dyn(source),
+        })
+    }
+
+    /// Value of `h` or `g` at `p`
+    pub fn value(&self, p: &Vec<f64>) -> Result<f64, Error> {
+        self.function.cost(p)
+    }
+
+    /// Gradient of `h` or `g` at `p`
+    pub fn gradient(&self, p: &Vec<f64>) -> Result<Vec<f64>, Error> {
+        self.function.gradient(p)
+    }
+}
+
+impl fmt::Debug for Constraint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Constraint")
+            .field("source", &self.source)
+            .field("kind", &self.kind)
+            .finish()
+    }
+}
+
+/// Constraints given as expressions
+#[derive(Clone, Debug, Default)]
+pub struct ConstraintSet {
+    equalities: Vec<Constraint>,
+    inequalities: Vec<Constraint>,
+}
+
+impl ConstraintSet {
+    /// Parse every constraint of `sources`, see [`Constraint::parse`]
+    pub fn parse(
+        sources: &[String],
+        variables: &[String],
+        parameters: &BTreeMap<String, f64>,
+    ) -> Result<ConstraintSet, Error> {
+        let mut set = ConstraintSet::default();
+        for source in sources {
+            set.push(Constraint::parse(source, variables, parameters)?);
+        }
+        Ok(set)
+    }
+
+    /// Add `constraint`
+    pub fn push(&mut self, constraint: Constraint) {
+        match constraint.kind {
+            ConstraintKind::Equality => self.equalities.push(constraint),
+            ConstraintKind::Inequality => self.inequalities.push(constraint),
+        }
+    }
+
+    /// Whether there are no constraints
+    pub fn is_empty(&self) -> bool {
+        self.equalities.is_empty() && self.inequalities.is_empty()
+    }
+}
+
+impl Constraints for ConstraintSet {
+    type Param = Vec<f64>;
+
+    fn equalities(&self, p: &Vec<f64>) -> Result<Vec<f64>, Error> {
+        self.equalities.iter().map(|c| c.value(p)).collect()
+    }
+
+    fn inequalities(&self, p: &Vec<f64>) -> Result<Vec<f64>, Error> {
+        self.inequalities.iter().map(|c| c.value(p)).collect()
+    }
+
+    fn equality_gradients(&self, p: &Vec<f64>) -> Result<Vec<Vec<f64>>, Error> {
+        self.equalities.iter().map(|c| c.gradient(p)).collect()
+    }
+
+    fn inequality_gradients(&self, p: &Vec<f64>) -> Result<Vec<Vec<f64>>, Error> {
+        self.inequalities.iter().map(|c| c.gradient(p)).collect()
+    }
+
+    /// The relation as given
+    fn constraint_name(&self, kind: ConstraintKind, index: usize) -> String {
+        let constraints = match kind {
+            ConstraintKind::Equality => &self.equalities,
+            ConstraintKind::Inequality => &self.inequalities,
+        };
+        constraints[index].source.clone()
+    }
+}
+
+/// How the outer loop turns the constrained problem into unconstrained ones
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum PenaltyMethod {
+    /// Quadratic penalty of the constraint violation
+    QuadraticPenalty,
+    /// Logarithmic barrier of the inequality constraints
+    LogBarrier,
+    /// Augmented Lagrangian with multiplier updates
+    #[default]
+    AugmentedLagrangian,
+}
+
+impl PenaltyMethod {
+    /// All methods, in the order in which they are listed
+    pub const ALL: &'static [PenaltyMethod] = &[
+        PenaltyMethod::QuadraticPenalty,
+        PenaltyMethod::LogBarrier,
+        PenaltyMethod::AugmentedLagrangian,
+    ];
+
+    /// Name used to select the method
+    pub fn name(self) -> &'static str {
+        match self {
+            PenaltyMethod::QuadraticPenalty => "quadratic-penalty",
+            PenaltyMethod::LogBarrier => "log-barrier",
+            PenaltyMethod::AugmentedLagrangian => "augmented-lagrangian",
+        }
+    }
+
+    /// One line description
+    pub fn description(self) -> &'static str {
+        match self {
+            PenaltyMethod::QuadraticPenalty => "Quadratic penalty, growing penalty weight",
+            PenaltyMethod::LogBarrier => {
+                "Logarithmic barrier, needs a start inside the inequality constraints"
+            }
+            PenaltyMethod::AugmentedLagrangian => "Augmented Lagrangian with multiplier updates",
+        }
+    }
+
+    /// Look up a method by its name
+    pub fn from_name(name: &str) -> Option<PenaltyMethod> {
+        PenaltyMethod::ALL
+            .iter()
+            .copied()
+            .find(|m| m.name() == name)
+    }
+}
+
+/// Settings of the outer loop
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct OuterOptions {
+    /// How the constraints enter the subproblems
+    pub method: PenaltyMethod,
+    /// Maximum number of subproblems solved
+    pub outer_iters: u64,
+    /// Initial penalty weight `mu`; the log barrier starts with the barrier
+    /// weight `r = 1 / penalty`
+    pub penalty: f64,
+    /// Factor by which the penalty weight grows (and the barrier weight
+    /// shrinks) between subproblems
+    pub penalty_growth: f64,
+    /// Largest constraint violation accepted as feasible
+    pub constraint_tol: f64,
+}
+
+impl Default for OuterOptions {
+    fn default() -> Self {
+        OuterOptions {
+            method: PenaltyMethod::AugmentedLagrangian,
+            outer_iters: 20,
+            penalty: 10.0,
+            penalty_growth: 10.0,
+            constraint_tol: 1e-6,
+        }
+    }
+}
+
+impl OuterOptions {
+    /// Check that the settings can be used
+    ///
+    /// Messages start with the name of the offending field.
+    pub fn validate(&self) -> Result<(), Error> {
+        let invalid = |text: &str| -> Result<(), Error> {
+            Err(ArgminError::InvalidParameter {
+                text: text.to_string(),
+            }
+            .into())
+        };
+        if self.outer_iters == 0 {
+            return invalid("outer_iters must be at least 1");
+        }
+        if !(self.penalty.is_finite() && self.penalty > 0.0) {
+            return invalid("penalty must be positive");
+        }
+        if !(self.penalty_growth.is_finite() && self.penalty_growth > 1.0) {
+            return invalid("penalty_growth must be greater than 1");
+        }
+        if !(self.constraint_tol.is_finite() && self.constraint_tol > 0.0) {
+            return invalid("constraint_tol must be positive");
+        }
+        Ok(())
+    }
+}
+
+/// Value and multiplier of one constraint at the solution
+#[derive(Clone, Debug, Serialize)]
+pub struct ConstraintValue {
+    /// Name of the constraint ([`Constraints::constraint_name`])
+    pub name: String,
+    /// Equality or inequality
+    pub kind: ConstraintKind,
+    /// `h_j(x)` or `g_i(x)`
+    pub value: f64,
+    /// `|h_j(x)|` or `max(0, g_i(x))`
+    pub violation: f64,
+    /// Estimate of the Lagrange multiplier
+    pub multiplier: f64,
+}
+
+/// One subproblem of the outer loop
+#[derive(Clone, Debug, Serialize)]
+pub struct OuterIteration {
+    /// Penalty weight `mu`, or barrier weight `r` of the log barrier
+    pub weight: f64,
+    /// Cost function value at the solution of the subproblem
+    pub cost: f64,
+    /// Constraint violation at the solution of the subproblem
+    pub violation: f64,
+    /// Iterations of the solver
+    pub iterations: u64,
+    /// Why the solver stopped
+    pub termination: String,
+}
+
+/// Outcome of [`solve_constrained`]
+#[derive(Clone, Debug, Serialize)]
+pub struct ConstrainedReport {
+    /// Name of the problem
+    pub problem: String,
+    /// Name of the solver of the subproblems
+    pub solver: String,
+    /// How the subproblems were formed
+    pub method: PenaltyMethod,
+    /// Solution of the last subproblem
+    pub param: Vec<f64>,
+    /// Cost function value (without penalty) at `param`
+    pub cost: f64,
+    /// Largest violation of a constraint at `param`
+    pub violation: f64,
+    /// Every constraint at `param`, equalities first
+    pub constraints: Vec<ConstraintValue>,
+    /// The subproblems in the order in which they were solved
+    pub outer: Vec<OuterIteration>,
+    /// Why the outer loop stopped
+    pub termination: String,
+    /// Evaluations of the subproblems, summed over all of them (see
+    /// [`Report::counts`])
+    pub counts: BTreeMap<String, u64>,
+    /// Total time spent in the solver
+    #[serde(rename = "time_secs", serialize_with = "report::serialize_secs")]
+    pub time: Option<Duration>,
+}
+
+impl fmt::Display for ConstrainedReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "ConstrainedResult:")?;
+        writeln!(f, "    Problem:        {}", self.problem)?;
+        writeln!(
+            f,
+            "    Solver:         {} ({})",
+            self.solver,
+            self.method.name()
+        )?;
+        writeln!(f, "    param (best):   {:?}", self.param)?;
+        writeln!(f, "    cost (best):    {}", self.cost)?;
+        writeln!(f, "    violation:      {}", self.violation)?;
+        writeln!(f, "    outer iters:    {}", self.outer.len())?;
+        writeln!(f, "    termination:    {}", self.termination)?;
+        for (counter, n) in &self.counts {
+            writeln!(f, "    {:<15} {}", format!("{}:", counter), n)?;
+        }
+        if let Some(time) = self.time {
+            writeln!(f, "    time:           {:?}", time)?;
+        }
+        writeln!(f)?;
+        let mut table = Table::new(&["constraint", "kind", "value", "multiplier"]);
+        for c in &self.constraints {
+            table.push(vec![
+                c.name.clone(),
+                c.kind.name().to_string(),
+                format!("{:.6e}", c.value),
+                format!("{:.6e}", c.multiplier),
+            ]);
+        }
+        write!(f, "{}", table.markdown())?;
+        writeln!(f)?;
+        let mut table = Table::new(&[
+            "outer",
+            "weight",
+            "cost",
+            "violation",
+            "iters",
+            "termination",
+        ]);
+        for (k, outer) in self.outer.iter().enumerate() {
+            table.push(vec![
+                k.to_string(),
+                format!("{:.3e}", outer.weight),
+                format!("{:.6e}", outer.cost),
+                format!("{:.3e}", outer.violation),
+                outer.iterations.to_string(),
+                outer.termination.clone(),
+            ]);
+        }
+        write!(f, "{}", table.markdown())
+    }
+}
+
+/// The unconstrained subproblem of the outer loop
+struct Subproblem<C> {
+    problem: DynProblem,
+    constraints: Arc<C>,
+    method: PenaltyMethod,
+    /// `mu`, or `r` of the log barrier
+    weight: f64,
+    /// Multiplier estimates of the augmented Lagrangian, zero otherwise
+    lambda: Vec<f64>,
+    nu: Vec<f64>,
+}
+
+impl<C: Constraints<Param = Vec<f64>>> CostFunction for Subproblem<C> {
+    type Param = Vec<f64>;
+    type Output = f64;
+
+    fn cost(&self, p: &Vec<f64>) -> Result<f64, Error> {
+        let cost = self.problem.cost(p)?;
+        let h = self.constraints.equalities(p)?;
+        let g = self.constraints.inequalities(p)?;
+        let w = self.weight;
+        if self.method == PenaltyMethod::LogBarrier {
+            // The barrier is infinite outside the interior
+            if !g.iter().all(|&g| g < 0.0) {
+                return Ok(f64::INFINITY);
+            }
+            let barrier: f64 = g.iter().map(|g| -(-g).ln()).sum();
+            let penalty: f64 = h.iter().map(|h| h * h).sum();
+            return Ok(cost + w * barrier + penalty / (2.0 * w));
+        }
+        let equalities: f64 = h
+            .iter()
+            .zip(&self.lambda)
+            .map(|(h, l)| l * h + w / 2.0 * h * h)
+            .sum();
+        let inequalities: f64 = g
+            .iter()
+            .zip(&self.nu)
+            .map(|(g, n)| ((n + w * g).max(0.0).powi(2) - n * n) / (2.0 * w))
+            .sum();
+        Ok(cost + equalities + inequalities)
+    }
+}
+
+impl<C: Constraints<Param = Vec<f64>>> Gradient for Subproblem<C> {
+    type Param = Vec<f64>;
+    type Gradient = Vec<f64>;
+
+    fn gradient(&self, p: &Vec<f64>) -> Result<Vec<f64>, Error> {
+        let mut gradient = self.problem.gradient(p)?;
+        let h = self.constraints.equalities(p)?;
+        let g = self.constraints.inequalities(p)?;
+        // The factors of the constraint gradients in the gradient of the
+        // subproblem are the current multiplier estimates
+        let (eq, ineq) = multipliers(self.method, self.weight, &h, &g, &self.lambda, &self.nu);
+        let gradients = self.constraints.equality_gradients(p)?.into_iter().zip(eq);
+        let gradients = gradients.chain(
+            self.constraints
+                .inequality_gradients(p)?
+                .into_iter()
+                .zip(ineq),
+        );
+        for (dc, factor) in gradients {
+            if factor != 0.0 {
+                gradient
+                    .iter_mut()
+                    .zip(dc)
+                    .for_each(|(d, dc)| *d += factor * dc);
+            }
+        }
+        Ok(gradient)
+    }
+}
+
+/// Multiplier estimates at the solution of a subproblem with weight `w`
+/// and the multipliers `lambda` and `nu` of the augmented Lagrangian
+///
+/// These are also the factors of the constraint gradients in the gradient
+/// of the subproblem.
+fn multipliers(
+    method: PenaltyMethod,
+    w: f64,
+    h: &[f64],
+    g: &[f64],
+    lambda: &[f64],
+    nu: &[f64],
+) -> (Vec<f64>, Vec<f64>) {
+    match method {
+        PenaltyMethod::LogBarrier => (
+            h.iter().map(|h| h / w).collect(),
+            g.iter().map(|g| w / -g).collect(),
+        ),
+        _ => (
+            h.iter().zip(lambda).map(|(h, l)| l + w * h).collect(),
+            g.iter()
+                .zip(nu)
+                .map(|(g, n)| (n + w * g).max(0.0))
+                .collect(),
+        ),
+    }
+}
+
+/// Largest of `|h_j|` and `max(0, g_i)`
+fn violation(h: &[f64], g: &[f64]) -> f64 {
+    h.iter()
+        .map(|h| h.abs())
+        .chain(g.iter().map(|g| g.max(0.0)))
+        .fold(0.0, f64::max)
+}
+
+/// Solver options for the subproblems of the log barrier
+///
+/// More-Thuente and Hager-Zhang interpolate the cost along the search
+/// direction and fail on the infinite cost outside the interior, so they
+/// are replaced by backtracking with the Armijo condition, which shrinks
+/// the step until it is back inside. `c1`, the initial step and the
+/// contraction factor are kept; the backtracking line searches and solvers
+/// without a line search are used as given.
+pub fn barrier_solver(solver: &SolverOptions) -> SolverOptions {
+    let mut solver = solver.clone();
+    let linesearch = &mut solver.linesearch;
+    if matches!(
+        linesearch.method,
+        LineSearchMethod::MoreThuente | LineSearchMethod::HagerZhang
+    ) {
+        // Hager-Zhang's `c1` is its `delta`, which the Armijo condition
+        // does not share
+        if linesearch.method == LineSearchMethod::HagerZhang {
+            linesearch.c1 = None;
+        }
+        linesearch.method = LineSearchMethod::BacktrackingArmijo;
+        linesearch.c2 = None;
+        linesearch.step_bounds = None;
+    }
+    solver
+}
+
+/// Minimize `problem` subject to `constraints`
+///
+/// Every subproblem is solved by `solver` from the solution of the previous
+/// one, the first from `config.init_param`, with the stopping criteria of
+/// `config`. Trace, checkpoint and multi-start of `config` are not used; its
+/// history records the iterations of all subproblems. The log barrier
+/// solves its subproblems with [`barrier_solver`]`(solver)`.
+pub fn solve_constrained<C>(
+    problem: &DynProblem,
+    constraints: C,
+    solver: &SolverOptions,
+    config: &RunConfig,
+    options: &OuterOptions,
+) -> Result<ConstrainedReport, Error>
+where
+    C: Constraints<Param = Vec<f64>> + Send + Sync + 'static,
+{
+    options.validate()?;
+    let info = problem.info();
+    let constraints = Arc::new(constraints);
+    let method = options.method;
+    let mut x = info.project(&config.init_param);
+    let mut h = constraints.equalities(&x)?;
+    let mut g = constraints.inequalities(&x)?;
+    if method == PenaltyMethod::LogBarrier && !g.iter().all(|&g| g < 0.0) {
+        return Err(ArgminError::InvalidParameter {
+            text: "log-barrier needs a starting point which strictly satisfies every \
+                   inequality constraint"
+                .to_string(),
+        }
+        .into());
+    }
+
+    let mut weight = match method {
+        PenaltyMethod::LogBarrier => 1.0 / options.penalty,
+        _ => options.penalty,
+    };
+    let subsolver = match method {
+        PenaltyMethod::LogBarrier => barrier_solver(solver),
+        _ => solver.clone(),
+    };
+    let (mut lambda, mut nu) = (vec![0.0; h.len()], vec![0.0; g.len()]);
+    let mut previous = violation(&h, &g);
+    let mut outer = Vec::new();
+    let mut counts: BTreeMap<String, u64> = BTreeMap::new();
+    let mut time: Option<Duration> = None;
+    let mut termination = "Maximum number of outer iterations reached".to_string();
+    for _ in 0..options.outer_iters {
+        // Only the augmented Lagrangian has multipliers in its subproblems
+        if method != PenaltyMethod::AugmentedLagrangian {
+            lambda
+                .iter_mut()
+                .chain(nu.iter_mut())
+                .for_each(|m| *m = 0.0);
+        }
+        let subproblem = Subproblem {
+            problem: problem.clone(),
+            constraints: constraints.clone(),
+            method,
+            weight,
+            lambda: lambda.clone(),
+            nu: nu.clone(),
+        };
+        let run = RunConfig {
+            init_param: x.clone(),
+            stop: config.stop.clone(),
+            trace: None,
+            checkpoint: None,
+            contour: None,
+            convergence: None,
+            multistart: None,
+            outer: None,
+            history: config.history.clone(),
+        };
+        let subproblem = DynProblem::new(subproblem, info.clone());
+        let report: Report = solver::solve(subproblem, &subsolver, &run).map_err(|err| {
+            match method {
+                // The barrier is infinite outside the feasible region, which
+                // not every solver can step back from
+                PenaltyMethod::LogBarrier => ArgminError::InvalidParameter {
+                    text: format!(
+                        "log-barrier subproblem failed: {}; try a solver with a line search \
+                         or a derivative-free solver",
+                        err
+                    ),
+                }
+                .into(),
+                _ => err,
+            }
+        })?;
+        for (counter, n) in &report.counts {
+            *counts.entry(counter.clone()).or_insert(0) += n;
+        }
+        if let Some(t) = report.time {
+            time = Some(time.unwrap_or_default() + t);
+        }
+
+        x = report.param;
+        h = constraints.equalities(&x)?;
+        g = constraints.inequalities(&x)?;
+        let current = violation(&h, &g);
+        let estimates = multipliers(method, weight, &h, &g, &lambda, &nu);
+        outer.push(OuterIteration {
+            weight,
+            cost: problem.cost(&x)?,
+            violation: current,
+            iterations: report.iterations,
+            termination: report.termination,
+        });
+        (lambda, nu) = estimates;
+
+        let gap = match method {
+            PenaltyMethod::LogBarrier => weight * g.len() as f64,
+            _ => 0.0,
+        };
+        if current <= options.constraint_tol && gap <= options.constraint_tol {
+            termination = format!(
+                "Constraint violation {} reached tolerance {}",
+                current, options.constraint_tol
+            );
+            break;
+        }
+        match method {
+            PenaltyMethod::QuadraticPenalty => weight *= options.penalty_growth,
+            PenaltyMethod::LogBarrier => weight /= options.penalty_growth,
+            // Only grow the penalty if the multipliers alone do not reduce
+            // the violation fast enough
+            PenaltyMethod::AugmentedLagrangian => {
+                if current > 0.25 * previous {
+                    weight *= options.penalty_growth;
+                }
+            }
+        }
+        previous = current;
+    }
+
+    let kinds = [ConstraintKind::Equality, ConstraintKind::Inequality];
+    let values = kinds
+        .into_iter()
+        .zip([(&h, &lambda), (&g, &nu)])
+        .flat_map(|(kind, (values, multipliers))| {
+            let constraints = &constraints;
+            values
+                .iter()
+                .zip(multipliers)
+                .enumerate()
+                .map(move |(k, (&value, &multiplier))| ConstraintValue {
+                    name: constraints.constraint_name(kind, k),
+                    kind,
+                    value,
+                    violation: match kind {
+                        ConstraintKind::Equality => value.abs(),
+                        ConstraintKind::Inequality => value.max(0.0),
+                    },
+                    multiplier,
+                })
+        })
+        .collect();
+    Ok(ConstrainedReport {
+        problem: info.name.clone(),
+        solver: solver.method.name().to_string(),
+        method,
+        cost: problem.cost(&x)?,
+        violation: violation(&h, &g),
+        param: x,
+        constraints: values,
+        outer,
+        termination,
+        counts,
+        time,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::solver::{LineSearchOptions, Method};
+    use crate::stopping::StopCriteria;
+
+    fn variables() -> Vec<String> {
+        vec!["x1".to_string(), "x2".to_string()]
+    }
+
+    fn constraints(sources: &[&str]) -> ConstraintSet {
+        let sources: Vec<String> = sources.iter().map(|s| s.to_string()).collect();
+        ConstraintSet::parse(&sources, &variables(), &BTreeMap::new()).unwrap()
+    }
+
+    /// `x1^2 + x2^2`, whose minimum subject to `x1 + x2 = 1` (or `>= 1`) is
+    /// `(0.5, 0.5)` with multiplier `-1` (or `1`)
+    fn sphere() -> DynProblem {
+        Expression::parse("x1^2 + x2^2", Some(&variables()), &BTreeMap::new())
+            .unwrap()
+            .into_dyn("sphere")
+    }
+
+    fn solve_with(
+        sources: &[&str],
+        solver: &SolverOptions,
+        method: PenaltyMethod,
+        x0: Vec<f64>,
+    ) -> Result<ConstrainedReport, Error> {
+        let config = RunConfig {
+            init_param: x0,
+            stop: StopCriteria {
+                max_iters: 1000,
+                gradient_tol: Some(1e-10),
+                ..StopCriteria::default()
+            },
+            ..RunConfig::default()
+        };
+        let options = OuterOptions {
+            method,
+            ..OuterOptions::default()
+        };
+        solve_constrained(&sphere(), constraints(sources), solver, &config, &options)
+    }
+
+    fn assert_close(a: &[f64], b: &[f64], tol: f64) {
+        assert!(a.iter().zip(b).all(|(a, b)| (a - b).abs() <= tol), "{:?} != {:?}", a, b);
+    }
+
+    #[test]
+    fn method_names_round_trip() {
+        for &method in PenaltyMethod::ALL {
+            assert_eq!(PenaltyMethod::from_name(method.name()), Some(method));
+        }
+        assert_eq!(PenaltyMethod::from_name("barrier"), None);
+    }
+
+    #[test]
+    fn relations_become_equalities_and_inequalities() {
+        let p = vec![2.0, 3.0];
+        for (source, kind, value, gradient) in [
+            ("x1 + x2 = 1", ConstraintKind::Equality, 4.0, [1.0, 1.0]),
+            ("x1 + x2 == 1", ConstraintKind::Equality, 4.0, [1.0, 1.0]),
+            ("x1 * x2 <= 1", ConstraintKind::Inequality, 5.0, [3.0, 2.0]),
+            ("x1 >= x2^2", ConstraintKind::Inequality, 7.0, [-1.0, 6.0]),
+        ] {
+            let constraint = Constraint::parse(source, &variables(), &BTreeMap::new()).unwrap();
+            assert_eq!(constraint.kind, kind, "{}", source);
+            assert_eq!(constraint.value(&p).unwrap(), value, "{}", source);
+            assert_eq!(constraint.gradient(&p).unwrap(), gradient, "{}", source);
+        }
+        let parameters = BTreeMap::from([("r".to_string(), 2.0)]);
+        let circle = Constraint::parse("x1^2 + x2^2 <= r^2", &variables(), &parameters).unwrap();
+        assert_eq!(circle.value(&p).unwrap(), 9.0);
+
+        let set = constraints(&["x1 >= 0", "x1 + x2 = 1", "x2 <= 2"]);
+        assert_eq!(set.equalities(&p).unwrap(), [4.0]);
+        assert_eq!(set.inequalities(&p).unwrap(), [-2.0, 1.0]);
+        assert_eq!(set.constraint_name(ConstraintKind::Inequality, 1), "x2 <= 2");
+    }
+
+    #[test]
+    fn malformed_constraints_are_rejected() {
+        for (source, message) in [
+            ("x1 + x2", "needs exactly one of"),
+            ("x1 < 1", "needs exactly one of"),
+            ("0 <= x1 <= 1", "needs exactly one of"),
+            ("x1 = 1 = x2", "needs exactly one of"),
+            (" <= 1", "has an empty side"),
+            ("x1 + <= 1", "at column"),
+        ] {
+            let err = Constraint::parse(source, &variables(), &BTreeMap::new()).unwrap_err();
+            assert!(err.to_string().contains(message), "{}: {}", source, err);
+        }
+        assert!(Constraint::parse("x3 <= 1", &variables(), &BTreeMap::new()).is_err());
+    }
+
+    #[test]
+    fn invalid_outer_options_are_rejected() {
+        assert!(OuterOptions::default().validate().is_ok());
+        for (options, field) in [
+            (OuterOptions { outer_iters: 0, ..OuterOptions::default() }, "outer_iters"),
+            (OuterOptions { penalty: 0.0, ..OuterOptions::default() }, "penalty must"),
+            (OuterOptions { penalty_growth: 1.0, ..OuterOptions::default() }, "penalty_growth"),
+            (
+                OuterOptions { constraint_tol: f64::NAN, ..OuterOptions::default() },
+                "constraint_tol",
+            ),
+        ] {
+            let err = options.validate().unwrap_err();
+            assert!(err.to_string().contains(field), "{}", err);
+        }
+    }
+
+    #[test]
+    fn penalty_methods_solve_an_equality_constraint() {
+        let lbfgs = SolverOptions::new(Method::Lbfgs);
+        for method in [PenaltyMethod::QuadraticPenalty, PenaltyMethod::AugmentedLagrangian] {
+            let report = solve_with(&["x1 + x2 = 1"], &lbfgs, method, vec![2.0, -1.0]).unwrap();
+            assert!(report.termination.starts_with("Constraint violation"), "{}", report);
+            assert!(report.violation <= 1e-6, "{}", report);
+            assert_close(&report.param, &[0.5, 0.5], 1e-5);
+            assert!((report.cost - 0.5).abs() <= 1e-5, "{}", report);
+            assert_eq!(report.constraints.len(), 1);
+            assert_eq!(report.constraints[0].name, "x1 + x2 = 1");
+            assert!((report.constraints[0].multiplier + 1.0).abs() <= 1e-3, "{}", report);
+        }
+    }
+
+    #[test]
+    fn augmented_lagrangian_keeps_the_penalty_moderate() {
+        let lbfgs = SolverOptions::new(Method::Lbfgs);
+        let method = PenaltyMethod::AugmentedLagrangian;
+        let report = solve_with(&["x1 + x2 = 1"], &lbfgs, method, vec![2.0, -1.0]).unwrap();
+        let quadratic = PenaltyMethod::QuadraticPenalty;
+        let penalty = solve_with(&["x1 + x2 = 1"], &lbfgs, quadratic, vec![2.0, -1.0]).unwrap();
+        assert!(report.outer.len() < penalty.outer.len(), "{}\n{}", report, penalty);
+        let last = |r: &ConstrainedReport| r.outer.last().unwrap().weight;
+        assert!(last(&report) < last(&penalty), "{}\n{}", report, penalty);
+    }
+
+    #[test]
+    fn inactive_inequalities_do_not_move_the_minimum() {
+        let lbfgs = SolverOptions::new(Method::Lbfgs);
+        for &method in PenaltyMethod::ALL {
+            let report = solve_with(&["x1 + x2 >= -1"], &lbfgs, method, vec![2.0, 1.0]).unwrap();
+            assert_close(&report.param, &[0.0, 0.0], 1e-3);
+            assert!(report.constraints[0].multiplier.abs() <= 1e-3, "{}", report);
+        }
+    }
+
+    #[test]
+    fn log_barrier_works_with_the_default_line_search() {
+        // Steepest descent and L-BFGS default to More-Thuente, which cannot
+        // handle the infinite cost outside the interior by itself
+        let method = PenaltyMethod::LogBarrier;
+        for solver in [SolverOptions::default(), SolverOptions::new(Method::Lbfgs)] {
+            let report = solve_with(&["x1 + x2 >= 1"], &solver, method, vec![2.0, 3.0]).unwrap();
+            assert!(report.termination.starts_with("Constraint violation"), "{}", report);
+            assert_close(&report.param, &[0.5, 0.5], 1e-3);
+            // Every subproblem stays in the interior
+            assert!(report.outer.iter().all(|o| o.violation == 0.0), "{}", report);
+        }
+        // Steepest descent solves the ill-conditioned subproblems too roughly
+        // for an accurate multiplier
+        let lbfgs = SolverOptions::new(Method::Lbfgs);
+        let report = solve_with(&["x1 + x2 >= 1"], &lbfgs, method, vec![2.0, 3.0]).unwrap();
+        assert!((report.constraints[0].multiplier - 1.0).abs() <= 1e-3, "{}", report);
+    }
+
+    #[test]
+    fn log_barrier_needs_an_interior_start() {
+        let solver = SolverOptions::default();
+        for x0 in [vec![0.0, 0.0], vec![0.5, 0.5]] {
+            let result = solve_with(&["x1 + x2 >= 1"], &solver, PenaltyMethod::LogBarrier, x0);
+            let err = result.err().unwrap();
+            assert!(err.to_string().contains("strictly satisfies"), "{}", err);
+        }
+    }
+
+    #[test]
+    fn barrier_solver_replaces_interpolating_line_searches() {
+        let mut solver = SolverOptions {
+            linesearch: LineSearchOptions {
+                c1: Some(1e-3),
+                c2: Some(0.5),
+                initial_step: Some(0.5),
+                step_bounds: Some((1e-8, 10.0)),
+                ..LineSearchOptions::new(LineSearchMethod::MoreThuente)
+            },
+            ..SolverOptions::default()
+        };
+        let linesearch = barrier_solver(&solver).linesearch;
+        assert_eq!(linesearch.method, LineSearchMethod::BacktrackingArmijo);
+        assert_eq!(linesearch.c1, Some(1e-3));
+        assert_eq!(linesearch.initial_step, Some(0.5));
+        assert_eq!((linesearch.c2, linesearch.step_bounds), (None, None));
+
+        solver.linesearch.method = LineSearchMethod::HagerZhang;
+        let linesearch = barrier_solver(&solver).linesearch;
+        assert_eq!(linesearch.method, LineSearchMethod::BacktrackingArmijo);
+        assert_eq!(linesearch.c1, None);
+
+        solver.linesearch.method = LineSearchMethod::BacktrackingWolfe;
+        let linesearch = barrier_solver(&solver).linesearch;
+        assert_eq!(linesearch.method, LineSearchMethod::BacktrackingWolfe);
+        assert_eq!(linesearch.c2, Some(0.5));
+    }
+}
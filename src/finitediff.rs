@@ -0,0 +1,492 @@
+//! Finite-difference derivatives
+//!
+//! [`FiniteDiff`] approximates gradients, Jacobians and Hessians from
+//! function values, so that problems without hand-written derivatives can
+//! still be solved by the gradient based solvers. [`Numeric`] wraps any
+//! `CostFunction` and provides `Gradient` and `Hessian` this way;
+//! [`ComplexStep`] does the same for problems which can be evaluated at
+//! complex arguments ([`ComplexCost`]).
+//!
+//! Steps are relative: component `i` is perturbed by `step * max(1, |x_i|)`.
+//! The default steps balance truncation and rounding error for each scheme:
+//!
+//! | scheme         | gradient step     | Hessian step      |
+//! |----------------|-------------------|-------------------|
+//! | `forward`      | `sqrt(eps)`       | `cbrt(eps)`       |
+//! | `central`      | `cbrt(eps)`       | `eps^(1/4)`       |
+//! | `complex-step` | `1e-20`           | `cbrt(eps)`       |
+//!
+//! With complex steps, the Hessian is the central difference of the (exact)
+//! complex step gradient.
+
+use crate::scalar::{Complex, GenericCost};
+use argmin::core::{ArgminError, CostFunction, Error, Gradient, Hessian};
+use serde::{Deserialize, Serialize};
+
+/// Finite-difference schemes which can be selected by name
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Scheme {
+    /// `(f(x + h) - f(x)) / h`, error `O(h)`
+    Forward,
+    /// `(f(x + h) - f(x - h)) / 2h`, error `O(h^2)`
+    #[default]
+    Central,
+    /// `Im(f(x + ih)) / h`, exact up to rounding; requires [`ComplexCost`]
+    ComplexStep,
+}
+
+impl Scheme {
+    /// All schemes, in the order in which they are listed
+    pub const ALL: &'static [Scheme] = &[Scheme::Forward, Scheme::Central, Scheme::ComplexStep];
+
+    /// Name used to select the scheme
+    pub fn name(self) -> &'static str {
+        match self {
+            Scheme::Forward => "forward",
+            Scheme::Central => "central",
+            Scheme::ComplexStep => "complex-step",
+        }
+    }
+
+    /// Look up a scheme by its name
+    pub fn from_name(name: &str) -> Option<Scheme> {
+        Scheme::ALL.iter().copied().find(|s| s.name() == name)
+    }
+}
+
+/// A cost function which can be evaluated at complex arguments
+///
+/// Implemented for every [`GenericCost`]; type-erased problems implement it
+/// directly.
+pub trait ComplexCost {
+    /// Evaluate the cost function at `p`
+    fn cost_complex(&self, p: &[Complex]) -> Result<Complex, Error>;
+}
+
+impl<O: GenericCost> ComplexCost for O {
+    fn cost_complex(&self, p: &[Complex]) -> Result<Complex, Error> {
+        self.cost_generic(p)
+    }
+}
+
+/// Finite-difference scheme and step sizes
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct FiniteDiff {
+    /// Scheme used for gradients and Jacobians
+    pub scheme: Scheme,
+    /// Relative step of gradients and Jacobians (default depends on `scheme`)
+    pub step: Option<f64>,
+    /// Relative step of Hessians (default depends on `scheme`)
+    pub hessian_step: Option<f64>,
+}
+
+impl FiniteDiff {
+    /// Differences with `scheme` and the default steps
+    pub fn new(scheme: Scheme) -> Self {
+        FiniteDiff {
+            scheme,
+            step: None,
+            hessian_step: None,
+        }
+    }
+
+    /// Relative step of gradients and Jacobians
+    pub fn step(&self) -> f64 {
+        self.step.unwrap_or(match self.scheme {
+            Scheme::Forward => f64::EPSILON.sqrt(),
+            Scheme::Central => f64::EPSILON.cbrt(),
+            Scheme::ComplexStep => 1e-20,
+        })
+    }
+
+    /// Relative step of Hessians
+    pub fn hessian_step(&self) -> f64 {
+        self.hessian_step.unwrap_or(match self.scheme {
+            Scheme::Forward | Scheme::ComplexStep => f64::EPSILON.cbrt(),
+            Scheme::Central => f64::EPSILON.powf(0.25),
+        })
+    }
+
+    /// Check that the steps are positive and finite
+    pub fn validate(&self) -> Result<(), Error> {
+        for (name, step) in [("step", self.step), ("Hessian step", self.hessian_step)] {
+            if step.is_some_and(|h| !(h.is_finite() && h > 0.0)) {
+                return Err(ArgminError::InvalidParameter {
+                    text: format!("finite differences: {} must be positive", name),
+                }
+                .into());
+            }
+        }
+        Ok(())
+    }
+
+    /// Gradient of `problem` at `x`
+    ///
+    /// Complex steps are not available for plain cost functions; use
+    /// [`FiniteDiff::complex_gradient`] for those.
+    pub fn gradient<O>(&self, problem: &O, x: &[f64]) -> Result<Vec<f64>, Error>
+    where
+        O: CostFunction<Param = Vec<f64>, Output = f64>,
+    {
+        let jacobian = self.jacobian(|p| Ok(vec![problem.cost(&p.to_vec())?]), x)?;
+        Ok(jacobian.into_iter().next().unwrap_or_default())
+    }
+
+    /// Gradient of `problem` at `x` by complex steps
+    pub fn complex_gradient<O: ComplexCost + ?Sized>(
+        &self,
+        problem: &O,
+        x: &[f64],
+    ) -> Result<Vec<f64>, Error> {
+        let jacobian = self.complex_jacobian(|p| Ok(vec![problem.cost_complex(p)?]), x)?;
+        Ok(jacobian.into_iter().next().unwrap_or_default())
+    }
+
+    /// Jacobian of `f` at `x`; row `i` is the gradient of output `i`
+    ///
+    /// Uses forward or central differences; complex steps fall back to
+    /// central differences since `f` is only defined for real arguments.
+    pub fn jacobian<F>(&self, f: F, x: &[f64]) -> Result<Vec<Vec<f64>>, Error>
+    where
+        F: Fn(&[f64]) -> Result<Vec<f64>, Error>,
+    {
+        let step = self.step.unwrap_or(match self.scheme {
+            Scheme::Forward => f64::EPSILON.sqrt(),
+            Scheme::Central | Scheme::ComplexStep => f64::EPSILON.cbrt(),
+        });
+        let fx = match self.scheme {
+            Scheme::Forward => Some(f(x)?),
+            Scheme::Central | Scheme::ComplexStep => None,
+        };
+        let mut p = x.to_vec();
+        let mut columns = Vec::with_capacity(x.len());
+        for i in 0..x.len() {
+            let h = step * x[i].abs().max(1.0);
+            p[i] = x[i] + h;
+            let forward = f(&p)?;
+            let column: Vec<f64> = match &fx {
+                Some(fx) => forward.iter().zip(fx).map(|(a, b)| (a - b) / h).collect(),
+                None => {
+                    p[i] = x[i] - h;
+                    let backward = f(&p)?;
+                    forward
+                        .iter()
+                        .zip(&backward)
+                        .map(|(a, b)| (a - b) / (2.0 * h))
+                        .collect()
+                }
+            };
+            p[i] = x[i];
+            columns.push(column);
+        }
+        Ok(transpose(columns))
+    }
+
+    /// Jacobian of `f` at `x` by complex steps
+    pub fn complex_jacobian<F>(&self, f: F, x: &[f64]) -> Result<Vec<Vec<f64>>, Error>
+    where
+        F: Fn(&[Complex]) -> Result<Vec<Complex>, Error>,
+    {
+        let step = self.step.unwrap_or(1e-20);
+        let mut p: Vec<Complex> = x.iter().copied().map(Complex::real).collect();
+        let mut columns = Vec::with_capacity(x.len());
+        for i in 0..x.len() {
+            let h = step * x[i].abs().max(1.0);
+            p[i].im = h;
+            columns.push(f(&p)?.iter().map(|y| y.im / h).collect());
+            p[i].im = 0.0;
+        }
+        Ok(transpose(columns))
+    }
+
+    /// Hessian of `problem` at `x` from second differences of the cost
+    ///
+    /// Forward differences need `n (n + 3) / 2 + 1` cost evaluations, central
+    /// differences `2 n^2 + 1`.
+    pub fn hessian<O>(&self, problem: &O, x: &[f64]) -> Result<Vec<Vec<f64>>, Error>
+    where
+        O: CostFunction<Param = Vec<f64>, Output = f64>,
+    {
+        let n = x.len();
+        let step = self.hessian_step();
+        let h: Vec<f64> = x.iter().map(|xi| step * xi.abs().max(1.0)).collect();
+        // Cost at `x` displaced by `si * h_i` and `sj * h_j`
+        let f = |i: usize, si: f64, j: usize, sj: f64| -> Result<f64, Error> {
+            let mut p = x.to_vec();
+            p[i] += si * h[i];
+            p[j] += sj * h[j];
+            problem.cost(&p)
+        };
+        let fx = problem.cost(&x.to_vec())?;
+        let mut hessian = vec![vec![0.0; n]; n];
+        match self.scheme {
+            Scheme::Forward => {
+                let single = (0..n)
+                    .map(|i| f(i, 1.0, i, 0.0))
+                    .collect::<Result<Vec<f64>, Error>>()?;
+                for i in 0..n {
+                    for j in i..n {
+                        let fij = f(i, 1.0, j, 1.0)?;
+                        hessian[i][j] = (fij - single[i] - single[j] + fx) / (h[i] * h[j]);
+                        hessian[j][i] = hessian[i][j];
+                    }
+                }
+            }
+            Scheme::Central | Scheme::ComplexStep => {
+                for i in 0..n {
+                    let (fp, fm) = (f(i, 1.0, i, 0.0)?, f(i, -1.0, i, 0.0)?);
+                    hessian[i][i] = (fp - 2.0 * fx + fm) / (h[i] * h[i]);
+                    for j in i + 1..n {
+                        let d = f(i, 1.0, j, 1.0)? - f(i, 1.0, j, -1.0)? - f(i, -1.0, j, 1.0)?
+                            + f(i, -1.0, j, -1.0)?;
+                        hessian[i][j] = d / (4.0 * h[i] * h[j]);
+                        hessian[j][i] = hessian[i][j];
+                    }
+                }
+            }
+        }
+        Ok(hessian)
+    }
+
+    /// Hessian at `x` as the Jacobian of `gradient`, made symmetric
+    ///
+    /// Uses the Hessian step; complex steps use central differences.
+    pub fn hessian_from_gradient<F>(&self, gradient: F, x: &[f64]) -> Result<Vec<Vec<f64>>, Error>
+    where
+        F: Fn(&[f64]) -> Result<Vec<f64>, Error>,
+    {
+        let outer = FiniteDiff {
+            scheme: match self.scheme {
+                Scheme::Forward => Scheme::Forward,
+                Scheme::Central | Scheme::ComplexStep => Scheme::Central,
+            },
+            step: Some(self.hessian_step()),
+            hessian_step: None,
+        };
+        let jacobian = outer.jacobian(gradient, x)?;
+        let n = jacobian.len();
+        Ok((0..n)
+            .map(|i| (0..n).map(|j| 0.5 * (jacobian[i][j] + jacobian[j][i])).collect())
+            .collect())
+    }
+}
+
+/// Turn a list of columns into a list of rows
+fn transpose(columns: Vec<Vec<f64>>) -> Vec<Vec<f64>> {
+    let rows = columns.first().map_or(0, Vec::len);
+    (0..rows)
+        .map(|i| columns.iter().map(|column| column[i]).collect())
+        .collect()
+}
+
+/// A cost function with finite-difference gradient and Hessian
+///
+/// Uses forward or central differences; see [`ComplexStep`] for complex
+/// steps. The gradient costs `n` (forward) or `2 n` (central) evaluations of
+/// the cost function.
+#[derive(Clone, Debug)]
+pub struct Numeric<O> {
+    /// The wrapped cost function
+    pub problem: O,
+    /// Scheme and step sizes
+    pub diff: FiniteDiff,
+}
+
+impl<O> Numeric<O> {
+    /// Differentiate `problem` numerically with `diff`
+    pub fn new(problem: O, diff: FiniteDiff) -> Self {
+        Numeric { problem, diff }
+    }
+}
+
+impl<O> CostFunction for Numeric<O>
+where
+    O: CostFunction<Param = Vec<f64>, Output = f64>,
+{
+    type Param = Vec<f64>;
+    type Output = f64;
+
+    fn cost(&self, p: &Self::Param) -> Result<Self::Output, Error> {
+        self.problem.cost(p)
+    }
+}
+
+impl<O> Gradient for Numeric<O>
+where
+    O: CostFunction<Param = Vec<f64>, Output = f64>,
+{
+    type Param = Vec<f64>;
+    type Gradient = Vec<f64>;
+
+    fn gradient(&self, p: &Self::Param) -> Result<Self::Gradient, Error> {
+        self.diff.gradient(&self.problem, p)
+    }
+}
+
+impl<O> Hessian for Numeric<O>
+where
+    O: CostFunction<Param = Vec<f64>, Output = f64>,
+{
+    type Param = Vec<f64>;
+    type Hessian = Vec<Vec<f64>>;
+
+    fn hessian(&self, p: &Self::Param) -> Result<Self::Hessian, Error> {
+        self.diff.hessian(&self.problem, p)
+    }
+}
+
+/// A cost function with complex step gradient and Hessian
+///
+/// The gradient is exact up to rounding and costs `n` complex evaluations.
+#[derive(Clone, Debug)]
+pub struct ComplexStep<O> {
+    /// The wrapped cost function
+    pub problem: O,
+    /// Step sizes (the scheme is ignored)
+    pub diff: FiniteDiff,
+}
+
+impl<O> ComplexStep<O> {
+    /// Differentiate `problem` by complex steps of the sizes in `diff`
+    pub fn new(problem: O, diff: FiniteDiff) -> Self {
+        ComplexStep {
+            problem,
+            diff: FiniteDiff {
+                scheme: Scheme::ComplexStep,
+                ..diff
+            },
+        }
+    }
+}
+
+impl<O> CostFunction for ComplexStep<O>
+where
+    O: CostFunction<Param = Vec<f64>, Output = f64> + ComplexCost,
+{
+    type Param = Vec<f64>;
+    type Output = f64;
+
+    fn cost(&self, p: &Self::Param) -> Result<Self::Output, Error> {
+        self.problem.cost(p)
+    }
+}
+
+impl<O> Gradient for ComplexStep<O>
+where
+    O: CostFunction<Param = Vec<f64>, Output = f64> + ComplexCost,
+{
+    type Param = Vec<f64>;
+    type Gradient = Vec<f64>;
+
+    fn gradient(&self, p: &Self::Param) -> Result<Self::Gradient, Error> {
+        self.diff.complex_gradient(&self.problem, p)
+    }
+}
+
+impl<O> Hessian for ComplexStep<O>
+where
+    O: CostFunction<Param = Vec<f64>, Output = f64> + ComplexCost,
+{
+    type Param = Vec<f64>;
+    type Hessian = Vec<Vec<f64>>;
+
+    fn hessian(&self, p: &Self::Param) -> Result<Self::Hessian, Error> {
+        self.diff
+            .hessian_from_gradient(|x| self.diff.complex_gradient(&self.problem, x), p)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::problem::Rosenbrock;
+
+    const X: [f64; 2] = [-1.2, 1.0];
+
+    /// Largest absolute difference relative to the magnitude of `exact`
+    fn error(approx: &[f64], exact: &[f64]) -> f64 {
+        approx
+            .iter()
+            .zip(exact)
+            .map(|(a, e)| (a - e).abs() / e.abs().max(1.0))
+            .fold(0.0, f64::max)
+    }
+
+    #[test]
+    fn names_round_trip() {
+        for &scheme in Scheme::ALL {
+            assert_eq!(Scheme::from_name(scheme.name()), Some(scheme));
+        }
+        assert_eq!(Scheme::from_name("backward"), None);
+    }
+
+    #[test]
+    fn invalid_steps_are_rejected() {
+        for step in [0.0, -1e-6, f64::NAN, f64::INFINITY] {
+            let diff = FiniteDiff {
+                step: Some(step),
+                ..FiniteDiff::new(Scheme::Central)
+            };
+            assert!(diff.validate().is_err(), "{}", step);
+            let diff = FiniteDiff {
+                hessian_step: Some(step),
+                ..FiniteDiff::new(Scheme::Central)
+            };
+            assert!(diff.validate().is_err(), "{}", step);
+        }
+        assert!(FiniteDiff::new(Scheme::Forward).validate().is_ok());
+    }
+
+    #[test]
+    fn gradients_match_the_analytic_gradient() {
+        let problem = Rosenbrock::default();
+        let exact = problem.gradient(&X.to_vec()).unwrap();
+        let forward = FiniteDiff::new(Scheme::Forward).gradient(&problem, &X).unwrap();
+        let central = FiniteDiff::new(Scheme::Central).gradient(&problem, &X).unwrap();
+        let complex = FiniteDiff::new(Scheme::ComplexStep)
+            .complex_gradient(&problem, &X)
+            .unwrap();
+        assert!(error(&forward, &exact) < 1e-5);
+        assert!(error(&central, &exact) < 1e-8);
+        assert!(error(&complex, &exact) < 1e-13);
+        assert!(error(&central, &exact) < error(&forward, &exact));
+    }
+
+    #[test]
+    fn hessians_match_the_analytic_hessian() {
+        let problem = Rosenbrock::default();
+        let exact = problem.hessian(&X.to_vec()).unwrap();
+        let gradient = |p: &[f64]| problem.gradient(&p.to_vec());
+        for scheme in [Scheme::Forward, Scheme::Central] {
+            let diff = FiniteDiff::new(scheme);
+            let from_cost = diff.hessian(&problem, &X).unwrap();
+            let from_gradient = diff.hessian_from_gradient(gradient, &X).unwrap();
+            for i in 0..2 {
+                assert!(error(&from_cost[i], &exact[i]) < 1e-3, "{}", scheme.name());
+                assert!(error(&from_gradient[i], &exact[i]) < 1e-4, "{}", scheme.name());
+            }
+        }
+    }
+
+    #[test]
+    fn jacobian_rows_are_the_gradients_of_the_outputs() {
+        let f = |p: &[f64]| Ok(vec![p[0] * p[1], p[0].sin(), 3.0]);
+        let jacobian = FiniteDiff::new(Scheme::Central).jacobian(f, &X).unwrap();
+        let exact = [[X[1], X[0]], [X[0].cos(), 0.0], [0.0, 0.0]];
+        assert_eq!(jacobian.len(), 3);
+        for (row, exact) in jacobian.iter().zip(&exact) {
+            assert!(error(row, exact) < 1e-9, "{:?}", jacobian);
+        }
+    }
+
+    #[test]
+    fn numeric_problem_keeps_the_cost_function() {
+        let exact = Rosenbrock::default();
+        let problem = Numeric::new(exact, FiniteDiff::new(Scheme::Central));
+        let x = X.to_vec();
+        assert_eq!(problem.cost(&x).unwrap(), exact.cost(&x).unwrap());
+        let gradient = problem.gradient(&x).unwrap();
+        assert!(error(&gradient, &exact.gradient(&x).unwrap()) < 1e-8);
+    }
+}
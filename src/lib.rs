@@ -0,0 +1,299 @@
+//! Optimization experiments built on top of argmin
+//!
+//! The crate exposes the test problems ([`problem`]), helpers to build the
+//! solvers ([`solver`]) and [`run`], which executes a solver on a problem.
+//! The `opt` binary is a thin command line interface on top of this library.
+
+pub mod autodiff;
+pub mod bench;
+pub mod backend;
+pub mod check;
+pub mod checkpoint;
+pub mod config;
+pub mod constraint;
+pub mod finitediff;
+pub mod multistart;
+pub mod parallel;
+pub mod plot;
+pub mod problem;
+pub mod profile;
+pub mod report;
+pub mod scalar;
+pub mod solver;
+pub mod stopping;
+pub mod trace;
+
+pub use report::{Record, Report};
+
+use argmin::core::observers::ObserverMode;
+use argmin::core::{Error, Executor, IterState, OptimizationResult, PopulationState, Solver};
+use argmin::solver::particleswarm::Particle;
+use backend::Vector;
+use checkpoint::CheckpointOptions;
+use constraint::OuterOptions;
+use multistart::MultiStartOptions;
+use plot::contour::ContourOptions;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use stopping::{StopCriteria, StopState, Stopping};
+use trace::{History, Trace, TraceOptions, TraceState};
+
+/// State used by the gradient based solvers
+///
+/// Parameter vector and gradient are of type `P` (`Vec<f64>` unless another
+/// [`backend`] is used) and cost function values are `f64`. `H` is the type
+/// of the (inverse) Hessian: `Vec<Vec<f64>>` (or the matrix type of the
+/// backend) for solvers which use it, `()` for the others.
+pub type GradientState<H, P = Vec<f64>> = IterState<P, P, (), H, f64>;
+
+/// State used by the derivative free solvers working on a single parameter
+/// vector (Nelder-Mead and simulated annealing)
+pub type CostState<P = Vec<f64>> = IterState<P, (), (), (), f64>;
+
+/// State used by particle swarm optimization
+///
+/// Population based solvers do not operate on an `IterState` but on a
+/// `PopulationState`, whose parameter is a whole particle (position,
+/// velocity and cost) rather than a parameter vector.
+pub type SwarmState<P = Vec<f64>> = PopulationState<Particle<P, f64>, f64>;
+
+/// Outcome of a run: argmin's `OptimizationResult`, whose solver is wrapped
+/// in [`Stopping`]
+pub type RunResult<O, S, I> = OptimizationResult<O, Stopping<S>, I>;
+
+/// Settings of a single optimization run
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RunConfig {
+    /// Initial parameter vector (converted to the parameter type of the solver)
+    pub init_param: Vec<f64>,
+    /// When the run stops
+    pub stop: StopCriteria,
+    /// Write a per-iteration trace (not written if `None`)
+    pub trace: Option<TraceOptions>,
+    /// Save checkpoints (not saved if `None`); an existing checkpoint in the
+    /// directory is resumed
+    pub checkpoint: Option<CheckpointOptions>,
+    /// Plot the iterates over the cost function (not plotted if `None`);
+    /// only possible for problems with two parameters. The plot is drawn by
+    /// the caller from `history`, see [`plot::contour`]
+    #[serde(default)]
+    pub contour: Option<ContourOptions>,
+    /// Write convergence plots of the run to this SVG file (not written if
+    /// `None`); drawn by the caller from `history` like `contour`, see
+    /// [`plot::convergence`]
+    #[serde(default)]
+    pub convergence: Option<PathBuf>,
+    /// Run the solver from many starting points instead of `init_param`
+    /// (a single run if `None`); handled by the caller with
+    /// [`multistart::multistart`], which does not support `trace`,
+    /// `checkpoint` and `contour`
+    #[serde(default)]
+    pub multistart: Option<MultiStartOptions>,
+    /// Settings of the outer loop which solves problems with constraints
+    /// (see [`ProblemConfig::constraints`](problem::ProblemConfig::constraints));
+    /// handled by the caller with [`constraint::solve_constrained`], which
+    /// does not support `trace`, `checkpoint`, `convergence` and
+    /// `multistart`. The defaults are used if `None`
+    #[serde(default)]
+    pub outer: Option<OuterOptions>,
+    /// Record every iteration in memory (not recorded if `None`); this is
+    /// not part of the serialized settings
+    #[serde(skip)]
+    pub history: Option<History>,
+}
+
+impl Default for RunConfig {
+    fn default() -> Self {
+        RunConfig {
+            init_param: vec![1.0, -2.0],
+            stop: StopCriteria::default(),
+            trace: None,
+            checkpoint: None,
+            contour: None,
+            convergence: None,
+            multistart: None,
+            outer: None,
+            history: None,
+        }
+    }
+}
+
+/// Run `solver` on `problem` with the settings in `config`
+///
+/// This returns argmin's `OptimizationResult`, which holds the problem, the
+/// solver and the final state of the run. The solver is wrapped in
+/// [`Stopping`], which enforces `config.stop` and knows which criterion
+/// stopped the run.
+pub fn run<O, S, H, P>(
+    problem: O,
+    solver: S,
+    config: &RunConfig,
+) -> Result<RunResult<O, S, GradientState<H, P>>, Error>
+where
+    S: Solver<O, GradientState<H, P>> + Serialize + DeserializeOwned,
+    GradientState<H, P>: StopState<O> + Serialize + DeserializeOwned,
+    P: Vector,
+{
+    run_with(problem, solver, config, |state| state)
+}
+
+/// Like [`run`], but `init` can further modify the initial state
+///
+/// Some solvers need more than an initial parameter vector, for instance
+/// BFGS requires an initial inverse Hessian.
+pub fn run_with<O, S, H, P, C>(
+    problem: O,
+    solver: S,
+    config: &RunConfig,
+    init: C,
+) -> Result<RunResult<O, S, GradientState<H, P>>, Error>
+where
+    S: Solver<O, GradientState<H, P>> + Serialize + DeserializeOwned,
+    GradientState<H, P>: StopState<O> + Serialize + DeserializeOwned,
+    P: Vector,
+    C: FnOnce(GradientState<H, P>) -> GradientState<H, P>,
+{
+    let executor = Executor::new(problem, stopping(solver, config)?)
+        // Via `configure`, one has access to the internally used state.
+        // This state can be initialized, for instance by providing an
+        // initial parameter vector.
+        // The maximum number of iterations is also set via this method.
+        // In this particular case, the state exposed is of type `IterState`.
+        // The documentation of `IterState` shows how this struct can be
+        // manipulated.
+        // Population based solvers use `PopulationState` instead of
+        // `IterState`.
+        .configure(|state| {
+            let state = state
+                // Set initial parameters (depending on the solver,
+                // this may be required)
+                .param(P::from_vec(config.init_param.clone()))
+                // Set maximum iterations
+                // (optional, set to `std::u64::MAX` if not provided)
+                .max_iters(config.stop.max_iters)
+                // Set target cost. The solver stops when this cost
+                // function value is reached (optional)
+                .target_cost(config.stop.target());
+            init(state)
+        });
+    // run the solver on the defined problem
+    observe(executor, config)?.run()
+}
+
+/// Run a derivative free `solver` on `problem` with the settings in `config`
+///
+/// Apart from the state, this is the same as [`run`].
+pub fn run_derivative_free<O, S, P>(
+    problem: O,
+    solver: S,
+    config: &RunConfig,
+) -> Result<RunResult<O, S, CostState<P>>, Error>
+where
+    S: Solver<O, CostState<P>> + Serialize + DeserializeOwned,
+    CostState<P>: StopState<O> + Serialize + DeserializeOwned,
+    P: Vector,
+{
+    let executor = Executor::new(problem, stopping(solver, config)?).configure(|state| {
+        state
+            .param(P::from_vec(config.init_param.clone()))
+            .max_iters(config.stop.max_iters)
+            .target_cost(config.stop.target())
+    });
+    observe(executor, config)?.run()
+}
+
+/// Run a population based `solver` on `problem`
+///
+/// The initial parameter vector of `config` is not used; instead the solver
+/// starts from `population`, or creates its own population if this is `None`.
+pub fn run_swarm<O, S, P>(
+    problem: O,
+    solver: S,
+    config: &RunConfig,
+    population: Option<Vec<Particle<P, f64>>>,
+) -> Result<RunResult<O, S, SwarmState<P>>, Error>
+where
+    S: Solver<O, SwarmState<P>> + Serialize + DeserializeOwned,
+    SwarmState<P>: StopState<O> + Serialize + DeserializeOwned,
+{
+    let executor = Executor::new(problem, stopping(solver, config)?).configure(|state| {
+        let state = state
+            .max_iters(config.stop.max_iters)
+            .target_cost(config.stop.target());
+        match population {
+            Some(population) => state.population(population),
+            None => state,
+        }
+    });
+    observe(executor, config)?.run()
+}
+
+/// Wrap `solver` to stop on the criteria of `config`
+fn stopping<S>(solver: S, config: &RunConfig) -> Result<Stopping<S>, Error> {
+    let stopping = Stopping::new(solver, config.stop.clone())?;
+    // Traces and histories record the gradient norm
+    Ok(if config.trace.is_some() || config.history.is_some() {
+        stopping.observed()
+    } else {
+        stopping
+    })
+}
+
+/// Attach the observers and the checkpointing requested in `config` to
+/// `executor`
+fn observe<O, S, I>(executor: Executor<O, S, I>, config: &RunConfig) -> Result<Executor<O, S, I>, Error>
+where
+    S: Solver<O, I> + Serialize + DeserializeOwned,
+    I: TraceState + Serialize + DeserializeOwned,
+{
+    let executor = match &config.trace {
+        // `Trace` selects the iterations it writes itself
+        Some(options) => executor.add_observer(Trace::create(options)?, ObserverMode::Always),
+        None => executor,
+    };
+    let executor = match &config.history {
+        Some(history) => executor.add_observer(history.clone(), ObserverMode::Always),
+        None => executor,
+    };
+    Ok(match &config.checkpoint {
+        Some(options) => executor.checkpointing(options.file_checkpoint()?),
+        None => executor,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::problem::Rosenbrock;
+    use argmin::core::{CostFunction, State};
+
+    fn config(init_param: Vec<f64>, max_iters: u64) -> RunConfig {
+        RunConfig {
+            init_param,
+            stop: StopCriteria {
+                max_iters,
+                ..StopCriteria::default()
+            },
+            ..RunConfig::default()
+        }
+    }
+
+    #[test]
+    fn run_stops_at_max_iters() {
+        let config = config(vec![-1.2, 1.0], 5);
+        let res = run(Rosenbrock::default(), solver::steepest_descent(), &config).unwrap();
+        assert_eq!(res.state.get_iter(), 5);
+        let start = Rosenbrock::default().cost(&config.init_param).unwrap();
+        assert!(res.state.get_best_cost() < start);
+    }
+
+    #[test]
+    fn run_reaches_target_cost() {
+        let mut config = config(vec![-1.2, 1.0], 100_000);
+        config.stop.target_cost = Some(1e-4);
+        let res = run(Rosenbrock::default(), solver::steepest_descent(), &config).unwrap();
+        assert!(res.state.get_best_cost() <= 1e-4);
+        assert!(res.state.get_iter() < 100_000);
+    }
+}
@@ -1,125 +1,1487 @@
-
-#![allow(unused)]
-
-use argmin::core::{ArgminFloat, OptimizationResult};
 use std::env;
-extern crate argmin;
-extern crate argmin_testfunctions;
-use argmin_testfunctions::{
-    rosenbrock_2d, rosenbrock_2d_derivative, rosenbrock_2d_hessian
+use std::process;
+use clap::error::ErrorKind;
+use clap::{ArgMatches, Args, CommandFactory, FromArgMatches, Parser, Subcommand};
+use clap::builder::{PossibleValuesParser, TypedValueParser};
+use clap::parser::ValueSource;
+use argmin::core::{ArgminError, Error};
+use opt::backend::Backend;
+use opt::bench::{self, BenchFormat, BenchOptions};
+use opt::check::{self, CheckOptions};
+use opt::checkpoint::{self, CheckpointOptions, RunSpec};
+use opt::config;
+use opt::constraint::{self, OuterOptions, PenaltyMethod};
+use opt::finitediff::{FiniteDiff, Scheme};
+use opt::multistart::{self, MultiStartOptions, Sampling};
+use opt::plot::contour::{self, ContourOptions, LevelScale};
+use opt::plot::convergence::{self, ConvergenceRun};
+use opt::problem::{self, Dimension, DynProblem, ProblemConfig};
+use opt::profile::{self, Measure, ProfileOptions};
+use opt::solver::{
+    self, AnnealingOptions, LineSearchMethod, LineSearchOptions, Method, NelderMeadOptions,
+    SimplexInit, SolverOptions, SwarmInit, SwarmOptions, TemperatureSchedule,
 };
-use argmin::core::{Error, CostFunction, Gradient, Hessian};
-use argmin::solver::gradientdescent::SteepestDescent;
-use argmin::solver::linesearch::MoreThuenteLineSearch;
-use argmin::core::Executor;
+use opt::report::OutputFormat;
+use opt::stopping::StopCriteria;
+use opt::trace::{History, TraceFormat, TraceFrequency, TraceOptions};
+use opt::{Record, RunConfig};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+/// Command line interface of the `opt` binary
+///
+/// Everything which used to be hard-coded in `main` (problem, solver,
+/// starting point and stopping criteria) can now be chosen at runtime.
+#[derive(Parser)]
+#[command(name = "opt", version, about = "Run argmin solvers on test problems")]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+// Parsed once, so the size of the variants does not matter
+#[allow(clippy::large_enum_variant)]
+#[derive(Subcommand)]
+enum Command {
+    /// Run a solver on a problem and print the result
+    Run(RunArgs),
+    /// Continue a checkpointed run from its latest checkpoint
+    Resume(ResumeArgs),
+    /// Compare the analytic derivatives of a problem with finite differences
+    CheckDerivatives(CheckArgs),
+    /// Compare solvers on several problems and starting points
+    Bench(BenchArgs),
+    /// List the available problems and solvers
+    List,
+}
+
+#[derive(Args)]
+struct ResumeArgs {
+    /// Checkpoint directory of the run (`--checkpoint-dir` of `opt run`)
+    directory: PathBuf,
+
+    /// Output format of the result
+    #[arg(long, default_value = "text", value_parser = output_format())]
+    format: OutputFormat,
+}
+
+#[derive(Args)]
+struct CheckArgs {
+    /// Problem whose derivatives are checked (see `opt list`)
+    #[arg(long, default_value = "rosenbrock",
+          value_parser = PossibleValuesParser::new(problem::PROBLEMS.iter().map(|p| p.name)))]
+    problem: String,
+
+    /// Cost function as an expression instead of a registered problem,
+    /// e.g. `"(1-x)^2 + 100*(y-x^2)^2"`
+    #[arg(long, conflicts_with = "problem", allow_hyphen_values = true)]
+    objective: Option<String>,
+
+    /// Order of the variables of `--objective`, comma separated; by name if
+    /// not given
+    #[arg(long, requires = "objective", value_delimiter = ',')]
+    variables: Option<Vec<String>>,
+
+    /// Check every registered problem
+    #[arg(long, conflicts_with_all = ["problem", "objective", "dim", "at"])]
+    all: bool,
+
+    /// Dimension of problems which are defined for any dimension
+    #[arg(long)]
+    dim: Option<usize>,
+
+    /// Rosenbrock parameter `a` (only used by `rosenbrock` and `rosenbrock-nd`)
+    #[arg(long, allow_hyphen_values = true, default_value_t = 1.0)]
+    a: f64,
+
+    /// Rosenbrock parameter `b` (only used by `rosenbrock` and `rosenbrock-nd`)
+    #[arg(long, allow_hyphen_values = true, default_value_t = 100.0)]
+    b: f64,
+
+    /// Number of random points in the domain of the problem
+    #[arg(long, default_value_t = 5)]
+    points: usize,
+
+    /// Also check at this point, comma separated (can be repeated)
+    #[arg(long, allow_hyphen_values = true)]
+    at: Vec<String>,
+
+    /// Seed of the random points
+    #[arg(long)]
+    seed: Option<u64>,
+
+    /// Finite differences used as reference; defaults to `complex-step`
+    /// if the problem supports it and `central` otherwise
+    #[arg(long, value_parser = PossibleValuesParser::new(Scheme::ALL.iter().map(|s| s.name())))]
+    derivatives: Option<String>,
+
+    /// Relative step of finite-difference gradients
+    #[arg(long, requires = "derivatives")]
+    fd_step: Option<f64>,
+
+    /// Relative step of finite-difference Hessians
+    #[arg(long, requires = "derivatives")]
+    fd_hessian_step: Option<f64>,
+
+    /// Largest accepted error relative to `max(1, |reference|)`
+    #[arg(long, default_value_t = 1e-5)]
+    tolerance: f64,
+
+    /// Output format of the result
+    #[arg(long, default_value = "text", value_parser = output_format())]
+    format: OutputFormat,
+}
+
+impl CheckArgs {
+    /// Problems selected on the command line
+    fn problem_configs(&self) -> Vec<ProblemConfig> {
+        if let Some(objective) = &self.objective {
+            return vec![expression_config(objective, &self.variables, self.dim)];
+        }
+        let names: Vec<&str> = if self.all {
+            problem::PROBLEMS.iter().map(|p| p.name).collect()
+        } else {
+            vec![self.problem.as_str()]
+        };
+        names
+            .into_iter()
+            .map(|name| registered_config(name, self.dim, self.a, self.b))
+            .collect()
+    }
+
+    /// Check options selected on the command line
+    fn options(&self) -> Result<CheckOptions, String> {
+        let points = self
+            .at
+            .iter()
+            .map(|point| {
+                point
+                    .split(',')
+                    .map(|x| x.trim().parse::<f64>())
+                    .collect::<Result<Vec<f64>, _>>()
+                    .map_err(|err| format!("invalid --at `{}`: {}", point, err))
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+        if points.iter().flatten().any(|x| !x.is_finite()) {
+            return Err("--at must only contain finite values".to_string());
+        }
+        let diff = self.derivatives.as_deref().and_then(Scheme::from_name).map(|scheme| FiniteDiff {
+            scheme,
+            step: self.fd_step,
+            hessian_step: self.fd_hessian_step,
+        });
+        Ok(CheckOptions {
+            random_points: self.points,
+            points,
+            seed: self.seed,
+            diff,
+            tolerance: self.tolerance,
+        })
+    }
+}
+
+#[derive(Args)]
+struct BenchArgs {
+    /// Problems to solve, comma separated; all registered problems if not
+    /// given (see `opt list`)
+    #[arg(long, value_delimiter = ',',
+          value_parser = PossibleValuesParser::new(problem::PROBLEMS.iter().map(|p| p.name)))]
+    problems: Vec<String>,
+
+    /// Dimension of the problems which are defined for any dimension
+    #[arg(long)]
+    dim: Option<usize>,
+
+    /// Rosenbrock parameter `a` (only used by `rosenbrock` and `rosenbrock-nd`)
+    #[arg(long, allow_hyphen_values = true, default_value_t = 1.0)]
+    a: f64,
+
+    /// Rosenbrock parameter `b` (only used by `rosenbrock` and `rosenbrock-nd`)
+    #[arg(long, allow_hyphen_values = true, default_value_t = 100.0)]
+    b: f64,
+
+    /// Derivatives of the problems, see `opt run --help`
+    #[arg(long, default_value = "analytic",
+          value_parser = PossibleValuesParser::new(problem::DERIVATIVES))]
+    derivatives: String,
+
+    /// Relative step of finite-difference gradients
+    #[arg(long)]
+    fd_step: Option<f64>,
+
+    /// Relative step of finite-difference Hessians
+    #[arg(long)]
+    fd_hessian_step: Option<f64>,
+
+    /// Constrain the parameters to the domains of the problems; without
+    /// `--solvers`, only the solvers which respect bounds are compared
+    #[arg(long)]
+    bounded: bool,
+
+    /// Solvers to compare, comma separated; all solvers if not given (see
+    /// `opt list`)
+    #[arg(long, value_delimiter = ',',
+          value_parser = PossibleValuesParser::new(Method::ALL.iter().map(|m| m.name())))]
+    solvers: Vec<String>,
+
+    #[command(flatten)]
+    tuning: SolverArgs,
+
+    #[command(flatten)]
+    stop: StopArgs,
+
+    /// Number of random starting points per problem, in addition to the
+    /// default starting point
+    #[arg(long, default_value_t = 0)]
+    random_starts: usize,
+
+    /// Seed of the random starting points
+    #[arg(long)]
+    start_seed: Option<u64>,
+
+    /// A run solves the problem if its cost function value is within this
+    /// tolerance, relative to `max(1, |f*|)`, of the known minimum `f*`
+    #[arg(long, default_value_t = 1e-6)]
+    success_tol: f64,
+
+    /// Output format of the results
+    #[arg(long, default_value = "markdown",
+          value_parser = PossibleValuesParser::new(BenchFormat::ALL.iter().map(|f| f.name()))
+              // The name is one of the format names
+              .map(|name| BenchFormat::from_name(&name).unwrap_or_default()))]
+    format: BenchFormat,
+
+    /// Print one row per run instead of one per problem and solver
+    #[arg(long)]
+    runs: bool,
+
+    /// Write performance and data profiles (SVG plots and CSV curves) to
+    /// this directory
+    #[arg(long)]
+    profiles: Option<PathBuf>,
+
+    /// Tolerance of the convergence test of the profiles, in (0, 1)
+    #[arg(long, requires = "profiles", default_value_t = 1e-3)]
+    profile_tol: f64,
+
+    /// Work counted by the profiles
+    #[arg(long, requires = "profiles", default_value = "evals",
+          value_parser = PossibleValuesParser::new(Measure::ALL.iter().map(|m| m.name())))]
+    profile_measure: String,
+
+    /// Write one convergence plot per problem, overlaying the runs of all
+    /// solvers, to this directory
+    #[arg(long)]
+    convergence: Option<PathBuf>,
+
+    /// Number of runs executed concurrently, 0 for one per core; the
+    /// results do not depend on it, but concurrent runs compete for the
+    /// cores, which shows in their wall times
+    #[arg(long, default_value_t = 1)]
+    jobs: usize,
+}
+
+impl BenchArgs {
+    /// Benchmark selected on the command line
+    fn options(&self) -> Result<BenchOptions, String> {
+        let names: Vec<&str> = if self.problems.is_empty() {
+            problem::PROBLEMS.iter().map(|p| p.name).collect()
+        } else {
+            self.problems.iter().map(String::as_str).collect()
+        };
+        let problems = names
+            .into_iter()
+            .map(|name| {
+                // `--dim` does not apply to problems of fixed dimension
+                let dim = self.dim.filter(|_| {
+                    problem::find(name).is_some_and(|entry| matches!(entry.dim, Dimension::Any { .. }))
+                });
+                let config = ProblemConfig {
+                    bounded: self.bounded,
+                    ..registered_config(name, dim, self.a, self.b)
+                };
+                config
+                    .with_derivatives(&self.derivatives, self.fd_step, self.fd_hessian_step)
+                    .map_err(|err| err.to_string())
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+        let methods: Vec<Method> = if self.solvers.is_empty() {
+            Method::ALL
+                .iter()
+                .copied()
+                .filter(|method| !self.bounded || method.respects_bounds())
+                .collect()
+        } else {
+            // `solvers` is restricted to the method names by clap
+            self.solvers.iter().filter_map(|name| Method::from_name(name)).collect()
+        };
+        self.stop.validate(&methods)?;
+        if !self.a.is_finite() || !self.b.is_finite() {
+            return Err("--a and --b must be finite".to_string());
+        }
+        if !(self.profile_tol > 0.0 && self.profile_tol < 1.0) {
+            return Err("--profile-tol must be in (0, 1)".to_string());
+        }
+        Ok(BenchOptions {
+            problems,
+            solvers: methods.into_iter().map(|method| self.tuning.options(method)).collect(),
+            random_starts: self.random_starts,
+            seed: self.start_seed,
+            stop: self.stop.criteria(),
+            success_tol: self.success_tol,
+        })
+    }
+}
+
+#[derive(Args)]
+struct RunArgs {
+    /// Read problem, starting point, solver, stopping criteria and output
+    /// settings from this experiment file (`.toml`, `.yaml` or `.json`)
+    /// instead of the other flags
+    #[arg(long)]
+    config: Option<PathBuf>,
+
+    /// Problem to solve (see `opt list`)
+    #[arg(long, default_value = "rosenbrock",
+          value_parser = PossibleValuesParser::new(problem::PROBLEMS.iter().map(|p| p.name)))]
+    problem: String,
+
+    /// Cost function as an expression instead of a registered problem,
+    /// e.g. `"(1-x)^2 + 100*(y-x^2)^2"`
+    #[arg(long, conflicts_with = "problem", allow_hyphen_values = true)]
+    objective: Option<String>,
+
+    /// Order of the variables of `--objective`, comma separated; by name if
+    /// not given
+    #[arg(long, requires = "objective", value_delimiter = ',')]
+    variables: Option<Vec<String>>,
+
+    /// Dimension of problems which are defined for any dimension
+    #[arg(long)]
+    dim: Option<usize>,
+
+    /// Constrain the parameters to the domain of the problem (see `opt
+    /// list`); only the solvers which respect bounds can be used
+    #[arg(long)]
+    bounded: bool,
+
+    /// Lower bounds of the parameters, one for all or one per parameter,
+    /// comma separated; implies `--bounded`
+    #[arg(long, value_delimiter = ',', allow_hyphen_values = true)]
+    lower: Option<Vec<f64>>,
+
+    /// Upper bounds of the parameters, like `--lower`
+    #[arg(long, value_delimiter = ',', allow_hyphen_values = true)]
+    upper: Option<Vec<f64>>,
+
+    /// Constraint such as `"x1 + x2 = 1"` or `"x1^2 + x2^2 <= 1"`, in the
+    /// variables of `--objective` or `x1`, ..., `xn`; can be repeated. The
+    /// problem is then solved by an outer loop (see `--outer-method`)
+    #[arg(long = "constraint", allow_hyphen_values = true,
+          conflicts_with_all = ["trace", "checkpoint_dir", "convergence", "starts"])]
+    constraints: Vec<String>,
+
+    /// Solver to use (see `opt list`)
+    #[arg(long, default_value = "steepest-descent",
+          value_parser = PossibleValuesParser::new(Method::ALL.iter().map(|m| m.name())))]
+    solver: String,
+
+    #[command(flatten)]
+    tuning: SolverArgs,
+
+    /// Initial parameter vector, comma separated (e.g. `1.0,-2.0`);
+    /// defaults to the starting point of the problem
+    #[arg(long, value_delimiter = ',', allow_hyphen_values = true)]
+    x0: Option<Vec<f64>>,
+
+    #[command(flatten)]
+    stop: StopArgs,
+
+    /// Rosenbrock parameter `a` (only used by `rosenbrock` and `rosenbrock-nd`)
+    #[arg(long, allow_hyphen_values = true, default_value_t = 1.0)]
+    a: f64,
+
+    /// Rosenbrock parameter `b` (only used by `rosenbrock` and `rosenbrock-nd`)
+    #[arg(long, allow_hyphen_values = true, default_value_t = 100.0)]
+    b: f64,
+
+    /// Derivatives of the problem: `analytic` uses the hand-written ones,
+    /// `forward-ad` and `reverse-ad` automatic differentiation, the other
+    /// choices finite differences
+    #[arg(long, default_value = "analytic",
+          value_parser = PossibleValuesParser::new(problem::DERIVATIVES))]
+    derivatives: String,
+
+    /// Relative step of finite-difference gradients
+    #[arg(long)]
+    fd_step: Option<f64>,
+
+    /// Relative step of finite-difference Hessians
+    #[arg(long)]
+    fd_hessian_step: Option<f64>,
+
+    /// Output format of the result
+    #[arg(long, default_value = "text", value_parser = output_format())]
+    format: OutputFormat,
+
+    /// Write a per-iteration trace to this file
+    #[arg(long)]
+    trace: Option<PathBuf>,
+
+    /// Format of the trace; defaults to `jsonl` for `.jsonl`/`.json` files
+    /// and `csv` otherwise
+    #[arg(long, requires = "trace", value_parser = PossibleValuesParser::new(["csv", "jsonl"]))]
+    trace_format: Option<String>,
+
+    /// Only trace every this many iterations
+    #[arg(long, requires = "trace", default_value_t = 1)]
+    trace_every: u64,
+
+    /// Only trace iterations which find a new best point
+    #[arg(long, requires = "trace", conflicts_with = "trace_every")]
+    trace_best: bool,
+
+    /// Save checkpoints to this directory, so that the run can be continued
+    /// with `opt resume`
+    #[arg(long)]
+    checkpoint_dir: Option<PathBuf>,
+
+    /// Save a checkpoint every this many iterations
+    #[arg(long, requires = "checkpoint_dir", default_value_t = 100)]
+    checkpoint_every: u64,
+
+    /// Plot the iterates over a contour plot of the cost function to this
+    /// file (problems with two parameters only); PNG for `.png` files
+    /// (without text), SVG otherwise
+    #[arg(long)]
+    contour: Option<PathBuf>,
+
+    /// Box of the contour plot as `x_lo,x_hi,y_lo,y_hi`; defaults to the
+    /// iterates and the known minima with a margin
+    #[arg(long, requires = "contour", value_delimiter = ',', allow_hyphen_values = true)]
+    contour_bounds: Option<Vec<f64>>,
+
+    /// Number of contour lines
+    #[arg(long, requires = "contour", default_value_t = 20)]
+    contour_levels: usize,
+
+    /// Number of grid points along each axis of the contour plot
+    #[arg(long, requires = "contour", default_value_t = 200)]
+    contour_resolution: usize,
+
+    /// Spacing of colors and contour lines: `log` in the distance to the
+    /// smallest value, or `linear`
+    #[arg(long, requires = "contour", default_value = "log",
+          value_parser = PossibleValuesParser::new(LevelScale::ALL.iter().map(|s| s.name())))]
+    contour_scale: String,
+
+    /// Write convergence plots (cost, gradient norm and distance to the
+    /// optimum against iterations and evaluations) to this SVG file
+    #[arg(long)]
+    convergence: Option<PathBuf>,
+
+    /// Run the solver from this many starting points sampled in the domain
+    /// of the problem and report the distinct minima found
+    #[arg(long, conflicts_with_all = ["x0", "trace", "checkpoint_dir", "contour"])]
+    starts: Option<usize>,
+
+    /// How the starting points of `--starts` are drawn
+    #[arg(long, requires = "starts", default_value = "latin-hypercube",
+          value_parser = PossibleValuesParser::new(Sampling::ALL.iter().map(|s| s.name())))]
+    sampling: String,
+
+    /// Seed of the starting points of `--starts`
+    #[arg(long, requires = "starts")]
+    start_seed: Option<u64>,
+
+    /// Distance below which two minima count as the same, relative to the
+    /// size of the domain
+    #[arg(long, requires = "starts", default_value_t = 1e-2)]
+    cluster_tol: f64,
+
+    /// How the outer loop of problems with `--constraint` forms its
+    /// subproblems
+    #[arg(long, requires = "constraints", default_value = "augmented-lagrangian",
+          value_parser = PossibleValuesParser::new(PenaltyMethod::ALL.iter().map(|m| m.name())))]
+    outer_method: String,
+
+    /// Maximum number of subproblems solved by the outer loop
+    #[arg(long, requires = "constraints", default_value_t = 20)]
+    outer_iters: u64,
+
+    /// Initial penalty weight of the outer loop (the inverse of the initial
+    /// barrier weight of `log-barrier`)
+    #[arg(long, requires = "constraints", default_value_t = 10.0)]
+    penalty: f64,
+
+    /// Factor by which the penalty weight grows between subproblems
+    #[arg(long, requires = "constraints", default_value_t = 10.0)]
+    penalty_growth: f64,
+
+    /// Largest constraint violation accepted as feasible
+    #[arg(long, requires = "constraints", default_value_t = 1e-6)]
+    constraint_tol: f64,
+
+    /// Number of runs of a multi-start executed concurrently, 0 for one per
+    /// core; the results do not depend on it. Unlike the other flags, this
+    /// can be combined with `--config`
+    #[arg(long, default_value_t = 1)]
+    jobs: usize,
+}
+
+/// Stopping criteria
+#[derive(Args)]
+struct StopArgs {
+    /// Maximum number of iterations
+    #[arg(long, default_value_t = 1000)]
+    max_iters: u64,
+
+    /// Stop when the cost function value is at most this value; by default
+    /// there is no target
+    #[arg(long, allow_hyphen_values = true)]
+    target_cost: Option<f64>,
+
+    /// Stop when the norm of the gradient is at most this value
+    #[arg(long)]
+    gradient_tol: Option<f64>,
+
+    /// Stop when the cost function value changes by at most this value in
+    /// an iteration (not for the derivative free solvers)
+    #[arg(long)]
+    cost_tol_abs: Option<f64>,
+
+    /// Stop when the cost function value changes by at most this fraction
+    /// of its magnitude in an iteration (not for the derivative free solvers)
+    #[arg(long)]
+    cost_tol_rel: Option<f64>,
+
+    /// Stop when the step between two iterates is at most this long (not for
+    /// the derivative free solvers)
+    #[arg(long)]
+    step_tol: Option<f64>,
+
+    /// Stop when the best cost function value has not improved within this
+    /// many iterations
+    #[arg(long)]
+    stagnation_iters: Option<u64>,
+
+    /// Relative improvement of the best cost function value which
+    /// `--stagnation-iters` does not count as an improvement
+    #[arg(long, requires = "stagnation_iters", default_value_t = 0.0)]
+    stagnation_tol: f64,
+
+    /// Stop after this many seconds
+    #[arg(long)]
+    timeout: Option<f64>,
+
+    /// Stop after this many cost function evaluations; checked between
+    /// iterations, so the last iteration may exceed it
+    #[arg(long)]
+    max_evals: Option<u64>,
+}
+
+impl StopArgs {
+    /// Stopping criteria selected on the command line
+    fn criteria(&self) -> StopCriteria {
+        StopCriteria {
+            max_iters: self.max_iters,
+            target_cost: self.target_cost,
+            gradient_tol: self.gradient_tol,
+            cost_tol_abs: self.cost_tol_abs,
+            cost_tol_rel: self.cost_tol_rel,
+            step_tol: self.step_tol,
+            stagnation_iters: self.stagnation_iters,
+            stagnation_tol: self.stagnation_tol,
+            timeout: self.timeout,
+            max_evals: self.max_evals,
+        }
+    }
+
+    /// Check the criteria for each of `methods`
+    fn validate(&self, methods: &[Method]) -> Result<(), String> {
+        let criteria = self.criteria();
+        criteria.validate().map_err(flag_error(""))?;
+        for &method in methods {
+            criteria.validate_for(method).map_err(flag_error(""))?;
+        }
+        Ok(())
+    }
+}
+
+/// Parser of `--format`
+fn output_format() -> impl TypedValueParser<Value = OutputFormat> {
+    PossibleValuesParser::new(OutputFormat::ALL.iter().map(|f| f.name()))
+        // The name is one of the format names
+        .map(|name| OutputFormat::from_name(&name).unwrap_or_default())
+}
+
+/// Tuning parameters of the solvers
+#[derive(Args)]
+struct SolverArgs {
+    /// Number of correction pairs stored by L-BFGS
+    #[arg(long, default_value_t = 7)]
+    lbfgs_memory: usize,
+
+    /// Step length of Newton's method, in (0, 1]
+    #[arg(long, default_value_t = 1.0)]
+    newton_gamma: f64,
+
+    /// Let Newton-CG use Hessian-vector products instead of forming the
+    /// Hessian (cheap with `--derivatives reverse-ad`)
+    #[arg(long)]
+    hessian_products: bool,
+
+    /// Initial trust region radius of SR1
+    #[arg(long, default_value_t = 1.0)]
+    trust_region_radius: f64,
+
+    /// Restart nonlinear CG every this many iterations
+    #[arg(long)]
+    cg_restart_iters: Option<u64>,
+
+    /// Restart nonlinear CG if consecutive gradients are less orthogonal
+    /// [default: 0.1; inf disables]
+    #[arg(long)]
+    cg_restart_orthogonality: Option<f64>,
+
+    /// Line search used by the line search based solvers (see `opt list`)
+    #[arg(long, default_value = "more-thuente",
+          value_parser = PossibleValuesParser::new(LineSearchMethod::ALL.iter().map(|m| m.name())))]
+    linesearch: String,
+
+    /// Sufficient decrease constant of the line search
+    #[arg(long)]
+    c1: Option<f64>,
+
+    /// Curvature constant of the line search
+    #[arg(long)]
+    c2: Option<f64>,
+
+    /// First step length tried by the line search
+    #[arg(long)]
+    initial_step: Option<f64>,
+
+    /// Smallest step length allowed (More-Thuente and Hager-Zhang)
+    #[arg(long, requires = "step_max")]
+    step_min: Option<f64>,
+
+    /// Largest step length allowed (More-Thuente and Hager-Zhang)
+    #[arg(long, requires = "step_min")]
+    step_max: Option<f64>,
+
+    /// Factor by which backtracking shrinks the step length, in (0, 1)
+    #[arg(long)]
+    contraction: Option<f64>,
+
+    /// Initial simplex of Nelder-Mead
+    #[arg(long, default_value = "pfeffer",
+          value_parser = PossibleValuesParser::new(SimplexInit::ALL.iter().map(|s| s.name())))]
+    simplex: String,
+
+    /// Step (`axis`) or edge length (`regular`) of the initial simplex
+    #[arg(long, default_value_t = 1.0)]
+    simplex_size: f64,
+
+    /// Nelder-Mead stops when the standard deviation of the cost function
+    /// values at the vertices falls below this value
+    #[arg(long)]
+    sd_tolerance: Option<f64>,
+
+    /// Number of particles of particle swarm optimization
+    #[arg(long, default_value_t = 40)]
+    particles: usize,
+
+    /// Placement of the initial particles
+    #[arg(long, default_value = "uniform",
+          value_parser = PossibleValuesParser::new(SwarmInit::ALL.iter().map(|s| s.name())))]
+    swarm_init: String,
+
+    /// Half width of the box around the starting point (`--swarm-init around-start`)
+    #[arg(long, default_value_t = 1.0)]
+    swarm_spread: f64,
+
+    /// Inertia weight of particle swarm optimization
+    #[arg(long)]
+    inertia: Option<f64>,
+
+    /// Cognitive weight of particle swarm optimization
+    #[arg(long)]
+    cognitive: Option<f64>,
+
+    /// Social weight of particle swarm optimization
+    #[arg(long)]
+    social: Option<f64>,
+
+    /// Initial temperature of simulated annealing
+    #[arg(long, default_value_t = 15.0)]
+    temperature: f64,
+
+    /// Temperature schedule of simulated annealing
+    #[arg(long, default_value = "boltzmann",
+          value_parser = PossibleValuesParser::new(TemperatureSchedule::ALL.iter().map(|s| s.name())))]
+    temp_schedule: String,
+
+    /// Factor of the `exponential` temperature schedule, in (0, 1)
+    #[arg(long, default_value_t = 0.95)]
+    temp_decay: f64,
+
+    /// Half width of the neighbourhood of simulated annealing at the initial temperature
+    #[arg(long, default_value_t = 0.5)]
+    anneal_step: f64,
+
+    /// Stop simulated annealing if the best point has not improved for this many iterations
+    #[arg(long)]
+    stall_best: Option<u64>,
+
+    /// Stop simulated annealing if no point has been accepted for this many iterations
+    #[arg(long)]
+    stall_accepted: Option<u64>,
+
+    /// Reanneal every this many iterations
+    #[arg(long)]
+    reanneal_fixed: Option<u64>,
+
+    /// Reanneal if no point has been accepted for this many iterations
+    #[arg(long)]
+    reanneal_accepted: Option<u64>,
+
+    /// Reanneal if the best point has not improved for this many iterations
+    #[arg(long)]
+    reanneal_best: Option<u64>,
+
+    /// Seed of the random number generator of the stochastic solvers
+    #[arg(long)]
+    seed: Option<u64>,
+
+    /// Math backend of parameter vectors and matrices
+    #[arg(long, default_value = "vec",
+          value_parser = PossibleValuesParser::new(Backend::ALL.iter().map(|b| b.name())))]
+    backend: String,
+}
+
+impl SolverArgs {
+    /// Options of `method` with the tuning parameters
+    fn options(&self, method: Method) -> SolverOptions {
+        SolverOptions {
+            method,
+            lbfgs_memory: self.lbfgs_memory,
+            newton_gamma: self.newton_gamma,
+            trust_region_radius: self.trust_region_radius,
+            cg_restart_iters: self.cg_restart_iters,
+            cg_restart_orthogonality: self.cg_restart_orthogonality,
+            linesearch: LineSearchOptions {
+                // `linesearch` is restricted to the line search names by clap
+                method: LineSearchMethod::from_name(&self.linesearch)
+                    .unwrap_or(LineSearchMethod::MoreThuente),
+                c1: self.c1,
+                c2: self.c2,
+                initial_step: self.initial_step,
+                step_bounds: self.step_min.zip(self.step_max),
+                contraction: self.contraction,
+            },
+            nelder_mead: NelderMeadOptions {
+                init: SimplexInit::from_name(&self.simplex).unwrap_or(SimplexInit::Pfeffer),
+                size: self.simplex_size,
+                sd_tolerance: self.sd_tolerance,
+            },
+            swarm: SwarmOptions {
+                particles: self.particles,
+                init: SwarmInit::from_name(&self.swarm_init).unwrap_or(SwarmInit::Uniform),
+                spread: self.swarm_spread,
+                inertia: self.inertia,
+                cognitive: self.cognitive,
+                social: self.social,
+            },
+            annealing: AnnealingOptions {
+                temperature: self.temperature,
+                schedule: TemperatureSchedule::from_name(&self.temp_schedule)
+                    .unwrap_or(TemperatureSchedule::Boltzmann),
+                decay: self.temp_decay,
+                step: self.anneal_step,
+                stall_best: self.stall_best,
+                stall_accepted: self.stall_accepted,
+                reanneal_fixed: self.reanneal_fixed,
+                reanneal_accepted: self.reanneal_accepted,
+                reanneal_best: self.reanneal_best,
+            },
+            seed: self.seed,
+            // `backend` is restricted to the backend names by clap
+            backend: Backend::from_name(&self.backend).unwrap_or(Backend::Vec),
+            hessian_products: self.hessian_products,
+        }
+    }
+}
+
+impl RunArgs {
+    /// Trace requested on the command line
+    fn trace(&self) -> Option<TraceOptions> {
+        let path = self.trace.clone()?;
+        let format = match self.trace_format.as_deref() {
+            Some("csv") => TraceFormat::Csv,
+            Some(_) => TraceFormat::Jsonl,
+            None => TraceFormat::from_path(&path),
+        };
+        let frequency = if self.trace_best {
+            TraceFrequency::NewBest
+        } else {
+            TraceFrequency::Every(self.trace_every)
+        };
+        Some(TraceOptions {
+            path,
+            format,
+            frequency,
+            append: false,
+        })
+    }
+
+    /// Contour plot requested on the command line
+    fn contour(&self) -> Option<ContourOptions> {
+        let path = self.contour.clone()?;
+        Some(ContourOptions {
+            bounds: self
+                .contour_bounds
+                .as_deref()
+                .and_then(|b| b.try_into().ok()),
+            levels: self.contour_levels,
+            resolution: self.contour_resolution,
+            // `contour_scale` is restricted to the scale names by clap
+            scale: LevelScale::from_name(&self.contour_scale).unwrap_or_default(),
+            ..ContourOptions::new(path)
+        })
+    }
+
+    /// Multi-start requested on the command line
+    fn multistart(&self) -> Option<MultiStartOptions> {
+        Some(MultiStartOptions {
+            starts: self.starts?,
+            // `sampling` is restricted to the scheme names by clap
+            sampling: Sampling::from_name(&self.sampling).unwrap_or_default(),
+            start_seed: self.start_seed,
+            cluster_tol: self.cluster_tol,
+        })
+    }
+
+    /// Outer loop selected on the command line, if there are constraints
+    fn outer(&self) -> Option<OuterOptions> {
+        if self.constraints.is_empty() {
+            return None;
+        }
+        Some(OuterOptions {
+            // `outer_method` is restricted to the method names by clap
+            method: PenaltyMethod::from_name(&self.outer_method).unwrap_or_default(),
+            outer_iters: self.outer_iters,
+            penalty: self.penalty,
+            penalty_growth: self.penalty_growth,
+            constraint_tol: self.constraint_tol,
+        })
+    }
+
+    /// Problem selected on the command line
+    fn problem_config(&self) -> Result<ProblemConfig, String> {
+        let config = match &self.objective {
+            Some(objective) => expression_config(objective, &self.variables, self.dim),
+            None => registered_config(&self.problem, self.dim, self.a, self.b),
+        };
+        let config = ProblemConfig {
+            bounded: self.bounded,
+            lower: self.lower.clone(),
+            upper: self.upper.clone(),
+            constraints: self.constraints.clone(),
+            ..config
+        };
+        config
+            .with_derivatives(&self.derivatives, self.fd_step, self.fd_hessian_step)
+            .map_err(|err| err.to_string())
+    }
+
+    /// Description of the run selected on the command line and its problem
+    fn spec(&self) -> Result<(RunSpec, DynProblem), String> {
+        let config = self.problem_config()?;
+        let problem = config.build().map_err(|err| err.to_string())?;
+        self.validate(&problem)?;
+        config.build_constraints(&problem).map_err(|err| err.to_string())?;
+        let checkpoint = self.checkpoint_dir.clone().map(|directory| CheckpointOptions {
+            directory,
+            every: self.checkpoint_every,
+        });
+        let spec = RunSpec {
+            // Record the dimension actually used rather than the one requested
+            problem: ProblemConfig {
+                dim: Some(problem.info().dim),
+                ..config
+            },
+            // `solver` is restricted to the method names by clap
+            solver: self
+                .tuning
+                .options(Method::from_name(&self.solver).unwrap_or(Method::SteepestDescent)),
+            run: RunConfig {
+                init_param: self.x0.clone().unwrap_or_else(|| problem.info().default_start.clone()),
+                stop: self.stop.criteria(),
+                trace: self.trace(),
+                checkpoint,
+                contour: self.contour(),
+                convergence: self.convergence.clone(),
+                multistart: self.multistart(),
+                outer: self.outer(),
+                history: None,
+            },
+        };
+        Ok((spec, problem))
+    }
+
+    /// Check the arguments which clap cannot validate on its own
+    fn validate(&self, problem: &DynProblem) -> Result<(), String> {
+        if let Some(x0) = &self.x0 {
+            let dim = problem.info().dim;
+            if x0.len() != dim {
+                return Err(format!(
+                    "--x0 has {} components, but the problem expects {}",
+                    x0.len(),
+                    dim
+                ));
+            }
+            if x0.iter().any(|x| !x.is_finite()) {
+                return Err("--x0 must only contain finite values".to_string());
+            }
+        }
+        if self.checkpoint_every == 0 {
+            return Err("--checkpoint-every must be at least 1".to_string());
+        }
+        if self.trace_every == 0 {
+            return Err("--trace-every must be at least 1".to_string());
+        }
+        // `solver` is restricted to the method names by clap
+        let method = Method::from_name(&self.solver).unwrap_or(Method::SteepestDescent);
+        self.stop.validate(&[method])?;
+        if let Some(contour) = self.contour() {
+            let info = problem.info();
+            if info.dim != 2 {
+                return Err(format!(
+                    "--contour needs a problem with 2 parameters, {} has {}",
+                    info.name, info.dim
+                ));
+            }
+            if self.contour_bounds.as_ref().is_some_and(|b| b.len() != 4) {
+                return Err("--contour-bounds needs 4 values: x_lo,x_hi,y_lo,y_hi".to_string());
+            }
+            contour.validate().map_err(flag_error("contour-"))?;
+        }
+        if let Some(multistart) = self.multistart() {
+            multistart
+                .validate(problem.info().dim)
+                .map_err(flag_error(""))?;
+        }
+        if let Some(outer) = self.outer() {
+            outer.validate().map_err(flag_error(""))?;
+        }
+        if !self.a.is_finite() || !self.b.is_finite() {
+            return Err("--a and --b must be finite".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// Rewrite a validation error of options into one about their flags
+///
+/// The messages of `validate` start with the name of the field, which is
+/// the name of the flag after `prefix`, with underscores instead of dashes:
+/// `max_iters must be at least 1` becomes `--max-iters must be at least 1`.
+fn flag_error(prefix: &'static str) -> impl Fn(Error) -> String {
+    move |err| match err.downcast_ref() {
+        Some(ArgminError::InvalidParameter { text }) => {
+            let (field, rest) = text.split_once(' ').unwrap_or((text, ""));
+            format!("--{}{} {}", prefix, field.replace('_', "-"), rest)
+        }
+        _ => err.to_string(),
+    }
+}
+
+/// Registered problem `name` with the parameters given on the command line
+fn registered_config(name: &str, dim: Option<usize>, a: f64, b: f64) -> ProblemConfig {
+    ProblemConfig {
+        name: name.to_string(),
+        dim,
+        parameters: problem_parameters(name, a, b),
+        numeric: None,
+        autodiff: None,
+        expression: None,
+        variables: None,
+        bounded: false,
+        lower: None,
+        upper: None,
+        constraints: Vec::new(),
+    }
+}
+
+/// Problem given by `--objective`, named after the expression
+fn expression_config(
+    objective: &str,
+    variables: &Option<Vec<String>>,
+    dim: Option<usize>,
+) -> ProblemConfig {
+    ProblemConfig {
+        name: objective.to_string(),
+        dim,
+        parameters: BTreeMap::new(),
+        numeric: None,
+        autodiff: None,
+        expression: Some(objective.to_string()),
+        variables: variables.clone(),
+        bounded: false,
+        lower: None,
+        upper: None,
+        constraints: Vec::new(),
+    }
+}
+
+/// Parameters of the problem `name` given on the command line
+fn problem_parameters(name: &str, a: f64, b: f64) -> BTreeMap<String, f64> {
+    let mut parameters = BTreeMap::new();
+    if matches!(name, "rosenbrock" | "rosenbrock-nd") {
+        parameters.insert("a".to_string(), a);
+        parameters.insert("b".to_string(), b);
+    }
+    parameters
+}
 
 fn main() {
 
     env::set_var("RUST_BACKTRACE", "1");
 
-    /// First, we create a struct called `Rosenbrock` for your problem
-    struct Rosenbrock {
-        a: f64,
-        b: f64,
-    }
-
-    /// Implement `CostFunction` for `Rosenbrock`
-    ///
-    /// First, we need to define the types which we will be using. Our parameter
-    /// vector will be a `Vec` of `f64` values and our cost function value will 
-    /// be a 64 bit floating point value.
-    /// This is reflected in the associated types `Param` and `Output`, respectively.
-    ///
-    /// The method `cost` then defines how the cost function is computed for a
-    /// parameter vector `p`. Note that we have access to the fields `a` and `b`
-    /// of `Rosenbrock`.
-    impl CostFunction for Rosenbrock {
-        /// Type of the parameter vector
-        type Param = Vec<f64>;
-        /// Type of the return value computed by the cost function
-        type Output = f64;
-
-        /// Apply the cost function to a parameter `p`
-        fn cost(&self, p: &Self::Param) -> Result<Self::Output, Error> {
-            // Evaluate 2D Rosenbrock function
-            Ok(rosenbrock_2d(p, self.a, self.b))
-        }
-    }
-
-    /// Implement `Gradient` for `Rosenbrock`
-    ///
-    /// Similarly to `CostFunction`, we need to define the type of our parameter
-    /// vectors and of the gradient we are computing. Since the gradient is also
-    /// a vector, it is of type `Vec<f64>` just like `Param`.
-    impl Gradient for Rosenbrock {
-        /// Type of the parameter vector
-        type Param = Vec<f64>;
-        /// Type of the gradient
-        type Gradient = Vec<f64>;
-
-        /// Compute the gradient at parameter `p`.
-        fn gradient(&self, p: &Self::Param) -> Result<Self::Gradient, Error> {
-            // Compute gradient of 2D Rosenbrock function
-            Ok(rosenbrock_2d_derivative(p, self.a, self.b))
-        }
-    }
-
-    /// Implement `Hessian` for `Rosenbrock`
-    ///
-    /// Again the types of the involved parameter vector and the Hessian needs to
-    /// be defined. Since the Hessian is a 2D matrix, we use `Vec<Vec<f64>>` here.
-    impl Hessian for Rosenbrock {
-        /// Type of the parameter vector
-        type Param = Vec<f64>;
-        /// Type of the Hessian
-        type Hessian = Vec<Vec<f64>>;
-
-        /// Compute the Hessian at parameter `p`.
-        fn hessian(&self, p: &Self::Param) -> Result<Self::Hessian, Error> {
-            // Compute Hessian of 2D Rosenbrock function
-            let t = rosenbrock_2d_hessian(p, self.a, self.b);
-            // Reshape the output
-            Ok(vec![vec![t[0], t[1]], vec![t[2], t[3]]])
-        }
-    }
-
-    let init_param = vec![1.0, -2.0];
-
-    let cost = Rosenbrock { a: (1.0), b: (100.0) };
-    let linesearch: MoreThuenteLineSearch<Vec<f64>, Vec<f64>, f64> = MoreThuenteLineSearch::new();
-    let solver = SteepestDescent::new(linesearch);
-
-    let res = Executor::new(cost, solver)
-        // Via `configure`, one has access to the internally used state.
-        // This state can be initialized, for instance by providing an
-        // initial parameter vector.
-        // The maximum number of iterations is also set via this method.
-        // In this particular case, the state exposed is of type `IterState`.
-        // The documentation of `IterState` shows how this struct can be
-        // manipulated.
-        // Population based solvers use `PopulationState` instead of 
-        // `IterState`.
-        .configure(|state|
-            state
-                // Set initial parameters (depending on the solver,
-                // this may be required)
-                .param(init_param)
-                // Set maximum iterations to 10
-                // (optional, set to `std::u64::MAX` if not provided)
-                .max_iters(1000)
-                // Set target cost. The solver stops when this cost
-                // function value is reached (optional)
-                .target_cost(0.0)
-        )
-        // run the solver on the defined problem
-        .run();
+    let matches = Cli::command().get_matches();
+    let cli = Cli::from_arg_matches(&matches).unwrap_or_else(|err| err.exit());
+
+    match cli.command {
+        Command::Run(args) => {
+            let spec = match &args.config {
+                Some(path) => {
+                    reject_flags_with_config(&matches);
+                    config::load(path)
+                }
+                None => match args.spec() {
+                    Ok((spec, problem)) => Ok((spec, problem, args.format)),
+                    Err(msg) => usage_error("run", ErrorKind::ValueValidation, msg),
+                },
+            };
+            match spec {
+                Ok((spec, problem, format)) => run(spec, problem, format, args.jobs),
+                Err(err) => {
+                    eprintln!("error: {}", err);
+                    process::exit(1);
+                }
+            }
+        }
+        Command::Resume(args) => resume(args),
+        Command::CheckDerivatives(args) => check_derivatives(args),
+        Command::Bench(args) => bench(args),
+        Command::List => list(),
+    }
+
+}
+
+/// Exit with the error `msg` of the subcommand `name`, followed by the usage
+/// of that subcommand
+fn usage_error(name: &str, kind: ErrorKind, msg: String) -> ! {
+    let mut cli = Cli::command();
+    // Building sets the binary names shown in the usage of the subcommands
+    cli.build();
+    match cli.find_subcommand(name).cloned() {
+        Some(mut subcommand) => subcommand.error(kind, msg).exit(),
+        None => cli.error(kind, msg).exit(),
+    }
+}
+
+/// Exit with an error if `opt run --config` is combined with other flags
+///
+/// The experiment file is the complete description of the run, so that it
+/// can be repeated exactly.
+fn reject_flags_with_config(matches: &ArgMatches) {
+    let Some(("run", run_matches)) = matches.subcommand() else {
+        return;
+    };
+    let flags: Vec<(String, String)> = Cli::command()
+        .find_subcommand("run")
+        .into_iter()
+        .flat_map(|run| run.get_arguments())
+        .filter_map(|arg| Some((arg.get_id().to_string(), arg.get_long()?.to_string())))
+        .collect();
+    for (id, long) in flags {
+        // `jobs` only decides how the runs are executed, not their results
+        if id != "config" && id != "jobs" && run_matches.value_source(&id) == Some(ValueSource::CommandLine) {
+            usage_error(
+                "run",
+                ErrorKind::ArgumentConflict,
+                format!("--{} cannot be used with --config, set it in the experiment file", long),
+            );
+        }
+    }
+}
+
+/// Print every problem and solver which can be passed to `run`
+fn list() {
+    println!("Problems:");
+    for entry in problem::PROBLEMS {
+        println!(
+            "  {:<24} {:<6} {:<20} {}",
+            entry.name,
+            entry.dim.to_string(),
+            domain(entry.name),
+            entry.description
+        );
+    }
+    println!("Solvers:");
+    for method in Method::ALL {
+        println!("  {:<24} {}", method.name(), method.description());
+    }
+    println!("Line searches:");
+    for method in LineSearchMethod::ALL {
+        println!("  {:<24} {}", method.name(), method.description());
+    }
+    println!("Constraint methods:");
+    for method in PenaltyMethod::ALL {
+        println!("  {:<24} {}", method.name(), method.description());
+    }
+    println!("Backends:");
+    for &backend in Backend::ALL {
+        let unsupported: Vec<&str> = Method::ALL
+            .iter()
+            .filter(|method| !method.supports(backend))
+            .map(|method| method.name())
+            .collect();
+        let note = if unsupported.is_empty() {
+            String::new()
+        } else {
+            format!(" (not with {})", unsupported.join(", "))
+        };
+        println!("  {:<24} {}{}", backend.name(), backend.description(), note);
+    }
+}
+
+/// Canonical domain of the registered problem `name`, e.g. `[-5, 5]^n`
+/// if all parameters share the same bounds
+fn domain(name: &str) -> String {
+    let Ok(problem) = problem::build(name, None) else {
+        return String::new();
+    };
+    let info = problem.info();
+    let exponent = match problem::find(name).map(|entry| entry.dim) {
+        Some(Dimension::Any { .. }) => "^n".to_string(),
+        _ => format!("^{}", info.dim),
+    };
+    match info.bounds.split_first() {
+        Some((&(l, u), rest)) if rest.iter().all(|&b| b == (l, u)) => {
+            format!("[{}, {}]{}", l, u, exponent)
+        }
+        _ => "per parameter".to_string(),
+    }
+}
+
+/// Start the run described by `spec`
+fn run(spec: RunSpec, problem: DynProblem, format: OutputFormat, jobs: usize) {
+
+    if spec.run.checkpoint.is_some() {
+        if let Err(err) = checkpoint::prepare(&spec) {
+            eprintln!("error: {}", err);
+            process::exit(1);
+        }
+    }
+
+    execute(spec, problem, format, jobs);
+
+}
+
+/// Continue a checkpointed run
+fn resume(args: ResumeArgs) {
+    let spec = checkpoint::load(&args.directory).and_then(|mut spec| {
+        // Continue the trace of the interrupted run instead of replacing it
+        if let Some(trace) = spec.run.trace.as_mut() {
+            trace.append = true;
+        }
+        let problem = spec.problem.build()?;
+        Ok((spec, problem))
+    });
+    match spec {
+        Ok((spec, problem)) => execute(spec, problem, args.format, 1),
+        Err(err) => {
+            eprintln!("error: {}", err);
+            process::exit(1);
+        }
+    }
+}
+
+/// Check the derivatives of the selected problems
+///
+/// Exits with status 1 if a check fails.
+fn check_derivatives(args: CheckArgs) {
+    let options = match args.options() {
+        Ok(options) => options,
+        Err(msg) => usage_error("check-derivatives", ErrorKind::ValueValidation, msg),
+    };
+    let configs = args.problem_configs();
+    let reports = configs.iter().map(|config| {
+        let problem = config.build()?;
+        check::check(&problem, &options)
+    });
+    let reports = match reports.collect::<Result<Vec<_>, _>>() {
+        Ok(reports) => reports,
+        Err(err) => {
+            eprintln!("error: {}", err);
+            process::exit(1);
+        }
+    };
+    match args.format {
+        OutputFormat::Text => {
+            for report in &reports {
+                println!("{}", report);
+            }
+        }
+        OutputFormat::Json => {
+            // Serializing plain data into a `String` cannot fail
+            let json = serde_json::to_string_pretty(&reports).expect("reports are serializable");
+            println!("{}", json);
+        }
+    }
+    if reports.iter().any(|report| !report.passed) {
+        process::exit(1);
+    }
+}
+
+/// Run the benchmark selected on the command line and print its results
+fn bench(args: BenchArgs) {
+    let options = match args.options() {
+        Ok(options) => options,
+        Err(msg) => usage_error("bench", ErrorKind::ValueValidation, msg),
+    };
+    let report = match bench::bench(&options, args.jobs) {
+        Ok(report) => report,
+        Err(err) => {
+            eprintln!("error: {}", err);
+            process::exit(1);
+        }
+    };
+    print!("{}", report.render(args.format, args.runs));
+    if let Some(directory) = &args.profiles {
+        let options = ProfileOptions {
+            tolerance: args.profile_tol,
+            // `profile_measure` is restricted to the measure names by clap
+            measure: Measure::from_name(&args.profile_measure).unwrap_or_default(),
+        };
+        if let Err(err) = profile::write(&report, &options, directory) {
+            eprintln!("error: {}", err);
+            process::exit(1);
+        }
+    }
+    if let Some(directory) = &args.convergence {
+        if let Err(err) = convergence::write_bench(&report, &options, directory) {
+            eprintln!("error: {}", err);
+            process::exit(1);
+        }
+    }
+}
+
+/// Solve `problem` as described by `spec`, print the result and draw the
+/// requested plots
+fn execute(spec: RunSpec, problem: DynProblem, format: OutputFormat, jobs: usize) {
+
+    if !spec.problem.constraints.is_empty() {
+        execute_constrained(&spec, &problem, format);
+        return;
+    }
+    if let Some(options) = &spec.run.multistart {
+        execute_multistart(&spec, &problem, options, format, jobs);
+        return;
+    }
+
+    // The plots need every iterate
+    let mut run = spec.run.clone();
+    if run.contour.is_some() || run.convergence.is_some() {
+        run.history = Some(History::new());
+    }
+    let res = solver::solve(problem.clone(), &spec.solver, &run);
 
     let res = match res {
         Ok(res) => res,
-        Err(err) => !panic!("{}", err),
+        Err(err) => {
+            eprintln!("error: {}", err);
+            process::exit(1);
+        }
     };
 
     // print result
-    println!("{}", res);
+    match format {
+        OutputFormat::Text => println!("{}", res),
+        OutputFormat::Json => {
+            let record = Record {
+                problem: spec.problem,
+                solver: &spec.solver,
+                run: &spec.run,
+                result: &res,
+            };
+            println!("{}", record.to_json());
+        }
+    }
+
+    if let (Some(options), Some(history)) = (&run.contour, &run.history) {
+        // The first point of the history is the first iterate, not the start
+        let path: Vec<Vec<f64>> = std::iter::once(run.init_param.clone())
+            .chain(history.points().into_iter().map(|point| point.param))
+            .collect();
+        if let Err(err) = contour::render(&problem, options, &path) {
+            eprintln!("error: {}", err);
+            process::exit(1);
+        }
+    }
+    if let (Some(path), Some(history)) = (&run.convergence, &run.history) {
+        let runs = [ConvergenceRun {
+            label: spec.solver.method.name().to_string(),
+            points: history.points(),
+        }];
+        if let Err(err) = convergence::write(path, problem.info(), &runs) {
+            eprintln!("error: {}", err);
+            process::exit(1);
+        }
+    }
+
+}
+
+/// Solve the constrained problem described by `spec` with its outer loop,
+/// print the report and draw the contour plot if requested
+fn execute_constrained(spec: &RunSpec, problem: &DynProblem, format: OutputFormat) {
+    let fail = |err: argmin::core::Error| -> ! {
+        eprintln!("error: {}", err);
+        process::exit(1);
+    };
+    let constraints = spec.problem.build_constraints(problem).unwrap_or_else(|err| fail(err));
+    let options = spec.run.outer.clone().unwrap_or_default();
+    // The contour plot shows the iterates of every subproblem
+    let mut run = spec.run.clone();
+    if run.contour.is_some() {
+        run.history = Some(History::new());
+    }
+    let report = constraint::solve_constrained(problem, constraints, &spec.solver, &run, &options)
+        .unwrap_or_else(|err| fail(err));
+    match format {
+        OutputFormat::Text => print!("{}", report),
+        OutputFormat::Json => {
+            let record = Record {
+                problem: spec.problem.clone(),
+                solver: &spec.solver,
+                run: &spec.run,
+                result: &report,
+            };
+            println!("{}", record.to_json());
+        }
+    }
+    if let (Some(options), Some(history)) = (&run.contour, &run.history) {
+        let path: Vec<Vec<f64>> = std::iter::once(problem.info().project(&run.init_param))
+            .chain(history.points().into_iter().map(|point| point.param))
+            .collect();
+        if let Err(err) = contour::render(problem, options, &path) {
+            fail(err);
+        }
+    }
+}
 
+/// Run the multi-start described by `spec`, print its report and draw the
+/// convergence plot if requested
+fn execute_multistart(
+    spec: &RunSpec,
+    problem: &DynProblem,
+    options: &MultiStartOptions,
+    format: OutputFormat,
+    jobs: usize,
+) {
+    let report = match multistart::multistart(problem, &spec.solver, &spec.run, options, jobs) {
+        Ok(report) => report,
+        Err(err) => {
+            eprintln!("error: {}", err);
+            process::exit(1);
+        }
+    };
+    match format {
+        OutputFormat::Text => print!("{}", report),
+        OutputFormat::Json => {
+            let record = Record {
+                problem: spec.problem.clone(),
+                solver: &spec.solver,
+                run: &spec.run,
+                result: &report,
+            };
+            println!("{}", record.to_json());
+        }
+    }
+    if let Some(path) = &spec.run.convergence {
+        let runs: Vec<ConvergenceRun> = report
+            .runs
+            .iter()
+            .map(|run| ConvergenceRun {
+                label: format!("start {}", run.start),
+                points: run.trace.clone(),
+            })
+            .collect();
+        if let Err(err) = convergence::write(path, problem.info(), &runs) {
+            eprintln!("error: {}", err);
+            process::exit(1);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cli_definition_is_consistent() {
+        Cli::command().debug_assert();
+    }
+
+    #[test]
+    fn run_arguments_are_parsed() {
+        let argv = ["opt", "run", "--problem", "sphere", "--dim", "3", "--x0", "1,-2,3"];
+        let cli = Cli::try_parse_from(argv).unwrap();
+        let Command::Run(args) = cli.command else {
+            panic!("expected the run subcommand");
+        };
+        let (spec, problem) = args.spec().unwrap();
+        assert_eq!(problem.info().dim, 3);
+        assert_eq!(spec.run.init_param, vec![1.0, -2.0, 3.0]);
+    }
+
+    #[test]
+    fn invalid_starting_point_is_rejected() {
+        let argv = ["opt", "run", "--problem", "rosenbrock", "--x0", "1,2,3"];
+        let cli = Cli::try_parse_from(argv).unwrap();
+        let Command::Run(args) = cli.command else {
+            panic!("expected the run subcommand");
+        };
+        let err = args.spec().err().unwrap();
+        assert!(err.starts_with("--x0"), "{}", err);
+    }
+
+    #[test]
+    fn validation_errors_name_the_flag() {
+        let cases: [(&[&str], &str); 4] = [
+            (&["--max-iters", "0"], "--max-iters must be at least 1"),
+            (&["--starts", "4", "--cluster-tol", "0"], "--cluster-tol must be positive"),
+            (&["--contour", "a.svg", "--contour-levels", "0"], "--contour-levels must be"),
+            (
+                &["--constraint", "x1 + x2 = 1", "--penalty-growth", "1"],
+                "--penalty-growth must be greater than 1",
+            ),
+        ];
+        for (flags, message) in cases {
+            let argv = ["opt", "run", "--problem", "booth"].iter().chain(flags);
+            let cli = Cli::try_parse_from(argv).unwrap();
+            let Command::Run(args) = cli.command else {
+                panic!("expected the run subcommand");
+            };
+            let err = args.spec().err().unwrap();
+            assert!(err.starts_with(message), "{}", err);
+        }
+        let other: Error = ArgminError::NotImplemented {
+            text: "no".to_string(),
+        }
+        .into();
+        assert_eq!(flag_error("")(other), "Not implemented: \"no\"");
+    }
 }
@@ -0,0 +1,571 @@
+//! Multi-start global optimization
+//!
+//! Local solvers find the minimum of the basin they start in, so a single
+//! run says little about multimodal problems. [`multistart`] samples starting
+//! points in the canonical domain of a problem ([`ProblemInfo::bounds`]),
+//! runs the local solver from each of them and groups the minima found into
+//! basins: two minima belong to the same basin if they are closer than
+//! `cluster_tol` after scaling every parameter by the width of its domain.
+//! The [`MultiStartReport`] lists the basins by cost, the first one being
+//! the best minimum found.
+//!
+//! Starting points are drawn by one of three [`Sampling`] schemes:
+//!
+//! * `uniform`: independently and uniformly distributed,
+//! * `latin-hypercube`: every parameter takes each of `starts` equally wide
+//!   strata of its domain exactly once, and
+//! * `sobol`: the Sobol low-discrepancy sequence (direction numbers of Joe
+//!   and Kuo), skipping its first point, the lower corner of the domain.
+//!   The sequence is deterministic and supports up to 21 parameters.
+//!
+//! Runs which fail with an error are reported but do not stop the others.
+//! The runs can be executed concurrently ([`crate::parallel`]).
+//!
+//! [`ProblemInfo::bounds`]: crate::problem::ProblemInfo::bounds
+
+use crate::bench::Table;
+use crate::problem::DynProblem;
+use crate::report::Report;
+use crate::parallel;
+use crate::solver::{self, derivative_free, SolverOptions};
+use crate::trace::{History, TracePoint};
+use crate::RunConfig;
+use argmin::core::{ArgminError, Error};
+use rand::seq::SliceRandom;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// How the starting points are drawn
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Sampling {
+    /// Independent uniformly distributed points
+    Uniform,
+    /// Latin hypercube sample
+    #[default]
+    LatinHypercube,
+    /// Sobol sequence
+    Sobol,
+}
+
+impl Sampling {
+    /// All sampling schemes
+    pub const ALL: [Sampling; 3] = [Sampling::Uniform, Sampling::LatinHypercube, Sampling::Sobol];
+
+    /// Name of the scheme on the command line
+    pub fn name(self) -> &'static str {
+        match self {
+            Sampling::Uniform => "uniform",
+            Sampling::LatinHypercube => "latin-hypercube",
+            Sampling::Sobol => "sobol",
+        }
+    }
+
+    /// Scheme called `name`
+    pub fn from_name(name: &str) -> Option<Self> {
+        Sampling::ALL.into_iter().find(|s| s.name() == name)
+    }
+}
+
+/// Settings of a multi-start run
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct MultiStartOptions {
+    /// Number of starting points
+    pub starts: usize,
+    /// How the starting points are drawn
+    pub sampling: Sampling,
+    /// Seed of the starting points (drawn from entropy if `None`); the Sobol
+    /// sequence does not use it
+    pub start_seed: Option<u64>,
+    /// Distance below which two minima belong to the same basin, relative to
+    /// the size of the domain
+    pub cluster_tol: f64,
+}
+
+impl Default for MultiStartOptions {
+    fn default() -> Self {
+        MultiStartOptions {
+            starts: 20,
+            sampling: Sampling::LatinHypercube,
+            start_seed: None,
+            cluster_tol: 1e-2,
+        }
+    }
+}
+
+impl MultiStartOptions {
+    /// Check the options for a problem with `dim` parameters
+    ///
+    /// Messages start with the name of the offending field.
+    pub fn validate(&self, dim: usize) -> Result<(), Error> {
+        let invalid = |text: String| -> Result<(), Error> {
+            Err(ArgminError::InvalidParameter { text }.into())
+        };
+        if self.starts == 0 {
+            return invalid("starts must be at least 1".to_string());
+        }
+        if !(self.cluster_tol.is_finite() && self.cluster_tol > 0.0) {
+            return invalid("cluster_tol must be positive".to_string());
+        }
+        if self.sampling == Sampling::Sobol && dim > SOBOL_DIRECTIONS.len() + 1 {
+            return invalid(format!(
+                "sampling sobol supports at most {} parameters, the problem has {}",
+                SOBOL_DIRECTIONS.len() + 1,
+                dim
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// One run of a multi-start
+#[derive(Clone, Debug, Serialize)]
+pub struct StartRun {
+    /// Index of the starting point
+    pub start: usize,
+    /// Starting point
+    pub x0: Vec<f64>,
+    /// Outcome of the run (`None` if it failed)
+    pub report: Option<Report>,
+    /// Why the run failed
+    pub error: Option<String>,
+    /// Index of the basin of the minimum found, in [`MultiStartReport::basins`]
+    pub basin: Option<usize>,
+    /// Every iteration of the run, for convergence plots; not serialized
+    #[serde(skip)]
+    pub trace: Vec<TracePoint>,
+}
+
+/// Minima found by several runs which lie close together
+#[derive(Clone, Debug, Serialize)]
+pub struct Basin {
+    /// Best parameter vector found in the basin
+    pub param: Vec<f64>,
+    /// Cost function value at `param`
+    pub cost: f64,
+    /// Starting points whose runs ended in the basin
+    pub starts: Vec<usize>,
+}
+
+/// Outcome of a multi-start run
+#[derive(Clone, Debug, Serialize)]
+pub struct MultiStartReport {
+    /// Name of the problem
+    pub problem: String,
+    /// Name of the local solver
+    pub solver: String,
+    /// Settings of the multi-start
+    pub options: MultiStartOptions,
+    /// The runs, in the order of their starting points
+    pub runs: Vec<StartRun>,
+    /// Distinct basins found, best first
+    pub basins: Vec<Basin>,
+}
+
+impl MultiStartReport {
+    /// Best basin found, `None` if every run failed
+    pub fn best(&self) -> Option<&Basin> {
+        self.basins.first()
+    }
+
+    /// Number of runs which failed with an error
+    pub fn failures(&self) -> usize {
+        self.runs.iter().filter(|run| run.error.is_some()).count()
+    }
+}
+
+impl fmt::Display for MultiStartReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "MultiStart:")?;
+        writeln!(f, "    Problem:        {}", self.problem)?;
+        writeln!(f, "    Solver:         {}", self.solver)?;
+        writeln!(
+            f,
+            "    starts:         {} ({})",
+            self.runs.len(),
+            self.options.sampling.name()
+        )?;
+        writeln!(f, "    failures:       {}", self.failures())?;
+        match self.best() {
+            Some(best) => {
+                writeln!(f, "    param (best):   {:?}", best.param)?;
+                writeln!(f, "    cost (best):    {}", best.cost)?;
+            }
+            None => writeln!(f, "    param (best):   none, every run failed")?,
+        }
+        writeln!(f, "    basins:         {}", self.basins.len())?;
+        writeln!(f)?;
+        let mut table = Table::new(&["basin", "cost", "param", "runs", "starts"]);
+        for (k, basin) in self.basins.iter().enumerate() {
+            let starts: Vec<String> = basin.starts.iter().map(|s| s.to_string()).collect();
+            table.push(vec![
+                k.to_string(),
+                format!("{:.6e}", basin.cost),
+                format!("{:?}", basin.param),
+                basin.starts.len().to_string(),
+                starts.join(","),
+            ]);
+        }
+        write!(f, "{}", table.markdown())?;
+        for run in &self.runs {
+            if let Some(error) = &run.error {
+                writeln!(f, "start {} failed: {}", run.start, error)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Run `solver` on `problem` from `options.starts` starting points
+///
+/// Starting point, trace and checkpoint of `config` are not used; every run
+/// stops by the criteria of `config`. The runs are executed on `jobs`
+/// threads ([`parallel::map`]); the report does not depend on their number.
+pub fn multistart(
+    problem: &DynProblem,
+    solver: &SolverOptions,
+    config: &RunConfig,
+    options: &MultiStartOptions,
+    jobs: usize,
+) -> Result<MultiStartReport, Error> {
+    let info = problem.info();
+    options.validate(info.dim)?;
+    let starts = sample(
+        options.sampling,
+        options.starts,
+        &info.bounds,
+        options.start_seed,
+    );
+
+    let outcomes = parallel::map(starts.clone(), jobs, |x0| {
+        let history = History::new();
+        let run = RunConfig {
+            init_param: x0,
+            stop: config.stop.clone(),
+            trace: None,
+            checkpoint: None,
+            contour: None,
+            convergence: None,
+            multistart: None,
+            outer: None,
+            history: Some(history.clone()),
+        };
+        let report = solver::solve(problem.clone(), solver, &run)?;
+        Ok((report, history.points()))
+    });
+    let mut runs: Vec<StartRun> = starts
+        .into_iter()
+        .zip(outcomes)
+        .enumerate()
+        .map(|(start, (x0, outcome))| {
+            let (report, error, trace) = match outcome {
+                Ok((report, trace)) => (Some(report), None, trace),
+                Err(err) => (None, Some(err.to_string()), Vec::new()),
+            };
+            StartRun {
+                start,
+                x0,
+                report,
+                error,
+                basin: None,
+                trace,
+            }
+        })
+        .collect();
+
+    let basins = cluster(&mut runs, &info.bounds, options.cluster_tol);
+    Ok(MultiStartReport {
+        problem: info.name.clone(),
+        solver: solver.method.name().to_string(),
+        options: options.clone(),
+        runs,
+        basins,
+    })
+}
+
+/// Group the minima found by `runs` into basins and record the basin of
+/// every run
+///
+/// Minima are visited from the lowest cost on; each joins the first basin
+/// whose best point is within `tol`, in coordinates scaled to the unit box,
+/// or else founds a new basin. Runs ending at a non-finite cost function
+/// value are left out.
+fn cluster(runs: &mut [StartRun], bounds: &[(f64, f64)], tol: f64) -> Vec<Basin> {
+    let scaled_distance = |a: &[f64], b: &[f64]| {
+        a.iter()
+            .zip(b)
+            .zip(bounds)
+            .map(|((x, y), (lo, hi))| ((x - y) / (hi - lo)).powi(2))
+            .sum::<f64>()
+            .sqrt()
+    };
+    let mut order: Vec<usize> = (0..runs.len())
+        .filter(|&k| runs[k].report.as_ref().is_some_and(|r| r.cost.is_finite()))
+        .collect();
+    order.sort_by(|&a, &b| {
+        let cost = |k: usize| runs[k].report.as_ref().map_or(f64::NAN, |r| r.cost);
+        cost(a).total_cmp(&cost(b))
+    });
+
+    let mut basins: Vec<Basin> = Vec::new();
+    for k in order {
+        let Some(report) = &runs[k].report else {
+            continue;
+        };
+        let found = basins
+            .iter()
+            .position(|basin| scaled_distance(&basin.param, &report.param) <= tol);
+        let index = match found {
+            Some(index) => index,
+            None => {
+                basins.push(Basin {
+                    param: report.param.clone(),
+                    cost: report.cost,
+                    starts: Vec::new(),
+                });
+                basins.len() - 1
+            }
+        };
+        basins[index].starts.push(runs[k].start);
+        runs[k].basin = Some(index);
+    }
+    for basin in &mut basins {
+        basin.starts.sort_unstable();
+    }
+    basins
+}
+
+/// `n` starting points in the box `bounds`, drawn by `sampling`
+pub fn sample(
+    sampling: Sampling,
+    n: usize,
+    bounds: &[(f64, f64)],
+    seed: Option<u64>,
+) -> Vec<Vec<f64>> {
+    let mut rng = derivative_free::rng(seed);
+    let dim = bounds.len();
+    // Points in the unit cube
+    let unit: Vec<Vec<f64>> = match sampling {
+        Sampling::Uniform => (0..n)
+            .map(|_| (0..dim).map(|_| rng.gen::<f64>()).collect())
+            .collect(),
+        Sampling::LatinHypercube => {
+            let mut points = vec![vec![0.0; dim]; n];
+            for d in 0..dim {
+                let mut strata: Vec<usize> = (0..n).collect();
+                strata.shuffle(&mut rng);
+                for (point, stratum) in points.iter_mut().zip(strata) {
+                    point[d] = (stratum as f64 + rng.gen::<f64>()) / n as f64;
+                }
+            }
+            points
+        }
+        Sampling::Sobol => sobol(n, dim),
+    };
+    unit.into_iter()
+        .map(|point| {
+            point
+                .iter()
+                .zip(bounds)
+                .map(|(u, (lo, hi))| lo + u * (hi - lo))
+                .collect()
+        })
+        .collect()
+}
+
+/// Primitive polynomials and initial direction numbers `(s, a, m)` of the
+/// Sobol sequence in dimensions 2 to 21 (Joe and Kuo, `new-joe-kuo-6.21201`)
+const SOBOL_DIRECTIONS: [(u32, u32, &[u32]); 20] = [
+    (1, 0, &[1]),
+    (2, 1, &[1, 3]),
+    (3, 1, &[1, 3, 1]),
+    (3, 2, &[1, 1, 1]),
+    (4, 1, &[1, 1, 3, 3]),
+    (4, 4, &[1, 3, 5, 13]),
+    (5, 2, &[1, 1, 5, 5, 17]),
+    (5, 4, &[1, 1, 5, 5, 5]),
+    (5, 7, &[1, 1, 7, 11, 19]),
+    (5, 11, &[1, 1, 5, 1, 1]),
+    (5, 13, &[1, 1, 1, 3, 11]),
+    (5, 14, &[1, 3, 5, 5, 31]),
+    (6, 1, &[1, 3, 3, 9, 7, 49]),
+    (6, 13, &[1, 1, 1, 15, 21, 21]),
+    (6, 16, &[1, 3, 1, 13, 27, 49]),
+    (6, 19, &[1, 1, 1, 15, 7, 5]),
+    (6, 22, &[1, 3, 1, 15, 13, 25]),
+    (6, 25, &[1, 1, 5, 5, 19, 61]),
+    (7, 1, &[1, 3, 7, 11, 23, 15, 103]),
+    (7, 4, &[1, 3, 7, 13, 13, 15, 69]),
+];
+
+/// Bits of the Sobol points
+const SOBOL_BITS: usize = 32;
+
+/// Points 1 to `n` of the `dim`-dimensional Sobol sequence
+fn sobol(n: usize, dim: usize) -> Vec<Vec<f64>> {
+    // Direction numbers v[d][k], scaled by 2^32
+    let directions: Vec<Vec<u32>> = (0..dim)
+        .map(|d| {
+            let mut v = vec![0u32; SOBOL_BITS];
+            if d == 0 {
+                for (k, v) in v.iter_mut().enumerate() {
+                    *v = 1 << (SOBOL_BITS - 1 - k);
+                }
+                return v;
+            }
+            let (s, a, m) = SOBOL_DIRECTIONS[d - 1];
+            let s = s as usize;
+            for k in 0..s.min(SOBOL_BITS) {
+                v[k] = m[k] << (SOBOL_BITS - 1 - k);
+            }
+            for k in s..SOBOL_BITS {
+                let mut next = v[k - s] ^ (v[k - s] >> s);
+                for i in 1..s {
+                    if (a >> (s - 1 - i)) & 1 == 1 {
+                        next ^= v[k - i];
+                    }
+                }
+                v[k] = next;
+            }
+            v
+        })
+        .collect();
+
+    // Gray code order: point i + 1 differs from point i in the direction
+    // of the lowest zero bit of i
+    let mut x = vec![0u32; dim];
+    let mut points = Vec::with_capacity(n);
+    for i in 0..n {
+        let bit = (!(i as u64)).trailing_zeros() as usize;
+        for (x, v) in x.iter_mut().zip(&directions) {
+            *x ^= v[bit.min(SOBOL_BITS - 1)];
+        }
+        points.push(x.iter().map(|&x| x as f64 / 2f64.powi(32)).collect());
+    }
+    points
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::problem;
+    use crate::solver::Method;
+
+    fn run(
+        name: &str,
+        method: Method,
+        options: &MultiStartOptions,
+        jobs: usize,
+    ) -> MultiStartReport {
+        let problem = problem::build(name, None).unwrap();
+        let config = RunConfig::default();
+        multistart(&problem, &SolverOptions::new(method), &config, options, jobs).unwrap()
+    }
+
+    #[test]
+    fn names_round_trip() {
+        for sampling in Sampling::ALL {
+            assert_eq!(Sampling::from_name(sampling.name()), Some(sampling));
+        }
+        assert_eq!(Sampling::from_name("grid"), None);
+    }
+
+    #[test]
+    fn invalid_options_are_rejected() {
+        let defaults = MultiStartOptions::default();
+        let invalid = [
+            MultiStartOptions { starts: 0, ..defaults.clone() },
+            MultiStartOptions { cluster_tol: 0.0, ..defaults.clone() },
+            MultiStartOptions { cluster_tol: f64::NAN, ..defaults.clone() },
+        ];
+        for options in invalid {
+            assert!(options.validate(2).is_err(), "{:?}", options);
+        }
+        let sobol = MultiStartOptions { sampling: Sampling::Sobol, ..defaults };
+        assert!(sobol.validate(21).is_ok());
+        let err = sobol.validate(22).unwrap_err().to_string();
+        assert!(err.contains("sampling sobol supports at most 21 parameters"), "{}", err);
+    }
+
+    #[test]
+    fn sobol_points_match_the_reference_sequence() {
+        let points = sobol(4, 2);
+        assert_eq!(points, [[0.5, 0.5], [0.75, 0.25], [0.25, 0.75], [0.375, 0.375]]);
+        // With the skipped origin, the first 2^k points have one coordinate
+        // in each of the 2^k strata of every axis
+        let points = sobol(63, 21);
+        for d in 0..21 {
+            let mut strata: Vec<usize> = points.iter().map(|p| (p[d] * 64.0) as usize).collect();
+            strata.push(0);
+            strata.sort_unstable();
+            assert_eq!(strata, (0..64).collect::<Vec<_>>(), "dimension {}", d);
+        }
+    }
+
+    #[test]
+    fn samples_fill_the_box() {
+        let bounds = [(-1.0, 1.0), (10.0, 20.0)];
+        for sampling in Sampling::ALL {
+            let points = sample(sampling, 16, &bounds, Some(3));
+            assert_eq!(points.len(), 16);
+            for point in &points {
+                assert!(point.iter().zip(&bounds).all(|(x, (lo, hi))| lo <= x && x <= hi));
+            }
+            assert_eq!(points, sample(sampling, 16, &bounds, Some(3)));
+        }
+        let points = sample(Sampling::LatinHypercube, 10, &bounds, Some(3));
+        for (d, (lo, hi)) in bounds.iter().enumerate() {
+            let mut strata: Vec<usize> =
+                points.iter().map(|p| ((p[d] - lo) / (hi - lo) * 10.0) as usize).collect();
+            strata.sort_unstable();
+            assert_eq!(strata, (0..10).collect::<Vec<_>>());
+        }
+        assert_ne!(
+            sample(Sampling::Uniform, 4, &bounds, Some(1)),
+            sample(Sampling::Uniform, 4, &bounds, Some(2))
+        );
+    }
+
+    #[test]
+    fn himmelblau_has_four_basins() {
+        let options = MultiStartOptions {
+            starts: 32,
+            sampling: Sampling::Sobol,
+            ..MultiStartOptions::default()
+        };
+        let report = run("himmelblau", Method::Lbfgs, &options, 1);
+        assert_eq!(report.failures(), 0);
+        assert_eq!(report.basins.len(), 4, "{}", report);
+        assert!(report.basins.iter().all(|basin| basin.cost < 1e-8));
+        assert!(report.basins.windows(2).all(|w| w[0].cost <= w[1].cost));
+        let mut starts: Vec<usize> = report.basins.iter().flat_map(|b| b.starts.clone()).collect();
+        starts.sort_unstable();
+        assert_eq!(starts, (0..32).collect::<Vec<_>>());
+        for run in &report.runs {
+            assert!(report.basins[run.basin.unwrap()].starts.contains(&run.start));
+        }
+        // The report does not depend on the number of threads
+        let parallel = run("himmelblau", Method::Lbfgs, &options, 4);
+        assert_eq!(
+            serde_json::to_value(&report.basins).unwrap(),
+            serde_json::to_value(&parallel.basins).unwrap()
+        );
+    }
+
+    #[test]
+    fn failed_runs_are_reported() {
+        let options = MultiStartOptions {
+            starts: 3,
+            start_seed: Some(1),
+            ..MultiStartOptions::default()
+        };
+        // Ackley has no Hessian
+        let report = run("ackley", Method::Newton, &options, 1);
+        assert_eq!(report.failures(), 3);
+        assert!(report.best().is_none());
+        let text = report.to_string();
+        assert!(text.contains("every run failed") && text.contains("start 2 failed"), "{}", text);
+    }
+}
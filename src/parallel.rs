@@ -0,0 +1,174 @@
+//! Running independent optimizations concurrently
+//!
+//! Multi-starts and benchmarks consist of many runs which do not depend on
+//! each other. [`map`] executes them on a pool of at most `jobs` threads
+//! (plain `std::thread`s, so that thread-local state like the tape of
+//! reverse-mode automatic differentiation stays private to each run) and
+//! returns the results in the order of the inputs, whatever order the runs
+//! finish in.
+//!
+//! Every run is isolated from the others: an error or even a panic of one
+//! run is returned as that run's `Err` while the remaining runs continue.
+
+use argmin::core::{ArgminError, Error};
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+
+/// Number of threads used for `jobs`: all available cores for 0, otherwise
+/// `jobs`
+pub fn threads(jobs: usize) -> usize {
+    if jobs == 0 {
+        thread::available_parallelism().map_or(1, |n| n.get())
+    } else {
+        jobs
+    }
+}
+
+/// Apply `f` to every item on at most `jobs` threads (0: one per core)
+///
+/// The results are in the order of `items`. A panic in `f` is caught and
+/// returned as an error of that item.
+pub fn map<T, R, F>(items: Vec<T>, jobs: usize, f: F) -> Vec<Result<R, Error>>
+where
+    T: Send,
+    R: Send,
+    F: Fn(T) -> Result<R, Error> + Sync,
+{
+    let run = |item: T| {
+        panic::catch_unwind(AssertUnwindSafe(|| f(item))).unwrap_or_else(|payload| {
+            let message = payload
+                .downcast_ref::<&str>()
+                .map(|s| s.to_string())
+                .or_else(|| payload.downcast_ref::<String>().cloned())
+                .unwrap_or_else(|| "unknown cause".to_string());
+            Err(ArgminError::PotentialBug {
+                text: format!("run panicked: {}", message),
+            }
+            .into())
+        })
+    };
+
+    let threads = threads(jobs).min(items.len());
+    if threads <= 1 {
+        return items.into_iter().map(run).collect();
+    }
+
+    // Each worker takes the next item which nobody has taken yet
+    let count = items.len();
+    let items: Vec<Mutex<Option<T>>> = items.into_iter().map(|i| Mutex::new(Some(i))).collect();
+    let results: Vec<Mutex<Option<Result<R, Error>>>> =
+        (0..count).map(|_| Mutex::new(None)).collect();
+    let next = AtomicUsize::new(0);
+    thread::scope(|scope| {
+        for _ in 0..threads {
+            scope.spawn(|| loop {
+                let k = next.fetch_add(1, Ordering::Relaxed);
+                if k >= count {
+                    break;
+                }
+                // Panics are caught in `run`, so no lock is ever poisoned
+                let item = items[k].lock().ok().and_then(|mut item| item.take());
+                if let Some(item) = item {
+                    let result = run(item);
+                    if let Ok(mut slot) = results[k].lock() {
+                        *slot = Some(result);
+                    }
+                }
+            });
+        }
+    });
+    results
+        .into_iter()
+        .map(|slot| {
+            slot.into_inner().ok().flatten().unwrap_or_else(|| {
+                Err(ArgminError::PotentialBug {
+                    text: "parallel: run produced no result".to_string(),
+                }
+                .into())
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::problem::ProblemConfig;
+    use crate::solver::{solve, Method, SolverOptions};
+    use crate::RunConfig;
+    use std::time::Duration;
+
+    #[test]
+    fn results_keep_the_order_of_the_items() {
+        let items: Vec<u64> = (0..16).collect();
+        for jobs in [0, 1, 3, 16, 100] {
+            // Later items finish first
+            let results = map(items.clone(), jobs, |k| {
+                thread::sleep(Duration::from_millis(16 - k));
+                Ok(k * k)
+            });
+            let results: Vec<u64> = results.into_iter().map(Result::unwrap).collect();
+            assert_eq!(results, items.iter().map(|k| k * k).collect::<Vec<_>>());
+        }
+        assert!(map(Vec::<u64>::new(), 4, Ok).is_empty());
+        assert!(threads(0) >= 1);
+        assert_eq!(threads(5), 5);
+    }
+
+    #[test]
+    fn at_most_jobs_threads_run_at_once() {
+        let running = AtomicUsize::new(0);
+        let most = AtomicUsize::new(0);
+        map((0..12).collect(), 3, |_: u32| {
+            let now = running.fetch_add(1, Ordering::SeqCst) + 1;
+            most.fetch_max(now, Ordering::SeqCst);
+            thread::sleep(Duration::from_millis(5));
+            running.fetch_sub(1, Ordering::SeqCst);
+            Ok(())
+        });
+        assert!(most.into_inner() <= 3);
+    }
+
+    #[test]
+    fn errors_and_panics_only_affect_their_item() {
+        for jobs in [1, 4] {
+            let results = map(vec![1, 2, 3, 4], jobs, |k: i32| match k {
+                2 => Err(ArgminError::InvalidParameter {
+                    text: "two".to_string(),
+                }
+                .into()),
+                3 => panic!("three"),
+                k => Ok(k),
+            });
+            assert_eq!(results[0].as_ref().unwrap(), &1);
+            assert!(results[1].as_ref().unwrap_err().to_string().contains("two"));
+            let panicked = results[2].as_ref().unwrap_err().to_string();
+            assert!(panicked.contains("run panicked: three"), "{}", panicked);
+            assert_eq!(results[3].as_ref().unwrap(), &4);
+        }
+    }
+
+    #[test]
+    fn reverse_mode_runs_do_not_share_their_tape() {
+        let config: ProblemConfig =
+            serde_json::from_value(serde_json::json!({ "name": "rosenbrock" })).unwrap();
+        let config = config.with_derivatives("reverse-ad", None, None).unwrap();
+        let starts: Vec<Vec<f64>> = (0..8).map(|k| vec![-1.0 + 0.1 * k as f64, 1.0]).collect();
+        let solve_from = |x0: Vec<f64>| {
+            let run = RunConfig {
+                init_param: x0,
+                ..RunConfig::default()
+            };
+            solve(config.build()?, &SolverOptions::new(Method::Lbfgs), &run)
+        };
+        let serial = map(starts.clone(), 1, solve_from);
+        let parallel = map(starts, 4, solve_from);
+        for (serial, parallel) in serial.iter().zip(&parallel) {
+            let (serial, parallel) = (serial.as_ref().unwrap(), parallel.as_ref().unwrap());
+            assert_eq!(serial.param, parallel.param);
+            assert_eq!(serial.counts, parallel.counts);
+        }
+    }
+}
@@ -0,0 +1,842 @@
+//! Contour plots of two-dimensional problems
+//!
+//! [`Contour`] samples the cost function of a problem with two parameters on
+//! a grid over a box and draws it as a heatmap with contour lines (found by
+//! marching squares). The iterates of a run are drawn on top as a path, with
+//! markers at the starting point, at the final point and at the known global
+//! minimizers of the problem.
+//!
+//! The plot is written as SVG, with axes, a legend and a color bar, or as
+//! PNG, which shows the same picture without any text. Both are produced
+//! without a display or external tools; the heatmap of the SVG is an
+//! embedded PNG image.
+//!
+//! Cost function values typically span many orders of magnitude, so colors
+//! and contour levels are by default spaced logarithmically in the distance
+//! to the smallest sampled value ([`LevelScale::Log`]). Points where the cost
+//! function cannot be evaluated or is not finite are gray.
+
+use super::png::{base64, Raster, Rgb};
+use super::{escape, frame, header, Axis, Scale, HEIGHT, MARGIN, WIDTH};
+use crate::problem::DynProblem;
+use argmin::core::{ArgminError, CostFunction, Error};
+use serde::{Deserialize, Serialize};
+use std::fmt::Write;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Color of points where the cost function is not finite
+const UNDEFINED: Rgb = [200, 200, 200];
+
+/// Colors from low to high cost function values, interpolated linearly
+/// (close to the viridis colormap)
+const COLORMAP: [Rgb; 5] = [
+    [68, 1, 84],
+    [59, 82, 139],
+    [33, 145, 140],
+    [94, 201, 98],
+    [253, 231, 37],
+];
+
+const PATH_COLOR: Rgb = [255, 255, 255];
+const START_COLOR: Rgb = [44, 160, 44];
+const END_COLOR: Rgb = [214, 39, 40];
+const MINIMUM_COLOR: Rgb = [255, 127, 14];
+
+/// File format of a contour plot
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ImageFormat {
+    /// Scalable vector graphics with axes and legend
+    Svg,
+    /// PNG image without text
+    Png,
+}
+
+impl ImageFormat {
+    /// Format implied by the extension of `path`: PNG for `.png`, SVG
+    /// otherwise
+    pub fn from_path(path: &Path) -> ImageFormat {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("png") => ImageFormat::Png,
+            _ => ImageFormat::Svg,
+        }
+    }
+}
+
+/// How cost function values are mapped to colors and contour levels
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum LevelScale {
+    /// Evenly spaced in the logarithm of the distance to the smallest value
+    #[default]
+    Log,
+    /// Evenly spaced in the value
+    Linear,
+}
+
+impl LevelScale {
+    /// All scales
+    pub const ALL: [LevelScale; 2] = [LevelScale::Log, LevelScale::Linear];
+
+    /// Name of the scale on the command line
+    pub fn name(self) -> &'static str {
+        match self {
+            LevelScale::Log => "log",
+            LevelScale::Linear => "linear",
+        }
+    }
+
+    /// Scale called `name`
+    pub fn from_name(name: &str) -> Option<Self> {
+        LevelScale::ALL.into_iter().find(|s| s.name() == name)
+    }
+}
+
+/// Where and how a contour plot is drawn
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ContourOptions {
+    /// File the plot is written to; it is overwritten if it exists
+    pub path: PathBuf,
+    /// File format
+    pub format: ImageFormat,
+    /// Box `[x_lo, x_hi, y_lo, y_hi]` which is plotted; if `None`, the box
+    /// around the iterates and the known minimizers, with a margin
+    pub bounds: Option<[f64; 4]>,
+    /// Number of contour lines
+    pub levels: usize,
+    /// Number of grid points along each axis at which the cost function is
+    /// evaluated
+    pub resolution: usize,
+    /// Spacing of colors and contour levels
+    pub scale: LevelScale,
+}
+
+impl ContourOptions {
+    /// Default plot written to `path`, in the format implied by its extension
+    pub fn new<P: Into<PathBuf>>(path: P) -> Self {
+        let path = path.into();
+        ContourOptions {
+            format: ImageFormat::from_path(&path),
+            path,
+            bounds: None,
+            levels: 20,
+            resolution: 200,
+            scale: LevelScale::Log,
+        }
+    }
+
+    /// Check the options
+    ///
+    /// Messages start with the name of the offending field.
+    pub fn validate(&self) -> Result<(), Error> {
+        if let Some([x_lo, x_hi, y_lo, y_hi]) = self.bounds {
+            if [x_lo, x_hi, y_lo, y_hi].iter().any(|v| !v.is_finite()) {
+                return Err(invalid("bounds must be finite"));
+            }
+            if x_lo >= x_hi || y_lo >= y_hi {
+                return Err(invalid("bounds must have lower below upper limits"));
+            }
+        }
+        if self.levels == 0 {
+            return Err(invalid("levels must be at least 1"));
+        }
+        if self.resolution < 2 {
+            return Err(invalid("resolution must be at least 2"));
+        }
+        Ok(())
+    }
+}
+
+fn invalid(text: &str) -> Error {
+    ArgminError::InvalidParameter {
+        text: text.to_string(),
+    }
+    .into()
+}
+
+/// Cost function of a problem sampled on a grid, with the iterates of a run
+#[derive(Clone, Debug)]
+pub struct Contour {
+    /// Name of the problem
+    pub title: String,
+    /// Plotted box `[x_lo, x_hi, y_lo, y_hi]`
+    pub bounds: [f64; 4],
+    /// Cost function values, `values[j][i]` at the `i`-th grid point along
+    /// x and the `j`-th along y (NaN where not finite)
+    pub values: Vec<Vec<f64>>,
+    /// Iterates in order, starting with the starting point
+    pub path: Vec<[f64; 2]>,
+    /// Known global minimizers
+    pub minima: Vec<[f64; 2]>,
+    /// Number of contour lines
+    pub levels: usize,
+    /// Spacing of colors and contour levels
+    pub scale: LevelScale,
+}
+
+impl Contour {
+    /// Sample `problem` for a plot of the iterates `path`
+    pub fn new(
+        problem: &DynProblem,
+        options: &ContourOptions,
+        path: &[Vec<f64>],
+    ) -> Result<Contour, Error> {
+        options.validate()?;
+        let info = problem.info();
+        if info.dim != 2 {
+            return Err(invalid(&format!(
+                "contour plots need a problem with 2 parameters, {} has {}",
+                info.name, info.dim
+            )));
+        }
+        let pair = |p: &Vec<f64>| [p[0], p[1]];
+        let path: Vec<[f64; 2]> = path.iter().filter(|p| p.len() == 2).map(pair).collect();
+        let minima: Vec<[f64; 2]> = info.minima.iter().map(pair).collect();
+        let bounds = options.bounds.unwrap_or_else(|| {
+            let around = |axis: usize| {
+                let values = path.iter().chain(&minima).map(|p| p[axis]);
+                fit(values, info.bounds[axis])
+            };
+            let ((x_lo, x_hi), (y_lo, y_hi)) = (around(0), around(1));
+            [x_lo, x_hi, y_lo, y_hi]
+        });
+
+        let n = options.resolution;
+        let coordinate = |lo: f64, hi: f64, k: usize| lo + (hi - lo) * k as f64 / (n - 1) as f64;
+        let values = (0..n)
+            .map(|j| {
+                let y = coordinate(bounds[2], bounds[3], j);
+                (0..n)
+                    .map(|i| {
+                        let x = coordinate(bounds[0], bounds[1], i);
+                        match problem.cost(&vec![x, y]) {
+                            Ok(cost) if cost.is_finite() => cost,
+                            _ => f64::NAN,
+                        }
+                    })
+                    .collect()
+            })
+            .collect();
+        Ok(Contour {
+            title: info.name.clone(),
+            bounds,
+            values,
+            path,
+            minima,
+            levels: options.levels,
+            scale: options.scale,
+        })
+    }
+
+    /// Smallest and largest sampled value, `None` if no value is finite
+    fn range(&self) -> Option<(f64, f64)> {
+        let (lo, hi) = self
+            .values
+            .iter()
+            .flatten()
+            .filter(|v| v.is_finite())
+            .fold((f64::INFINITY, f64::NEG_INFINITY), |(lo, hi), &v| {
+                (lo.min(v), hi.max(v))
+            });
+        (lo <= hi).then_some((lo, hi))
+    }
+
+    /// Map from cost function values to `[0, 1]`, in which colors and
+    /// levels are evenly spaced
+    fn normalize(&self) -> impl Fn(f64) -> f64 {
+        let (lo, hi) = self.range().unwrap_or((0.0, 1.0));
+        let scale = self.scale;
+        // Offset which keeps the logarithm of the smallest value finite
+        let offset = if hi > lo { 1e-4 * (hi - lo) } else { 1.0 };
+        let transform = move |v: f64| match scale {
+            LevelScale::Log => (v - lo + offset).log10(),
+            LevelScale::Linear => v,
+        };
+        let (t_lo, t_hi) = (transform(lo), transform(hi));
+        move |v| {
+            if t_hi > t_lo {
+                ((transform(v) - t_lo) / (t_hi - t_lo)).clamp(0.0, 1.0)
+            } else {
+                0.5
+            }
+        }
+    }
+
+    /// Cost function values of the contour lines
+    fn level_values(&self) -> Vec<f64> {
+        let Some((lo, hi)) = self.range() else {
+            return Vec::new();
+        };
+        let offset = if hi > lo { 1e-4 * (hi - lo) } else { 1.0 };
+        (1..=self.levels)
+            .map(|k| {
+                let t = k as f64 / (self.levels + 1) as f64;
+                match self.scale {
+                    LevelScale::Log => {
+                        let (a, b) = (offset.log10(), (hi - lo + offset).log10());
+                        lo - offset + 10f64.powf(a + t * (b - a))
+                    }
+                    LevelScale::Linear => lo + t * (hi - lo),
+                }
+            })
+            .collect()
+    }
+
+    /// Grid point `(i, j)` in data coordinates
+    fn grid_point(&self, i: f64, j: f64) -> (f64, f64) {
+        let n = (self.values.len() - 1) as f64;
+        let [x_lo, x_hi, y_lo, y_hi] = self.bounds;
+        (x_lo + (x_hi - x_lo) * i / n, y_lo + (y_hi - y_lo) * j / n)
+    }
+
+    /// Contour lines as segments in data coordinates, one list per level
+    fn contour_lines(&self) -> Vec<Vec<[(f64, f64); 2]>> {
+        let v = &self.values;
+        let n = v.len();
+        self.level_values()
+            .into_iter()
+            .map(|level| {
+                let mut segments = Vec::new();
+                for j in 0..n - 1 {
+                    for i in 0..n - 1 {
+                        let corners = [v[j][i], v[j][i + 1], v[j + 1][i + 1], v[j + 1][i]];
+                        if corners.iter().any(|c| !c.is_finite()) {
+                            continue;
+                        }
+                        for [a, b] in marching_square(corners, level) {
+                            segments.push([
+                                self.grid_point(i as f64 + a.0, j as f64 + a.1),
+                                self.grid_point(i as f64 + b.0, j as f64 + b.1),
+                            ]);
+                        }
+                    }
+                }
+                segments
+            })
+            .collect()
+    }
+
+    /// Heatmap with one pixel per grid point, the top row at the largest y
+    fn heatmap(&self) -> Raster {
+        let n = self.values.len();
+        let normalize = self.normalize();
+        let mut raster = Raster::new(n, n, UNDEFINED);
+        for (j, row) in self.values.iter().enumerate() {
+            for (i, &v) in row.iter().enumerate() {
+                if v.is_finite() {
+                    raster.set(i as i64, (n - 1 - j) as i64, color(normalize(v)));
+                }
+            }
+        }
+        raster
+    }
+
+    /// Axes mapping the plotted box to the plot area
+    fn axes(&self) -> (Axis, Axis) {
+        let [x_lo, x_hi, y_lo, y_hi] = self.bounds;
+        let x = Axis::fit(
+            Scale::Linear,
+            Some((x_lo, x_hi)),
+            std::iter::empty(),
+            (MARGIN.0, WIDTH - MARGIN.1),
+        );
+        let y = Axis::fit(
+            Scale::Linear,
+            Some((y_lo, y_hi)),
+            std::iter::empty(),
+            (HEIGHT - MARGIN.3, MARGIN.2),
+        );
+        (x, y)
+    }
+
+    /// Pixel position of `(px, py)`, `None` if it cannot be drawn
+    fn pixel(x: &Axis, y: &Axis, (px, py): (f64, f64)) -> Option<(f64, f64)> {
+        Some((x.map(px)?, y.map(py)?))
+    }
+
+    /// Render the plot as an SVG document
+    pub fn to_svg(&self) -> String {
+        let (x, y) = self.axes();
+        let mut svg = header(
+            &format!("{}: cost function and iterates", self.title),
+            "plot-area",
+        );
+
+        // Grid points are the centers of the pixels of the heatmap
+        let half = |lo: f64, hi: f64| (hi - lo) / (2.0 * (self.values.len() - 1) as f64);
+        let [x_lo, x_hi, y_lo, y_hi] = self.bounds;
+        let (dx, dy) = (half(x_lo, x_hi), half(y_lo, y_hi));
+        if let (Some((left, top)), Some((right, bottom))) = (
+            Self::pixel(&x, &y, (x_lo - dx, y_hi + dy)),
+            Self::pixel(&x, &y, (x_hi + dx, y_lo - dy)),
+        ) {
+            let _ = writeln!(
+                svg,
+                r#"<image clip-path="url(#plot-area)" x="{:.2}" y="{:.2}" width="{:.2}" height="{:.2}" preserveAspectRatio="none" href="data:image/png;base64,{}"/>"#,
+                left,
+                top,
+                right - left,
+                bottom - top,
+                base64(&self.heatmap().to_png())
+            );
+        }
+
+        let _ = writeln!(
+            svg,
+            r#"<g clip-path="url(#plot-area)" fill="none" stroke="black" stroke-opacity="0.45" stroke-width="0.8">"#
+        );
+        for segments in self.contour_lines() {
+            let mut d = String::new();
+            for [a, b] in segments {
+                if let (Some(a), Some(b)) = (Self::pixel(&x, &y, a), Self::pixel(&x, &y, b)) {
+                    let _ = write!(d, "M{:.2},{:.2}L{:.2},{:.2}", a.0, a.1, b.0, b.1);
+                }
+            }
+            if !d.is_empty() {
+                let _ = writeln!(svg, r#"<path d="{}"/>"#, d);
+            }
+        }
+        svg.push_str("</g>\n");
+        frame(&mut svg, &x, &y, "x1", "x2");
+
+        svg.push_str("<g clip-path=\"url(#plot-area)\">\n");
+        let points: Vec<(f64, f64)> = self
+            .path
+            .iter()
+            .filter_map(|p| Self::pixel(&x, &y, (p[0], p[1])))
+            .collect();
+        if points.len() > 1 {
+            let coordinates: Vec<String> = points
+                .iter()
+                .map(|(px, py)| format!("{:.2},{:.2}", px, py))
+                .collect();
+            let _ = writeln!(
+                svg,
+                r#"<polyline points="{}" fill="none" stroke="{}" stroke-width="1.5"/>"#,
+                coordinates.join(" "),
+                hex(PATH_COLOR)
+            );
+        }
+        for (px, py) in &points {
+            let _ = writeln!(
+                svg,
+                r#"<circle cx="{:.2}" cy="{:.2}" r="1.8" fill="{}"/>"#,
+                px,
+                py,
+                hex(PATH_COLOR)
+            );
+        }
+        for m in &self.minima {
+            if let Some(p) = Self::pixel(&x, &y, (m[0], m[1])) {
+                svg_marker(&mut svg, Marker::Minimum, p);
+            }
+        }
+        let ends = [
+            (self.path.first(), Marker::Start),
+            (self.path.last(), Marker::End),
+        ];
+        for (point, marker) in ends {
+            if let Some(p) = point.and_then(|p| Self::pixel(&x, &y, (p[0], p[1]))) {
+                svg_marker(&mut svg, marker, p);
+            }
+        }
+        svg.push_str("</g>\n");
+        self.svg_legend(&mut svg);
+        svg.push_str("</svg>\n");
+        svg
+    }
+
+    /// Legend of the markers and color bar
+    fn svg_legend(&self, svg: &mut String) {
+        let x = WIDTH - MARGIN.1 + 15.0;
+        let entries = [
+            (Marker::Start, "start"),
+            (Marker::End, "end"),
+            (Marker::Minimum, "known minimum"),
+        ];
+        let _ = writeln!(
+            svg,
+            r#"<line x1="{x}" y1="{y}" x2="{}" y2="{y}" stroke="black" stroke-width="3.5"/><line x1="{x}" y1="{y}" x2="{}" y2="{y}" stroke="{}" stroke-width="1.5"/><text x="{}" y="{}">iterates</text>"#,
+            x + 20.0,
+            x + 20.0,
+            hex(PATH_COLOR),
+            x + 26.0,
+            MARGIN.2 + 14.0,
+            y = MARGIN.2 + 10.0
+        );
+        for (i, (marker, name)) in entries.into_iter().enumerate() {
+            let y = MARGIN.2 + 28.0 + 18.0 * i as f64;
+            svg_marker(svg, marker, (x + 10.0, y));
+            let _ = writeln!(
+                svg,
+                r#"<text x="{}" y="{}">{}</text>"#,
+                x + 26.0,
+                y + 4.0,
+                escape(name)
+            );
+        }
+
+        // Color bar, with the smallest value at the bottom
+        let Some((lo, hi)) = self.range() else {
+            return;
+        };
+        let (top, height, width) = (MARGIN.2 + 110.0, 200.0, 16.0);
+        let steps = 64;
+        for k in 0..steps {
+            let t = 1.0 - (k as f64 + 0.5) / steps as f64;
+            let _ = writeln!(
+                svg,
+                r#"<rect x="{x}" y="{:.2}" width="{width}" height="{:.2}" fill="{}"/>"#,
+                top + height * k as f64 / steps as f64,
+                height / steps as f64 + 0.5,
+                hex(color(t))
+            );
+        }
+        let _ = writeln!(
+            svg,
+            r#"<rect x="{x}" y="{top}" width="{width}" height="{height}" fill="none" stroke="black"/>"#
+        );
+        let labels = [
+            (top + 4.0, format!("{:.4e}", hi)),
+            (top + height + 4.0, format!("{:.4e}", lo)),
+            (top + height + 22.0, format!("{} scale", self.scale.name())),
+        ];
+        for (y, label) in labels {
+            let _ = writeln!(
+                svg,
+                r#"<text x="{}" y="{:.2}">{}</text>"#,
+                x + width + 6.0,
+                y,
+                escape(&label)
+            );
+        }
+    }
+
+    /// Render the plot as a PNG image
+    pub fn to_png(&self) -> Vec<u8> {
+        let (x, y) = self.axes();
+        let (width, height) = (WIDTH as usize, HEIGHT as usize);
+        let mut raster = Raster::new(width, height, [255, 255, 255]);
+
+        // Each pixel of the plot area shows the nearest grid point
+        let n = self.values.len();
+        let normalize = self.normalize();
+        let (left, right) = (MARGIN.0 as usize, width - MARGIN.1 as usize);
+        let (top, bottom) = (MARGIN.2 as usize, height - MARGIN.3 as usize);
+        for row in top..bottom {
+            let j = (bottom - 1 - row) * n / (bottom - top);
+            for column in left..right {
+                let i = (column - left) * n / (right - left);
+                let v = self.values[j][i];
+                let c = if v.is_finite() {
+                    color(normalize(v))
+                } else {
+                    UNDEFINED
+                };
+                raster.set(column as i64, row as i64, c);
+            }
+        }
+
+        for segments in self.contour_lines() {
+            for [a, b] in segments {
+                if let (Some(a), Some(b)) = (Self::pixel(&x, &y, a), Self::pixel(&x, &y, b)) {
+                    raster.line(a, b, 1.0, [40, 40, 40]);
+                }
+            }
+        }
+        let points: Vec<(f64, f64)> = self
+            .path
+            .iter()
+            .filter_map(|p| Self::pixel(&x, &y, (p[0], p[1])))
+            .collect();
+        for pair in points.windows(2) {
+            raster.line(pair[0], pair[1], 1.5, PATH_COLOR);
+        }
+        for &p in &points {
+            raster.disc(p, 1.8, PATH_COLOR);
+        }
+        for m in &self.minima {
+            if let Some(p) = Self::pixel(&x, &y, (m[0], m[1])) {
+                png_marker(&mut raster, Marker::Minimum, p);
+            }
+        }
+        for (point, marker) in [
+            (points.first(), Marker::Start),
+            (points.last(), Marker::End),
+        ] {
+            if let Some(&p) = point {
+                png_marker(&mut raster, marker, p);
+            }
+        }
+
+        // Clear what was drawn outside of the plot area
+        let white = [255, 255, 255];
+        let (l, r, t, b) = (left as i64, right as i64, top as i64, bottom as i64);
+        let (w, h) = (width as i64, height as i64);
+        raster.fill_rect(0, 0, w, t, white);
+        raster.fill_rect(0, b, w, h, white);
+        raster.fill_rect(0, t, l, b, white);
+        raster.fill_rect(r, t, w, b, white);
+
+        // Frame of the plot area
+        let black = [0, 0, 0];
+        raster.fill_rect(l - 1, t - 1, r + 1, t, black);
+        raster.fill_rect(l - 1, b, r + 1, b + 1, black);
+        raster.fill_rect(l - 1, t - 1, l, b + 1, black);
+        raster.fill_rect(r, t - 1, r + 1, b + 1, black);
+        raster.to_png()
+    }
+
+    /// Write the plot to `path` in `format`
+    pub fn write(&self, path: &Path, format: ImageFormat) -> Result<(), Error> {
+        match format {
+            ImageFormat::Svg => fs::write(path, self.to_svg())?,
+            ImageFormat::Png => fs::write(path, self.to_png())?,
+        }
+        Ok(())
+    }
+}
+
+/// Plot the iterates `path` of a run on `problem` as described by `options`
+pub fn render(
+    problem: &DynProblem,
+    options: &ContourOptions,
+    path: &[Vec<f64>],
+) -> Result<(), Error> {
+    Contour::new(problem, options, path)?.write(&options.path, options.format)
+}
+
+/// Range around the finite `values` with a margin of 10%, or `fallback` if
+/// they do not span a range
+fn fit(values: impl Iterator<Item = f64>, fallback: (f64, f64)) -> (f64, f64) {
+    let (lo, hi) = values
+        .filter(|v| v.is_finite())
+        .fold((f64::INFINITY, f64::NEG_INFINITY), |(lo, hi), v| {
+            (lo.min(v), hi.max(v))
+        });
+    let width = hi - lo;
+    if !width.is_finite() || width <= 1e-12 * lo.abs().max(hi.abs()).max(1.0) {
+        return fallback;
+    }
+    let margin = 0.1 * width;
+    (lo - margin, hi + margin)
+}
+
+/// Segments of the contour line at `level` through the unit square with
+/// values `corners` at `(0, 0)`, `(1, 0)`, `(1, 1)` and `(0, 1)`
+fn marching_square(corners: [f64; 4], level: f64) -> Vec<[(f64, f64); 2]> {
+    const POSITIONS: [(f64, f64); 4] = [(0.0, 0.0), (1.0, 0.0), (1.0, 1.0), (0.0, 1.0)];
+    // Crossings of the level on the edges, counterclockwise from the bottom
+    let mut crossings = Vec::with_capacity(4);
+    for k in 0..4 {
+        let (a, b) = (corners[k], corners[(k + 1) % 4]);
+        if (a > level) != (b > level) {
+            let t = (level - a) / (b - a);
+            let (p, q) = (POSITIONS[k], POSITIONS[(k + 1) % 4]);
+            crossings.push(Some((p.0 + t * (q.0 - p.0), p.1 + t * (q.1 - p.1))));
+        } else {
+            crossings.push(None);
+        }
+    }
+    let found: Vec<(f64, f64)> = crossings.iter().flatten().copied().collect();
+    match found.len() {
+        2 => vec![[found[0], found[1]]],
+        4 => {
+            // Saddle: the mean of the corners decides whether the region
+            // of the corner at (0, 0) connects to the opposite corner
+            let center = corners.iter().sum::<f64>() / 4.0;
+            if (center > level) == (corners[0] > level) {
+                vec![[found[0], found[1]], [found[2], found[3]]]
+            } else {
+                vec![[found[3], found[0]], [found[1], found[2]]]
+            }
+        }
+        _ => Vec::new(),
+    }
+}
+
+/// Color of the normalized value `t` in `[0, 1]`
+fn color(t: f64) -> Rgb {
+    let scaled = t.clamp(0.0, 1.0) * (COLORMAP.len() - 1) as f64;
+    let k = (scaled.floor() as usize).min(COLORMAP.len() - 2);
+    let f = scaled - k as f64;
+    let (a, b) = (COLORMAP[k], COLORMAP[k + 1]);
+    [0, 1, 2].map(|c| (a[c] as f64 + f * (b[c] as f64 - a[c] as f64)).round() as u8)
+}
+
+fn hex(color: Rgb) -> String {
+    format!("#{:02x}{:02x}{:02x}", color[0], color[1], color[2])
+}
+
+/// Points of interest marked in the plot
+#[derive(Clone, Copy)]
+enum Marker {
+    Start,
+    End,
+    Minimum,
+}
+
+fn svg_marker(svg: &mut String, marker: Marker, (px, py): (f64, f64)) {
+    let _ = match marker {
+        Marker::Start => writeln!(
+            svg,
+            r#"<circle cx="{px:.2}" cy="{py:.2}" r="5" fill="{}" stroke="black"/>"#,
+            hex(START_COLOR)
+        ),
+        Marker::End => writeln!(
+            svg,
+            r#"<circle cx="{px:.2}" cy="{py:.2}" r="5" fill="{}" stroke="black"/>"#,
+            hex(END_COLOR)
+        ),
+        Marker::Minimum => writeln!(
+            svg,
+            r#"<path d="M{:.2},{:.2}l10,10m0,-10l-10,10" stroke="black" stroke-width="4"/><path d="M{:.2},{:.2}l10,10m0,-10l-10,10" stroke="{}" stroke-width="2"/>"#,
+            px - 5.0,
+            py - 5.0,
+            px - 5.0,
+            py - 5.0,
+            hex(MINIMUM_COLOR)
+        ),
+    };
+}
+
+fn png_marker(raster: &mut Raster, marker: Marker, (px, py): (f64, f64)) {
+    match marker {
+        Marker::Start | Marker::End => {
+            let fill = match marker {
+                Marker::Start => START_COLOR,
+                _ => END_COLOR,
+            };
+            raster.disc((px, py), 6.0, [0, 0, 0]);
+            raster.disc((px, py), 5.0, fill);
+        }
+        Marker::Minimum => {
+            for (width, color) in [(4.0, [0, 0, 0]), (2.0, MINIMUM_COLOR)] {
+                raster.line((px - 5.0, py - 5.0), (px + 5.0, py + 5.0), width, color);
+                raster.line((px + 5.0, py - 5.0), (px - 5.0, py + 5.0), width, color);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::problem;
+
+    fn booth() -> DynProblem {
+        problem::build("booth", None).unwrap()
+    }
+
+    #[test]
+    fn names_and_formats() {
+        for scale in LevelScale::ALL {
+            assert_eq!(LevelScale::from_name(scale.name()), Some(scale));
+        }
+        assert_eq!(ImageFormat::from_path(Path::new("a.png")), ImageFormat::Png);
+        assert_eq!(ImageFormat::from_path(Path::new("a.svg")), ImageFormat::Svg);
+        assert_eq!(ContourOptions::new("plot").format, ImageFormat::Svg);
+    }
+
+    #[test]
+    fn invalid_options_are_rejected() {
+        let defaults = ContourOptions::new("a.svg");
+        let invalid = [
+            ContourOptions { bounds: Some([1.0, 0.0, 0.0, 1.0]), ..defaults.clone() },
+            ContourOptions { bounds: Some([0.0, 1.0, 0.0, f64::NAN]), ..defaults.clone() },
+            ContourOptions { levels: 0, ..defaults.clone() },
+            ContourOptions { resolution: 1, ..defaults },
+        ];
+        for options in invalid {
+            assert!(options.validate().is_err(), "{:?}", options);
+        }
+        let sphere = problem::build("sphere", Some(3)).unwrap();
+        assert!(Contour::new(&sphere, &ContourOptions::new("a.svg"), &[]).is_err());
+    }
+
+    #[test]
+    fn grid_samples_the_cost_function() {
+        let options = ContourOptions {
+            bounds: Some([0.0, 2.0, 2.0, 4.0]),
+            resolution: 3,
+            ..ContourOptions::new("a.svg")
+        };
+        let contour = Contour::new(&booth(), &options, &[]).unwrap();
+        assert_eq!(contour.values.len(), 3);
+        // Booth's minimum 0 is at (1, 3)
+        assert_eq!(contour.values[1][1], 0.0);
+        let corner = booth().cost(&vec![2.0, 2.0]).unwrap();
+        assert_eq!(contour.values[0][2], corner);
+        assert_eq!(contour.minima, [[1.0, 3.0]]);
+    }
+
+    #[test]
+    fn default_bounds_fit_the_path_and_the_minima() {
+        let path = [vec![-1.0, 0.0], vec![0.0, 2.0]];
+        let contour = Contour::new(&booth(), &ContourOptions::new("a.svg"), &path).unwrap();
+        // x spans [-1, 1] and y [0, 3], each widened by 10%
+        let expected = [-1.2, 1.2, -0.3, 3.3];
+        for (bound, expected) in contour.bounds.iter().zip(expected) {
+            assert!((bound - expected).abs() < 1e-12, "{:?}", contour.bounds);
+        }
+        assert_eq!(fit([2.0, 2.0].into_iter(), (0.0, 1.0)), (0.0, 1.0));
+    }
+
+    #[test]
+    fn levels_lie_within_the_sampled_values() {
+        for scale in LevelScale::ALL {
+            let options = ContourOptions {
+                scale,
+                levels: 10,
+                resolution: 20,
+                ..ContourOptions::new("a.svg")
+            };
+            let contour = Contour::new(&booth(), &options, &[vec![-5.0, -5.0]]).unwrap();
+            let (lo, hi) = contour.range().unwrap();
+            let levels = contour.level_values();
+            assert_eq!(levels.len(), 10);
+            assert!(levels.windows(2).all(|w| w[0] < w[1]));
+            assert!(lo < levels[0] && levels[9] < hi);
+            let low_half = levels.iter().filter(|&&v| v < (lo + hi) / 2.0).count();
+            match scale {
+                LevelScale::Log => assert!(low_half > 5),
+                LevelScale::Linear => assert_eq!(low_half, 5),
+            }
+        }
+    }
+
+    #[test]
+    fn marching_squares_interpolate_the_crossings() {
+        assert_eq!(
+            marching_square([0.0, 1.0, 1.0, 0.0], 0.5),
+            [[(0.5, 0.0), (0.5, 1.0)]]
+        );
+        assert!(marching_square([0.0, 0.0, 0.0, 0.0], 0.5).is_empty());
+        // Saddles: the center decides whether the low corners are cut off
+        // (the high corners connect) or the high ones
+        let high = marching_square([0.0, 1.0, 0.0, 1.0], 0.25);
+        let low = marching_square([0.0, 1.0, 0.0, 1.0], 0.75);
+        assert_eq!(high, [[(0.0, 0.25), (0.25, 0.0)], [(1.0, 0.75), (0.75, 1.0)]]);
+        assert_eq!(low, [[(0.75, 0.0), (1.0, 0.25)], [(0.25, 1.0), (0.0, 0.75)]]);
+    }
+
+    #[test]
+    fn plots_are_written_as_svg_and_png() {
+        let directory = std::env::temp_dir().join(format!("opt-contour-{}", std::process::id()));
+        fs::create_dir_all(&directory).unwrap();
+        let path = [vec![-1.0, 0.0], vec![0.5, 2.0], vec![1.0, 3.0]];
+        for name in ["plot.svg", "plot.png"] {
+            let options = ContourOptions {
+                resolution: 20,
+                ..ContourOptions::new(directory.join(name))
+            };
+            render(&booth(), &options, &path).unwrap();
+        }
+        let svg = fs::read_to_string(directory.join("plot.svg")).unwrap();
+        assert!(svg.contains("<svg") && svg.contains("booth") && svg.contains("data:image/png"));
+        let png = fs::read(directory.join("plot.png")).unwrap();
+        assert!(png.starts_with(b"\x89PNG\r\n\x1a\n"));
+        fs::remove_dir_all(&directory).unwrap();
+    }
+}
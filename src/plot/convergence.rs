@@ -0,0 +1,292 @@
+//! Convergence plots of one or more runs
+//!
+//! A convergence plot shows, on logarithmic y axes, how fast runs approach
+//! the minimum: the cost function value (its distance to the known minimum
+//! `f*` if that is known), the norm of the gradient and the distance of the
+//! iterate to the nearest known minimizer, each against the iteration and
+//! against the number of evaluations (cost function, gradient and Hessian
+//! evaluations counted once each). The runs are overlaid in every chart of
+//! the [`Figure`], so that solvers can be compared at a glance.
+//!
+//! The data are the iterations recorded by a [`History`](crate::trace::History)
+//! observer. Charts without any drawable point, e.g. gradient norms of
+//! derivative free solvers or distances on problems without known minimizers,
+//! are left out.
+
+use super::{Figure, LineChart, Scale, Series};
+use crate::bench::{BenchOptions, BenchReport};
+use crate::problem::ProblemInfo;
+use crate::trace::TracePoint;
+use argmin::core::Error;
+use std::fs;
+use std::path::Path;
+
+/// Iterations of one run
+#[derive(Clone, Debug)]
+pub struct ConvergenceRun {
+    /// Name of the run in the legend
+    pub label: String,
+    /// The recorded iterations
+    pub points: Vec<TracePoint>,
+}
+
+/// Quantity on the y axis of a chart
+#[derive(Clone, Copy)]
+enum Quantity {
+    Cost,
+    GradientNorm,
+    Distance,
+}
+
+impl Quantity {
+    const ALL: [Quantity; 3] = [Quantity::Cost, Quantity::GradientNorm, Quantity::Distance];
+
+    fn label(self, info: &ProblemInfo) -> &'static str {
+        match self {
+            Quantity::Cost if info.min_cost.is_some() => "cost - f*",
+            Quantity::Cost => "cost",
+            Quantity::GradientNorm => "gradient norm",
+            Quantity::Distance => "distance to nearest minimizer",
+        }
+    }
+
+    /// Value at the iteration `p`, `None` if not available
+    fn of(self, p: &TracePoint, info: &ProblemInfo) -> Option<f64> {
+        match self {
+            Quantity::Cost => Some(p.cost - info.min_cost.unwrap_or(0.0)),
+            Quantity::GradientNorm => p.gradient_norm,
+            Quantity::Distance => info
+                .minima
+                .iter()
+                .map(|minimum| distance(&p.param, minimum))
+                .min_by(f64::total_cmp),
+        }
+    }
+}
+
+/// Quantity on the x axis of a chart
+#[derive(Clone, Copy)]
+enum Abscissa {
+    Iteration,
+    Evaluations,
+}
+
+impl Abscissa {
+    const ALL: [Abscissa; 2] = [Abscissa::Iteration, Abscissa::Evaluations];
+
+    /// Name in chart titles
+    fn name(self) -> &'static str {
+        match self {
+            Abscissa::Iteration => "iteration",
+            Abscissa::Evaluations => "evaluations",
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            Abscissa::Iteration => "iteration",
+            Abscissa::Evaluations => "evaluations (cost + gradient + Hessian)",
+        }
+    }
+
+    fn of(self, p: &TracePoint) -> f64 {
+        match self {
+            Abscissa::Iteration => p.iter as f64,
+            Abscissa::Evaluations => (p.cost_evals + p.gradient_evals + p.hessian_evals) as f64,
+        }
+    }
+}
+
+/// Convergence plot of `runs` on the problem described by `info`
+pub fn figure(info: &ProblemInfo, runs: &[ConvergenceRun]) -> Figure {
+    let mut charts = Vec::new();
+    for quantity in Quantity::ALL {
+        // Only drawable values make a chart worth showing
+        let drawable = runs
+            .iter()
+            .flat_map(|run| run.points.iter())
+            .filter_map(|p| quantity.of(p, info))
+            .any(|v| v.is_finite() && v > 0.0);
+        if !drawable {
+            continue;
+        }
+        let y_label = quantity.label(info);
+        for x in Abscissa::ALL {
+            let title = format!("{} by {}", y_label, x.name());
+            let mut chart = LineChart::new(&title, x.label(), y_label);
+            chart.y_scale = Scale::Log10;
+            chart.series = runs
+                .iter()
+                .map(|run| {
+                    let points = run
+                        .points
+                        .iter()
+                        .map(|p| (x.of(p), quantity.of(p, info).unwrap_or(f64::NAN)))
+                        .collect();
+                    Series::new(&run.label, points)
+                })
+                .collect();
+            charts.push(chart);
+        }
+    }
+    Figure {
+        title: format!("Convergence on {}", info.name),
+        columns: 2,
+        charts,
+    }
+}
+
+/// Write the convergence plot of `runs` on the problem described by `info`
+/// to `path` as SVG
+pub fn write(path: &Path, info: &ProblemInfo, runs: &[ConvergenceRun]) -> Result<(), Error> {
+    fs::write(path, figure(info, runs).to_svg())?;
+    Ok(())
+}
+
+/// Write one convergence plot per problem of a benchmark to `directory`
+///
+/// The plot of a problem is called `<problem>.svg` and overlays the runs of
+/// every solver, labelled with the solver and, if there are several
+/// starting points, the index of the starting point.
+pub fn write_bench(
+    report: &BenchReport,
+    options: &BenchOptions,
+    directory: &Path,
+) -> Result<(), Error> {
+    fs::create_dir_all(directory)?;
+    let several_starts = options.random_starts > 0;
+    for config in &options.problems {
+        let problem = config.build()?;
+        let info = problem.info();
+        let runs: Vec<ConvergenceRun> = report
+            .runs
+            .iter()
+            .filter(|run| run.problem == info.name)
+            .map(|run| ConvergenceRun {
+                label: if several_starts {
+                    format!("{} #{}", run.solver, run.start)
+                } else {
+                    run.solver.clone()
+                },
+                points: run.trace.clone(),
+            })
+            .collect();
+        write(&directory.join(format!("{}.svg", info.name)), info, &runs)?;
+    }
+    Ok(())
+}
+
+fn distance(a: &[f64], b: &[f64]) -> f64 {
+    a.iter()
+        .zip(b)
+        .map(|(x, y)| (x - y).powi(2))
+        .sum::<f64>()
+        .sqrt()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bench::bench;
+    use crate::solver::{Method, SolverOptions};
+    use crate::stopping::StopCriteria;
+
+    fn info(min_cost: Option<f64>, minima: Vec<Vec<f64>>) -> ProblemInfo {
+        ProblemInfo {
+            name: "test".to_string(),
+            dim: 1,
+            default_start: vec![1.0],
+            minima,
+            min_cost,
+            bounds: vec![(-1.0, 1.0)],
+            bounded: false,
+        }
+    }
+
+    fn run(label: &str, gradient: bool) -> ConvergenceRun {
+        let points = (0..4)
+            .map(|k| {
+                let x = 0.5f64.powi(k);
+                TracePoint {
+                    iter: k as u64,
+                    cost: 1.0 + x * x,
+                    best_cost: 1.0 + x * x,
+                    gradient_norm: gradient.then_some(2.0 * x),
+                    step_length: None,
+                    cost_evals: 2 * k as u64,
+                    gradient_evals: k as u64,
+                    hessian_evals: 0,
+                    time_secs: 0.0,
+                    param: vec![x],
+                }
+            })
+            .collect();
+        ConvergenceRun {
+            label: label.to_string(),
+            points,
+        }
+    }
+
+    fn titles(figure: &Figure) -> Vec<&str> {
+        figure.charts.iter().map(|chart| chart.title.as_str()).collect()
+    }
+
+    #[test]
+    fn every_quantity_is_plotted_against_both_abscissas() {
+        let figure = figure(&info(Some(1.0), vec![vec![0.0]]), &[run("a", true)]);
+        assert_eq!(
+            titles(&figure),
+            [
+                "cost - f* by iteration",
+                "cost - f* by evaluations",
+                "gradient norm by iteration",
+                "gradient norm by evaluations",
+                "distance to nearest minimizer by iteration",
+                "distance to nearest minimizer by evaluations",
+            ]
+        );
+        let chart = &figure.charts[1];
+        assert_eq!(chart.series[0].points[2], (6.0, 0.0625));
+        assert!(figure.to_svg().contains("plot-area-5"));
+    }
+
+    #[test]
+    fn charts_without_drawable_points_are_left_out() {
+        let runs = [run("a", false), run("b", false)];
+        let figure = figure(&info(None, Vec::new()), &runs);
+        assert_eq!(titles(&figure), ["cost by iteration", "cost by evaluations"]);
+        assert_eq!(figure.charts[0].series.len(), 2);
+        assert_eq!(figure.charts[0].series[1].name, "b");
+    }
+
+    #[test]
+    fn benchmarks_get_one_plot_per_problem() {
+        let options = BenchOptions {
+            problems: ["booth", "matyas"]
+                .iter()
+                .map(|name| serde_json::from_value(serde_json::json!({ "name": name })).unwrap())
+                .collect(),
+            solvers: vec![
+                SolverOptions::new(Method::Lbfgs),
+                SolverOptions::new(Method::NelderMead),
+            ],
+            random_starts: 1,
+            seed: Some(1),
+            stop: StopCriteria {
+                max_iters: 50,
+                ..StopCriteria::default()
+            },
+            success_tol: 1e-6,
+        };
+        let report = bench(&options, 1).unwrap();
+        let directory =
+            std::env::temp_dir().join(format!("opt-convergence-{}", std::process::id()));
+        write_bench(&report, &options, &directory).unwrap();
+        for problem in ["booth", "matyas"] {
+            let svg = fs::read_to_string(directory.join(format!("{}.svg", problem))).unwrap();
+            assert!(svg.contains(&format!("Convergence on {}", problem)));
+            assert!(svg.contains("lbfgs #1") && svg.contains("nelder-mead #0"));
+        }
+        fs::remove_dir_all(&directory).unwrap();
+    }
+}
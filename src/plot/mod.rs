@@ -0,0 +1,497 @@
+//! Static SVG plots
+//!
+//! A small plotting facility without dependencies. [`LineChart`] draws one
+//! or more curves on linear or logarithmic axes, with ticks, axis labels and
+//! a legend, and renders them as a standalone SVG document which browsers
+//! and most document tools display directly.
+//!
+//! Points which cannot be drawn, i.e. non-finite values or non-positive
+//! values on a logarithmic axis, interrupt a curve instead of failing.
+//!
+//! A [`Figure`] arranges several charts in one document, as done by the
+//! [`convergence`] plots. [`contour`] draws the cost function of
+//! two-dimensional problems with the iterates of a run, as SVG or as PNG
+//! ([`png`]).
+
+pub mod contour;
+pub mod convergence;
+pub mod png;
+
+use std::fmt::Write;
+
+/// Width of a plot in pixels
+const WIDTH: f64 = 760.0;
+/// Height of a plot in pixels
+const HEIGHT: f64 = 480.0;
+/// Space around the plot area: left, right (legend), top and bottom
+const MARGIN: (f64, f64, f64, f64) = (80.0, 190.0, 40.0, 60.0);
+
+/// Colors of the curves, in order
+const PALETTE: [&str; 10] = [
+    "#1f77b4", "#ff7f0e", "#2ca02c", "#d62728", "#9467bd", "#8c564b", "#e377c2", "#7f7f7f",
+    "#bcbd22", "#17becf",
+];
+
+/// Scale of an axis
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Scale {
+    /// Linear axis
+    Linear,
+    /// Logarithmic axis with ticks at powers of 2
+    Log2,
+    /// Logarithmic axis with ticks at powers of 10
+    Log10,
+}
+
+impl Scale {
+    fn base(self) -> Option<f64> {
+        match self {
+            Scale::Linear => None,
+            Scale::Log2 => Some(2.0),
+            Scale::Log10 => Some(10.0),
+        }
+    }
+
+    /// Position of `value` on the axis, `None` if it cannot be drawn
+    fn transform(self, value: f64) -> Option<f64> {
+        let t = match self.base() {
+            None => value,
+            Some(_) if value <= 0.0 => return None,
+            Some(base) => value.log(base),
+        };
+        t.is_finite().then_some(t)
+    }
+
+    fn inverse(self, t: f64) -> f64 {
+        match self.base() {
+            None => t,
+            Some(base) => base.powf(t),
+        }
+    }
+}
+
+/// A curve of a [`LineChart`]
+#[derive(Clone, Debug)]
+pub struct Series {
+    /// Name shown in the legend
+    pub name: String,
+    /// Points `(x, y)` in the order in which they are connected
+    pub points: Vec<(f64, f64)>,
+    /// Draw a step function which keeps each `y` until the next `x`, as
+    /// for cumulative distributions, instead of connecting the points
+    pub steps: bool,
+}
+
+impl Series {
+    /// Curve connecting `points`
+    pub fn new(name: &str, points: Vec<(f64, f64)>) -> Self {
+        Series {
+            name: name.to_string(),
+            points,
+            steps: false,
+        }
+    }
+
+    /// Step function through `points`
+    pub fn steps(name: &str, points: Vec<(f64, f64)>) -> Self {
+        Series {
+            steps: true,
+            ..Series::new(name, points)
+        }
+    }
+}
+
+/// Plot of curves against a common x axis
+#[derive(Clone, Debug)]
+pub struct LineChart {
+    /// Title above the plot
+    pub title: String,
+    /// Label of the x axis
+    pub x_label: String,
+    /// Label of the y axis
+    pub y_label: String,
+    /// Scale of the x axis
+    pub x_scale: Scale,
+    /// Scale of the y axis
+    pub y_scale: Scale,
+    /// Range of the x axis; fitted to the data if `None`
+    pub x_range: Option<(f64, f64)>,
+    /// Range of the y axis; fitted to the data if `None`
+    pub y_range: Option<(f64, f64)>,
+    /// The curves
+    pub series: Vec<Series>,
+}
+
+impl LineChart {
+    /// Empty chart with linear axes
+    pub fn new(title: &str, x_label: &str, y_label: &str) -> Self {
+        LineChart {
+            title: title.to_string(),
+            x_label: x_label.to_string(),
+            y_label: y_label.to_string(),
+            x_scale: Scale::Linear,
+            y_scale: Scale::Linear,
+            x_range: None,
+            y_range: None,
+            series: Vec::new(),
+        }
+    }
+
+    /// Render the chart as an SVG document
+    pub fn to_svg(&self) -> String {
+        self.render("plot-area")
+    }
+
+    /// SVG document of the chart, whose plot area is clipped by the path
+    /// with id `clip`
+    fn render(&self, clip: &str) -> String {
+        let points = || self.series.iter().flat_map(|s| s.points.iter());
+        let x = Axis::fit(
+            self.x_scale,
+            self.x_range,
+            points().map(|p| p.0),
+            (MARGIN.0, WIDTH - MARGIN.1),
+        );
+        let y = Axis::fit(
+            self.y_scale,
+            self.y_range,
+            points().map(|p| p.1),
+            (HEIGHT - MARGIN.3, MARGIN.2),
+        );
+
+        let mut svg = header(&self.title, clip);
+        frame(&mut svg, &x, &y, &self.x_label, &self.y_label);
+        let _ = writeln!(
+            svg,
+            r#"<g clip-path="url(#{})" fill="none" stroke-width="1.8">"#,
+            clip
+        );
+        for (series, color) in self.series.iter().zip(PALETTE.iter().cycle()) {
+            let points = if series.steps {
+                staircase(&series.points)
+            } else {
+                series.points.clone()
+            };
+            for segment in segments(&points, &x, &y) {
+                let path: Vec<String> = segment
+                    .iter()
+                    .map(|(px, py)| format!("{:.2},{:.2}", px, py))
+                    .collect();
+                if path.len() == 1 {
+                    let (px, py) = segment[0];
+                    let _ = writeln!(
+                        svg,
+                        r#"<circle cx="{:.2}" cy="{:.2}" r="2" fill="{}"/>"#,
+                        px, py, color
+                    );
+                } else {
+                    let _ = writeln!(
+                        svg,
+                        r#"<polyline stroke="{}" points="{}"/>"#,
+                        color,
+                        path.join(" ")
+                    );
+                }
+            }
+        }
+        svg.push_str("</g>\n");
+        legend(&mut svg, self.series.iter().map(|s| s.name.as_str()));
+        svg.push_str("</svg>\n");
+        svg
+    }
+}
+
+/// Several charts side by side in one SVG document
+#[derive(Clone, Debug)]
+pub struct Figure {
+    /// Title above the charts
+    pub title: String,
+    /// Number of charts per row
+    pub columns: usize,
+    /// The charts, row by row
+    pub charts: Vec<LineChart>,
+}
+
+impl Figure {
+    /// Render the figure as an SVG document
+    pub fn to_svg(&self) -> String {
+        let columns = self.columns.max(1);
+        let rows = self.charts.len().div_ceil(columns);
+        let top = 40.0;
+        let (width, height) = (WIDTH * columns as f64, top + HEIGHT * rows as f64);
+        let mut svg = String::new();
+        let _ = writeln!(
+            svg,
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="{w}" height="{h}" viewBox="0 0 {w} {h}" font-family="sans-serif" font-size="12">"#,
+            w = width,
+            h = height
+        );
+        let _ = writeln!(
+            svg,
+            r#"<rect width="{}" height="{}" fill="white"/><text x="{}" y="26" text-anchor="middle" font-size="17">{}</text>"#,
+            width,
+            height,
+            width / 2.0,
+            escape(&self.title)
+        );
+        for (k, chart) in self.charts.iter().enumerate() {
+            let (row, column) = (k / columns, k % columns);
+            // Every chart needs its own clip path id
+            let _ = writeln!(
+                svg,
+                r#"<g transform="translate({},{})">"#,
+                WIDTH * column as f64,
+                top + HEIGHT * row as f64
+            );
+            svg.push_str(&chart.render(&format!("plot-area-{}", k)));
+            svg.push_str("</g>\n");
+        }
+        svg.push_str("</svg>\n");
+        svg
+    }
+}
+
+/// An axis mapping data values to pixels
+#[derive(Clone, Debug)]
+pub(crate) struct Axis {
+    scale: Scale,
+    /// Range of the axis, transformed by the scale
+    lo: f64,
+    hi: f64,
+    /// Pixel positions of `lo` and `hi`
+    pixels: (f64, f64),
+}
+
+impl Axis {
+    /// Axis over `range`, or over the drawable `values` if `None`
+    pub(crate) fn fit(
+        scale: Scale,
+        range: Option<(f64, f64)>,
+        values: impl Iterator<Item = f64>,
+        pixels: (f64, f64),
+    ) -> Self {
+        let (mut lo, mut hi) = match range {
+            Some((lo, hi)) => (
+                scale.transform(lo).unwrap_or(f64::NAN),
+                scale.transform(hi).unwrap_or(f64::NAN),
+            ),
+            None => values
+                .filter_map(|v| scale.transform(v))
+                .fold((f64::INFINITY, f64::NEG_INFINITY), |(lo, hi), t| {
+                    (lo.min(t), hi.max(t))
+                }),
+        };
+        if !(lo.is_finite() && hi.is_finite()) {
+            (lo, hi) = (0.0, 1.0);
+        }
+        if hi - lo <= 1e-12 * lo.abs().max(1.0) {
+            (lo, hi) = (lo - 0.5, hi + 0.5);
+        }
+        Axis {
+            scale,
+            lo,
+            hi,
+            pixels,
+        }
+    }
+
+    /// Pixel position of `value`, `None` if it cannot be drawn
+    pub(crate) fn map(&self, value: f64) -> Option<f64> {
+        let t = self.scale.transform(value)?;
+        Some(self.pixels.0 + (t - self.lo) / (self.hi - self.lo) * (self.pixels.1 - self.pixels.0))
+    }
+
+    /// Values at which ticks are drawn, with their labels
+    pub(crate) fn ticks(&self) -> Vec<(f64, String)> {
+        match self.scale.base() {
+            None => {
+                let step = nice_step((self.hi - self.lo) / 6.0);
+                let first = (self.lo / step).ceil() as i64;
+                let last = (self.hi / step).floor() as i64;
+                (first..=last)
+                    .map(|k| {
+                        let v = k as f64 * step;
+                        (v, format_tick(v, step))
+                    })
+                    .collect()
+            }
+            Some(base) => {
+                let first = self.lo.ceil() as i64;
+                let last = self.hi.floor() as i64;
+                // At most about ten labelled powers
+                let stride = ((last - first) / 10 + 1).max(1);
+                (first..=last)
+                    .filter(|k| k.rem_euclid(stride) == 0)
+                    .map(|k| {
+                        let v = self.scale.inverse(k as f64);
+                        let label = if base == 2.0 && (0..=20).contains(&k) {
+                            format!("{}", v)
+                        } else if base == 2.0 {
+                            format!("2^{}", k)
+                        } else if (-2..=4).contains(&k) {
+                            format!("{}", v)
+                        } else {
+                            format!("1e{}", k)
+                        };
+                        (v, label)
+                    })
+                    .collect()
+            }
+        }
+    }
+}
+
+/// 1, 2 or 5 times a power of ten, close to `raw`
+fn nice_step(raw: f64) -> f64 {
+    let magnitude = 10f64.powf(raw.log10().floor());
+    let fraction = raw / magnitude;
+    let nice = if fraction < 1.5 {
+        1.0
+    } else if fraction < 3.5 {
+        2.0
+    } else if fraction < 7.5 {
+        5.0
+    } else {
+        10.0
+    };
+    nice * magnitude
+}
+
+/// Label of a tick at `value` on a linear axis with ticks `step` apart
+fn format_tick(value: f64, step: f64) -> String {
+    if value.abs() < step * 1e-9 {
+        return "0".to_string();
+    }
+    if value.abs() >= 1e5 || value.abs() < 1e-3 {
+        return format!("{:e}", value);
+    }
+    let decimals = (-step.log10().floor()).max(0.0) as usize;
+    format!("{:.*}", decimals, value)
+}
+
+/// Points of the step function through `points`
+fn staircase(points: &[(f64, f64)]) -> Vec<(f64, f64)> {
+    let mut steps = Vec::with_capacity(2 * points.len());
+    for (i, &(x, y)) in points.iter().enumerate() {
+        if i > 0 {
+            steps.push((x, points[i - 1].1));
+        }
+        steps.push((x, y));
+    }
+    steps
+}
+
+/// Pixel coordinates of `points`, split where a point cannot be drawn
+fn segments(points: &[(f64, f64)], x: &Axis, y: &Axis) -> Vec<Vec<(f64, f64)>> {
+    let mut segments = vec![Vec::new()];
+    for &(px, py) in points {
+        match (x.map(px), y.map(py)) {
+            (Some(px), Some(py)) => segments.last_mut().expect("not empty").push((px, py)),
+            _ => segments.push(Vec::new()),
+        }
+    }
+    segments.retain(|s| !s.is_empty());
+    segments
+}
+
+/// Start of an SVG document with `title`, defining the plot area as the
+/// clip path `clip`
+pub(crate) fn header(title: &str, clip: &str) -> String {
+    let mut svg = String::new();
+    let _ = writeln!(
+        svg,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{w}" height="{h}" viewBox="0 0 {w} {h}" font-family="sans-serif" font-size="12">"#,
+        w = WIDTH,
+        h = HEIGHT
+    );
+    let _ = writeln!(
+        svg,
+        r#"<rect width="{}" height="{}" fill="white"/>"#,
+        WIDTH, HEIGHT
+    );
+    let _ = writeln!(
+        svg,
+        r#"<clipPath id="{}"><rect x="{}" y="{}" width="{}" height="{}"/></clipPath>"#,
+        clip,
+        MARGIN.0,
+        MARGIN.2,
+        WIDTH - MARGIN.0 - MARGIN.1,
+        HEIGHT - MARGIN.2 - MARGIN.3
+    );
+    let _ = writeln!(
+        svg,
+        r#"<text x="{}" y="24" text-anchor="middle" font-size="15">{}</text>"#,
+        MARGIN.0 + (WIDTH - MARGIN.0 - MARGIN.1) / 2.0,
+        escape(title)
+    );
+    svg
+}
+
+/// Box, grid, ticks and labels of the axes
+pub(crate) fn frame(svg: &mut String, x: &Axis, y: &Axis, x_label: &str, y_label: &str) {
+    let (left, right, top, bottom) = (MARGIN.0, WIDTH - MARGIN.1, MARGIN.2, HEIGHT - MARGIN.3);
+    for (value, label) in x.ticks() {
+        if let Some(px) = x.map(value) {
+            let _ = writeln!(
+                svg,
+                r##"<line x1="{px:.2}" y1="{top}" x2="{px:.2}" y2="{bottom}" stroke="#e0e0e0"/><text x="{px:.2}" y="{}" text-anchor="middle">{}</text>"##,
+                bottom + 16.0,
+                escape(&label)
+            );
+        }
+    }
+    for (value, label) in y.ticks() {
+        if let Some(py) = y.map(value) {
+            let _ = writeln!(
+                svg,
+                r##"<line x1="{left}" y1="{py:.2}" x2="{right}" y2="{py:.2}" stroke="#e0e0e0"/><text x="{}" y="{:.2}" text-anchor="end">{}</text>"##,
+                left - 6.0,
+                py + 4.0,
+                escape(&label)
+            );
+        }
+    }
+    let _ = writeln!(
+        svg,
+        r#"<rect x="{left}" y="{top}" width="{}" height="{}" fill="none" stroke="black"/>"#,
+        right - left,
+        bottom - top
+    );
+    let _ = writeln!(
+        svg,
+        r#"<text x="{}" y="{}" text-anchor="middle">{}</text>"#,
+        (left + right) / 2.0,
+        HEIGHT - 16.0,
+        escape(x_label)
+    );
+    let _ = writeln!(
+        svg,
+        r#"<text transform="translate(20,{}) rotate(-90)" text-anchor="middle">{}</text>"#,
+        (top + bottom) / 2.0,
+        escape(y_label)
+    );
+}
+
+/// Legend with one entry per name, in the colors of the curves
+fn legend<'a>(svg: &mut String, names: impl Iterator<Item = &'a str>) {
+    let x = WIDTH - MARGIN.1 + 15.0;
+    for (i, (name, color)) in names.zip(PALETTE.iter().cycle()).enumerate() {
+        let y = MARGIN.2 + 10.0 + 18.0 * i as f64;
+        let _ = writeln!(
+            svg,
+            r#"<line x1="{x}" y1="{y}" x2="{}" y2="{y}" stroke="{color}" stroke-width="2.5"/><text x="{}" y="{}">{}</text>"#,
+            x + 20.0,
+            x + 26.0,
+            y + 4.0,
+            escape(name)
+        );
+    }
+}
+
+/// `text` with the characters which are special in XML escaped
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
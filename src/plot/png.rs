@@ -0,0 +1,257 @@
+//! Raster images and their PNG encoding
+//!
+//! [`Raster`] is an RGB image with a few drawing primitives, enough for the
+//! contour plots. [`Raster::to_png`] encodes it without compression (the
+//! deflate stream consists of stored blocks), which keeps the encoder short
+//! at the price of larger files; every PNG reader accepts it.
+
+/// An RGB color
+pub type Rgb = [u8; 3];
+
+/// Image of `width * height` RGB pixels, row by row from the top left
+#[derive(Clone, Debug)]
+pub struct Raster {
+    width: usize,
+    height: usize,
+    pixels: Vec<Rgb>,
+}
+
+impl Raster {
+    /// Image filled with `background`
+    pub fn new(width: usize, height: usize, background: Rgb) -> Self {
+        Raster {
+            width,
+            height,
+            pixels: vec![background; width * height],
+        }
+    }
+
+    /// Width in pixels
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// Height in pixels
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Color the pixel at column `x` and row `y`; pixels outside the image
+    /// are ignored
+    pub fn set(&mut self, x: i64, y: i64, color: Rgb) {
+        if (0..self.width as i64).contains(&x) && (0..self.height as i64).contains(&y) {
+            self.pixels[y as usize * self.width + x as usize] = color;
+        }
+    }
+
+    /// Fill the rectangle of pixels `[x0, x1) x [y0, y1)`
+    pub fn fill_rect(&mut self, x0: i64, y0: i64, x1: i64, y1: i64, color: Rgb) {
+        for y in y0..y1 {
+            for x in x0..x1 {
+                self.set(x, y, color);
+            }
+        }
+    }
+
+    /// Draw a line `width` pixels wide from `(x0, y0)` to `(x1, y1)`
+    ///
+    /// Only the part of the line close to the image is drawn, so far away
+    /// end points do not cost time.
+    pub fn line(&mut self, a: (f64, f64), b: (f64, f64), width: f64, color: Rgb) {
+        let pad = width + 1.0;
+        let Some(((x0, y0), (x1, y1))) = clip(
+            a,
+            b,
+            (-pad, self.width as f64 + pad),
+            (-pad, self.height as f64 + pad),
+        ) else {
+            return;
+        };
+        let steps = (x1 - x0).abs().max((y1 - y0).abs()).ceil().max(1.0) as usize;
+        for i in 0..=steps {
+            let t = i as f64 / steps as f64;
+            self.disc((x0 + t * (x1 - x0), y0 + t * (y1 - y0)), width / 2.0, color);
+        }
+    }
+
+    /// Fill the disc of `radius` around `(cx, cy)`
+    pub fn disc(&mut self, (cx, cy): (f64, f64), radius: f64, color: Rgb) {
+        let r = radius.max(0.5);
+        for y in (cy - r).floor() as i64..=(cy + r).ceil() as i64 {
+            for x in (cx - r).floor() as i64..=(cx + r).ceil() as i64 {
+                let (dx, dy) = (x as f64 + 0.5 - cx, y as f64 + 0.5 - cy);
+                if dx * dx + dy * dy <= r * r {
+                    self.set(x, y, color);
+                }
+            }
+        }
+    }
+
+    /// Encode the image as PNG
+    pub fn to_png(&self) -> Vec<u8> {
+        // Every row starts with filter type 0 (none)
+        let mut data = Vec::with_capacity(self.height * (3 * self.width + 1));
+        for row in self.pixels.chunks(self.width) {
+            data.push(0);
+            data.extend(row.iter().flatten());
+        }
+
+        let mut png = b"\x89PNG\r\n\x1a\n".to_vec();
+        let mut header = Vec::with_capacity(13);
+        header.extend((self.width as u32).to_be_bytes());
+        header.extend((self.height as u32).to_be_bytes());
+        // 8 bits per channel, RGB, deflate, no filtering, no interlace
+        header.extend([8, 2, 0, 0, 0]);
+        chunk(&mut png, b"IHDR", &header);
+        chunk(&mut png, b"IDAT", &zlib_stored(&data));
+        chunk(&mut png, b"IEND", &[]);
+        png
+    }
+}
+
+/// Part of the segment from `a` to `b` inside the rectangle `xs` times `ys`
+/// (Liang-Barsky), `None` if it misses the rectangle
+fn clip(
+    a: (f64, f64),
+    b: (f64, f64),
+    xs: (f64, f64),
+    ys: (f64, f64),
+) -> Option<((f64, f64), (f64, f64))> {
+    let (dx, dy) = (b.0 - a.0, b.1 - a.1);
+    let (mut t0, mut t1) = (0.0f64, 1.0f64);
+    for (p, q) in [
+        (-dx, a.0 - xs.0),
+        (dx, xs.1 - a.0),
+        (-dy, a.1 - ys.0),
+        (dy, ys.1 - a.1),
+    ] {
+        if p == 0.0 {
+            if q < 0.0 {
+                return None;
+            }
+        } else if p < 0.0 {
+            t0 = t0.max(q / p);
+        } else {
+            t1 = t1.min(q / p);
+        }
+    }
+    (t0 <= t1 && t0.is_finite() && t1.is_finite()).then_some((
+        (a.0 + t0 * dx, a.1 + t0 * dy),
+        (a.0 + t1 * dx, a.1 + t1 * dy),
+    ))
+}
+
+/// Append the chunk `kind` with `data` and its checksum to `png`
+fn chunk(png: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+    png.extend((data.len() as u32).to_be_bytes());
+    let start = png.len();
+    png.extend(kind);
+    png.extend(data);
+    let crc = crc32(&png[start..]);
+    png.extend(crc.to_be_bytes());
+}
+
+/// zlib stream of `data` in uncompressed deflate blocks
+fn zlib_stored(data: &[u8]) -> Vec<u8> {
+    // Deflate, 32K window, no preset dictionary, checksum of the header bits
+    let mut out = vec![0x78, 0x01];
+    let mut blocks = data.chunks(u16::MAX as usize).peekable();
+    if blocks.peek().is_none() {
+        out.extend([1, 0, 0, 0xff, 0xff]);
+    }
+    while let Some(block) = blocks.next() {
+        out.push(u8::from(blocks.peek().is_none()));
+        let len = block.len() as u16;
+        out.extend(len.to_le_bytes());
+        out.extend((!len).to_le_bytes());
+        out.extend(block);
+    }
+    out.extend(adler32(data).to_be_bytes());
+    out
+}
+
+fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &byte in bytes {
+        crc ^= u32::from(byte);
+        for _ in 0..8 {
+            crc = if crc & 1 == 1 {
+                (crc >> 1) ^ 0xedb8_8320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+fn adler32(bytes: &[u8]) -> u32 {
+    let (mut a, mut b) = (1u32, 0u32);
+    for &byte in bytes {
+        a = (a + u32::from(byte)) % 65521;
+        b = (b + a) % 65521;
+    }
+    (b << 16) | a
+}
+
+/// Standard base64 encoding of `bytes`, for images embedded in SVG
+pub fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut out = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for group in bytes.chunks(3) {
+        let n = group
+            .iter()
+            .enumerate()
+            .fold(0u32, |n, (i, &b)| n | u32::from(b) << (16 - 8 * i));
+        for i in 0..4 {
+            if i <= group.len() {
+                out.push(ALPHABET[(n >> (18 - 6 * i) & 63) as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn checksums_match_reference_values() {
+        assert_eq!(crc32(b"123456789"), 0xcbf4_3926);
+        assert_eq!(adler32(b"Wikipedia"), 0x11e6_0398);
+    }
+
+    #[test]
+    fn base64_pads_partial_groups() {
+        assert_eq!(base64(b"Man"), "TWFu");
+        assert_eq!(base64(b"Ma"), "TWE=");
+        assert_eq!(base64(b"M"), "TQ==");
+        assert_eq!(base64(b""), "");
+    }
+
+    #[test]
+    fn long_data_is_split_into_stored_blocks() {
+        let data = vec![7u8; 70_000];
+        let zlib = zlib_stored(&data);
+        // Header, two block headers, the data and the checksum
+        assert_eq!(zlib.len(), 2 + 2 * 5 + data.len() + 4);
+        assert_eq!(zlib[2], 0);
+        assert_eq!(zlib[2 + 5 + u16::MAX as usize], 1);
+    }
+
+    #[test]
+    fn png_has_the_size_of_the_raster() {
+        let mut raster = Raster::new(3, 2, [255, 255, 255]);
+        raster.set(1, 1, [1, 2, 3]);
+        // Pixels outside the raster are ignored
+        raster.set(5, -1, [1, 2, 3]);
+        let png = raster.to_png();
+        assert!(png.starts_with(b"\x89PNG\r\n\x1a\n"));
+        assert_eq!(&png[12..16], b"IHDR");
+        assert_eq!(png[16..24], [0, 0, 0, 3, 0, 0, 0, 2]);
+        assert!(png.ends_with(b"IEND\xae\x42\x60\x82"));
+    }
+}
@@ -0,0 +1,992 @@
+//! Cost functions given as mathematical expressions
+//!
+//! [`Expression`] parses a formula such as `(1-x)^2 + 100*(y-x^2)^2` and
+//! turns it into a problem with symbolic gradient and Hessian, so that
+//! functions can be tried without writing Rust. The grammar is the usual
+//! one:
+//!
+//! * numbers (`2`, `0.5`, `1e-3`) and the constants `pi` and `e`
+//! * variables: any other identifier, e.g. `x`, `y` or `x1`
+//! * `+`, `-`, `*`, `/` and powers `^` (or `**`), which bind tightest and
+//!   associate to the right: `-x^2` is `-(x^2)` and `2^3^2` is `2^9`
+//! * the functions in [`Func`], e.g. `sin(x)` or `exp(-x^2)`
+//!
+//! Unless given explicitly, the variables are ordered by name, with numeric
+//! suffixes compared as numbers (`x2` before `x10`), so `x` is the first
+//! parameter and `y` the second.
+//!
+//! Derivatives are differentiated symbolically once, when the expression is
+//! parsed, and simplified just enough to drop terms which vanish. The
+//! expression can also be evaluated on other scalar types ([`GenericCost`]),
+//! so complex steps and automatic differentiation work as well.
+
+use super::{DynProblem, ProblemInfo};
+use crate::scalar::{GenericCost, Scalar};
+use argmin::core::{ArgminError, CostFunction, Error, Gradient, Hessian};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::ops::{Add, Div, Mul, Neg, Sub};
+
+/// Elementary functions which can be called in expressions
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Func {
+    /// Square root
+    Sqrt,
+    /// Exponential
+    Exp,
+    /// Natural logarithm (also available as `log`)
+    Ln,
+    /// Logarithm to base 2
+    Log2,
+    /// Logarithm to base 10
+    Log10,
+    /// Sine
+    Sin,
+    /// Cosine
+    Cos,
+    /// Tangent
+    Tan,
+    /// Inverse sine
+    Asin,
+    /// Inverse cosine
+    Acos,
+    /// Inverse tangent
+    Atan,
+    /// Hyperbolic sine
+    Sinh,
+    /// Hyperbolic cosine
+    Cosh,
+    /// Hyperbolic tangent
+    Tanh,
+    /// Absolute value
+    Abs,
+    /// Sign, `-1` or `1`
+    Sign,
+}
+
+impl Func {
+    /// All functions, in the order in which they are listed
+    pub const ALL: &'static [Func] = &[
+        Func::Sqrt,
+        Func::Exp,
+        Func::Ln,
+        Func::Log2,
+        Func::Log10,
+        Func::Sin,
+        Func::Cos,
+        Func::Tan,
+        Func::Asin,
+        Func::Acos,
+        Func::Atan,
+        Func::Sinh,
+        Func::Cosh,
+        Func::Tanh,
+        Func::Abs,
+        Func::Sign,
+    ];
+
+    /// Name under which the function is called
+    pub fn name(self) -> &'static str {
+        match self {
+            Func::Sqrt => "sqrt",
+            Func::Exp => "exp",
+            Func::Ln => "ln",
+            Func::Log2 => "log2",
+            Func::Log10 => "log10",
+            Func::Sin => "sin",
+            Func::Cos => "cos",
+            Func::Tan => "tan",
+            Func::Asin => "asin",
+            Func::Acos => "acos",
+            Func::Atan => "atan",
+            Func::Sinh => "sinh",
+            Func::Cosh => "cosh",
+            Func::Tanh => "tanh",
+            Func::Abs => "abs",
+            Func::Sign => "sign",
+        }
+    }
+
+    /// Look up a function by its name
+    pub fn from_name(name: &str) -> Option<Func> {
+        match name {
+            "log" => Some(Func::Ln),
+            _ => Func::ALL.iter().copied().find(|f| f.name() == name),
+        }
+    }
+
+    fn apply<T: Scalar>(self, x: T) -> T {
+        match self {
+            Func::Sqrt => x.sqrt(),
+            Func::Exp => x.exp(),
+            Func::Ln => x.ln(),
+            Func::Log2 => x.log2(),
+            Func::Log10 => x.log10(),
+            Func::Sin => x.sin(),
+            Func::Cos => x.cos(),
+            Func::Tan => x.tan(),
+            Func::Asin => x.asin(),
+            Func::Acos => x.acos(),
+            Func::Atan => x.atan(),
+            Func::Sinh => x.sinh(),
+            Func::Cosh => x.cosh(),
+            Func::Tanh => x.tanh(),
+            Func::Abs => x.abs(),
+            Func::Sign => x.signum(),
+        }
+    }
+
+    /// Derivative of the function at `x`
+    fn derivative(self, x: &Expr) -> Expr {
+        use std::f64::consts::{LN_10, LN_2};
+        let one = || Expr::Const(1.0);
+        let square = |x: Expr| Expr::pow(x, Expr::Const(2.0));
+        let call = |f: Func, x: &Expr| Expr::call(f, x.clone());
+        match self {
+            Func::Sqrt => Expr::Const(0.5) / call(Func::Sqrt, x),
+            Func::Exp => call(Func::Exp, x),
+            Func::Ln => one() / x.clone(),
+            Func::Log2 => one() / (x.clone() * Expr::Const(LN_2)),
+            Func::Log10 => one() / (x.clone() * Expr::Const(LN_10)),
+            Func::Sin => call(Func::Cos, x),
+            Func::Cos => -call(Func::Sin, x),
+            Func::Tan => one() / square(call(Func::Cos, x)),
+            Func::Asin => one() / Expr::call(Func::Sqrt, one() - square(x.clone())),
+            Func::Acos => -(one() / Expr::call(Func::Sqrt, one() - square(x.clone()))),
+            Func::Atan => one() / (one() + square(x.clone())),
+            Func::Sinh => call(Func::Cosh, x),
+            Func::Cosh => call(Func::Sinh, x),
+            Func::Tanh => one() - square(call(Func::Tanh, x)),
+            Func::Abs => call(Func::Sign, x),
+            Func::Sign => Expr::Const(0.0),
+        }
+    }
+}
+
+/// Syntax tree of an expression
+///
+/// Trees built with the arithmetic operators, [`Expr::pow`] and
+/// [`Expr::call`] fold constants and drop neutral elements.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Expr {
+    /// Number
+    Const(f64),
+    /// Parameter with the given index
+    Var(usize),
+    /// `-a`
+    Neg(Box<Expr>),
+    /// `a + b`
+    Add(Box<Expr>, Box<Expr>),
+    /// `a - b`
+    Sub(Box<Expr>, Box<Expr>),
+    /// `a * b`
+    Mul(Box<Expr>, Box<Expr>),
+    /// `a / b`
+    Div(Box<Expr>, Box<Expr>),
+    /// `a ^ b`
+    Pow(Box<Expr>, Box<Expr>),
+    /// `f(a)`
+    Call(Func, Box<Expr>),
+}
+
+impl Expr {
+    /// `a ^ b`
+    pub fn pow(a: Expr, b: Expr) -> Expr {
+        match (a, b) {
+            (Expr::Const(a), Expr::Const(b)) => Expr::Const(a.powf(b)),
+            (_, Expr::Const(0.0)) => Expr::Const(1.0),
+            (a, Expr::Const(1.0)) => a,
+            (a, b) => Expr::Pow(Box::new(a), Box::new(b)),
+        }
+    }
+
+    /// `f(a)`
+    pub fn call(f: Func, a: Expr) -> Expr {
+        match a {
+            Expr::Const(a) => Expr::Const(f.apply(a)),
+            a => Expr::Call(f, Box::new(a)),
+        }
+    }
+
+    /// Value of the expression at `x`
+    ///
+    /// Integer powers are evaluated by repeated multiplication, so that
+    /// negative bases are fine.
+    pub fn eval<T: Scalar>(&self, x: &[T]) -> T {
+        match self {
+            Expr::Const(c) => T::constant(*c),
+            Expr::Var(i) => x[*i],
+            Expr::Neg(a) => -a.eval(x),
+            Expr::Add(a, b) => a.eval(x) + b.eval(x),
+            Expr::Sub(a, b) => a.eval(x) - b.eval(x),
+            Expr::Mul(a, b) => a.eval(x) * b.eval(x),
+            Expr::Div(a, b) => a.eval(x) / b.eval(x),
+            Expr::Pow(a, b) => match **b {
+                Expr::Const(n) if n.fract() == 0.0 && n.abs() <= f64::from(i32::MAX) => {
+                    a.eval(x).powi(n as i32)
+                }
+                _ => a.eval(x).powf(b.eval(x)),
+            },
+            Expr::Call(f, a) => f.apply(a.eval(x)),
+        }
+    }
+
+    /// Whether the expression depends on parameter `i`
+    pub fn depends_on(&self, i: usize) -> bool {
+        match self {
+            Expr::Const(_) => false,
+            Expr::Var(j) => *j == i,
+            Expr::Neg(a) | Expr::Call(_, a) => a.depends_on(i),
+            Expr::Add(a, b)
+            | Expr::Sub(a, b)
+            | Expr::Mul(a, b)
+            | Expr::Div(a, b)
+            | Expr::Pow(a, b) => a.depends_on(i) || b.depends_on(i),
+        }
+    }
+
+    /// Partial derivative with respect to parameter `i`
+    pub fn derivative(&self, i: usize) -> Expr {
+        if !self.depends_on(i) {
+            return Expr::Const(0.0);
+        }
+        match self {
+            Expr::Const(_) => Expr::Const(0.0),
+            Expr::Var(j) => Expr::Const(if *j == i { 1.0 } else { 0.0 }),
+            Expr::Neg(a) => -a.derivative(i),
+            Expr::Add(a, b) => a.derivative(i) + b.derivative(i),
+            Expr::Sub(a, b) => a.derivative(i) - b.derivative(i),
+            Expr::Mul(a, b) => a.derivative(i) * (**b).clone() + (**a).clone() * b.derivative(i),
+            Expr::Div(a, b) => {
+                let numerator = a.derivative(i) * (**b).clone() - (**a).clone() * b.derivative(i);
+                numerator / Expr::pow((**b).clone(), Expr::Const(2.0))
+            }
+            Expr::Pow(a, b) if !b.depends_on(i) => {
+                // d a^b = b a^(b - 1) da
+                let power = Expr::pow((**a).clone(), (**b).clone() - Expr::Const(1.0));
+                (**b).clone() * power * a.derivative(i)
+            }
+            Expr::Pow(a, b) => {
+                // d a^b = a^b (ln(a) db + b da / a)
+                let log = Expr::call(Func::Ln, (**a).clone()) * b.derivative(i);
+                let base = (**b).clone() * a.derivative(i) / (**a).clone();
+                self.clone() * (log + base)
+            }
+            Expr::Call(f, a) => f.derivative(a) * a.derivative(i),
+        }
+    }
+
+    /// Replace parameter `i` by parameter `map[i]`
+    fn renumber(&mut self, map: &[usize]) {
+        match self {
+            Expr::Const(_) => {}
+            Expr::Var(i) => *i = map[*i],
+            Expr::Neg(a) | Expr::Call(_, a) => a.renumber(map),
+            Expr::Add(a, b)
+            | Expr::Sub(a, b)
+            | Expr::Mul(a, b)
+            | Expr::Div(a, b)
+            | Expr::Pow(a, b) => {
+                a.renumber(map);
+                b.renumber(map);
+            }
+        }
+    }
+
+    /// Binding strength, for printing parentheses
+    fn precedence(&self) -> u8 {
+        match self {
+            Expr::Add(..) | Expr::Sub(..) => 1,
+            Expr::Mul(..) | Expr::Div(..) => 2,
+            Expr::Neg(_) => 3,
+            Expr::Pow(..) => 4,
+            Expr::Const(c) if *c < 0.0 => 3,
+            Expr::Const(_) | Expr::Var(_) | Expr::Call(..) => 5,
+        }
+    }
+
+    /// Print the expression with the given variable names
+    pub fn display<'a>(&'a self, variables: &'a [String]) -> impl fmt::Display + 'a {
+        Display {
+            expr: self,
+            variables,
+        }
+    }
+}
+
+impl Neg for Expr {
+    type Output = Expr;
+
+    fn neg(self) -> Expr {
+        match self {
+            Expr::Const(a) => Expr::Const(-a),
+            Expr::Neg(a) => *a,
+            a => Expr::Neg(Box::new(a)),
+        }
+    }
+}
+
+impl Add for Expr {
+    type Output = Expr;
+
+    fn add(self, other: Expr) -> Expr {
+        match (self, other) {
+            (Expr::Const(a), Expr::Const(b)) => Expr::Const(a + b),
+            (Expr::Const(0.0), e) | (e, Expr::Const(0.0)) => e,
+            (a, Expr::Neg(b)) => a - *b,
+            (a, b) => Expr::Add(Box::new(a), Box::new(b)),
+        }
+    }
+}
+
+impl Sub for Expr {
+    type Output = Expr;
+
+    fn sub(self, other: Expr) -> Expr {
+        match (self, other) {
+            (Expr::Const(a), Expr::Const(b)) => Expr::Const(a - b),
+            (a, Expr::Const(0.0)) => a,
+            (Expr::Const(0.0), b) => -b,
+            (a, Expr::Neg(b)) => a + *b,
+            (a, b) => Expr::Sub(Box::new(a), Box::new(b)),
+        }
+    }
+}
+
+impl Mul for Expr {
+    type Output = Expr;
+
+    fn mul(self, other: Expr) -> Expr {
+        match (self, other) {
+            (Expr::Const(a), Expr::Const(b)) => Expr::Const(a * b),
+            (Expr::Const(0.0), _) | (_, Expr::Const(0.0)) => Expr::Const(0.0),
+            (Expr::Const(1.0), e) | (e, Expr::Const(1.0)) => e,
+            (Expr::Const(-1.0), e) | (e, Expr::Const(-1.0)) => -e,
+            (a, b) => Expr::Mul(Box::new(a), Box::new(b)),
+        }
+    }
+}
+
+impl Div for Expr {
+    type Output = Expr;
+
+    fn div(self, other: Expr) -> Expr {
+        match (self, other) {
+            (Expr::Const(a), Expr::Const(b)) if b != 0.0 => Expr::Const(a / b),
+            (Expr::Const(0.0), _) => Expr::Const(0.0),
+            (a, Expr::Const(1.0)) => a,
+            (a, b) => Expr::Div(Box::new(a), Box::new(b)),
+        }
+    }
+}
+
+struct Display<'a> {
+    expr: &'a Expr,
+    variables: &'a [String],
+}
+
+impl Display<'_> {
+    fn child<'b>(&'b self, expr: &'b Expr) -> Display<'b> {
+        Display {
+            expr,
+            variables: self.variables,
+        }
+    }
+
+    /// Print `a op b` with the given precedences required of the operands
+    fn binary(
+        &self,
+        f: &mut fmt::Formatter<'_>,
+        a: &Expr,
+        op: &str,
+        b: &Expr,
+        left: u8,
+        right: u8,
+    ) -> fmt::Result {
+        self.operand(f, a, left)?;
+        write!(f, " {} ", op)?;
+        self.operand(f, b, right)
+    }
+
+    /// Print `expr`, in parentheses if it binds less than `precedence`
+    fn operand(&self, f: &mut fmt::Formatter<'_>, expr: &Expr, precedence: u8) -> fmt::Result {
+        if expr.precedence() < precedence {
+            write!(f, "({})", self.child(expr))
+        } else {
+            write!(f, "{}", self.child(expr))
+        }
+    }
+}
+
+impl fmt::Display for Display<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.expr {
+            Expr::Const(c) => write!(f, "{}", c),
+            Expr::Var(i) => write!(f, "{}", self.variables[*i]),
+            Expr::Neg(a) => {
+                write!(f, "-")?;
+                self.operand(f, a, 4)
+            }
+            // The right operand of `-` and `/` needs parentheses at equal
+            // precedence, `^` associates to the right
+            Expr::Add(a, b) => self.binary(f, a, "+", b, 1, 1),
+            Expr::Sub(a, b) => self.binary(f, a, "-", b, 1, 2),
+            Expr::Mul(a, b) => self.binary(f, a, "*", b, 2, 3),
+            Expr::Div(a, b) => self.binary(f, a, "/", b, 2, 3),
+            Expr::Pow(a, b) => {
+                self.operand(f, a, 5)?;
+                write!(f, "^")?;
+                self.operand(f, b, 4)
+            }
+            Expr::Call(func, a) => write!(f, "{}({})", func.name(), self.child(a)),
+        }
+    }
+}
+
+/// Tokens of the expression language
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Number(f64),
+    Ident(String),
+    Op(char),
+    Open,
+    Close,
+}
+
+/// Split `source` into tokens and the columns at which they start
+fn tokenize(source: &str) -> Result<Vec<(Token, usize)>, (String, usize)> {
+    let chars: Vec<char> = source.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        let start = i;
+        if c.is_whitespace() {
+            i += 1;
+            continue;
+        }
+        let token = if c.is_ascii_digit() || c == '.' {
+            while i < chars.len() && (chars[i].is_ascii_digit() || chars[i] == '.') {
+                i += 1;
+            }
+            // Exponent, e.g. `1e-3`
+            if i < chars.len() && (chars[i] == 'e' || chars[i] == 'E') {
+                let mut j = i + 1;
+                if j < chars.len() && (chars[j] == '+' || chars[j] == '-') {
+                    j += 1;
+                }
+                if j < chars.len() && chars[j].is_ascii_digit() {
+                    i = j;
+                    while i < chars.len() && chars[i].is_ascii_digit() {
+                        i += 1;
+                    }
+                }
+            }
+            let text: String = chars[start..i].iter().collect();
+            let value = text
+                .parse()
+                .map_err(|_| (format!("invalid number `{}`", text), start))?;
+            Token::Number(value)
+        } else if c.is_alphabetic() || c == '_' {
+            while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_') {
+                i += 1;
+            }
+            Token::Ident(chars[start..i].iter().collect())
+        } else {
+            i += 1;
+            match c {
+                '*' if chars.get(i) == Some(&'*') => {
+                    i += 1;
+                    Token::Op('^')
+                }
+                '+' | '-' | '*' | '/' | '^' => Token::Op(c),
+                '(' => Token::Open,
+                ')' => Token::Close,
+                _ => return Err((format!("unexpected character `{}`", c), start)),
+            }
+        };
+        tokens.push((token, start));
+    }
+    Ok(tokens)
+}
+
+/// Recursive descent parser over the tokens of an expression
+struct Parser<'a> {
+    tokens: Vec<(Token, usize)>,
+    pos: usize,
+    /// Column of the end of the input
+    end: usize,
+    parameters: &'a BTreeMap<String, f64>,
+    /// Variables in order of their first appearance
+    variables: Vec<String>,
+}
+
+type ParseResult = Result<Expr, (String, usize)>;
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos).map(|(t, _)| t)
+    }
+
+    fn column(&self) -> usize {
+        self.tokens.get(self.pos).map_or(self.end, |(_, c)| *c)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).map(|(t, _)| t.clone());
+        self.pos += 1;
+        token
+    }
+
+    fn error(&self, text: &str) -> (String, usize) {
+        let found = match self.peek() {
+            None => "end of input".to_string(),
+            Some(Token::Number(n)) => format!("`{}`", n),
+            Some(Token::Ident(name)) => format!("`{}`", name),
+            Some(Token::Op(op)) => format!("`{}`", op),
+            Some(Token::Open) => "`(`".to_string(),
+            Some(Token::Close) => "`)`".to_string(),
+        };
+        (format!("expected {}, found {}", text, found), self.column())
+    }
+
+    /// `term (('+' | '-') term)*`
+    fn sum(&mut self) -> ParseResult {
+        let mut expr = self.product()?;
+        while let Some(Token::Op(op @ ('+' | '-'))) = self.peek() {
+            let op = *op;
+            self.pos += 1;
+            let rhs = self.product()?;
+            expr = match op {
+                '+' => Expr::Add(Box::new(expr), Box::new(rhs)),
+                _ => Expr::Sub(Box::new(expr), Box::new(rhs)),
+            };
+        }
+        Ok(expr)
+    }
+
+    /// `unary (('*' | '/') unary)*`
+    fn product(&mut self) -> ParseResult {
+        let mut expr = self.unary()?;
+        while let Some(Token::Op(op @ ('*' | '/'))) = self.peek() {
+            let op = *op;
+            self.pos += 1;
+            let rhs = self.unary()?;
+            expr = match op {
+                '*' => Expr::Mul(Box::new(expr), Box::new(rhs)),
+                _ => Expr::Div(Box::new(expr), Box::new(rhs)),
+            };
+        }
+        Ok(expr)
+    }
+
+    /// `('-' | '+') unary | power`
+    fn unary(&mut self) -> ParseResult {
+        match self.peek() {
+            Some(Token::Op('-')) => {
+                self.pos += 1;
+                // Fold negative numbers, so that `-1` is a constant
+                Ok(-self.unary()?)
+            }
+            Some(Token::Op('+')) => {
+                self.pos += 1;
+                self.unary()
+            }
+            _ => self.power(),
+        }
+    }
+
+    /// `atom ('^' unary)?`
+    fn power(&mut self) -> ParseResult {
+        let base = self.atom()?;
+        if let Some(Token::Op('^')) = self.peek() {
+            self.pos += 1;
+            let exponent = self.unary()?;
+            return Ok(Expr::Pow(Box::new(base), Box::new(exponent)));
+        }
+        Ok(base)
+    }
+
+    /// `number | name | name '(' sum ')' | '(' sum ')'`
+    fn atom(&mut self) -> ParseResult {
+        let column = self.column();
+        match self.next() {
+            Some(Token::Number(n)) => Ok(Expr::Const(n)),
+            Some(Token::Open) => {
+                let expr = self.sum()?;
+                self.expect_close()?;
+                Ok(expr)
+            }
+            Some(Token::Ident(name)) if self.peek() == Some(&Token::Open) => {
+                let func = Func::from_name(&name)
+                    .ok_or_else(|| (format!("unknown function `{}`", name), column))?;
+                self.pos += 1;
+                let arg = self.sum()?;
+                self.expect_close()?;
+                Ok(Expr::Call(func, Box::new(arg)))
+            }
+            Some(Token::Ident(name)) => Ok(self.name(name)),
+            _ => {
+                self.pos -= 1;
+                Err(self.error("a number, variable, function or `(`"))
+            }
+        }
+    }
+
+    fn expect_close(&mut self) -> Result<(), (String, usize)> {
+        match self.peek() {
+            Some(Token::Close) => {
+                self.pos += 1;
+                Ok(())
+            }
+            _ => Err(self.error("`)`")),
+        }
+    }
+
+    /// Parameter, constant or variable called `name`
+    fn name(&mut self, name: String) -> Expr {
+        if let Some(value) = self.parameters.get(&name) {
+            return Expr::Const(*value);
+        }
+        match name.as_str() {
+            "pi" => return Expr::Const(std::f64::consts::PI),
+            "e" => return Expr::Const(std::f64::consts::E),
+            _ => {}
+        }
+        let index = match self.variables.iter().position(|v| *v == name) {
+            Some(index) => index,
+            None => {
+                self.variables.push(name);
+                self.variables.len() - 1
+            }
+        };
+        Expr::Var(index)
+    }
+}
+
+/// Order of variable names: numeric suffixes compare as numbers
+fn natural_key(name: &str) -> (&str, u64, &str) {
+    let prefix = name.trim_end_matches(|c: char| c.is_ascii_digit());
+    let number = name[prefix.len()..].parse().unwrap_or(0);
+    (prefix, number, name)
+}
+
+/// A problem whose cost function is a parsed expression
+///
+/// See the [module documentation](self) for the syntax.
+#[derive(Clone, Debug)]
+pub struct Expression {
+    source: String,
+    variables: Vec<String>,
+    cost: Expr,
+    gradient: Vec<Expr>,
+    hessian: Vec<Vec<Expr>>,
+}
+
+impl Expression {
+    /// Parse `source` and differentiate it
+    ///
+    /// `variables` fixes the order of the parameters; it may contain
+    /// variables which do not occur in the expression. Identifiers in
+    /// `parameters` are replaced by their values.
+    pub fn parse(
+        source: &str,
+        variables: Option<&[String]>,
+        parameters: &BTreeMap<String, f64>,
+    ) -> Result<Expression, Error> {
+        let invalid = |(text, column): (String, usize)| -> Error {
+            ArgminError::InvalidParameter {
+                text: format!(
+                    "invalid expression `{}`: {} at column {}",
+                    source,
+                    text,
+                    column + 1
+                ),
+            }
+            .into()
+        };
+        let tokens = tokenize(source).map_err(invalid)?;
+        let mut parser = Parser {
+            tokens,
+            pos: 0,
+            end: source.chars().count(),
+            parameters,
+            variables: Vec::new(),
+        };
+        let mut cost = parser.sum().map_err(invalid)?;
+        if parser.peek().is_some() {
+            return Err(invalid(parser.error("an operator")));
+        }
+
+        let names = match variables {
+            Some(names) => {
+                if let Some(unknown) = parser.variables.iter().find(|v| !names.contains(v)) {
+                    return Err(invalid_expression(format!(
+                        "`{}` uses the undeclared variable `{}`",
+                        source, unknown
+                    )));
+                }
+                for (i, name) in names.iter().enumerate() {
+                    if names[..i].contains(name) {
+                        return Err(invalid_expression(format!(
+                            "variable `{}` is declared twice",
+                            name
+                        )));
+                    }
+                    if parameters.contains_key(name) || Func::from_name(name).is_some() {
+                        return Err(invalid_expression(format!(
+                            "`{}` cannot be used as a variable",
+                            name
+                        )));
+                    }
+                }
+                names.to_vec()
+            }
+            None => {
+                let mut names = parser.variables.clone();
+                names.sort_by(|a, b| natural_key(a).cmp(&natural_key(b)));
+                names
+            }
+        };
+        if names.is_empty() {
+            return Err(invalid_expression(format!("`{}` has no variables", source)));
+        }
+        let map: Vec<usize> = parser
+            .variables
+            .iter()
+            .map(|v| names.iter().position(|n| n == v).unwrap_or_default())
+            .collect();
+        cost.renumber(&map);
+
+        let n = names.len();
+        let gradient: Vec<Expr> = (0..n).map(|i| cost.derivative(i)).collect();
+        let hessian = (0..n)
+            .map(|i| (0..n).map(|j| gradient[i].derivative(j)).collect())
+            .collect();
+        Ok(Expression {
+            source: source.to_string(),
+            variables: names,
+            cost,
+            gradient,
+            hessian,
+        })
+    }
+
+    /// The expression as given
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    /// Names of the parameters, in order
+    pub fn variables(&self) -> &[String] {
+        &self.variables
+    }
+
+    /// Syntax tree of the cost function
+    pub fn cost_expr(&self) -> &Expr {
+        &self.cost
+    }
+
+    /// Syntax trees of the partial derivatives
+    pub fn gradient_exprs(&self) -> &[Expr] {
+        &self.gradient
+    }
+
+    /// Type-erased problem called `name`
+    ///
+    /// Starts at the origin; the search domain is `[-5, 5]` in every
+    /// parameter. Nothing is known about the minima.
+    pub fn into_dyn(self, name: &str) -> DynProblem {
+        let dim = self.variables.len();
+        let info = ProblemInfo {
+            name: name.to_string(),
+            dim,
+            default_start: vec![0.0; dim],
+            minima: Vec::new(),
+            min_cost: None,
+            bounds: vec![(-5.0, 5.0); dim],
+            bounded: false,
+        };
+        DynProblem::with_hessian(self.clone(), info).generic(self)
+    }
+
+    fn check_dim<T>(&self, p: &[T]) -> Result<(), Error> {
+        if p.len() != self.variables.len() {
+            return Err(invalid_expression(format!(
+                "`{}` expects {} parameters, got {}",
+                self.source,
+                self.variables.len(),
+                p.len()
+            )));
+        }
+        Ok(())
+    }
+}
+
+fn invalid_expression(text: String) -> Error {
+    ArgminError::InvalidParameter { text }.into()
+}
+
+impl CostFunction for Expression {
+    type Param = Vec<f64>;
+    type Output = f64;
+
+    fn cost(&self, p: &Self::Param) -> Result<Self::Output, Error> {
+        self.cost_generic(p)
+    }
+}
+
+impl Gradient for Expression {
+    type Param = Vec<f64>;
+    type Gradient = Vec<f64>;
+
+    fn gradient(&self, p: &Self::Param) -> Result<Self::Gradient, Error> {
+        self.check_dim(p)?;
+        Ok(self.gradient.iter().map(|e| e.eval(p)).collect())
+    }
+}
+
+impl Hessian for Expression {
+    type Param = Vec<f64>;
+    type Hessian = Vec<Vec<f64>>;
+
+    fn hessian(&self, p: &Self::Param) -> Result<Self::Hessian, Error> {
+        self.check_dim(p)?;
+        Ok(self
+            .hessian
+            .iter()
+            .map(|row| row.iter().map(|e| e.eval(p)).collect())
+            .collect())
+    }
+}
+
+impl GenericCost for Expression {
+    fn cost_generic<T: Scalar>(&self, p: &[T]) -> Result<T, Error> {
+        self.check_dim(p)?;
+        Ok(self.cost.eval(p))
+    }
+}
+
+impl fmt::Display for Expression {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.cost.display(&self.variables))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::finitediff::{FiniteDiff, Scheme};
+
+    fn parse(source: &str) -> Expression {
+        Expression::parse(source, None, &BTreeMap::new()).unwrap()
+    }
+
+    fn error(source: &str) -> String {
+        match Expression::parse(source, None, &BTreeMap::new()) {
+            Ok(_) => panic!("`{}` was accepted", source),
+            Err(err) => err.to_string(),
+        }
+    }
+
+    fn value(source: &str, x: &[f64]) -> f64 {
+        parse(source).cost(&x.to_vec()).unwrap()
+    }
+
+    #[test]
+    fn operators_have_the_usual_precedence() {
+        assert_eq!(value("-x^2", &[3.0]), -9.0);
+        assert_eq!(value("2^3^2 + 0*x", &[1.0]), 512.0);
+        assert_eq!(value("x**2 - 2*x/4", &[3.0]), 7.5);
+        assert_eq!(value("(1 - x)^2 + 100*(y - x^2)^2", &[1.0, 1.0]), 0.0);
+        assert!((value("sin(pi*x) + log(e)", &[0.5]) - 2.0).abs() < 1e-15);
+    }
+
+    #[test]
+    fn variables_are_ordered_by_name() {
+        assert_eq!(parse("x10 + 2*x2 + y").variables(), ["x2", "x10", "y"]);
+        let order = ["y".to_string(), "x".to_string(), "z".to_string()];
+        let expression = Expression::parse("x - y", Some(&order), &BTreeMap::new()).unwrap();
+        assert_eq!(expression.cost(&vec![1.0, 5.0, 0.0]).unwrap(), 4.0);
+    }
+
+    #[test]
+    fn parameters_are_substituted() {
+        let parameters = BTreeMap::from([("a".to_string(), 3.0)]);
+        let expression = Expression::parse("a*x", None, &parameters).unwrap();
+        assert_eq!(expression.variables(), ["x"]);
+        assert_eq!(expression.cost(&vec![2.0]).unwrap(), 6.0);
+    }
+
+    #[test]
+    fn display_round_trips() {
+        let sources = [
+            "(1 - x)^2 + 100*(y - x^2)^2",
+            "-x^2 - (-y)^3",
+            "x/(y*2) - x/y/2",
+            "exp(-x^2 - y^2)*cos(2*pi*x)",
+            "2^x^y + abs(x - y)",
+        ];
+        let point = [0.7, -1.3];
+        for source in sources {
+            let expression = parse(source);
+            let printed = expression.to_string();
+            let reparsed = parse(&printed);
+            assert_eq!(reparsed.variables(), expression.variables(), "{}", printed);
+            assert_eq!(
+                reparsed.cost(&point.to_vec()).unwrap(),
+                expression.cost(&point.to_vec()).unwrap(),
+                "`{}` printed as `{}`",
+                source,
+                printed
+            );
+        }
+    }
+
+    #[test]
+    fn derivatives_match_finite_differences() {
+        let expression = parse("sin(x)*exp(y/2) + sqrt(x^2 + 1)*ln(y) - x^y + tanh(x*y)");
+        let x = [0.8, 1.7];
+        let diff = FiniteDiff::new(Scheme::ComplexStep);
+        let reference = diff.complex_gradient(&expression, &x).unwrap();
+        for (a, r) in expression.gradient(&x.to_vec()).unwrap().iter().zip(&reference) {
+            assert!((a - r).abs() < 1e-12 * r.abs().max(1.0), "{} != {}", a, r);
+        }
+        let hessian = expression.hessian(&x.to_vec()).unwrap();
+        let gradient = |p: &[f64]| expression.gradient(&p.to_vec());
+        let reference = FiniteDiff::new(Scheme::Central)
+            .hessian_from_gradient(gradient, &x)
+            .unwrap();
+        for (row, reference) in hessian.iter().zip(&reference) {
+            for (a, r) in row.iter().zip(reference) {
+                assert!((a - r).abs() < 1e-6 * r.abs().max(1.0), "{} != {}", a, r);
+            }
+        }
+    }
+
+    #[test]
+    fn syntax_errors_name_the_column() {
+        assert!(error("x +").contains("column 4"), "{}", error("x +"));
+        assert!(error("(x").contains("`)`"), "{}", error("(x"));
+        assert!(error("x $ y").contains("unexpected character `$` at column 3"));
+        assert!(error("x y").contains("an operator"));
+        assert!(error("sin(x").contains("`)`"));
+    }
+
+    #[test]
+    fn invalid_variables_are_rejected() {
+        assert!(error("3 + pi").contains("has no variables"));
+        let declared = ["x".to_string()];
+        let undeclared = Expression::parse("x + y", Some(&declared), &BTreeMap::new());
+        assert!(undeclared.unwrap_err().to_string().contains("undeclared variable `y`"));
+        let twice = ["x".to_string(), "x".to_string()];
+        assert!(Expression::parse("x", Some(&twice), &BTreeMap::new()).is_err());
+        let function = ["sin".to_string()];
+        assert!(Expression::parse("2", Some(&function), &BTreeMap::new()).is_err());
+    }
+
+    #[test]
+    fn wrong_dimension_is_rejected() {
+        assert!(parse("x + y").cost(&vec![1.0]).is_err());
+    }
+}
@@ -0,0 +1,838 @@
+//! Optimization problems
+//!
+//! Every problem implements the argmin trait `CostFunction` and, where
+//! available, `Gradient` and `Hessian`, so that it can be handed directly to
+//! an argmin `Executor` or to [`crate::run`]. Problems without a gradient can
+//! only be solved by the derivative free solvers.
+//!
+//! Problems which should be selectable by name are listed in the registry
+//! ([`PROBLEMS`]). The registry hands out [`DynProblem`]s, which bundle a
+//! type-erased problem with its [`ProblemInfo`].
+//!
+//! Problems without derivatives, or with derivatives one does not trust, can
+//! be switched to finite differences with [`DynProblem::numeric`] (or
+//! [`ProblemConfig::numeric`]). Problems registered with
+//! [`DynProblem::generic`] can instead be differentiated automatically with
+//! [`DynProblem::autodiff`] (or [`ProblemConfig::autodiff`]).
+//!
+//! Every problem describes its canonical search domain in
+//! [`ProblemInfo::bounds`]. By default the domain only guides sampling and
+//! plotting; [`DynProblem::with_bounds`] (or [`ProblemConfig::bounded`],
+//! [`ProblemConfig::lower`] and [`ProblemConfig::upper`]) turns it, or a box
+//! of one's own, into box constraints. Only the solvers for which
+//! [`Method::respects_bounds`](crate::solver::Method::respects_bounds) holds
+//! can solve bounded problems.
+
+mod registry;
+pub mod expression;
+pub mod rosenbrock;
+pub mod testfunctions;
+
+pub use expression::Expression;
+pub use registry::{build, find, Dimension, ProblemEntry, DEFAULT_DIM, PROBLEMS};
+pub use rosenbrock::{Rosenbrock, RosenbrockNd, Tridiagonal};
+
+use crate::autodiff::{AdCost, AutoDiff, Mode};
+use crate::constraint::ConstraintSet;
+use crate::finitediff::{ComplexCost, ComplexStep, FiniteDiff, Numeric, Scheme};
+use crate::scalar::{Complex, Dual, GenericCost, Var};
+use argmin::core::{ArgminError, CostFunction, Error, Gradient, Hessian};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+/// Names by which the derivatives of a problem can be selected, see
+/// [`ProblemConfig::with_derivatives`]
+pub const DERIVATIVES: &[&str] = &[
+    "analytic",
+    "forward",
+    "central",
+    "complex-step",
+    "forward-ad",
+    "reverse-ad",
+];
+
+/// Metadata of a problem instance
+#[derive(Clone, Debug)]
+pub struct ProblemInfo {
+    /// Name under which the problem is registered
+    pub name: String,
+    /// Number of parameters
+    pub dim: usize,
+    /// Starting point used if none is given
+    pub default_start: Vec<f64>,
+    /// Known global minimizers (empty if unknown)
+    pub minima: Vec<Vec<f64>>,
+    /// Cost function value at the global minimizers, if known
+    pub min_cost: Option<f64>,
+    /// Canonical search domain as `(lower, upper)` per parameter, or the
+    /// box constraints if `bounded`
+    pub bounds: Vec<(f64, f64)>,
+    /// Whether the parameters are constrained to `bounds`; otherwise
+    /// `bounds` only describe where to look for minima
+    pub bounded: bool,
+}
+
+impl ProblemInfo {
+    /// Box the parameters are confined to: `bounds` if the problem is
+    /// bounded, `None` if it is unconstrained
+    pub fn constraints(&self) -> Option<&[(f64, f64)]> {
+        self.bounded.then_some(self.bounds.as_slice())
+    }
+
+    /// The point of the box constraints closest to `p`; `p` itself if the
+    /// problem is unconstrained
+    pub fn project(&self, p: &[f64]) -> Vec<f64> {
+        match self.constraints() {
+            Some(bounds) => p.iter().zip(bounds).map(|(x, &(l, u))| x.clamp(l, u)).collect(),
+            None => p.to_vec(),
+        }
+    }
+}
+
+/// Description from which a problem instance can be built
+///
+/// Unlike [`DynProblem`], this can be serialized, so it is used to record
+/// which problem a run solved and to rebuild the problem when a run is
+/// resumed.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ProblemConfig {
+    /// Name of the registered problem, or the name given to `expression`
+    pub name: String,
+    /// Number of parameters (see [`ProblemEntry::build`])
+    pub dim: Option<usize>,
+    /// Parameters of the problem family, e.g. `a` and `b` of Rosenbrock
+    #[serde(default)]
+    pub parameters: BTreeMap<String, f64>,
+    /// Replace the derivatives of the problem by finite differences
+    #[serde(default)]
+    pub numeric: Option<FiniteDiff>,
+    /// Replace the derivatives of the problem by automatic differentiation
+    #[serde(default)]
+    pub autodiff: Option<Mode>,
+    /// Cost function given as an expression instead of a registered problem
+    /// (see [`Expression`]); `parameters` are substituted into it
+    #[serde(default)]
+    pub expression: Option<String>,
+    /// Order of the variables of `expression` (by name if `None`)
+    #[serde(default)]
+    pub variables: Option<Vec<String>>,
+    /// Constrain the parameters to the canonical domain of the problem
+    /// (see [`DynProblem::with_bounds`])
+    #[serde(default)]
+    pub bounded: bool,
+    /// Lower bounds of the parameters, a list of a single value for all of
+    /// them or of one value per parameter; implies `bounded`, with the
+    /// canonical domain for the bounds which are not given
+    #[serde(default)]
+    pub lower: Option<Vec<f64>>,
+    /// Upper bounds of the parameters, like `lower`
+    #[serde(default)]
+    pub upper: Option<Vec<f64>>,
+    /// Equality and inequality constraints such as `x1 + x2 = 1` or
+    /// `x1^2 + x2^2 <= 1` (see
+    /// [`Constraint::parse`](crate::constraint::Constraint::parse)); they are not part of
+    /// the problem built by [`ProblemConfig::build`] but of
+    /// [`ProblemConfig::build_constraints`]
+    #[serde(default)]
+    pub constraints: Vec<String>,
+}
+
+impl ProblemConfig {
+    /// Build the described problem
+    ///
+    /// `rosenbrock` and `rosenbrock-nd` accept the parameters `a` and `b`
+    /// (defaulting to 1 and 100); the other problems take no parameters.
+    /// If `expression` is set, it is parsed instead of looking up `name`.
+    /// If `numeric` is set, gradient and Hessian are computed by finite
+    /// differences, see [`DynProblem::numeric`]; if `autodiff` is set, they
+    /// are computed by automatic differentiation, see
+    /// [`DynProblem::autodiff`]. If `bounded`, `lower` or `upper` is set,
+    /// the parameters are constrained to a box, see
+    /// [`DynProblem::with_bounds`].
+    pub fn build(&self) -> Result<DynProblem, Error> {
+        let invalid = |text: String| -> Error { ArgminError::InvalidParameter { text }.into() };
+        if self.parameters.values().any(|v| !v.is_finite()) {
+            return Err(invalid(format!("parameters of {} must be finite", self.name)));
+        }
+        if let Some(source) = &self.expression {
+            let expression =
+                Expression::parse(source, self.variables.as_deref(), &self.parameters)?;
+            let dim = expression.variables().len();
+            if self.dim.is_some_and(|n| n != dim) {
+                return Err(invalid(format!("`{}` has {} variables", source, dim)));
+            }
+            let problem = self.differentiate(expression.into_dyn(&self.name))?;
+            return self.confine(problem);
+        }
+        if self.variables.is_some() {
+            return Err(invalid("variables are only used by expressions".to_string()));
+        }
+        let name = find(&self.name)
+            .ok_or_else(|| invalid(format!("unknown problem `{}`", self.name)))?
+            .name;
+        let known: &[&str] = match name {
+            "rosenbrock" | "rosenbrock-nd" => &["a", "b"],
+            _ => &[],
+        };
+        if let Some(unknown) = self.parameters.keys().find(|k| !known.contains(&k.as_str())) {
+            return Err(invalid(format!("{} has no parameter `{}`", name, unknown)));
+        }
+        let a = self.parameters.get("a").copied().unwrap_or(1.0);
+        let b = self.parameters.get("b").copied().unwrap_or(100.0);
+        let problem = match name {
+            "rosenbrock" => {
+                if self.dim.is_some_and(|n| n != 2) {
+                    return Err(invalid("rosenbrock is only defined for dimension 2".to_string()));
+                }
+                Rosenbrock { a, b }.into_dyn()
+            }
+            "rosenbrock-nd" => {
+                let dim = self.dim.unwrap_or(DEFAULT_DIM);
+                if dim < 2 {
+                    return Err(invalid(
+                        "rosenbrock-nd needs a dimension of at least 2".to_string(),
+                    ));
+                }
+                RosenbrockNd { a, b }.into_dyn(dim)
+            }
+            _ => build(name, self.dim)?,
+        };
+        self.confine(self.differentiate(problem)?)
+    }
+
+    /// Apply `bounded`, `lower` and `upper` to `problem`
+    fn confine(&self, problem: DynProblem) -> Result<DynProblem, Error> {
+        if !self.bounded && self.lower.is_none() && self.upper.is_none() {
+            return Ok(problem);
+        }
+        let dim = problem.info().dim;
+        let mut bounds = problem.info().bounds.clone();
+        for (side, values) in [("lower", &self.lower), ("upper", &self.upper)] {
+            let Some(values) = values else {
+                continue;
+            };
+            if values.len() != 1 && values.len() != dim {
+                return Err(ArgminError::InvalidParameter {
+                    text: format!(
+                        "{} bounds need 1 or {} values, got {}",
+                        side,
+                        dim,
+                        values.len()
+                    ),
+                }
+                .into());
+            }
+            for (k, bound) in bounds.iter_mut().enumerate() {
+                let value = values[k.min(values.len() - 1)];
+                match side {
+                    "lower" => bound.0 = value,
+                    _ => bound.1 = value,
+                }
+            }
+        }
+        problem.with_bounds(bounds)
+    }
+
+    /// Build the `constraints` of `problem`, which has been built from this
+    /// description
+    ///
+    /// The constraints of an expression use its variables and parameters;
+    /// the parameters of registered problems are called `x1`, ..., `xn`.
+    pub fn build_constraints(&self, problem: &DynProblem) -> Result<ConstraintSet, Error> {
+        match &self.expression {
+            Some(source) => {
+                let expression =
+                    Expression::parse(source, self.variables.as_deref(), &self.parameters)?;
+                ConstraintSet::parse(&self.constraints, expression.variables(), &self.parameters)
+            }
+            None => {
+                let variables: Vec<String> =
+                    (1..=problem.info().dim).map(|i| format!("x{}", i)).collect();
+                ConstraintSet::parse(&self.constraints, &variables, &BTreeMap::new())
+            }
+        }
+    }
+
+    /// Select the derivatives by one of the names in [`DERIVATIVES`]
+    ///
+    /// `analytic` keeps the derivatives of the problem, the names of the
+    /// finite-difference [`Scheme`]s set `numeric` with the relative steps
+    /// `step` and `hessian_step`, and `forward-ad` and `reverse-ad` set
+    /// `autodiff`.
+    pub fn with_derivatives(
+        self,
+        name: &str,
+        step: Option<f64>,
+        hessian_step: Option<f64>,
+    ) -> Result<Self, Error> {
+        let invalid = |text: String| -> Error { ArgminError::InvalidParameter { text }.into() };
+        if !DERIVATIVES.contains(&name) {
+            return Err(invalid(format!(
+                "unknown derivatives `{}`, expected one of {}",
+                name,
+                DERIVATIVES.join(", ")
+            )));
+        }
+        let numeric = Scheme::from_name(name).map(|scheme| FiniteDiff {
+            scheme,
+            step,
+            hessian_step,
+        });
+        if numeric.is_none() && (step.is_some() || hessian_step.is_some()) {
+            return Err(invalid(format!(
+                "step sizes require finite-difference derivatives, not `{}`",
+                name
+            )));
+        }
+        let autodiff = name.strip_suffix("-ad").and_then(Mode::from_name);
+        Ok(ProblemConfig {
+            numeric,
+            autodiff,
+            ..self
+        })
+    }
+
+    /// Apply `numeric` or `autodiff` to `problem`
+    fn differentiate(&self, problem: DynProblem) -> Result<DynProblem, Error> {
+        match (&self.numeric, self.autodiff) {
+            (Some(_), Some(_)) => Err(ArgminError::InvalidParameter {
+                text: "finite differences and automatic differentiation are exclusive"
+                    .to_string(),
+            }
+            .into()),
+            (Some(diff), None) => problem.numeric(*diff),
+            (None, Some(mode)) => problem.autodiff(mode),
+            (None, None) => Ok(problem),
+        }
+    }
+}
+
+/// Object safe view of a problem with `Vec<f64>` parameters
+///
+/// The argmin traits cannot be used as trait objects, therefore
+/// [`DynProblem`] stores problems behind this trait instead. The argmin
+/// traits take parameters as `&Vec<f64>`, hence the signatures.
+#[allow(clippy::ptr_arg)]
+trait Objective: Send + Sync {
+    fn cost(&self, p: &Vec<f64>) -> Result<f64, Error>;
+    fn gradient(&self, p: &Vec<f64>) -> Result<Vec<f64>, Error>;
+    fn hessian(&self, p: &Vec<f64>) -> Result<Vec<Vec<f64>>, Error>;
+
+    /// Product of the Hessian with `v`; forms the Hessian unless overridden
+    fn hessian_product(&self, p: &Vec<f64>, v: &[f64]) -> Result<Vec<f64>, Error> {
+        let hessian = self.hessian(p)?;
+        Ok(hessian
+            .iter()
+            .map(|row| row.iter().zip(v).map(|(h, v)| h * v).sum())
+            .collect())
+    }
+
+    /// Whether [`Objective::hessian`] is implemented
+    fn has_hessian(&self) -> bool {
+        true
+    }
+
+    /// Whether [`Objective::hessian_tridiagonal`] is implemented
+    fn is_tridiagonal(&self) -> bool {
+        false
+    }
+
+    /// Hessian of problems whose Hessian is tridiagonal
+    fn hessian_tridiagonal(&self, _p: &Vec<f64>) -> Result<Tridiagonal, Error> {
+        Err(ArgminError::NotImplemented {
+            text: "This problem does not provide a tridiagonal Hessian".to_string(),
+        }
+        .into())
+    }
+}
+
+/// Object safe view of a [`GenericCost`]
+trait GenericObjective: Send + Sync {
+    fn cost_complex(&self, p: &[Complex]) -> Result<Complex, Error>;
+    fn cost_dual(&self, p: &[Dual]) -> Result<Dual, Error>;
+    fn cost_dual2(&self, p: &[Dual<Dual>]) -> Result<Dual<Dual>, Error>;
+    fn cost_var(&self, p: &[Var]) -> Result<Var, Error>;
+    fn cost_var_dual(&self, p: &[Var<Dual>]) -> Result<Var<Dual>, Error>;
+}
+
+impl<O: GenericCost + Send + Sync> GenericObjective for O {
+    fn cost_complex(&self, p: &[Complex]) -> Result<Complex, Error> {
+        self.cost_generic(p)
+    }
+
+    fn cost_dual(&self, p: &[Dual]) -> Result<Dual, Error> {
+        self.cost_generic(p)
+    }
+
+    fn cost_dual2(&self, p: &[Dual<Dual>]) -> Result<Dual<Dual>, Error> {
+        self.cost_generic(p)
+    }
+
+    fn cost_var(&self, p: &[Var]) -> Result<Var, Error> {
+        self.cost_generic(p)
+    }
+
+    fn cost_var_dual(&self, p: &[Var<Dual>]) -> Result<Var<Dual>, Error> {
+        self.cost_generic(p)
+    }
+}
+
+/// Adapter for problems which only provide a cost function
+///
+/// Such problems can only be solved by the derivative free solvers.
+struct CostOnly<O>(O);
+
+impl<O> Objective for CostOnly<O>
+where
+    O: CostFunction<Param = Vec<f64>, Output = f64> + Send + Sync,
+{
+    fn cost(&self, p: &Vec<f64>) -> Result<f64, Error> {
+        self.0.cost(p)
+    }
+
+    fn gradient(&self, _p: &Vec<f64>) -> Result<Vec<f64>, Error> {
+        Err(ArgminError::NotImplemented {
+            text: "This problem does not provide a gradient".to_string(),
+        }
+        .into())
+    }
+
+    fn hessian(&self, _p: &Vec<f64>) -> Result<Vec<Vec<f64>>, Error> {
+        Err(ArgminError::NotImplemented {
+            text: "This problem does not provide a Hessian".to_string(),
+        }
+        .into())
+    }
+
+    fn has_hessian(&self) -> bool {
+        false
+    }
+}
+
+/// Adapter for problems which only provide cost function and gradient
+struct WithGradient<O>(O);
+
+impl<O> Objective for WithGradient<O>
+where
+    O: CostFunction<Param = Vec<f64>, Output = f64>
+        + Gradient<Param = Vec<f64>, Gradient = Vec<f64>>
+        + Send
+        + Sync,
+{
+    fn cost(&self, p: &Vec<f64>) -> Result<f64, Error> {
+        self.0.cost(p)
+    }
+
+    fn gradient(&self, p: &Vec<f64>) -> Result<Vec<f64>, Error> {
+        self.0.gradient(p)
+    }
+
+    fn hessian(&self, _p: &Vec<f64>) -> Result<Vec<Vec<f64>>, Error> {
+        Err(ArgminError::NotImplemented {
+            text: "This problem does not provide a Hessian".to_string(),
+        }
+        .into())
+    }
+
+    fn has_hessian(&self) -> bool {
+        false
+    }
+}
+
+/// Adapter for problems which also provide a Hessian
+struct WithHessian<O>(O);
+
+impl<O> Objective for WithHessian<O>
+where
+    O: CostFunction<Param = Vec<f64>, Output = f64>
+        + Gradient<Param = Vec<f64>, Gradient = Vec<f64>>
+        + Hessian<Param = Vec<f64>, Hessian = Vec<Vec<f64>>>
+        + Send
+        + Sync,
+{
+    fn cost(&self, p: &Vec<f64>) -> Result<f64, Error> {
+        self.0.cost(p)
+    }
+
+    fn gradient(&self, p: &Vec<f64>) -> Result<Vec<f64>, Error> {
+        self.0.gradient(p)
+    }
+
+    fn hessian(&self, p: &Vec<f64>) -> Result<Vec<Vec<f64>>, Error> {
+        self.0.hessian(p)
+    }
+}
+
+/// Adapter for problems with a tridiagonal Hessian
+///
+/// The dense Hessian is only formed on request; Hessian-vector products and
+/// Newton steps use the tridiagonal storage.
+struct WithTridiagonalHessian<O>(O);
+
+impl<O> Objective for WithTridiagonalHessian<O>
+where
+    O: CostFunction<Param = Vec<f64>, Output = f64>
+        + Gradient<Param = Vec<f64>, Gradient = Vec<f64>>
+        + Hessian<Param = Vec<f64>, Hessian = Tridiagonal>
+        + Send
+        + Sync,
+{
+    fn cost(&self, p: &Vec<f64>) -> Result<f64, Error> {
+        self.0.cost(p)
+    }
+
+    fn gradient(&self, p: &Vec<f64>) -> Result<Vec<f64>, Error> {
+        self.0.gradient(p)
+    }
+
+    fn hessian(&self, p: &Vec<f64>) -> Result<Vec<Vec<f64>>, Error> {
+        Ok(self.0.hessian(p)?.to_dense())
+    }
+
+    fn hessian_product(&self, p: &Vec<f64>, v: &[f64]) -> Result<Vec<f64>, Error> {
+        Ok(self.0.hessian(p)?.dot(v))
+    }
+
+    fn is_tridiagonal(&self) -> bool {
+        true
+    }
+
+    fn hessian_tridiagonal(&self, p: &Vec<f64>) -> Result<Tridiagonal, Error> {
+        self.0.hessian(p)
+    }
+}
+
+/// Adapter for automatically differentiated problems
+///
+/// Unlike [`WithHessian`], it computes Hessian-vector products without
+/// forming the Hessian.
+struct WithAutoDiff(AutoDiff<DynProblem>);
+
+impl Objective for WithAutoDiff {
+    fn cost(&self, p: &Vec<f64>) -> Result<f64, Error> {
+        self.0.cost(p)
+    }
+
+    fn gradient(&self, p: &Vec<f64>) -> Result<Vec<f64>, Error> {
+        self.0.gradient(p)
+    }
+
+    fn hessian(&self, p: &Vec<f64>) -> Result<Vec<Vec<f64>>, Error> {
+        self.0.hessian(p)
+    }
+
+    fn hessian_product(&self, p: &Vec<f64>, v: &[f64]) -> Result<Vec<f64>, Error> {
+        self.0.hessian_product(p, v)
+    }
+}
+
+/// A problem selected at runtime together with its metadata
+///
+/// `DynProblem` implements `CostFunction`, `Gradient` and `Hessian` and can
+/// therefore be used with any argmin solver. Parameter vectors of the wrong
+/// length are rejected with an error instead of reaching the wrapped problem.
+/// Problems registered with [`DynProblem::generic`] also implement
+/// [`ComplexCost`] and [`AdCost`].
+#[derive(Clone)]
+pub struct DynProblem {
+    objective: Arc<dyn Objective>,
+    info: Arc<ProblemInfo>,
+    generic: Option<Arc<dyn GenericObjective>>,
+}
+
+impl DynProblem {
+    /// Wrap a problem which only provides a cost function
+    pub fn cost_only<O>(problem: O, info: ProblemInfo) -> Self
+    where
+        O: CostFunction<Param = Vec<f64>, Output = f64> + Send + Sync + 'static,
+    {
+        DynProblem {
+            objective: Arc::new(CostOnly(problem)),
+            info: Arc::new(info),
+            generic: None,
+        }
+    }
+
+    /// Wrap a problem which provides cost function and gradient
+    pub fn new<O>(problem: O, info: ProblemInfo) -> Self
+    where
+        O: CostFunction<Param = Vec<f64>, Output = f64>
+            + Gradient<Param = Vec<f64>, Gradient = Vec<f64>>
+            + Send
+            + Sync
+            + 'static,
+    {
+        DynProblem {
+            objective: Arc::new(WithGradient(problem)),
+            info: Arc::new(info),
+            generic: None,
+        }
+    }
+
+    /// Wrap a problem which additionally provides a Hessian
+    pub fn with_hessian<O>(problem: O, info: ProblemInfo) -> Self
+    where
+        O: CostFunction<Param = Vec<f64>, Output = f64>
+            + Gradient<Param = Vec<f64>, Gradient = Vec<f64>>
+            + Hessian<Param = Vec<f64>, Hessian = Vec<Vec<f64>>>
+            + Send
+            + Sync
+            + 'static,
+    {
+        DynProblem {
+            objective: Arc::new(WithHessian(problem)),
+            info: Arc::new(info),
+            generic: None,
+        }
+    }
+
+    /// Wrap a problem whose Hessian is tridiagonal
+    ///
+    /// Newton's method solves its Newton systems in `O(n)` instead of
+    /// `O(n^3)`, see [`DynProblem::hessian_tridiagonal`].
+    pub fn with_tridiagonal_hessian<O>(problem: O, info: ProblemInfo) -> Self
+    where
+        O: CostFunction<Param = Vec<f64>, Output = f64>
+            + Gradient<Param = Vec<f64>, Gradient = Vec<f64>>
+            + Hessian<Param = Vec<f64>, Hessian = Tridiagonal>
+            + Send
+            + Sync
+            + 'static,
+    {
+        DynProblem {
+            objective: Arc::new(WithTridiagonalHessian(problem)),
+            info: Arc::new(info),
+            generic: None,
+        }
+    }
+
+    /// Make the problem evaluable at other scalar types
+    ///
+    /// `problem` must compute the same cost function as the wrapped problem;
+    /// it is used for complex step and automatic differentiation.
+    pub fn generic<O>(mut self, problem: O) -> Self
+    where
+        O: GenericCost + Send + Sync + 'static,
+    {
+        self.generic = Some(Arc::new(problem));
+        self
+    }
+
+    /// Whether the problem can be evaluated at complex arguments, dual
+    /// numbers and tape variables
+    pub fn is_generic(&self) -> bool {
+        self.generic.is_some()
+    }
+
+    /// The same problem with gradient and Hessian computed by `diff`
+    ///
+    /// The cost function is unchanged. Complex steps require a problem
+    /// registered with [`DynProblem::generic`].
+    pub fn numeric(&self, diff: FiniteDiff) -> Result<DynProblem, Error> {
+        diff.validate()?;
+        let objective: Arc<dyn Objective> = match diff.scheme {
+            Scheme::ComplexStep => {
+                if !self.is_generic() {
+                    return Err(ArgminError::InvalidParameter {
+                        text: format!(
+                            "{} cannot be evaluated at complex arguments",
+                            self.info.name
+                        ),
+                    }
+                    .into());
+                }
+                Arc::new(WithHessian(ComplexStep::new(self.clone(), diff)))
+            }
+            Scheme::Forward | Scheme::Central => {
+                Arc::new(WithHessian(Numeric::new(self.clone(), diff)))
+            }
+        };
+        Ok(DynProblem {
+            objective,
+            info: self.info.clone(),
+            generic: self.generic.clone(),
+        })
+    }
+
+    /// The same problem with gradient and Hessian computed by automatic
+    /// differentiation in `mode`
+    ///
+    /// The cost function is unchanged. Requires a problem registered with
+    /// [`DynProblem::generic`].
+    pub fn autodiff(&self, mode: Mode) -> Result<DynProblem, Error> {
+        if !self.is_generic() {
+            return Err(ArgminError::InvalidParameter {
+                text: format!(
+                    "{} cannot be differentiated automatically",
+                    self.info.name
+                ),
+            }
+            .into());
+        }
+        Ok(DynProblem {
+            objective: Arc::new(WithAutoDiff(AutoDiff::new(self.clone(), mode))),
+            info: self.info.clone(),
+            generic: self.generic.clone(),
+        })
+    }
+
+    /// Product of the Hessian at `p` with `v`
+    ///
+    /// Automatically differentiated problems compute it without forming the
+    /// Hessian; the others multiply their Hessian with `v`.
+    pub fn hessian_product(&self, p: &Vec<f64>, v: &[f64]) -> Result<Vec<f64>, Error> {
+        self.check_dim(p)?;
+        self.check_dim(v)?;
+        self.objective.hessian_product(p, v)
+    }
+
+    /// Whether the problem provides a Hessian (and Hessian-vector products)
+    pub fn has_hessian(&self) -> bool {
+        self.objective.has_hessian()
+    }
+
+    /// Whether the Hessian is available in tridiagonal storage
+    ///
+    /// This only holds for problems wrapped by
+    /// [`DynProblem::with_tridiagonal_hessian`] and not for their finite
+    /// difference or automatically differentiated versions.
+    pub fn has_tridiagonal_hessian(&self) -> bool {
+        self.objective.is_tridiagonal()
+    }
+
+    /// Hessian at `p` in tridiagonal storage, see
+    /// [`DynProblem::has_tridiagonal_hessian`]
+    pub fn hessian_tridiagonal(&self, p: &Vec<f64>) -> Result<Tridiagonal, Error> {
+        self.check_dim(p)?;
+        self.objective.hessian_tridiagonal(p)
+    }
+
+    /// The same problem with its parameters constrained to `bounds`, one
+    /// `(lower, upper)` pair per parameter
+    ///
+    /// The bounds must be finite, as they also serve as the search domain
+    /// (e.g. of particle swarm optimization and multi-starts). The cost
+    /// function and its derivatives are unchanged: it is up to the solver to
+    /// respect the bounds.
+    pub fn with_bounds(&self, bounds: Vec<(f64, f64)>) -> Result<DynProblem, Error> {
+        let invalid = |text: String| -> Error { ArgminError::InvalidParameter { text }.into() };
+        let info = &self.info;
+        if bounds.len() != info.dim {
+            return Err(invalid(format!(
+                "{} expects bounds for {} parameters, got {}",
+                info.name,
+                info.dim,
+                bounds.len()
+            )));
+        }
+        if bounds.iter().any(|(l, u)| !l.is_finite() || !u.is_finite()) {
+            return Err(invalid("bounds must be finite".to_string()));
+        }
+        if let Some(k) = bounds.iter().position(|(l, u)| l > u) {
+            return Err(invalid(format!(
+                "lower bound {} of parameter {} exceeds its upper bound {}",
+                bounds[k].0, k, bounds[k].1
+            )));
+        }
+        Ok(DynProblem {
+            objective: self.objective.clone(),
+            info: Arc::new(ProblemInfo {
+                bounds,
+                bounded: true,
+                ..ProblemInfo::clone(info)
+            }),
+            generic: self.generic.clone(),
+        })
+    }
+
+    /// Metadata of the wrapped problem
+    pub fn info(&self) -> &ProblemInfo {
+        &self.info
+    }
+
+    /// The generic cost function, after checking the dimension of `p`
+    fn generic_objective<T>(&self, p: &[T]) -> Result<&dyn GenericObjective, Error> {
+        self.check_dim(p)?;
+        match &self.generic {
+            Some(generic) => Ok(generic.as_ref()),
+            None => Err(ArgminError::NotImplemented {
+                text: format!("{} is not generic over the scalar type", self.info.name),
+            }
+            .into()),
+        }
+    }
+
+    fn check_dim<T>(&self, p: &[T]) -> Result<(), Error> {
+        if p.len() != self.info.dim {
+            return Err(ArgminError::InvalidParameter {
+                text: format!(
+                    "{} expects {} parameters, got {}",
+                    self.info.name,
+                    self.info.dim,
+                    p.len()
+                ),
+            }
+            .into());
+        }
+        Ok(())
+    }
+}
+
+impl CostFunction for DynProblem {
+    type Param = Vec<f64>;
+    type Output = f64;
+
+    fn cost(&self, p: &Self::Param) -> Result<Self::Output, Error> {
+        self.check_dim(p)?;
+        self.objective.cost(p)
+    }
+}
+
+impl Gradient for DynProblem {
+    type Param = Vec<f64>;
+    type Gradient = Vec<f64>;
+
+    fn gradient(&self, p: &Self::Param) -> Result<Self::Gradient, Error> {
+        self.check_dim(p)?;
+        self.objective.gradient(p)
+    }
+}
+
+impl Hessian for DynProblem {
+    type Param = Vec<f64>;
+    type Hessian = Vec<Vec<f64>>;
+
+    fn hessian(&self, p: &Self::Param) -> Result<Self::Hessian, Error> {
+        self.check_dim(p)?;
+        self.objective.hessian(p)
+    }
+}
+
+impl ComplexCost for DynProblem {
+    fn cost_complex(&self, p: &[Complex]) -> Result<Complex, Error> {
+        self.generic_objective(p)?.cost_complex(p)
+    }
+}
+
+impl AdCost for DynProblem {
+    fn cost_f64(&self, p: &[f64]) -> Result<f64, Error> {
+        self.cost(&p.to_vec())
+    }
+
+    fn cost_dual(&self, p: &[Dual]) -> Result<Dual, Error> {
+        self.generic_objective(p)?.cost_dual(p)
+    }
+
+    fn cost_dual2(&self, p: &[Dual<Dual>]) -> Result<Dual<Dual>, Error> {
+        self.generic_objective(p)?.cost_dual2(p)
+    }
+
+    fn cost_var(&self, p: &[Var]) -> Result<Var, Error> {
+        self.generic_objective(p)?.cost_var(p)
+    }
+
+    fn cost_var_dual(&self, p: &[Var<Dual>]) -> Result<Var<Dual>, Error> {
+        self.generic_objective(p)?.cost_var_dual(p)
+    }
+}
@@ -0,0 +1,463 @@
+//! Registry of the problems which can be selected by name
+
+use super::testfunctions::{
+    Ackley, Beale, Booth, Easom, GoldsteinPrice, Himmelblau, Levi, Matyas, Picheny, Rastrigin,
+    Sphere, StyblinskiTang, ThreeHumpCamel,
+};
+use super::{DynProblem, ProblemInfo, Rosenbrock, RosenbrockNd};
+use argmin::core::{ArgminError, Error};
+use std::f64::consts::PI;
+
+/// Dimension used for problems without a fixed dimension if none is given
+pub const DEFAULT_DIM: usize = 2;
+
+/// Dimensions for which a registered problem is defined
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Dimension {
+    /// The problem is only defined for this dimension
+    Fixed(usize),
+    /// The problem can be built for any dimension of at least `min`
+    Any { min: usize },
+}
+
+impl std::fmt::Display for Dimension {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Dimension::Fixed(n) => write!(f, "{}", n),
+            Dimension::Any { min } => write!(f, "n>={}", min),
+        }
+    }
+}
+
+/// A problem which can be built by name
+pub struct ProblemEntry {
+    /// Name used to select the problem
+    pub name: &'static str,
+    /// One line description
+    pub description: &'static str,
+    /// Dimensions for which the problem is defined
+    pub dim: Dimension,
+    build: fn(usize) -> DynProblem,
+}
+
+impl ProblemEntry {
+    /// Build an instance of the problem
+    ///
+    /// `dim` may only differ from the fixed dimension of the problem if the
+    /// problem can be built for any dimension. If `dim` is `None`, the fixed
+    /// dimension or [`DEFAULT_DIM`] is used.
+    pub fn build(&self, dim: Option<usize>) -> Result<DynProblem, Error> {
+        let dim = match (self.dim, dim) {
+            (Dimension::Fixed(fixed), Some(n)) if fixed != n => {
+                return Err(ArgminError::InvalidParameter {
+                    text: format!("{} is only defined for dimension {}", self.name, fixed),
+                }
+                .into())
+            }
+            (Dimension::Fixed(fixed), _) => fixed,
+            (Dimension::Any { min }, Some(n)) if n < min => {
+                return Err(ArgminError::InvalidParameter {
+                    text: format!("{} needs a dimension of at least {}", self.name, min),
+                }
+                .into())
+            }
+            (Dimension::Any { min }, n) => n.unwrap_or(DEFAULT_DIM.max(min)),
+        };
+        Ok((self.build)(dim))
+    }
+}
+
+/// All registered problems
+pub static PROBLEMS: &[ProblemEntry] = &[
+    ProblemEntry {
+        name: "rosenbrock",
+        description: "2D Rosenbrock function (a - x)^2 + b (y - x^2)^2",
+        dim: Dimension::Fixed(2),
+        build: rosenbrock,
+    },
+    ProblemEntry {
+        name: "rosenbrock-nd",
+        description: "Generalized Rosenbrock function in n dimensions",
+        dim: Dimension::Any { min: 2 },
+        build: rosenbrock_nd,
+    },
+    ProblemEntry {
+        name: "ackley",
+        description: "Ackley function, many local minima around a central funnel",
+        dim: Dimension::Any { min: 1 },
+        build: ackley,
+    },
+    ProblemEntry {
+        name: "beale",
+        description: "Beale function, sharp peaks at the corners of the domain",
+        dim: Dimension::Fixed(2),
+        build: beale,
+    },
+    ProblemEntry {
+        name: "booth",
+        description: "Booth function, a convex quadratic",
+        dim: Dimension::Fixed(2),
+        build: booth,
+    },
+    ProblemEntry {
+        name: "easom",
+        description: "Easom function, flat except for a small basin around (pi, pi)",
+        dim: Dimension::Fixed(2),
+        build: easom,
+    },
+    ProblemEntry {
+        name: "goldstein-price",
+        description: "Goldstein-Price function",
+        dim: Dimension::Fixed(2),
+        build: goldstein_price,
+    },
+    ProblemEntry {
+        name: "himmelblau",
+        description: "Himmelblau function, four identical global minima",
+        dim: Dimension::Fixed(2),
+        build: himmelblau,
+    },
+    ProblemEntry {
+        name: "levi",
+        description: "Levi function N.13",
+        dim: Dimension::Fixed(2),
+        build: levi,
+    },
+    ProblemEntry {
+        name: "matyas",
+        description: "Matyas function, a flat convex quadratic",
+        dim: Dimension::Fixed(2),
+        build: matyas,
+    },
+    ProblemEntry {
+        name: "picheny",
+        description: "Goldstein-Price function rescaled to [0, 1]^2 by Picheny et al.",
+        dim: Dimension::Fixed(2),
+        build: picheny,
+    },
+    ProblemEntry {
+        name: "rastrigin",
+        description: "Rastrigin function, regularly distributed local minima",
+        dim: Dimension::Any { min: 1 },
+        build: rastrigin,
+    },
+    ProblemEntry {
+        name: "sphere",
+        description: "Sphere function, sum of squares",
+        dim: Dimension::Any { min: 1 },
+        build: sphere,
+    },
+    ProblemEntry {
+        name: "styblinski-tang",
+        description: "Styblinski-Tang function",
+        dim: Dimension::Any { min: 1 },
+        build: styblinski_tang,
+    },
+    ProblemEntry {
+        name: "three-hump-camel",
+        description: "Three-hump camel function",
+        dim: Dimension::Fixed(2),
+        build: three_hump_camel,
+    },
+];
+
+/// Look up a registered problem by name
+///
+/// Case and the use of `-` or `_` as separator are ignored.
+pub fn find(name: &str) -> Option<&'static ProblemEntry> {
+    let name = name.to_ascii_lowercase().replace('_', "-");
+    PROBLEMS.iter().find(|entry| entry.name == name)
+}
+
+/// Build the registered problem `name`, see [`ProblemEntry::build`]
+pub fn build(name: &str, dim: Option<usize>) -> Result<DynProblem, Error> {
+    match find(name) {
+        Some(entry) => entry.build(dim),
+        None => Err(ArgminError::InvalidParameter {
+            text: format!("unknown problem `{}`", name),
+        }
+        .into()),
+    }
+}
+
+fn rosenbrock(_dim: usize) -> DynProblem {
+    Rosenbrock::default().into_dyn()
+}
+
+fn rosenbrock_nd(dim: usize) -> DynProblem {
+    RosenbrockNd::default().into_dyn(dim)
+}
+
+fn ackley(dim: usize) -> DynProblem {
+    let info = ProblemInfo {
+        name: "ackley".to_string(),
+        dim,
+        default_start: vec![1.5; dim],
+        minima: vec![vec![0.0; dim]],
+        min_cost: Some(0.0),
+        bounds: vec![(-32.768, 32.768); dim],
+        bounded: false,
+    };
+    DynProblem::new(Ackley, info).generic(Ackley)
+}
+
+fn beale(dim: usize) -> DynProblem {
+    let info = ProblemInfo {
+        name: "beale".to_string(),
+        dim,
+        default_start: vec![1.0, 1.0],
+        minima: vec![vec![3.0, 0.5]],
+        min_cost: Some(0.0),
+        bounds: vec![(-4.5, 4.5); dim],
+        bounded: false,
+    };
+    DynProblem::new(Beale, info).generic(Beale)
+}
+
+fn booth(dim: usize) -> DynProblem {
+    let info = ProblemInfo {
+        name: "booth".to_string(),
+        dim,
+        default_start: vec![0.0, 0.0],
+        minima: vec![vec![1.0, 3.0]],
+        min_cost: Some(0.0),
+        bounds: vec![(-10.0, 10.0); dim],
+        bounded: false,
+    };
+    DynProblem::with_hessian(Booth, info).generic(Booth)
+}
+
+fn easom(dim: usize) -> DynProblem {
+    let info = ProblemInfo {
+        name: "easom".to_string(),
+        dim,
+        default_start: vec![2.0, 2.0],
+        minima: vec![vec![PI, PI]],
+        min_cost: Some(-1.0),
+        bounds: vec![(-100.0, 100.0); dim],
+        bounded: false,
+    };
+    DynProblem::new(Easom, info).generic(Easom)
+}
+
+fn goldstein_price(dim: usize) -> DynProblem {
+    let info = ProblemInfo {
+        name: "goldstein-price".to_string(),
+        dim,
+        default_start: vec![0.5, 0.5],
+        minima: vec![vec![0.0, -1.0]],
+        min_cost: Some(3.0),
+        bounds: vec![(-2.0, 2.0); dim],
+        bounded: false,
+    };
+    DynProblem::new(GoldsteinPrice, info).generic(GoldsteinPrice)
+}
+
+fn himmelblau(dim: usize) -> DynProblem {
+    let info = ProblemInfo {
+        name: "himmelblau".to_string(),
+        dim,
+        default_start: vec![0.0, 0.0],
+        minima: vec![
+            vec![3.0, 2.0],
+            vec![-2.805118, 3.131312],
+            vec![-3.779310, -3.283186],
+            vec![3.584428, -1.848126],
+        ],
+        min_cost: Some(0.0),
+        bounds: vec![(-5.0, 5.0); dim],
+        bounded: false,
+    };
+    DynProblem::new(Himmelblau, info).generic(Himmelblau)
+}
+
+fn levi(dim: usize) -> DynProblem {
+    let info = ProblemInfo {
+        name: "levi".to_string(),
+        dim,
+        default_start: vec![-2.0, 2.0],
+        minima: vec![vec![1.0, 1.0]],
+        min_cost: Some(0.0),
+        bounds: vec![(-10.0, 10.0); dim],
+        bounded: false,
+    };
+    DynProblem::new(Levi, info).generic(Levi)
+}
+
+fn matyas(dim: usize) -> DynProblem {
+    let info = ProblemInfo {
+        name: "matyas".to_string(),
+        dim,
+        default_start: vec![5.0, -3.0],
+        minima: vec![vec![0.0, 0.0]],
+        min_cost: Some(0.0),
+        bounds: vec![(-10.0, 10.0); dim],
+        bounded: false,
+    };
+    DynProblem::with_hessian(Matyas, info).generic(Matyas)
+}
+
+fn picheny(dim: usize) -> DynProblem {
+    let info = ProblemInfo {
+        name: "picheny".to_string(),
+        dim,
+        default_start: vec![0.1, 0.9],
+        minima: vec![vec![0.5, 0.25]],
+        min_cost: Some(-3.385199318203682),
+        bounds: vec![(0.0, 1.0); dim],
+        bounded: false,
+    };
+    DynProblem::new(Picheny, info).generic(Picheny)
+}
+
+fn rastrigin(dim: usize) -> DynProblem {
+    let info = ProblemInfo {
+        name: "rastrigin".to_string(),
+        dim,
+        default_start: vec![2.5; dim],
+        minima: vec![vec![0.0; dim]],
+        min_cost: Some(0.0),
+        bounds: vec![(-5.12, 5.12); dim],
+        bounded: false,
+    };
+    DynProblem::new(Rastrigin, info).generic(Rastrigin)
+}
+
+fn sphere(dim: usize) -> DynProblem {
+    let info = ProblemInfo {
+        name: "sphere".to_string(),
+        dim,
+        default_start: vec![1.0; dim],
+        minima: vec![vec![0.0; dim]],
+        min_cost: Some(0.0),
+        bounds: vec![(-5.12, 5.12); dim],
+        bounded: false,
+    };
+    DynProblem::with_hessian(Sphere, info).generic(Sphere)
+}
+
+fn styblinski_tang(dim: usize) -> DynProblem {
+    let info = ProblemInfo {
+        name: "styblinski-tang".to_string(),
+        dim,
+        default_start: vec![0.0; dim],
+        minima: vec![vec![StyblinskiTang::MINIMIZER; dim]],
+        min_cost: Some(StyblinskiTang::MIN_COST_PER_DIM * dim as f64),
+        bounds: vec![(-5.0, 5.0); dim],
+        bounded: false,
+    };
+    DynProblem::new(StyblinskiTang, info).generic(StyblinskiTang)
+}
+
+fn three_hump_camel(dim: usize) -> DynProblem {
+    let info = ProblemInfo {
+        name: "three-hump-camel".to_string(),
+        dim,
+        default_start: vec![2.0, -1.0],
+        minima: vec![vec![0.0, 0.0]],
+        min_cost: Some(0.0),
+        bounds: vec![(-5.0, 5.0); dim],
+        bounded: false,
+    };
+    DynProblem::new(ThreeHumpCamel, info).generic(ThreeHumpCamel)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::finitediff::{FiniteDiff, Scheme};
+    use argmin::core::{CostFunction, Gradient, Hessian};
+
+    /// Every registered problem, with 3 parameters if the dimension is free
+    fn problems() -> Vec<DynProblem> {
+        PROBLEMS
+            .iter()
+            .map(|entry| match entry.dim {
+                Dimension::Fixed(_) => entry.build(None).unwrap(),
+                Dimension::Any { .. } => entry.build(Some(3)).unwrap(),
+            })
+            .collect()
+    }
+
+    /// The default start and a few points spread over the domain
+    fn points(info: &ProblemInfo) -> Vec<Vec<f64>> {
+        let mut points = vec![info.default_start.clone()];
+        for t in [0.23, 0.61, 0.87] {
+            let point = info
+                .bounds
+                .iter()
+                .enumerate()
+                .map(|(i, (lo, hi))| lo + (hi - lo) * ((t + 0.17 * i as f64) % 1.0))
+                .collect();
+            points.push(point);
+        }
+        points
+    }
+
+    fn assert_close(name: &str, analytic: f64, numeric: f64, tol: f64) {
+        assert!(
+            (analytic - numeric).abs() <= tol * numeric.abs().max(1.0),
+            "{}: analytic {} != numeric {}",
+            name,
+            analytic,
+            numeric
+        );
+    }
+
+    #[test]
+    fn gradients_match_finite_differences() {
+        let diff = FiniteDiff::new(Scheme::Central);
+        for problem in problems() {
+            let info = problem.info();
+            for p in points(info) {
+                let analytic = problem.gradient(&p).unwrap();
+                let numeric = diff.gradient(&problem, &p).unwrap();
+                for (a, n) in analytic.iter().zip(numeric) {
+                    assert_close(&info.name, *a, n, 1e-5);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn hessians_match_finite_differences() {
+        let diff = FiniteDiff::new(Scheme::Central);
+        for problem in problems() {
+            let info = problem.info();
+            for p in points(info) {
+                // Most problems have no analytic Hessian
+                let Ok(analytic) = problem.hessian(&p) else {
+                    continue;
+                };
+                let numeric = diff.hessian(&problem, &p).unwrap();
+                for (row, numeric) in analytic.iter().zip(numeric) {
+                    for (a, n) in row.iter().zip(numeric) {
+                        assert_close(&info.name, *a, n, 1e-4);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn minima_have_the_registered_cost() {
+        for problem in problems() {
+            let info = problem.info();
+            let Some(min_cost) = info.min_cost else {
+                continue;
+            };
+            for minimum in &info.minima {
+                assert_close(&info.name, problem.cost(minimum).unwrap(), min_cost, 1e-9);
+            }
+        }
+    }
+
+    #[test]
+    fn dimensions_are_checked() {
+        let rosenbrock = find("rosenbrock").unwrap();
+        assert!(rosenbrock.build(Some(3)).is_err());
+        let sphere = find("sphere").unwrap();
+        assert_eq!(sphere.build(Some(5)).unwrap().info().dim, 5);
+        assert!(sphere.build(Some(0)).is_err());
+    }
+}
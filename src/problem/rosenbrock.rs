@@ -0,0 +1,352 @@
+//! The Rosenbrock function
+//!
+//! [`Rosenbrock`] is the classic 2D function, [`RosenbrockNd`] the
+//! generalization to any dimension `n >= 2`:
+//!
+//! `f(x) = sum_{i=0}^{n-2} (a - x_i)^2 + b (x_{i+1} - x_i^2)^2`
+
+use super::{DynProblem, ProblemInfo};
+use crate::scalar::{GenericCost, Scalar};
+use argmin::core::{ArgminError, CostFunction, Error, Gradient, Hessian};
+use argmin_testfunctions::{
+    rosenbrock, rosenbrock_2d, rosenbrock_2d_derivative, rosenbrock_2d_hessian,
+};
+use serde::{Deserialize, Serialize};
+
+/// First, we create a struct called `Rosenbrock` for your problem
+#[derive(Clone, Copy, Debug)]
+pub struct Rosenbrock {
+    /// Parameter `a` of `(a - x)^2 + b * (y - x^2)^2`
+    pub a: f64,
+    /// Parameter `b` of `(a - x)^2 + b * (y - x^2)^2`
+    pub b: f64,
+}
+
+impl Default for Rosenbrock {
+    /// The classic parametrization with `a = 1` and `b = 100`
+    fn default() -> Self {
+        Rosenbrock { a: 1.0, b: 100.0 }
+    }
+}
+
+impl Rosenbrock {
+    /// Metadata of this instance; the global minimum is at `(a, a^2)`
+    pub fn info(&self) -> ProblemInfo {
+        ProblemInfo {
+            name: "rosenbrock".to_string(),
+            dim: 2,
+            default_start: vec![1.0, -2.0],
+            minima: vec![vec![self.a, self.a * self.a]],
+            min_cost: Some(0.0),
+            bounds: vec![(-5.0, 10.0); 2],
+            bounded: false,
+        }
+    }
+
+    /// Wrap this instance for use with the problem registry
+    pub fn into_dyn(self) -> DynProblem {
+        let info = self.info();
+        DynProblem::with_hessian(self, info).generic(self)
+    }
+}
+
+/// Implement `CostFunction` for `Rosenbrock`
+///
+/// First, we need to define the types which we will be using. Our parameter
+/// vector will be a `Vec` of `f64` values and our cost function value will
+/// be a 64 bit floating point value.
+/// This is reflected in the associated types `Param` and `Output`, respectively.
+///
+/// The method `cost` then defines how the cost function is computed for a
+/// parameter vector `p`. Note that we have access to the fields `a` and `b`
+/// of `Rosenbrock`.
+impl CostFunction for Rosenbrock {
+    /// Type of the parameter vector
+    type Param = Vec<f64>;
+    /// Type of the return value computed by the cost function
+    type Output = f64;
+
+    /// Apply the cost function to a parameter `p`
+    fn cost(&self, p: &Self::Param) -> Result<Self::Output, Error> {
+        // Evaluate 2D Rosenbrock function
+        Ok(rosenbrock_2d(p, self.a, self.b))
+    }
+}
+
+/// Implement `Gradient` for `Rosenbrock`
+///
+/// Similarly to `CostFunction`, we need to define the type of our parameter
+/// vectors and of the gradient we are computing. Since the gradient is also
+/// a vector, it is of type `Vec<f64>` just like `Param`.
+impl Gradient for Rosenbrock {
+    /// Type of the parameter vector
+    type Param = Vec<f64>;
+    /// Type of the gradient
+    type Gradient = Vec<f64>;
+
+    /// Compute the gradient at parameter `p`.
+    fn gradient(&self, p: &Self::Param) -> Result<Self::Gradient, Error> {
+        // Compute gradient of 2D Rosenbrock function
+        Ok(rosenbrock_2d_derivative(p, self.a, self.b))
+    }
+}
+
+/// Implement `Hessian` for `Rosenbrock`
+///
+/// Again the types of the involved parameter vector and the Hessian needs to
+/// be defined. Since the Hessian is a 2D matrix, we use `Vec<Vec<f64>>` here.
+impl Hessian for Rosenbrock {
+    /// Type of the parameter vector
+    type Param = Vec<f64>;
+    /// Type of the Hessian
+    type Hessian = Vec<Vec<f64>>;
+
+    /// Compute the Hessian at parameter `p`.
+    fn hessian(&self, p: &Self::Param) -> Result<Self::Hessian, Error> {
+        // Compute Hessian of 2D Rosenbrock function
+        let t = rosenbrock_2d_hessian(p, self.a, self.b);
+        // Reshape the output
+        Ok(vec![vec![t[0], t[1]], vec![t[2], t[3]]])
+    }
+}
+
+/// The cost function of `argmin_testfunctions` is generic over the scalar
+/// type, so `Rosenbrock` can also be evaluated at complex arguments
+impl GenericCost for Rosenbrock {
+    fn cost_generic<T: Scalar>(&self, p: &[T]) -> Result<T, Error> {
+        Ok(rosenbrock_2d(p, T::constant(self.a), T::constant(self.b)))
+    }
+}
+
+/// Rosenbrock function in `n >= 2` dimensions
+///
+/// The dimension is taken from the length of the parameter vector. Gradient
+/// and Hessian are computed analytically in `O(n)`. Since the Hessian is
+/// tridiagonal, it is provided as a [`Tridiagonal`] instead of a dense
+/// `n x n` matrix, so that Newton's method solves the Newton system in
+/// `O(n)` as well, which is what large scaling studies need.
+#[derive(Clone, Copy, Debug)]
+pub struct RosenbrockNd {
+    /// Parameter `a`
+    pub a: f64,
+    /// Parameter `b`
+    pub b: f64,
+}
+
+impl Default for RosenbrockNd {
+    /// The classic parametrization with `a = 1` and `b = 100`
+    fn default() -> Self {
+        RosenbrockNd { a: 1.0, b: 100.0 }
+    }
+}
+
+impl RosenbrockNd {
+    /// Metadata of the `dim` dimensional instance
+    ///
+    /// The global minimum is only known for `a = 1`, where it is located at
+    /// `(1, ..., 1)`. The default start is the usual `(-1.2, 1, -1.2, 1, ...)`.
+    pub fn info(&self, dim: usize) -> ProblemInfo {
+        let known = self.a == 1.0;
+        ProblemInfo {
+            name: "rosenbrock-nd".to_string(),
+            dim,
+            default_start: (0..dim).map(|i| if i % 2 == 0 { -1.2 } else { 1.0 }).collect(),
+            minima: if known { vec![vec![1.0; dim]] } else { vec![] },
+            min_cost: if known { Some(0.0) } else { None },
+            bounds: vec![(-5.0, 10.0); dim],
+            bounded: false,
+        }
+    }
+
+    /// Wrap the `dim` dimensional instance for use with the problem registry
+    pub fn into_dyn(self, dim: usize) -> DynProblem {
+        let info = self.info(dim);
+        DynProblem::with_tridiagonal_hessian(self, info).generic(self)
+    }
+}
+
+fn check_dim<T>(p: &[T]) -> Result<(), Error> {
+    if p.len() < 2 {
+        return Err(ArgminError::InvalidParameter {
+            text: "Rosenbrock function needs at least 2 parameters".to_string(),
+        }
+        .into());
+    }
+    Ok(())
+}
+
+impl CostFunction for RosenbrockNd {
+    type Param = Vec<f64>;
+    type Output = f64;
+
+    fn cost(&self, p: &Self::Param) -> Result<Self::Output, Error> {
+        check_dim(p)?;
+        Ok(rosenbrock(p, self.a, self.b))
+    }
+}
+
+impl Gradient for RosenbrockNd {
+    type Param = Vec<f64>;
+    type Gradient = Vec<f64>;
+
+    fn gradient(&self, p: &Self::Param) -> Result<Self::Gradient, Error> {
+        check_dim(p)?;
+        let n = p.len();
+        let mut g = vec![0.0; n];
+        for i in 0..n {
+            if i + 1 < n {
+                g[i] += -2.0 * (self.a - p[i]) - 4.0 * self.b * p[i] * (p[i + 1] - p[i] * p[i]);
+            }
+            if i > 0 {
+                g[i] += 2.0 * self.b * (p[i] - p[i - 1] * p[i - 1]);
+            }
+        }
+        Ok(g)
+    }
+}
+
+impl Hessian for RosenbrockNd {
+    type Param = Vec<f64>;
+    type Hessian = Tridiagonal;
+
+    fn hessian(&self, p: &Self::Param) -> Result<Self::Hessian, Error> {
+        check_dim(p)?;
+        let n = p.len();
+        let mut diag = vec![0.0; n];
+        let mut off = vec![0.0; n - 1];
+        for i in 0..n {
+            if i + 1 < n {
+                diag[i] += 2.0 - 4.0 * self.b * (p[i + 1] - 3.0 * p[i] * p[i]);
+                off[i] = -4.0 * self.b * p[i];
+            }
+            if i > 0 {
+                diag[i] += 2.0 * self.b;
+            }
+        }
+        Ok(Tridiagonal { diag, off })
+    }
+}
+
+impl GenericCost for RosenbrockNd {
+    fn cost_generic<T: Scalar>(&self, p: &[T]) -> Result<T, Error> {
+        check_dim(p)?;
+        Ok(rosenbrock(p, T::constant(self.a), T::constant(self.b)))
+    }
+}
+
+/// Symmetric tridiagonal matrix
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Tridiagonal {
+    /// Main diagonal, length `n`
+    pub diag: Vec<f64>,
+    /// First sub- and superdiagonal, length `n - 1`
+    pub off: Vec<f64>,
+}
+
+impl Tridiagonal {
+    /// Number of rows and columns
+    pub fn dim(&self) -> usize {
+        self.diag.len()
+    }
+
+    /// Expand into a dense `n x n` matrix
+    pub fn to_dense(&self) -> Vec<Vec<f64>> {
+        let n = self.dim();
+        let mut m = vec![vec![0.0; n]; n];
+        for i in 0..n {
+            m[i][i] = self.diag[i];
+            if i + 1 < n {
+                m[i][i + 1] = self.off[i];
+                m[i + 1][i] = self.off[i];
+            }
+        }
+        m
+    }
+
+    /// Compute the matrix-vector product `A v`
+    pub fn dot(&self, v: &[f64]) -> Vec<f64> {
+        let n = self.dim();
+        (0..n)
+            .map(|i| {
+                let mut s = self.diag[i] * v[i];
+                if i > 0 {
+                    s += self.off[i - 1] * v[i - 1];
+                }
+                if i + 1 < n {
+                    s += self.off[i] * v[i + 1];
+                }
+                s
+            })
+            .collect()
+    }
+
+    /// Solve `A x = rhs` with the Thomas algorithm
+    ///
+    /// No pivoting is performed, so this fails if a zero pivot is
+    /// encountered, for instance when `A` is singular.
+    pub fn solve(&self, rhs: &[f64]) -> Result<Vec<f64>, Error> {
+        let n = self.dim();
+        let mut c = vec![0.0; n];
+        let mut d = vec![0.0; n];
+        for i in 0..n {
+            let sub = if i > 0 { self.off[i - 1] } else { 0.0 };
+            let prev_c = if i > 0 { c[i - 1] } else { 0.0 };
+            let prev_d = if i > 0 { d[i - 1] } else { 0.0 };
+            let pivot = self.diag[i] - sub * prev_c;
+            if pivot == 0.0 || !pivot.is_finite() {
+                return Err(ArgminError::ConditionViolated {
+                    text: "Tridiagonal::solve: zero pivot".to_string(),
+                }
+                .into());
+            }
+            if i + 1 < n {
+                c[i] = self.off[i] / pivot;
+            }
+            d[i] = (rhs[i] - sub * prev_d) / pivot;
+        }
+        let mut x = d;
+        for i in (0..n.saturating_sub(1)).rev() {
+            x[i] -= c[i] * x[i + 1];
+        }
+        Ok(x)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::finitediff::{FiniteDiff, Scheme};
+
+    const POINTS: [[f64; 2]; 3] = [[-1.2, 1.0], [0.3, -0.7], [2.0, 3.5]];
+
+    fn assert_close(a: f64, b: f64, tol: f64) {
+        assert!((a - b).abs() <= tol * b.abs().max(1.0), "{} != {}", a, b);
+    }
+
+    #[test]
+    fn minimum_is_at_a_a_squared() {
+        let problem = Rosenbrock { a: 2.0, b: 50.0 };
+        let minimum = problem.info().minima[0].clone();
+        assert_eq!(minimum, vec![2.0, 4.0]);
+        assert_eq!(problem.cost(&minimum).unwrap(), 0.0);
+        assert!(problem.gradient(&minimum).unwrap().iter().all(|&g| g == 0.0));
+    }
+
+    #[test]
+    fn derivatives_match_finite_differences() {
+        let problem = Rosenbrock::default();
+        let diff = FiniteDiff::new(Scheme::Central);
+        for p in POINTS {
+            let gradient = problem.gradient(&p.to_vec()).unwrap();
+            for (a, n) in gradient.iter().zip(diff.gradient(&problem, &p).unwrap()) {
+                assert_close(*a, n, 1e-6);
+            }
+            let hessian = problem.hessian(&p.to_vec()).unwrap();
+            for (row, numeric) in hessian.iter().zip(diff.hessian(&problem, &p).unwrap()) {
+                for (a, n) in row.iter().zip(numeric) {
+                    assert_close(*a, n, 1e-4);
+                }
+            }
+        }
+    }
+}
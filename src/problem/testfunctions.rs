@@ -0,0 +1,481 @@
+//! Wrappers around the functions of `argmin_testfunctions`
+//!
+//! The cost functions are taken from `argmin_testfunctions`; the gradients
+//! (and, for the quadratic problems, the Hessians) are written out by hand
+//! since the crate only provides derivatives for a few of its functions.
+//!
+//! Problems without a fixed dimension take their dimension from the length
+//! of the parameter vector.
+
+use crate::scalar::{GenericCost, Scalar};
+use argmin::core::{CostFunction, Error, Gradient, Hessian};
+use argmin_testfunctions::{
+    ackley, beale, booth, easom, goldsteinprice, himmelblau, levy_n13, matyas, picheny, rastrigin,
+    sphere, styblinski_tang, threehumpcamel,
+};
+use std::f64::consts::{LN_10, PI};
+
+/// Ackley function with `a = 20`, `b = 0.2` and `c = 2 pi`
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Ackley;
+
+impl CostFunction for Ackley {
+    type Param = Vec<f64>;
+    type Output = f64;
+
+    fn cost(&self, p: &Self::Param) -> Result<Self::Output, Error> {
+        Ok(ackley(p))
+    }
+}
+
+impl Gradient for Ackley {
+    type Param = Vec<f64>;
+    type Gradient = Vec<f64>;
+
+    fn gradient(&self, p: &Self::Param) -> Result<Self::Gradient, Error> {
+        let (a, b, c) = (20.0, 0.2, 2.0 * PI);
+        let n = p.len() as f64;
+        let r = (p.iter().map(|x| x * x).sum::<f64>() / n).sqrt();
+        let s = (p.iter().map(|x| (c * x).cos()).sum::<f64>() / n).exp();
+        Ok(p.iter()
+            .map(|x| {
+                // The first term is not differentiable at the origin, where
+                // its subgradient contains zero.
+                let radial = if r > 0.0 {
+                    a * b * (-b * r).exp() * x / (n * r)
+                } else {
+                    0.0
+                };
+                radial + c / n * (c * x).sin() * s
+            })
+            .collect())
+    }
+}
+
+/// Beale function
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Beale;
+
+impl CostFunction for Beale {
+    type Param = Vec<f64>;
+    type Output = f64;
+
+    fn cost(&self, p: &Self::Param) -> Result<Self::Output, Error> {
+        Ok(beale(p))
+    }
+}
+
+impl Gradient for Beale {
+    type Param = Vec<f64>;
+    type Gradient = Vec<f64>;
+
+    fn gradient(&self, p: &Self::Param) -> Result<Self::Gradient, Error> {
+        let (x, y) = (p[0], p[1]);
+        let t1 = 1.5 - x + x * y;
+        let t2 = 2.25 - x + x * y.powi(2);
+        let t3 = 2.625 - x + x * y.powi(3);
+        Ok(vec![
+            2.0 * t1 * (y - 1.0) + 2.0 * t2 * (y.powi(2) - 1.0) + 2.0 * t3 * (y.powi(3) - 1.0),
+            2.0 * t1 * x + 4.0 * t2 * x * y + 6.0 * t3 * x * y.powi(2),
+        ])
+    }
+}
+
+/// Booth function
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Booth;
+
+impl CostFunction for Booth {
+    type Param = Vec<f64>;
+    type Output = f64;
+
+    fn cost(&self, p: &Self::Param) -> Result<Self::Output, Error> {
+        Ok(booth(p))
+    }
+}
+
+impl Gradient for Booth {
+    type Param = Vec<f64>;
+    type Gradient = Vec<f64>;
+
+    fn gradient(&self, p: &Self::Param) -> Result<Self::Gradient, Error> {
+        let u = p[0] + 2.0 * p[1] - 7.0;
+        let v = 2.0 * p[0] + p[1] - 5.0;
+        Ok(vec![2.0 * u + 4.0 * v, 4.0 * u + 2.0 * v])
+    }
+}
+
+impl Hessian for Booth {
+    type Param = Vec<f64>;
+    type Hessian = Vec<Vec<f64>>;
+
+    fn hessian(&self, _p: &Self::Param) -> Result<Self::Hessian, Error> {
+        Ok(vec![vec![10.0, 8.0], vec![8.0, 10.0]])
+    }
+}
+
+/// Easom function
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Easom;
+
+impl CostFunction for Easom {
+    type Param = Vec<f64>;
+    type Output = f64;
+
+    fn cost(&self, p: &Self::Param) -> Result<Self::Output, Error> {
+        Ok(easom(p))
+    }
+}
+
+impl Gradient for Easom {
+    type Param = Vec<f64>;
+    type Gradient = Vec<f64>;
+
+    fn gradient(&self, p: &Self::Param) -> Result<Self::Gradient, Error> {
+        let (x, y) = (p[0], p[1]);
+        let e = (-((x - PI).powi(2) + (y - PI).powi(2))).exp();
+        let cc = x.cos() * y.cos();
+        Ok(vec![
+            e * (x.sin() * y.cos() + 2.0 * (x - PI) * cc),
+            e * (x.cos() * y.sin() + 2.0 * (y - PI) * cc),
+        ])
+    }
+}
+
+/// Goldstein-Price function
+#[derive(Clone, Copy, Debug, Default)]
+pub struct GoldsteinPrice;
+
+/// Gradient of the Goldstein-Price function, shared with [`Picheny`]
+fn goldsteinprice_derivative(x: f64, y: f64) -> Vec<f64> {
+    let u = x + y + 1.0;
+    let v = 2.0 * x - 3.0 * y;
+    let a = 19.0 - 14.0 * x + 3.0 * x * x - 14.0 * y + 6.0 * x * y + 3.0 * y * y;
+    let b = 18.0 - 32.0 * x + 12.0 * x * x + 48.0 * y - 36.0 * x * y + 27.0 * y * y;
+    let f1 = 1.0 + u * u * a;
+    let f2 = 30.0 + v * v * b;
+    // `a` has the same partial derivative with respect to `x` and `y`
+    let df1 = 2.0 * u * a + u * u * (-14.0 + 6.0 * x + 6.0 * y);
+    let df2_dx = 4.0 * v * b + v * v * (-32.0 + 24.0 * x - 36.0 * y);
+    let df2_dy = -6.0 * v * b + v * v * (48.0 - 36.0 * x + 54.0 * y);
+    vec![df1 * f2 + f1 * df2_dx, df1 * f2 + f1 * df2_dy]
+}
+
+impl CostFunction for GoldsteinPrice {
+    type Param = Vec<f64>;
+    type Output = f64;
+
+    fn cost(&self, p: &Self::Param) -> Result<Self::Output, Error> {
+        Ok(goldsteinprice(p))
+    }
+}
+
+impl Gradient for GoldsteinPrice {
+    type Param = Vec<f64>;
+    type Gradient = Vec<f64>;
+
+    fn gradient(&self, p: &Self::Param) -> Result<Self::Gradient, Error> {
+        Ok(goldsteinprice_derivative(p[0], p[1]))
+    }
+}
+
+/// Himmelblau function
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Himmelblau;
+
+impl CostFunction for Himmelblau {
+    type Param = Vec<f64>;
+    type Output = f64;
+
+    fn cost(&self, p: &Self::Param) -> Result<Self::Output, Error> {
+        Ok(himmelblau(p))
+    }
+}
+
+impl Gradient for Himmelblau {
+    type Param = Vec<f64>;
+    type Gradient = Vec<f64>;
+
+    fn gradient(&self, p: &Self::Param) -> Result<Self::Gradient, Error> {
+        let (x, y) = (p[0], p[1]);
+        let u = x * x + y - 11.0;
+        let v = x + y * y - 7.0;
+        Ok(vec![4.0 * x * u + 2.0 * v, 2.0 * u + 4.0 * y * v])
+    }
+}
+
+/// Lévi function N.13
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Levi;
+
+impl CostFunction for Levi {
+    type Param = Vec<f64>;
+    type Output = f64;
+
+    fn cost(&self, p: &Self::Param) -> Result<Self::Output, Error> {
+        Ok(levy_n13(p))
+    }
+}
+
+impl Gradient for Levi {
+    type Param = Vec<f64>;
+    type Gradient = Vec<f64>;
+
+    fn gradient(&self, p: &Self::Param) -> Result<Self::Gradient, Error> {
+        let (x, y) = (p[0], p[1]);
+        Ok(vec![
+            3.0 * PI * (6.0 * PI * x).sin()
+                + 2.0 * (x - 1.0) * (1.0 + (3.0 * PI * y).sin().powi(2)),
+            3.0 * PI * (x - 1.0).powi(2) * (6.0 * PI * y).sin()
+                + 2.0 * (y - 1.0) * (1.0 + (2.0 * PI * y).sin().powi(2))
+                + 2.0 * PI * (y - 1.0).powi(2) * (4.0 * PI * y).sin(),
+        ])
+    }
+}
+
+/// Matyas function
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Matyas;
+
+impl CostFunction for Matyas {
+    type Param = Vec<f64>;
+    type Output = f64;
+
+    fn cost(&self, p: &Self::Param) -> Result<Self::Output, Error> {
+        Ok(matyas(p))
+    }
+}
+
+impl Gradient for Matyas {
+    type Param = Vec<f64>;
+    type Gradient = Vec<f64>;
+
+    fn gradient(&self, p: &Self::Param) -> Result<Self::Gradient, Error> {
+        Ok(vec![
+            0.52 * p[0] - 0.48 * p[1],
+            0.52 * p[1] - 0.48 * p[0],
+        ])
+    }
+}
+
+impl Hessian for Matyas {
+    type Param = Vec<f64>;
+    type Hessian = Vec<Vec<f64>>;
+
+    fn hessian(&self, _p: &Self::Param) -> Result<Self::Hessian, Error> {
+        Ok(vec![vec![0.52, -0.48], vec![-0.48, 0.52]])
+    }
+}
+
+/// Picheny function, a rescaled Goldstein-Price function on `[0, 1]^2`
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Picheny;
+
+impl CostFunction for Picheny {
+    type Param = Vec<f64>;
+    type Output = f64;
+
+    fn cost(&self, p: &Self::Param) -> Result<Self::Output, Error> {
+        Ok(picheny(p))
+    }
+}
+
+impl Gradient for Picheny {
+    type Param = Vec<f64>;
+    type Gradient = Vec<f64>;
+
+    fn gradient(&self, p: &Self::Param) -> Result<Self::Gradient, Error> {
+        // picheny(p) = (log10(goldsteinprice(4 p - 2)) - 8.693) / 2.427
+        let (x, y) = (4.0 * p[0] - 2.0, 4.0 * p[1] - 2.0);
+        let gp = goldsteinprice(&[x, y]);
+        Ok(goldsteinprice_derivative(x, y)
+            .into_iter()
+            .map(|g| 4.0 * g / (2.427 * gp * LN_10))
+            .collect())
+    }
+}
+
+/// Rastrigin function with `A = 10`
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Rastrigin;
+
+impl CostFunction for Rastrigin {
+    type Param = Vec<f64>;
+    type Output = f64;
+
+    fn cost(&self, p: &Self::Param) -> Result<Self::Output, Error> {
+        Ok(rastrigin(p))
+    }
+}
+
+impl Gradient for Rastrigin {
+    type Param = Vec<f64>;
+    type Gradient = Vec<f64>;
+
+    fn gradient(&self, p: &Self::Param) -> Result<Self::Gradient, Error> {
+        Ok(p.iter()
+            .map(|x| 2.0 * x + 20.0 * PI * (2.0 * PI * x).sin())
+            .collect())
+    }
+}
+
+/// Sphere function
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Sphere;
+
+impl CostFunction for Sphere {
+    type Param = Vec<f64>;
+    type Output = f64;
+
+    fn cost(&self, p: &Self::Param) -> Result<Self::Output, Error> {
+        Ok(sphere(p))
+    }
+}
+
+impl Gradient for Sphere {
+    type Param = Vec<f64>;
+    type Gradient = Vec<f64>;
+
+    fn gradient(&self, p: &Self::Param) -> Result<Self::Gradient, Error> {
+        Ok(p.iter().map(|x| 2.0 * x).collect())
+    }
+}
+
+impl Hessian for Sphere {
+    type Param = Vec<f64>;
+    type Hessian = Vec<Vec<f64>>;
+
+    fn hessian(&self, p: &Self::Param) -> Result<Self::Hessian, Error> {
+        let n = p.len();
+        Ok((0..n)
+            .map(|i| (0..n).map(|j| if i == j { 2.0 } else { 0.0 }).collect())
+            .collect())
+    }
+}
+
+/// Styblinski-Tang function
+#[derive(Clone, Copy, Debug, Default)]
+pub struct StyblinskiTang;
+
+impl StyblinskiTang {
+    /// Coordinate of the global minimizer, identical in every dimension
+    pub const MINIMIZER: f64 = -2.903534027771177;
+    /// Cost function value at the global minimizer per dimension
+    pub const MIN_COST_PER_DIM: f64 = -39.16616570377142;
+}
+
+impl CostFunction for StyblinskiTang {
+    type Param = Vec<f64>;
+    type Output = f64;
+
+    fn cost(&self, p: &Self::Param) -> Result<Self::Output, Error> {
+        Ok(styblinski_tang(p))
+    }
+}
+
+impl Gradient for StyblinskiTang {
+    type Param = Vec<f64>;
+    type Gradient = Vec<f64>;
+
+    fn gradient(&self, p: &Self::Param) -> Result<Self::Gradient, Error> {
+        Ok(p.iter()
+            .map(|x| 2.0 * x.powi(3) - 16.0 * x + 2.5)
+            .collect())
+    }
+}
+
+/// Three-hump camel function
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ThreeHumpCamel;
+
+impl CostFunction for ThreeHumpCamel {
+    type Param = Vec<f64>;
+    type Output = f64;
+
+    fn cost(&self, p: &Self::Param) -> Result<Self::Output, Error> {
+        Ok(threehumpcamel(p))
+    }
+}
+
+impl Gradient for ThreeHumpCamel {
+    type Param = Vec<f64>;
+    type Gradient = Vec<f64>;
+
+    fn gradient(&self, p: &Self::Param) -> Result<Self::Gradient, Error> {
+        let (x, y) = (p[0], p[1]);
+        Ok(vec![
+            4.0 * x - 4.2 * x.powi(3) + x.powi(5) + y,
+            x + 2.0 * y,
+        ])
+    }
+}
+
+/// Implement [`GenericCost`] by calling the generic cost function of
+/// `argmin_testfunctions`
+macro_rules! generic_cost {
+    ($($problem:ty => $cost:ident),* $(,)?) => {
+        $(
+            impl GenericCost for $problem {
+                fn cost_generic<T: Scalar>(&self, p: &[T]) -> Result<T, Error> {
+                    Ok($cost(p))
+                }
+            }
+        )*
+    };
+}
+
+// The cost functions of `argmin_testfunctions` are generic over the scalar
+// type, so every problem of this module can be evaluated at complex arguments.
+generic_cost! {
+    Ackley => ackley,
+    Beale => beale,
+    Booth => booth,
+    Easom => easom,
+    GoldsteinPrice => goldsteinprice,
+    Himmelblau => himmelblau,
+    Levi => levy_n13,
+    Matyas => matyas,
+    Picheny => picheny,
+    Rastrigin => rastrigin,
+    Sphere => sphere,
+    StyblinskiTang => styblinski_tang,
+    ThreeHumpCamel => threehumpcamel,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Central difference approximation of the gradient of `f` at `p`
+    fn numeric_gradient<F: CostFunction<Param = Vec<f64>, Output = f64>>(
+        f: &F,
+        p: &[f64],
+    ) -> Vec<f64> {
+        (0..p.len())
+            .map(|i| {
+                let h = 1e-6 * p[i].abs().max(1.0);
+                let (mut lo, mut hi) = (p.to_vec(), p.to_vec());
+                lo[i] -= h;
+                hi[i] += h;
+                (f.cost(&hi).unwrap() - f.cost(&lo).unwrap()) / (2.0 * h)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn picheny_gradient_matches_finite_differences() {
+        for p in [[0.1, 0.9], [0.3, 0.6], [0.7, 0.2]] {
+            let analytic = Picheny.gradient(&p.to_vec()).unwrap();
+            let numeric = numeric_gradient(&Picheny, &p);
+            for (a, n) in analytic.iter().zip(&numeric) {
+                assert!((a - n).abs() <= 1e-5 * n.abs().max(1.0), "{:?}: {} != {}", p, a, n);
+            }
+        }
+    }
+
+    #[test]
+    fn picheny_gradient_vanishes_at_minimum() {
+        let g = Picheny.gradient(&vec![0.5, 0.25]).unwrap();
+        assert!(g.iter().all(|g| g.abs() < 1e-10), "{:?}", g);
+    }
+}
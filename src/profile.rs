@@ -0,0 +1,494 @@
+//! Performance and data profiles of a benchmark
+//!
+//! Profiles compare solvers over the whole problem set of a
+//! [`BenchReport`] instead of problem by problem. Each problem solved from
+//! one starting point is an instance `p`. Following Moré and Wild (2009), a
+//! run solves its instance at tolerance `tau` as soon as
+//!
+//! ```text
+//! f(x0) - f_best >= (1 - tau) (f(x0) - f_L),
+//! ```
+//!
+//! where `f_best` is the best cost function value of the run so far and
+//! `f_L` the smallest value any solver found on the instance. `t(p, s)` is
+//! the work (a [`Measure`]) solver `s` spent until then, or infinite if it
+//! never got there.
+//!
+//! * The performance profile (Dolan and Moré, 2002) of a solver is the
+//!   fraction of instances with `t(p, s) <= alpha * min_s t(p, s)`, as a
+//!   function of the ratio `alpha >= 1`. Its value at 1 is the fraction of
+//!   instances on which the solver was fastest, its limit the fraction it
+//!   solved at all.
+//! * The data profile (Moré and Wild, 2009) of a solver is the fraction of
+//!   instances with `t(p, s) <= kappa (n_p + 1)`, as a function of the
+//!   budget `kappa` in units of `n_p + 1` evaluations (a simplex gradient),
+//!   where `n_p` is the dimension of the instance.
+//!
+//! Instances on which no solver improved the starting point are left out.
+//! [`write`] renders both profiles as SVG plots ([`crate::plot`]) and
+//! exports their curves as CSV.
+
+use crate::bench::{BenchReport, Progress};
+use crate::plot::{LineChart, Scale, Series};
+use argmin::core::{ArgminError, Error};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+
+/// Work counted until a run solves its instance
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Measure {
+    /// Evaluations of cost function, gradient and Hessian, each counted once
+    #[default]
+    Evals,
+    /// Cost function evaluations only
+    CostEvals,
+    /// Iterations
+    Iterations,
+}
+
+impl Measure {
+    /// All measures
+    pub const ALL: [Measure; 3] = [Measure::Evals, Measure::CostEvals, Measure::Iterations];
+
+    /// Name of the measure on the command line
+    pub fn name(self) -> &'static str {
+        match self {
+            Measure::Evals => "evals",
+            Measure::CostEvals => "cost-evals",
+            Measure::Iterations => "iterations",
+        }
+    }
+
+    /// Measure called `name`
+    pub fn from_name(name: &str) -> Option<Self> {
+        Measure::ALL.into_iter().find(|m| m.name() == name)
+    }
+
+    /// Work spent until the end of the iteration recorded in `progress`
+    fn of(self, progress: &Progress) -> f64 {
+        match self {
+            Measure::Evals => {
+                (progress.cost_evals + progress.gradient_evals + progress.hessian_evals) as f64
+            }
+            Measure::CostEvals => progress.cost_evals as f64,
+            Measure::Iterations => (progress.iter + 1) as f64,
+        }
+    }
+}
+
+/// How the profiles are computed
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct ProfileOptions {
+    /// Tolerance `tau` of the convergence test, in (0, 1)
+    pub tolerance: f64,
+    /// Work counted until an instance is solved
+    pub measure: Measure,
+}
+
+impl Default for ProfileOptions {
+    fn default() -> Self {
+        ProfileOptions {
+            tolerance: 1e-3,
+            measure: Measure::Evals,
+        }
+    }
+}
+
+/// The profile of one solver
+#[derive(Clone, Debug, Serialize)]
+pub struct Curve {
+    /// Name of the solver
+    pub solver: String,
+    /// Corners of the step function: from each `x` on, the fraction `y` of
+    /// the instances is solved
+    pub points: Vec<(f64, f64)>,
+}
+
+/// Performance or data profile of every solver of a benchmark
+#[derive(Clone, Debug, Serialize)]
+pub struct Profile {
+    /// Name of the variable on the x axis
+    pub variable: &'static str,
+    /// Options the profile was computed with
+    pub options: ProfileOptions,
+    /// Number of instances
+    pub instances: usize,
+    /// One curve per solver
+    pub curves: Vec<Curve>,
+}
+
+impl Profile {
+    /// The curves in long format: one line per corner of each curve
+    pub fn to_csv(&self) -> String {
+        let mut out = format!("solver,{},fraction\n", self.variable);
+        for curve in &self.curves {
+            for (x, y) in &curve.points {
+                out.push_str(&format!("{},{},{}\n", csv_field(&curve.solver), x, y));
+            }
+        }
+        out
+    }
+
+    /// Plot of the curves
+    pub fn chart(&self, title: &str, x_label: &str, x_scale: Scale) -> LineChart {
+        let mut chart = LineChart::new(
+            &format!(
+                "{} (tau = {}, {} instances, {})",
+                title,
+                self.options.tolerance,
+                self.instances,
+                self.options.measure.name()
+            ),
+            x_label,
+            "fraction of instances solved",
+        );
+        chart.x_scale = x_scale;
+        chart.y_range = Some((0.0, 1.0));
+        chart.series = self
+            .curves
+            .iter()
+            .map(|curve| Series::steps(&curve.solver, curve.points.clone()))
+            .collect();
+        chart
+    }
+}
+
+fn csv_field(text: &str) -> String {
+    if text.contains([',', '"', '\n']) {
+        format!("\"{}\"", text.replace('"', "\"\""))
+    } else {
+        text.to_string()
+    }
+}
+
+/// Work each solver spent on each instance: the solvers, the dimension of
+/// each instance and `t(p, s)` (infinite if not solved), indexed `[p][s]`
+fn work(report: &BenchReport, options: &ProfileOptions) -> Result<Work, Error> {
+    if !(options.tolerance > 0.0 && options.tolerance < 1.0) {
+        return Err(ArgminError::InvalidParameter {
+            text: "profile: tolerance must be in (0, 1)".to_string(),
+        }
+        .into());
+    }
+    let mut solvers: Vec<String> = Vec::new();
+    for run in &report.runs {
+        if !solvers.contains(&run.solver) {
+            solvers.push(run.solver.clone());
+        }
+    }
+    // Instances in the order of the runs: by problem, then starting point
+    let mut instances: Vec<(&str, usize)> = Vec::new();
+    for run in &report.runs {
+        if !instances.contains(&(run.problem.as_str(), run.start)) {
+            instances.push((run.problem.as_str(), run.start));
+        }
+    }
+
+    let mut work = Work {
+        solvers: solvers.clone(),
+        dims: Vec::new(),
+        times: Vec::new(),
+    };
+    for (problem, start) in instances {
+        let runs: Vec<_> = report
+            .runs
+            .iter()
+            .filter(|run| run.problem == problem && run.start == start)
+            .collect();
+        let start_cost = runs[0].start_cost;
+        let lowest = runs
+            .iter()
+            .flat_map(|run| run.progress.iter().map(|p| p.best_cost))
+            .fold(f64::INFINITY, f64::min);
+        if start_cost.is_nan() || lowest >= start_cost {
+            continue;
+        }
+        let target = start_cost - (1.0 - options.tolerance) * (start_cost - lowest);
+        let times = solvers
+            .iter()
+            .map(|solver| {
+                runs.iter()
+                    .filter(|run| &run.solver == solver)
+                    .flat_map(|run| run.progress.iter())
+                    .find(|p| p.best_cost <= target)
+                    .map_or(f64::INFINITY, |p| options.measure.of(p))
+            })
+            .collect();
+        work.dims.push(runs[0].x0.len());
+        work.times.push(times);
+    }
+    if work.times.is_empty() {
+        return Err(ArgminError::InvalidParameter {
+            text: "profile: no solver improved on any starting point".to_string(),
+        }
+        .into());
+    }
+    Ok(work)
+}
+
+struct Work {
+    solvers: Vec<String>,
+    dims: Vec<usize>,
+    times: Vec<Vec<f64>>,
+}
+
+/// Step function of the fraction of `values` which are at most `x`,
+/// starting at `from` and extended to `to`
+fn cumulative(values: &[f64], total: usize, from: f64, to: f64) -> Vec<(f64, f64)> {
+    let mut sorted: Vec<f64> = values.iter().copied().filter(|v| v.is_finite()).collect();
+    sorted.sort_by(f64::total_cmp);
+    let fraction = |count: usize| count as f64 / total as f64;
+    let below = sorted.iter().take_while(|&&v| v <= from).count();
+    let mut points = vec![(from, fraction(below))];
+    for (i, &v) in sorted.iter().enumerate().skip(below) {
+        // Only the last of equal values is a corner
+        if sorted.get(i + 1) != Some(&v) {
+            points.push((v, fraction(i + 1)));
+        }
+    }
+    let last = points.last().map_or(0.0, |p| p.1);
+    if points.last().is_some_and(|p| p.0 < to) {
+        points.push((to, last));
+    }
+    points
+}
+
+/// Dolan-Moré performance profile of the solvers of `report`
+pub fn performance_profile(
+    report: &BenchReport,
+    options: &ProfileOptions,
+) -> Result<Profile, Error> {
+    let work = work(report, options)?;
+    // Instances solved without any work count as solved after one unit
+    let ratios: Vec<Vec<f64>> = work
+        .times
+        .iter()
+        .map(|times| {
+            let best = times.iter().copied().fold(f64::INFINITY, f64::min).max(1.0);
+            times.iter().map(|t| t.max(1.0) / best).collect()
+        })
+        .collect();
+    let largest = ratios
+        .iter()
+        .flatten()
+        .copied()
+        .filter(|r| r.is_finite())
+        .fold(1.0, f64::max);
+    let curves = work
+        .solvers
+        .iter()
+        .enumerate()
+        .map(|(s, solver)| Curve {
+            solver: solver.clone(),
+            points: cumulative(
+                &ratios.iter().map(|r| r[s]).collect::<Vec<_>>(),
+                ratios.len(),
+                1.0,
+                2.0 * largest,
+            ),
+        })
+        .collect();
+    Ok(Profile {
+        variable: "ratio",
+        options: *options,
+        instances: ratios.len(),
+        curves,
+    })
+}
+
+/// Moré-Wild data profile of the solvers of `report`
+pub fn data_profile(report: &BenchReport, options: &ProfileOptions) -> Result<Profile, Error> {
+    let work = work(report, options)?;
+    let budgets: Vec<Vec<f64>> = work
+        .times
+        .iter()
+        .zip(&work.dims)
+        .map(|(times, &n)| times.iter().map(|t| t / (n + 1) as f64).collect())
+        .collect();
+    let largest = budgets
+        .iter()
+        .flatten()
+        .copied()
+        .filter(|b| b.is_finite())
+        .fold(0.0, f64::max);
+    let curves = work
+        .solvers
+        .iter()
+        .enumerate()
+        .map(|(s, solver)| Curve {
+            solver: solver.clone(),
+            points: cumulative(
+                &budgets.iter().map(|b| b[s]).collect::<Vec<_>>(),
+                budgets.len(),
+                0.0,
+                1.05 * largest,
+            ),
+        })
+        .collect();
+    Ok(Profile {
+        variable: "budget",
+        options: *options,
+        instances: budgets.len(),
+        curves,
+    })
+}
+
+/// Write both profiles of `report` to `directory`
+///
+/// The files are `performance.svg` and `performance.csv` for the
+/// performance profile and `data.svg` and `data.csv` for the data profile.
+pub fn write(
+    report: &BenchReport,
+    options: &ProfileOptions,
+    directory: &Path,
+) -> Result<(), Error> {
+    let performance = performance_profile(report, options)?;
+    let data = data_profile(report, options)?;
+    fs::create_dir_all(directory)?;
+    let chart = performance.chart(
+        "Performance profile",
+        "ratio to the best solver",
+        Scale::Log2,
+    );
+    fs::write(directory.join("performance.svg"), chart.to_svg())?;
+    fs::write(directory.join("performance.csv"), performance.to_csv())?;
+    let chart = data.chart(
+        "Data profile",
+        "budget in simplex gradients (n + 1 evaluations)",
+        Scale::Linear,
+    );
+    fs::write(directory.join("data.svg"), chart.to_svg())?;
+    fs::write(directory.join("data.csv"), data.to_csv())?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bench::BenchRun;
+
+    /// Run whose best cost function value is `best` after `evals` cost
+    /// function evaluations, for each pair in `progress`
+    fn run(problem: &str, solver: &str, start_cost: f64, progress: &[(u64, f64)]) -> BenchRun {
+        BenchRun {
+            problem: problem.to_string(),
+            solver: solver.to_string(),
+            start: 0,
+            x0: vec![0.0, 0.0],
+            report: None,
+            error: None,
+            wall_time_secs: 0.0,
+            cost_error: None,
+            param_error: None,
+            solved: None,
+            start_cost,
+            progress: progress
+                .iter()
+                .enumerate()
+                .map(|(i, &(evals, best_cost))| Progress {
+                    iter: i as u64,
+                    cost_evals: evals,
+                    gradient_evals: 0,
+                    hessian_evals: 0,
+                    best_cost,
+                })
+                .collect(),
+            trace: Vec::new(),
+        }
+    }
+
+    /// `a` solves `p` twice as fast as `b` and fails on `q`, which `b`
+    /// solves; nobody improves on `r`
+    fn report() -> BenchReport {
+        BenchReport {
+            success_tol: 1e-6,
+            runs: vec![
+                run("p", "a", 10.0, &[(3, 5.0), (6, 0.0)]),
+                run("p", "b", 10.0, &[(12, 0.0)]),
+                run("q", "a", 4.0, &[(3, 3.0)]),
+                run("q", "b", 4.0, &[(9, 1.0)]),
+                run("r", "a", 1.0, &[]),
+                run("r", "b", 1.0, &[]),
+            ],
+            summary: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn measure_names_round_trip() {
+        for measure in Measure::ALL {
+            assert_eq!(Measure::from_name(measure.name()), Some(measure));
+        }
+        assert_eq!(Measure::from_name("seconds"), None);
+    }
+
+    #[test]
+    fn performance_profile_counts_ratios_to_the_best_solver() {
+        let profile = performance_profile(&report(), &ProfileOptions::default()).unwrap();
+        assert_eq!(profile.instances, 2);
+        assert_eq!(profile.curves[0].solver, "a");
+        assert_eq!(profile.curves[0].points, [(1.0, 0.5), (4.0, 0.5)]);
+        assert_eq!(profile.curves[1].points, [(1.0, 0.5), (2.0, 1.0), (4.0, 1.0)]);
+    }
+
+    #[test]
+    fn data_profile_counts_simplex_gradients() {
+        let profile = data_profile(&report(), &ProfileOptions::default()).unwrap();
+        assert_eq!(profile.instances, 2);
+        assert_eq!(profile.curves[0].points, [(0.0, 0.0), (2.0, 0.5), (4.2, 0.5)]);
+        assert_eq!(
+            profile.curves[1].points,
+            [(0.0, 0.0), (3.0, 0.5), (4.0, 1.0), (4.2, 1.0)]
+        );
+    }
+
+    #[test]
+    fn loose_tolerances_accept_earlier_iterations() {
+        let options = ProfileOptions {
+            tolerance: 0.6,
+            measure: Measure::Iterations,
+        };
+        let profile = performance_profile(&report(), &options).unwrap();
+        // Both solve `p` in their first iteration, `a` gets to 5 instead of 0
+        assert_eq!(profile.curves[0].points, [(1.0, 0.5), (2.0, 0.5)]);
+        assert_eq!(profile.curves[1].points, [(1.0, 1.0), (2.0, 1.0)]);
+    }
+
+    #[test]
+    fn invalid_inputs_are_rejected() {
+        for tolerance in [0.0, 1.0, f64::NAN] {
+            let options = ProfileOptions {
+                tolerance,
+                ..ProfileOptions::default()
+            };
+            assert!(performance_profile(&report(), &options).is_err());
+        }
+        let mut report = report();
+        report.runs.retain(|run| run.problem == "r");
+        assert!(data_profile(&report, &ProfileOptions::default()).is_err());
+    }
+
+    #[test]
+    fn profiles_are_written_as_svg_and_csv() {
+        let directory = std::env::temp_dir().join(format!("opt-profile-{}", std::process::id()));
+        let mut report = report();
+        for run in &mut report.runs {
+            if run.solver == "b" {
+                run.solver = "b, tuned".to_string();
+            }
+        }
+        write(&report, &ProfileOptions::default(), &directory).unwrap();
+        let csv = fs::read_to_string(directory.join("performance.csv")).unwrap();
+        assert!(csv.starts_with("solver,ratio,fraction\na,1,0.5\n"), "{}", csv);
+        assert!(csv.contains("\"b, tuned\",2,1\n"), "{}", csv);
+        let csv = fs::read_to_string(directory.join("data.csv")).unwrap();
+        assert!(csv.starts_with("solver,budget,fraction\n"), "{}", csv);
+        for svg in ["performance.svg", "data.svg"] {
+            let svg = fs::read_to_string(directory.join(svg)).unwrap();
+            assert!(svg.starts_with("<svg") && svg.contains("b, tuned"));
+        }
+        fs::remove_dir_all(&directory).unwrap();
+    }
+}
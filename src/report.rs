@@ -0,0 +1,231 @@
+//! Solver independent summary of an optimization run
+
+use crate::backend::Vector;
+use crate::problem::ProblemConfig;
+use crate::solver::SolverOptions;
+use crate::{RunConfig, SwarmState};
+use argmin::core::State;
+use serde::{Deserialize, Serialize, Serializer};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::time::Duration;
+
+/// Outcome of an optimization run
+///
+/// Unlike argmin's `OptimizationResult`, a `Report` does not depend on the
+/// types of solver and state, so runs of different solvers can be collected
+/// and compared.
+#[derive(Clone, Debug, Serialize)]
+pub struct Report {
+    /// Name of the problem
+    pub problem: String,
+    /// Name of the solver
+    pub solver: String,
+    /// Best parameter vector found
+    pub param: Vec<f64>,
+    /// Cost function value at `param`
+    pub cost: f64,
+    /// Number of iterations performed
+    pub iterations: u64,
+    /// Iteration in which `param` was found
+    pub best_iteration: u64,
+    /// Why the solver stopped
+    pub termination: String,
+    /// Number of evaluations, keyed by argmin's counter names
+    /// (`cost_count`, `gradient_count`, `hessian_count`, ...)
+    ///
+    /// The counters of cost function, gradient and Hessian are always
+    /// present, so that reports of different solvers have the same keys.
+    pub counts: BTreeMap<String, u64>,
+    /// Total time spent in the solver
+    #[serde(rename = "time_secs", serialize_with = "serialize_secs")]
+    pub time: Option<Duration>,
+}
+
+impl Report {
+    /// Summarize the final `state` of a run
+    pub fn from_state<I>(problem: &str, solver: &str, state: &I) -> Self
+    where
+        I: State<Float = f64>,
+        I::Param: Vector,
+    {
+        let param = state.get_best_param().map(Vector::to_vec).unwrap_or_default();
+        Report::with_param(problem, solver, param, state)
+    }
+
+    /// Summarize the final state of a particle swarm run
+    ///
+    /// The best parameter vector is the position of the best particle.
+    pub fn from_swarm_state<P>(problem: &str, solver: &str, state: &SwarmState<P>) -> Self
+    where
+        P: Vector,
+    {
+        let param = state
+            .get_best_param()
+            .map(|particle| particle.position.to_vec())
+            .unwrap_or_default();
+        Report::with_param(problem, solver, param, state)
+    }
+
+    fn with_param<I>(problem: &str, solver: &str, param: Vec<f64>, state: &I) -> Self
+    where
+        I: State<Float = f64>,
+    {
+        let mut counts: BTreeMap<String, u64> = state
+            .get_func_counts()
+            .iter()
+            .map(|(k, v)| (k.clone(), *v))
+            .collect();
+        for counter in ["cost_count", "gradient_count", "hessian_count"] {
+            counts.entry(counter.to_string()).or_insert(0);
+        }
+        Report {
+            problem: problem.to_string(),
+            solver: solver.to_string(),
+            param,
+            cost: state.get_best_cost(),
+            iterations: state.get_iter(),
+            best_iteration: state.get_last_best_iter(),
+            termination: state.get_termination_reason().to_string(),
+            counts,
+            time: state.get_time(),
+        }
+    }
+
+    /// Number of evaluations recorded under `counter`, zero if none
+    pub fn count(&self, counter: &str) -> u64 {
+        self.counts.get(counter).copied().unwrap_or(0)
+    }
+}
+
+pub(crate) fn serialize_secs<S: Serializer>(time: &Option<Duration>, s: S) -> Result<S::Ok, S::Error> {
+    time.map(|t| t.as_secs_f64()).serialize(s)
+}
+
+/// How the outcome of a run is printed
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum OutputFormat {
+    /// Human readable summary ([`Report`]'s `Display`)
+    #[default]
+    Text,
+    /// JSON [`Record`] of configuration and result
+    Json,
+}
+
+impl OutputFormat {
+    /// All formats, in the order in which they are listed
+    pub const ALL: &'static [OutputFormat] = &[OutputFormat::Text, OutputFormat::Json];
+
+    /// Name used to select the format
+    pub fn name(self) -> &'static str {
+        match self {
+            OutputFormat::Text => "text",
+            OutputFormat::Json => "json",
+        }
+    }
+
+    /// Look up a format by its name
+    pub fn from_name(name: &str) -> Option<OutputFormat> {
+        OutputFormat::ALL.iter().copied().find(|f| f.name() == name)
+    }
+}
+
+/// Machine readable record of a run: its configuration and its outcome
+///
+/// This is what `opt run --format json` prints. Fields are always emitted
+/// in the same order and maps are sorted by key, so records of different
+/// runs can be compared with `diff`. Apart from `time_secs`, identical
+/// runs give identical records (stochastic solvers need a fixed seed).
+#[derive(Clone, Debug, Serialize)]
+pub struct Record<'a, R = Report> {
+    /// Problem which was solved
+    pub problem: ProblemConfig,
+    /// Solver and all of its tuning parameters
+    pub solver: &'a SolverOptions,
+    /// Starting point and stopping criteria
+    pub run: &'a RunConfig,
+    /// Outcome of the run (a [`MultiStartReport`](crate::multistart::MultiStartReport)
+    /// for multi-start runs)
+    pub result: &'a R,
+}
+
+impl<R: Serialize> Record<'_, R> {
+    /// Pretty printed JSON representation, one field per line
+    pub fn to_json(&self) -> String {
+        // Serializing plain data into a `String` cannot fail
+        serde_json::to_string_pretty(self).expect("record is serializable")
+    }
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "OptimizationResult:")?;
+        writeln!(f, "    Problem:        {}", self.problem)?;
+        writeln!(f, "    Solver:         {}", self.solver)?;
+        writeln!(f, "    param (best):   {:?}", self.param)?;
+        writeln!(f, "    cost (best):    {}", self.cost)?;
+        writeln!(f, "    iters (best):   {}", self.best_iteration)?;
+        writeln!(f, "    iters (total):  {}", self.iterations)?;
+        writeln!(f, "    termination:    {}", self.termination)?;
+        for (counter, n) in &self.counts {
+            writeln!(f, "    {:<15} {}", format!("{}:", counter), n)?;
+        }
+        if let Some(time) = self.time {
+            writeln!(f, "    time:           {:?}", time)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::solver::{solve, Method};
+    use serde_json::{json, Value};
+
+    fn record_of_run() -> Value {
+        let problem: ProblemConfig = serde_json::from_value(json!({"name": "rosenbrock"})).unwrap();
+        let solver = SolverOptions::new(Method::Lbfgs);
+        let run = RunConfig::default();
+        let report = solve(problem.build().unwrap(), &solver, &run).unwrap();
+        let record = Record {
+            problem,
+            solver: &solver,
+            run: &run,
+            result: &report,
+        };
+        serde_json::from_str(&record.to_json()).unwrap()
+    }
+
+    #[test]
+    fn format_names_round_trip() {
+        for &format in OutputFormat::ALL {
+            assert_eq!(OutputFormat::from_name(format.name()), Some(format));
+        }
+        assert_eq!(OutputFormat::from_name("yaml"), None);
+    }
+
+    #[test]
+    fn record_contains_configuration_and_result() {
+        let record = record_of_run();
+        assert_eq!(record["problem"]["name"], "rosenbrock");
+        assert_eq!(record["solver"]["method"], "lbfgs");
+        assert_eq!(record["run"]["init_param"], json!([1.0, -2.0]));
+        assert_eq!(record["result"]["solver"], "lbfgs");
+        assert!(record["result"]["cost"].as_f64().unwrap() < 1e-8);
+        for counter in ["cost_count", "gradient_count", "hessian_count"] {
+            assert!(record["result"]["counts"][counter].is_u64(), "{}", counter);
+        }
+        assert!(record["result"]["time_secs"].is_f64());
+    }
+
+    #[test]
+    fn identical_runs_give_identical_records() {
+        let mut first = record_of_run();
+        let mut second = record_of_run();
+        first["result"]["time_secs"].take();
+        second["result"]["time_secs"].take();
+        assert_eq!(first, second);
+    }
+}
@@ -0,0 +1,476 @@
+//! Complex numbers for complex step differentiation
+//!
+//! For a real analytic function `f`, `f'(x) = Im(f(x + i h)) / h + O(h^2)`.
+//! Unlike finite differences, this involves no subtraction, so `h` can be
+//! tiny (`1e-20`) and the derivative is exact to machine precision.
+//!
+//! [`Complex`] implements `num`'s `Float` following the "complexify"
+//! conventions, so that code written for real numbers runs unchanged:
+//! comparisons, `abs`, `max`, `min` and rounding act on the real part, and
+//! arithmetic is exact complex arithmetic. Elementary functions are evaluated
+//! by their first order expansion `f(a + i b) = f(a) + i b f'(a)`, which
+//! differs from the complex function only in terms of order `b^2`; these
+//! vanish for the step sizes used in complex step differentiation and the
+//! expansion does not suffer from cancellation near branch points.
+
+use num_traits::{Float, FromPrimitive, Num, NumCast, One, ToPrimitive, Zero};
+use std::cmp::Ordering;
+use std::iter::Sum;
+use std::num::FpCategory;
+use std::ops::{Add, AddAssign, Div, DivAssign, Mul, MulAssign, Neg, Rem, Sub, SubAssign};
+
+/// Complex number `re + i im`, see the [module documentation](self)
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Complex {
+    /// Real part
+    pub re: f64,
+    /// Imaginary part
+    pub im: f64,
+}
+
+impl Complex {
+    /// `re + i im`
+    pub fn new(re: f64, im: f64) -> Self {
+        Complex { re, im }
+    }
+
+    /// Real number `re`
+    pub fn real(re: f64) -> Self {
+        Complex { re, im: 0.0 }
+    }
+
+    /// `f(self)` given `value = f(re)` and `derivative = f'(re)`
+    fn chain(self, value: f64, derivative: f64) -> Self {
+        // Skip the product if the imaginary part vanishes, so that an
+        // infinite derivative (e.g. of `sqrt` at zero) does not produce NaN
+        // for real arguments.
+        let im = if self.im == 0.0 { 0.0 } else { self.im * derivative };
+        Complex { re: value, im }
+    }
+}
+
+impl Add for Complex {
+    type Output = Complex;
+
+    fn add(self, other: Complex) -> Complex {
+        Complex::new(self.re + other.re, self.im + other.im)
+    }
+}
+
+impl Sub for Complex {
+    type Output = Complex;
+
+    fn sub(self, other: Complex) -> Complex {
+        Complex::new(self.re - other.re, self.im - other.im)
+    }
+}
+
+impl Mul for Complex {
+    type Output = Complex;
+
+    fn mul(self, other: Complex) -> Complex {
+        Complex::new(
+            self.re * other.re - self.im * other.im,
+            self.re * other.im + self.im * other.re,
+        )
+    }
+}
+
+impl Div for Complex {
+    type Output = Complex;
+
+    fn div(self, other: Complex) -> Complex {
+        let d = other.re * other.re + other.im * other.im;
+        Complex::new(
+            (self.re * other.re + self.im * other.im) / d,
+            (self.im * other.re - self.re * other.im) / d,
+        )
+    }
+}
+
+impl Rem for Complex {
+    type Output = Complex;
+
+    /// Remainder of the real parts; `a % b = a - trunc(a / b) b`
+    fn rem(self, other: Complex) -> Complex {
+        let q = (self.re / other.re).trunc();
+        Complex::new(self.re % other.re, self.im - q * other.im)
+    }
+}
+
+impl Neg for Complex {
+    type Output = Complex;
+
+    fn neg(self) -> Complex {
+        Complex::new(-self.re, -self.im)
+    }
+}
+
+impl AddAssign for Complex {
+    fn add_assign(&mut self, other: Complex) {
+        *self = *self + other;
+    }
+}
+
+impl SubAssign for Complex {
+    fn sub_assign(&mut self, other: Complex) {
+        *self = *self - other;
+    }
+}
+
+impl MulAssign for Complex {
+    fn mul_assign(&mut self, other: Complex) {
+        *self = *self * other;
+    }
+}
+
+impl DivAssign for Complex {
+    fn div_assign(&mut self, other: Complex) {
+        *self = *self / other;
+    }
+}
+
+impl PartialOrd for Complex {
+    /// Compares the real parts
+    fn partial_cmp(&self, other: &Complex) -> Option<Ordering> {
+        self.re.partial_cmp(&other.re)
+    }
+}
+
+impl Sum for Complex {
+    fn sum<I: Iterator<Item = Complex>>(iter: I) -> Complex {
+        iter.fold(Complex::zero(), |acc, x| acc + x)
+    }
+}
+
+impl Zero for Complex {
+    fn zero() -> Self {
+        Complex::real(0.0)
+    }
+
+    fn is_zero(&self) -> bool {
+        self.re == 0.0 && self.im == 0.0
+    }
+}
+
+impl One for Complex {
+    fn one() -> Self {
+        Complex::real(1.0)
+    }
+}
+
+impl Num for Complex {
+    type FromStrRadixErr = <f64 as Num>::FromStrRadixErr;
+
+    fn from_str_radix(s: &str, radix: u32) -> Result<Self, Self::FromStrRadixErr> {
+        f64::from_str_radix(s, radix).map(Complex::real)
+    }
+}
+
+impl ToPrimitive for Complex {
+    fn to_i64(&self) -> Option<i64> {
+        self.re.to_i64()
+    }
+
+    fn to_u64(&self) -> Option<u64> {
+        self.re.to_u64()
+    }
+
+    fn to_f64(&self) -> Option<f64> {
+        Some(self.re)
+    }
+}
+
+impl NumCast for Complex {
+    fn from<T: ToPrimitive>(n: T) -> Option<Self> {
+        n.to_f64().map(Complex::real)
+    }
+}
+
+impl FromPrimitive for Complex {
+    fn from_i64(n: i64) -> Option<Self> {
+        Some(Complex::real(n as f64))
+    }
+
+    fn from_u64(n: u64) -> Option<Self> {
+        Some(Complex::real(n as f64))
+    }
+
+    fn from_f64(n: f64) -> Option<Self> {
+        Some(Complex::real(n))
+    }
+}
+
+impl Float for Complex {
+    fn nan() -> Self {
+        Complex::real(f64::NAN)
+    }
+
+    fn infinity() -> Self {
+        Complex::real(f64::INFINITY)
+    }
+
+    fn neg_infinity() -> Self {
+        Complex::real(f64::NEG_INFINITY)
+    }
+
+    fn neg_zero() -> Self {
+        Complex::real(-0.0)
+    }
+
+    fn min_value() -> Self {
+        Complex::real(f64::MIN)
+    }
+
+    fn min_positive_value() -> Self {
+        Complex::real(f64::MIN_POSITIVE)
+    }
+
+    fn epsilon() -> Self {
+        Complex::real(f64::EPSILON)
+    }
+
+    fn max_value() -> Self {
+        Complex::real(f64::MAX)
+    }
+
+    fn is_nan(self) -> bool {
+        self.re.is_nan() || self.im.is_nan()
+    }
+
+    fn is_infinite(self) -> bool {
+        self.re.is_infinite() || self.im.is_infinite()
+    }
+
+    fn is_finite(self) -> bool {
+        self.re.is_finite() && self.im.is_finite()
+    }
+
+    fn is_normal(self) -> bool {
+        self.re.is_normal()
+    }
+
+    fn classify(self) -> FpCategory {
+        self.re.classify()
+    }
+
+    fn floor(self) -> Self {
+        Complex::real(self.re.floor())
+    }
+
+    fn ceil(self) -> Self {
+        Complex::real(self.re.ceil())
+    }
+
+    fn round(self) -> Self {
+        Complex::real(self.re.round())
+    }
+
+    fn trunc(self) -> Self {
+        Complex::real(self.re.trunc())
+    }
+
+    fn fract(self) -> Self {
+        Complex::new(self.re.fract(), self.im)
+    }
+
+    fn abs(self) -> Self {
+        if self.re.is_sign_negative() {
+            -self
+        } else {
+            self
+        }
+    }
+
+    fn signum(self) -> Self {
+        Complex::real(self.re.signum())
+    }
+
+    fn is_sign_positive(self) -> bool {
+        self.re.is_sign_positive()
+    }
+
+    fn is_sign_negative(self) -> bool {
+        self.re.is_sign_negative()
+    }
+
+    fn mul_add(self, a: Self, b: Self) -> Self {
+        self * a + b
+    }
+
+    fn recip(self) -> Self {
+        Complex::one() / self
+    }
+
+    fn powi(self, n: i32) -> Self {
+        // Exponentiation by squaring keeps the arithmetic exact
+        let mut base = if n < 0 { self.recip() } else { self };
+        let mut exp = n.unsigned_abs();
+        let mut result = Complex::one();
+        while exp > 0 {
+            if exp & 1 == 1 {
+                result *= base;
+            }
+            base *= base;
+            exp >>= 1;
+        }
+        result
+    }
+
+    fn powf(self, n: Self) -> Self {
+        let value = self.re.powf(n.re);
+        let mut im = 0.0;
+        if self.im != 0.0 {
+            im += self.im * n.re * self.re.powf(n.re - 1.0);
+        }
+        if n.im != 0.0 {
+            im += n.im * value * self.re.ln();
+        }
+        Complex::new(value, im)
+    }
+
+    fn sqrt(self) -> Self {
+        let value = self.re.sqrt();
+        self.chain(value, 0.5 / value)
+    }
+
+    fn exp(self) -> Self {
+        let value = self.re.exp();
+        self.chain(value, value)
+    }
+
+    fn exp2(self) -> Self {
+        let value = self.re.exp2();
+        self.chain(value, value * std::f64::consts::LN_2)
+    }
+
+    fn ln(self) -> Self {
+        self.chain(self.re.ln(), 1.0 / self.re)
+    }
+
+    fn log(self, base: Self) -> Self {
+        self.ln() / base.ln()
+    }
+
+    fn log2(self) -> Self {
+        self.chain(self.re.log2(), 1.0 / (self.re * std::f64::consts::LN_2))
+    }
+
+    fn log10(self) -> Self {
+        self.chain(self.re.log10(), 1.0 / (self.re * std::f64::consts::LN_10))
+    }
+
+    fn to_degrees(self) -> Self {
+        Complex::new(self.re.to_degrees(), self.im.to_degrees())
+    }
+
+    fn to_radians(self) -> Self {
+        Complex::new(self.re.to_radians(), self.im.to_radians())
+    }
+
+    fn max(self, other: Self) -> Self {
+        if other.re > self.re || self.re.is_nan() {
+            other
+        } else {
+            self
+        }
+    }
+
+    fn min(self, other: Self) -> Self {
+        if other.re < self.re || self.re.is_nan() {
+            other
+        } else {
+            self
+        }
+    }
+
+    fn abs_sub(self, other: Self) -> Self {
+        if self.re > other.re {
+            self - other
+        } else {
+            Complex::zero()
+        }
+    }
+
+    fn cbrt(self) -> Self {
+        let value = self.re.cbrt();
+        self.chain(value, 1.0 / (3.0 * value * value))
+    }
+
+    fn hypot(self, other: Self) -> Self {
+        (self * self + other * other).sqrt()
+    }
+
+    fn sin(self) -> Self {
+        self.chain(self.re.sin(), self.re.cos())
+    }
+
+    fn cos(self) -> Self {
+        self.chain(self.re.cos(), -self.re.sin())
+    }
+
+    fn tan(self) -> Self {
+        let c = self.re.cos();
+        self.chain(self.re.tan(), 1.0 / (c * c))
+    }
+
+    fn asin(self) -> Self {
+        self.chain(self.re.asin(), 1.0 / (1.0 - self.re * self.re).sqrt())
+    }
+
+    fn acos(self) -> Self {
+        self.chain(self.re.acos(), -1.0 / (1.0 - self.re * self.re).sqrt())
+    }
+
+    fn atan(self) -> Self {
+        self.chain(self.re.atan(), 1.0 / (1.0 + self.re * self.re))
+    }
+
+    fn atan2(self, other: Self) -> Self {
+        // d atan2(y, x) = (x dy - y dx) / (x^2 + y^2)
+        let (y, x) = (self, other);
+        let r2 = x.re * x.re + y.re * y.re;
+        Complex::new(y.re.atan2(x.re), (x.re * y.im - y.re * x.im) / r2)
+    }
+
+    fn sin_cos(self) -> (Self, Self) {
+        (self.sin(), self.cos())
+    }
+
+    fn exp_m1(self) -> Self {
+        self.chain(self.re.exp_m1(), self.re.exp())
+    }
+
+    fn ln_1p(self) -> Self {
+        self.chain(self.re.ln_1p(), 1.0 / (1.0 + self.re))
+    }
+
+    fn sinh(self) -> Self {
+        self.chain(self.re.sinh(), self.re.cosh())
+    }
+
+    fn cosh(self) -> Self {
+        self.chain(self.re.cosh(), self.re.sinh())
+    }
+
+    fn tanh(self) -> Self {
+        let t = self.re.tanh();
+        self.chain(t, 1.0 - t * t)
+    }
+
+    fn asinh(self) -> Self {
+        self.chain(self.re.asinh(), 1.0 / (self.re * self.re + 1.0).sqrt())
+    }
+
+    fn acosh(self) -> Self {
+        self.chain(self.re.acosh(), 1.0 / (self.re * self.re - 1.0).sqrt())
+    }
+
+    fn atanh(self) -> Self {
+        self.chain(self.re.atanh(), 1.0 / (1.0 - self.re * self.re))
+    }
+
+    fn integer_decode(self) -> (u64, i16, i8) {
+        self.re.integer_decode()
+    }
+}
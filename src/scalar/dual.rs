@@ -0,0 +1,474 @@
+//! Dual numbers for forward mode automatic differentiation
+//!
+//! A dual number `re + eps ε` with `ε^2 = 0` carries a value and a
+//! directional derivative: `f(x + v ε) = f(x) + (∇f(x) · v) ε`. Evaluating a
+//! generic cost function at dual arguments therefore yields one directional
+//! derivative per evaluation, exact up to rounding.
+//!
+//! [`Dual`] is generic over its component type, so dual numbers can be
+//! nested: `Dual<Dual<f64>>` carries second derivatives, which gives Hessian
+//! entries and Hessian-vector products. As for [`Complex`](super::Complex),
+//! comparisons, `abs`, `max`, `min` and rounding act on the value, so code
+//! written for real numbers runs unchanged.
+
+use super::Scalar;
+use num_traits::{Float, FromPrimitive, Num, NumCast, One, ToPrimitive, Zero};
+use std::cmp::Ordering;
+use std::iter::Sum;
+use std::num::FpCategory;
+use std::ops::{Add, AddAssign, Div, DivAssign, Mul, MulAssign, Neg, Rem, Sub, SubAssign};
+
+/// Dual number `re + eps ε`, see the [module documentation](self)
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Dual<T = f64> {
+    /// Value
+    pub re: T,
+    /// Derivative
+    pub eps: T,
+}
+
+impl<T: Scalar> Dual<T> {
+    /// `re + eps ε`
+    pub fn new(re: T, eps: T) -> Self {
+        Dual { re, eps }
+    }
+
+    /// Constant `re`
+    pub fn real(re: T) -> Self {
+        Dual { re, eps: T::zero() }
+    }
+
+    /// `f(self)` given `value = f(re)` and `derivative = f'(re)`
+    fn chain(self, value: T, derivative: T) -> Self {
+        // Skip the product for constants, so that an infinite derivative
+        // (e.g. of `sqrt` at zero) does not produce NaN.
+        let eps = if self.eps.is_zero() {
+            T::zero()
+        } else {
+            self.eps * derivative
+        };
+        Dual { re: value, eps }
+    }
+}
+
+impl<T: Scalar> Add for Dual<T> {
+    type Output = Dual<T>;
+
+    fn add(self, other: Dual<T>) -> Dual<T> {
+        Dual::new(self.re + other.re, self.eps + other.eps)
+    }
+}
+
+impl<T: Scalar> Sub for Dual<T> {
+    type Output = Dual<T>;
+
+    fn sub(self, other: Dual<T>) -> Dual<T> {
+        Dual::new(self.re - other.re, self.eps - other.eps)
+    }
+}
+
+impl<T: Scalar> Mul for Dual<T> {
+    type Output = Dual<T>;
+
+    fn mul(self, other: Dual<T>) -> Dual<T> {
+        Dual::new(
+            self.re * other.re,
+            self.re * other.eps + self.eps * other.re,
+        )
+    }
+}
+
+impl<T: Scalar> Div for Dual<T> {
+    type Output = Dual<T>;
+
+    fn div(self, other: Dual<T>) -> Dual<T> {
+        let re = self.re / other.re;
+        Dual::new(re, (self.eps - re * other.eps) / other.re)
+    }
+}
+
+impl<T: Scalar> Rem for Dual<T> {
+    type Output = Dual<T>;
+
+    /// `a % b = a - trunc(a / b) b`
+    fn rem(self, other: Dual<T>) -> Dual<T> {
+        let q = (self.re / other.re).trunc();
+        Dual::new(self.re % other.re, self.eps - q * other.eps)
+    }
+}
+
+impl<T: Scalar> Neg for Dual<T> {
+    type Output = Dual<T>;
+
+    fn neg(self) -> Dual<T> {
+        Dual::new(-self.re, -self.eps)
+    }
+}
+
+impl<T: Scalar> AddAssign for Dual<T> {
+    fn add_assign(&mut self, other: Dual<T>) {
+        *self = *self + other;
+    }
+}
+
+impl<T: Scalar> SubAssign for Dual<T> {
+    fn sub_assign(&mut self, other: Dual<T>) {
+        *self = *self - other;
+    }
+}
+
+impl<T: Scalar> MulAssign for Dual<T> {
+    fn mul_assign(&mut self, other: Dual<T>) {
+        *self = *self * other;
+    }
+}
+
+impl<T: Scalar> DivAssign for Dual<T> {
+    fn div_assign(&mut self, other: Dual<T>) {
+        *self = *self / other;
+    }
+}
+
+impl<T: Scalar> PartialOrd for Dual<T> {
+    /// Compares the values
+    fn partial_cmp(&self, other: &Dual<T>) -> Option<Ordering> {
+        self.re.partial_cmp(&other.re)
+    }
+}
+
+impl<T: Scalar> Sum for Dual<T> {
+    fn sum<I: Iterator<Item = Dual<T>>>(iter: I) -> Dual<T> {
+        iter.fold(Dual::zero(), |acc, x| acc + x)
+    }
+}
+
+impl<T: Scalar> Zero for Dual<T> {
+    fn zero() -> Self {
+        Dual::real(T::zero())
+    }
+
+    fn is_zero(&self) -> bool {
+        self.re.is_zero() && self.eps.is_zero()
+    }
+}
+
+impl<T: Scalar> One for Dual<T> {
+    fn one() -> Self {
+        Dual::real(T::one())
+    }
+}
+
+impl<T: Scalar> Num for Dual<T> {
+    type FromStrRadixErr = T::FromStrRadixErr;
+
+    fn from_str_radix(s: &str, radix: u32) -> Result<Self, Self::FromStrRadixErr> {
+        T::from_str_radix(s, radix).map(Dual::real)
+    }
+}
+
+impl<T: Scalar> ToPrimitive for Dual<T> {
+    fn to_i64(&self) -> Option<i64> {
+        self.re.to_i64()
+    }
+
+    fn to_u64(&self) -> Option<u64> {
+        self.re.to_u64()
+    }
+
+    fn to_f64(&self) -> Option<f64> {
+        self.re.to_f64()
+    }
+}
+
+impl<T: Scalar> NumCast for Dual<T> {
+    fn from<N: ToPrimitive>(n: N) -> Option<Self> {
+        T::from(n).map(Dual::real)
+    }
+}
+
+impl<T: Scalar> FromPrimitive for Dual<T> {
+    fn from_i64(n: i64) -> Option<Self> {
+        T::from_i64(n).map(Dual::real)
+    }
+
+    fn from_u64(n: u64) -> Option<Self> {
+        T::from_u64(n).map(Dual::real)
+    }
+
+    fn from_f64(n: f64) -> Option<Self> {
+        T::from_f64(n).map(Dual::real)
+    }
+}
+
+impl<T: Scalar> Float for Dual<T> {
+    fn nan() -> Self {
+        Dual::real(T::nan())
+    }
+
+    fn infinity() -> Self {
+        Dual::real(T::infinity())
+    }
+
+    fn neg_infinity() -> Self {
+        Dual::real(T::neg_infinity())
+    }
+
+    fn neg_zero() -> Self {
+        Dual::real(T::neg_zero())
+    }
+
+    fn min_value() -> Self {
+        Dual::real(T::min_value())
+    }
+
+    fn min_positive_value() -> Self {
+        Dual::real(T::min_positive_value())
+    }
+
+    fn epsilon() -> Self {
+        Dual::real(T::epsilon())
+    }
+
+    fn max_value() -> Self {
+        Dual::real(T::max_value())
+    }
+
+    fn is_nan(self) -> bool {
+        self.re.is_nan() || self.eps.is_nan()
+    }
+
+    fn is_infinite(self) -> bool {
+        self.re.is_infinite() || self.eps.is_infinite()
+    }
+
+    fn is_finite(self) -> bool {
+        self.re.is_finite() && self.eps.is_finite()
+    }
+
+    fn is_normal(self) -> bool {
+        self.re.is_normal()
+    }
+
+    fn classify(self) -> FpCategory {
+        self.re.classify()
+    }
+
+    fn floor(self) -> Self {
+        Dual::real(self.re.floor())
+    }
+
+    fn ceil(self) -> Self {
+        Dual::real(self.re.ceil())
+    }
+
+    fn round(self) -> Self {
+        Dual::real(self.re.round())
+    }
+
+    fn trunc(self) -> Self {
+        Dual::real(self.re.trunc())
+    }
+
+    fn fract(self) -> Self {
+        Dual::new(self.re.fract(), self.eps)
+    }
+
+    fn abs(self) -> Self {
+        if self.re.is_sign_negative() {
+            -self
+        } else {
+            self
+        }
+    }
+
+    fn signum(self) -> Self {
+        Dual::real(self.re.signum())
+    }
+
+    fn is_sign_positive(self) -> bool {
+        self.re.is_sign_positive()
+    }
+
+    fn is_sign_negative(self) -> bool {
+        self.re.is_sign_negative()
+    }
+
+    fn mul_add(self, a: Self, b: Self) -> Self {
+        self * a + b
+    }
+
+    fn recip(self) -> Self {
+        Dual::one() / self
+    }
+
+    fn powi(self, n: i32) -> Self {
+        let derivative = match n {
+            0 => T::zero(),
+            _ => T::constant(<f64 as From<i32>>::from(n)) * self.re.powi(n - 1),
+        };
+        self.chain(self.re.powi(n), derivative)
+    }
+
+    fn powf(self, n: Self) -> Self {
+        let value = self.re.powf(n.re);
+        let mut eps = T::zero();
+        if !self.eps.is_zero() {
+            eps = eps + self.eps * n.re * self.re.powf(n.re - T::one());
+        }
+        if !n.eps.is_zero() {
+            eps = eps + n.eps * value * self.re.ln();
+        }
+        Dual::new(value, eps)
+    }
+
+    fn sqrt(self) -> Self {
+        let value = self.re.sqrt();
+        self.chain(value, (value + value).recip())
+    }
+
+    fn exp(self) -> Self {
+        let value = self.re.exp();
+        self.chain(value, value)
+    }
+
+    fn exp2(self) -> Self {
+        let value = self.re.exp2();
+        self.chain(value, value * T::constant(std::f64::consts::LN_2))
+    }
+
+    fn ln(self) -> Self {
+        self.chain(self.re.ln(), self.re.recip())
+    }
+
+    fn log(self, base: Self) -> Self {
+        self.ln() / base.ln()
+    }
+
+    fn log2(self) -> Self {
+        let derivative = (self.re * T::constant(std::f64::consts::LN_2)).recip();
+        self.chain(self.re.log2(), derivative)
+    }
+
+    fn log10(self) -> Self {
+        let derivative = (self.re * T::constant(std::f64::consts::LN_10)).recip();
+        self.chain(self.re.log10(), derivative)
+    }
+
+    fn to_degrees(self) -> Self {
+        Dual::new(self.re.to_degrees(), self.eps.to_degrees())
+    }
+
+    fn to_radians(self) -> Self {
+        Dual::new(self.re.to_radians(), self.eps.to_radians())
+    }
+
+    fn max(self, other: Self) -> Self {
+        if other.re > self.re || self.re.is_nan() {
+            other
+        } else {
+            self
+        }
+    }
+
+    fn min(self, other: Self) -> Self {
+        if other.re < self.re || self.re.is_nan() {
+            other
+        } else {
+            self
+        }
+    }
+
+    fn abs_sub(self, other: Self) -> Self {
+        if self.re > other.re {
+            self - other
+        } else {
+            Dual::zero()
+        }
+    }
+
+    fn cbrt(self) -> Self {
+        let value = self.re.cbrt();
+        self.chain(value, (T::constant(3.0) * value * value).recip())
+    }
+
+    fn hypot(self, other: Self) -> Self {
+        (self * self + other * other).sqrt()
+    }
+
+    fn sin(self) -> Self {
+        self.chain(self.re.sin(), self.re.cos())
+    }
+
+    fn cos(self) -> Self {
+        self.chain(self.re.cos(), -self.re.sin())
+    }
+
+    fn tan(self) -> Self {
+        let c = self.re.cos();
+        self.chain(self.re.tan(), (c * c).recip())
+    }
+
+    fn asin(self) -> Self {
+        let derivative = (T::one() - self.re * self.re).sqrt().recip();
+        self.chain(self.re.asin(), derivative)
+    }
+
+    fn acos(self) -> Self {
+        let derivative = -(T::one() - self.re * self.re).sqrt().recip();
+        self.chain(self.re.acos(), derivative)
+    }
+
+    fn atan(self) -> Self {
+        self.chain(self.re.atan(), (T::one() + self.re * self.re).recip())
+    }
+
+    fn atan2(self, other: Self) -> Self {
+        // d atan2(y, x) = (x dy - y dx) / (x^2 + y^2)
+        let (y, x) = (self, other);
+        let r2 = x.re * x.re + y.re * y.re;
+        Dual::new(y.re.atan2(x.re), (x.re * y.eps - y.re * x.eps) / r2)
+    }
+
+    fn sin_cos(self) -> (Self, Self) {
+        (self.sin(), self.cos())
+    }
+
+    fn exp_m1(self) -> Self {
+        self.chain(self.re.exp_m1(), self.re.exp())
+    }
+
+    fn ln_1p(self) -> Self {
+        self.chain(self.re.ln_1p(), (T::one() + self.re).recip())
+    }
+
+    fn sinh(self) -> Self {
+        self.chain(self.re.sinh(), self.re.cosh())
+    }
+
+    fn cosh(self) -> Self {
+        self.chain(self.re.cosh(), self.re.sinh())
+    }
+
+    fn tanh(self) -> Self {
+        let t = self.re.tanh();
+        self.chain(t, T::one() - t * t)
+    }
+
+    fn asinh(self) -> Self {
+        let derivative = (self.re * self.re + T::one()).sqrt().recip();
+        self.chain(self.re.asinh(), derivative)
+    }
+
+    fn acosh(self) -> Self {
+        let derivative = (self.re * self.re - T::one()).sqrt().recip();
+        self.chain(self.re.acosh(), derivative)
+    }
+
+    fn atanh(self) -> Self {
+        self.chain(self.re.atanh(), (T::one() - self.re * self.re).recip())
+    }
+
+    fn integer_decode(self) -> (u64, i16, i8) {
+        self.re.integer_decode()
+    }
+}
@@ -0,0 +1,41 @@
+//! Cost functions which are generic over the scalar type
+//!
+//! The functions of `argmin_testfunctions` are generic over `num`'s `Float`,
+//! so they can be evaluated on number types other than `f64`. Problems which
+//! implement [`GenericCost`] can therefore be evaluated at complex arguments
+//! ([`Complex`]), which enables complex step differentiation, and at dual
+//! numbers ([`Dual`]) and tape variables ([`Var`]), which enable forward and
+//! reverse mode automatic differentiation.
+
+mod complex;
+mod dual;
+mod var;
+
+pub use complex::Complex;
+pub use dual::Dual;
+pub use var::{Node, TapeScalar, Var};
+
+use argmin::core::Error;
+use num_traits::{Float, FromPrimitive};
+use std::fmt::Debug;
+use std::iter::Sum;
+
+/// Scalar types on which generic cost functions are evaluated
+pub trait Scalar: Float + FromPrimitive + Sum + Debug {
+    /// Convert a constant, e.g. a parameter of the problem
+    fn constant(x: f64) -> Self {
+        // `FromPrimitive::from_f64` only fails for integer types
+        Self::from_f64(x).expect("conversion from f64")
+    }
+}
+
+impl<T: Float + FromPrimitive + Sum + Debug> Scalar for T {}
+
+/// A cost function which can be evaluated on any [`Scalar`] type
+///
+/// For `T = f64`, `cost_generic` must agree with the problem's
+/// `CostFunction::cost`, including the errors it returns.
+pub trait GenericCost {
+    /// Evaluate the cost function at `p`
+    fn cost_generic<T: Scalar>(&self, p: &[T]) -> Result<T, Error>;
+}
@@ -0,0 +1,576 @@
+//! Tape-based reverse mode automatic differentiation
+//!
+//! Every operation on [`Var`]s is recorded on a tape together with the
+//! partial derivatives of its result with respect to its operands. After the
+//! cost function has been evaluated, one backward sweep over the tape
+//! accumulates the derivative of the cost with respect to every intermediate
+//! value (its adjoint), which gives the full gradient at the price of a few
+//! cost evaluations, regardless of the dimension.
+//!
+//! The tape is thread-local, so problems can be differentiated on several
+//! threads at once, but [`Var::gradient`] must not be nested on one thread
+//! for the same value type. Values of type [`Dual`] are supported as well:
+//! sweeping a tape of `Var<Dual<f64>>` ("forward over reverse") yields a
+//! Hessian-vector product alongside the gradient.
+
+use super::{Dual, Scalar};
+use argmin::core::Error;
+use num_traits::{Float, FromPrimitive, Num, NumCast, One, ToPrimitive, Zero};
+use std::cell::RefCell;
+use std::cmp::Ordering;
+use std::iter::Sum;
+use std::num::FpCategory;
+use std::ops::{Add, AddAssign, Div, DivAssign, Mul, MulAssign, Neg, Rem, Sub, SubAssign};
+
+/// Tape index of constants, which are not recorded
+const CONSTANT: usize = usize::MAX;
+
+/// Operation recorded on the tape: its operands and the partial derivatives
+/// of its result with respect to them
+#[derive(Clone, Copy, Debug)]
+pub struct Node<T> {
+    parents: [(usize, T); 2],
+}
+
+/// Value types which have a thread-local tape
+pub trait TapeScalar: Scalar {
+    /// Run `f` on the tape of the current thread
+    fn with_tape<R>(f: impl FnOnce(&mut Vec<Node<Self>>) -> R) -> R;
+}
+
+macro_rules! tape {
+    ($t:ty, $tape:ident) => {
+        thread_local! {
+            static $tape: RefCell<Vec<Node<$t>>> = const { RefCell::new(Vec::new()) };
+        }
+
+        impl TapeScalar for $t {
+            fn with_tape<R>(f: impl FnOnce(&mut Vec<Node<Self>>) -> R) -> R {
+                $tape.with(|tape| f(&mut tape.borrow_mut()))
+            }
+        }
+    };
+}
+
+tape!(f64, TAPE_F64);
+tape!(Dual<f64>, TAPE_DUAL);
+
+/// Variable of reverse mode differentiation, see the
+/// [module documentation](self)
+#[derive(Clone, Copy, Debug)]
+pub struct Var<T = f64> {
+    value: T,
+    index: usize,
+}
+
+impl<T: TapeScalar> Var<T> {
+    /// Constant `value`, which is not recorded on the tape
+    pub fn real(value: T) -> Self {
+        Var {
+            value,
+            index: CONSTANT,
+        }
+    }
+
+    /// Value of the variable
+    pub fn value(self) -> T {
+        self.value
+    }
+
+    /// Value and gradient of `f` at `x`
+    ///
+    /// `f` is evaluated once on variables recorded on a fresh tape, then the
+    /// tape is swept backwards.
+    pub fn gradient<F>(x: &[T], f: F) -> Result<(T, Vec<T>), Error>
+    where
+        F: FnOnce(&[Var<T>]) -> Result<Var<T>, Error>,
+    {
+        let inputs: Vec<Var<T>> = T::with_tape(|tape| {
+            tape.clear();
+            x.iter()
+                .map(|&value| Var::record(tape, value, [(CONSTANT, T::zero()); 2]))
+                .collect()
+        });
+        let output = f(&inputs);
+        // Leave an empty tape behind, also on errors
+        let tape = T::with_tape(std::mem::take);
+        let output = output?;
+
+        let mut adjoints = vec![T::zero(); tape.len()];
+        if output.index != CONSTANT {
+            adjoints[output.index] = T::one();
+        }
+        for (i, node) in tape.iter().enumerate().rev() {
+            let adjoint = adjoints[i];
+            if adjoint.is_zero() {
+                continue;
+            }
+            for &(parent, partial) in &node.parents {
+                if parent != CONSTANT {
+                    adjoints[parent] = adjoints[parent] + adjoint * partial;
+                }
+            }
+        }
+        adjoints.truncate(x.len());
+        Ok((output.value, adjoints))
+    }
+
+    fn record(tape: &mut Vec<Node<T>>, value: T, parents: [(usize, T); 2]) -> Self {
+        tape.push(Node { parents });
+        Var {
+            value,
+            index: tape.len() - 1,
+        }
+    }
+
+    /// `f(self)` given `value = f(self.value)` and `derivative = f'(self.value)`
+    fn chain(self, value: T, derivative: T) -> Self {
+        if self.index == CONSTANT {
+            return Var::real(value);
+        }
+        let parents = [(self.index, derivative), (CONSTANT, T::zero())];
+        T::with_tape(|tape| Var::record(tape, value, parents))
+    }
+
+    /// `f(self, other)` given its value and partial derivatives
+    fn chain2(self, other: Self, value: T, d_self: T, d_other: T) -> Self {
+        if self.index == CONSTANT && other.index == CONSTANT {
+            return Var::real(value);
+        }
+        let parents = [(self.index, d_self), (other.index, d_other)];
+        T::with_tape(|tape| Var::record(tape, value, parents))
+    }
+}
+
+impl<T: TapeScalar> Add for Var<T> {
+    type Output = Var<T>;
+
+    fn add(self, other: Var<T>) -> Var<T> {
+        self.chain2(other, self.value + other.value, T::one(), T::one())
+    }
+}
+
+impl<T: TapeScalar> Sub for Var<T> {
+    type Output = Var<T>;
+
+    fn sub(self, other: Var<T>) -> Var<T> {
+        self.chain2(other, self.value - other.value, T::one(), -T::one())
+    }
+}
+
+impl<T: TapeScalar> Mul for Var<T> {
+    type Output = Var<T>;
+
+    fn mul(self, other: Var<T>) -> Var<T> {
+        self.chain2(other, self.value * other.value, other.value, self.value)
+    }
+}
+
+impl<T: TapeScalar> Div for Var<T> {
+    type Output = Var<T>;
+
+    fn div(self, other: Var<T>) -> Var<T> {
+        let value = self.value / other.value;
+        let d_self = other.value.recip();
+        self.chain2(other, value, d_self, -value * d_self)
+    }
+}
+
+impl<T: TapeScalar> Rem for Var<T> {
+    type Output = Var<T>;
+
+    /// `a % b = a - trunc(a / b) b`
+    fn rem(self, other: Var<T>) -> Var<T> {
+        let q = (self.value / other.value).trunc();
+        self.chain2(other, self.value % other.value, T::one(), -q)
+    }
+}
+
+impl<T: TapeScalar> Neg for Var<T> {
+    type Output = Var<T>;
+
+    fn neg(self) -> Var<T> {
+        self.chain(-self.value, -T::one())
+    }
+}
+
+impl<T: TapeScalar> AddAssign for Var<T> {
+    fn add_assign(&mut self, other: Var<T>) {
+        *self = *self + other;
+    }
+}
+
+impl<T: TapeScalar> SubAssign for Var<T> {
+    fn sub_assign(&mut self, other: Var<T>) {
+        *self = *self - other;
+    }
+}
+
+impl<T: TapeScalar> MulAssign for Var<T> {
+    fn mul_assign(&mut self, other: Var<T>) {
+        *self = *self * other;
+    }
+}
+
+impl<T: TapeScalar> DivAssign for Var<T> {
+    fn div_assign(&mut self, other: Var<T>) {
+        *self = *self / other;
+    }
+}
+
+impl<T: TapeScalar> PartialEq for Var<T> {
+    /// Compares the values
+    fn eq(&self, other: &Var<T>) -> bool {
+        self.value == other.value
+    }
+}
+
+impl<T: TapeScalar> PartialOrd for Var<T> {
+    /// Compares the values
+    fn partial_cmp(&self, other: &Var<T>) -> Option<Ordering> {
+        self.value.partial_cmp(&other.value)
+    }
+}
+
+impl<T: TapeScalar> Sum for Var<T> {
+    fn sum<I: Iterator<Item = Var<T>>>(iter: I) -> Var<T> {
+        iter.fold(Var::zero(), |acc, x| acc + x)
+    }
+}
+
+impl<T: TapeScalar> Zero for Var<T> {
+    fn zero() -> Self {
+        Var::real(T::zero())
+    }
+
+    fn is_zero(&self) -> bool {
+        self.value.is_zero()
+    }
+}
+
+impl<T: TapeScalar> One for Var<T> {
+    fn one() -> Self {
+        Var::real(T::one())
+    }
+}
+
+impl<T: TapeScalar> Num for Var<T> {
+    type FromStrRadixErr = T::FromStrRadixErr;
+
+    fn from_str_radix(s: &str, radix: u32) -> Result<Self, Self::FromStrRadixErr> {
+        T::from_str_radix(s, radix).map(Var::real)
+    }
+}
+
+impl<T: TapeScalar> ToPrimitive for Var<T> {
+    fn to_i64(&self) -> Option<i64> {
+        self.value.to_i64()
+    }
+
+    fn to_u64(&self) -> Option<u64> {
+        self.value.to_u64()
+    }
+
+    fn to_f64(&self) -> Option<f64> {
+        self.value.to_f64()
+    }
+}
+
+impl<T: TapeScalar> NumCast for Var<T> {
+    fn from<N: ToPrimitive>(n: N) -> Option<Self> {
+        T::from(n).map(Var::real)
+    }
+}
+
+impl<T: TapeScalar> FromPrimitive for Var<T> {
+    fn from_i64(n: i64) -> Option<Self> {
+        T::from_i64(n).map(Var::real)
+    }
+
+    fn from_u64(n: u64) -> Option<Self> {
+        T::from_u64(n).map(Var::real)
+    }
+
+    fn from_f64(n: f64) -> Option<Self> {
+        T::from_f64(n).map(Var::real)
+    }
+}
+
+impl<T: TapeScalar> Float for Var<T> {
+    fn nan() -> Self {
+        Var::real(T::nan())
+    }
+
+    fn infinity() -> Self {
+        Var::real(T::infinity())
+    }
+
+    fn neg_infinity() -> Self {
+        Var::real(T::neg_infinity())
+    }
+
+    fn neg_zero() -> Self {
+        Var::real(T::neg_zero())
+    }
+
+    fn min_value() -> Self {
+        Var::real(T::min_value())
+    }
+
+    fn min_positive_value() -> Self {
+        Var::real(T::min_positive_value())
+    }
+
+    fn epsilon() -> Self {
+        Var::real(T::epsilon())
+    }
+
+    fn max_value() -> Self {
+        Var::real(T::max_value())
+    }
+
+    fn is_nan(self) -> bool {
+        self.value.is_nan()
+    }
+
+    fn is_infinite(self) -> bool {
+        self.value.is_infinite()
+    }
+
+    fn is_finite(self) -> bool {
+        self.value.is_finite()
+    }
+
+    fn is_normal(self) -> bool {
+        self.value.is_normal()
+    }
+
+    fn classify(self) -> FpCategory {
+        self.value.classify()
+    }
+
+    fn floor(self) -> Self {
+        Var::real(self.value.floor())
+    }
+
+    fn ceil(self) -> Self {
+        Var::real(self.value.ceil())
+    }
+
+    fn round(self) -> Self {
+        Var::real(self.value.round())
+    }
+
+    fn trunc(self) -> Self {
+        Var::real(self.value.trunc())
+    }
+
+    fn fract(self) -> Self {
+        self.chain(self.value.fract(), T::one())
+    }
+
+    fn abs(self) -> Self {
+        if self.value.is_sign_negative() {
+            -self
+        } else {
+            self
+        }
+    }
+
+    fn signum(self) -> Self {
+        Var::real(self.value.signum())
+    }
+
+    fn is_sign_positive(self) -> bool {
+        self.value.is_sign_positive()
+    }
+
+    fn is_sign_negative(self) -> bool {
+        self.value.is_sign_negative()
+    }
+
+    fn mul_add(self, a: Self, b: Self) -> Self {
+        self * a + b
+    }
+
+    fn recip(self) -> Self {
+        let value = self.value.recip();
+        self.chain(value, -value * value)
+    }
+
+    fn powi(self, n: i32) -> Self {
+        let derivative = match n {
+            0 => T::zero(),
+            _ => T::constant(<f64 as From<i32>>::from(n)) * self.value.powi(n - 1),
+        };
+        self.chain(self.value.powi(n), derivative)
+    }
+
+    fn powf(self, n: Self) -> Self {
+        let value = self.value.powf(n.value);
+        // The logarithm is only needed (and only defined for positive bases)
+        // if the exponent is a variable
+        let d_n = if n.index == CONSTANT {
+            T::zero()
+        } else {
+            value * self.value.ln()
+        };
+        let d_self = n.value * self.value.powf(n.value - T::one());
+        self.chain2(n, value, d_self, d_n)
+    }
+
+    fn sqrt(self) -> Self {
+        let value = self.value.sqrt();
+        self.chain(value, (value + value).recip())
+    }
+
+    fn exp(self) -> Self {
+        let value = self.value.exp();
+        self.chain(value, value)
+    }
+
+    fn exp2(self) -> Self {
+        let value = self.value.exp2();
+        self.chain(value, value * T::constant(std::f64::consts::LN_2))
+    }
+
+    fn ln(self) -> Self {
+        self.chain(self.value.ln(), self.value.recip())
+    }
+
+    fn log(self, base: Self) -> Self {
+        self.ln() / base.ln()
+    }
+
+    fn log2(self) -> Self {
+        let derivative = (self.value * T::constant(std::f64::consts::LN_2)).recip();
+        self.chain(self.value.log2(), derivative)
+    }
+
+    fn log10(self) -> Self {
+        let derivative = (self.value * T::constant(std::f64::consts::LN_10)).recip();
+        self.chain(self.value.log10(), derivative)
+    }
+
+    fn to_degrees(self) -> Self {
+        let derivative = T::constant(180.0 / std::f64::consts::PI);
+        self.chain(self.value.to_degrees(), derivative)
+    }
+
+    fn to_radians(self) -> Self {
+        let derivative = T::constant(std::f64::consts::PI / 180.0);
+        self.chain(self.value.to_radians(), derivative)
+    }
+
+    fn max(self, other: Self) -> Self {
+        if other.value > self.value || self.value.is_nan() {
+            other
+        } else {
+            self
+        }
+    }
+
+    fn min(self, other: Self) -> Self {
+        if other.value < self.value || self.value.is_nan() {
+            other
+        } else {
+            self
+        }
+    }
+
+    fn abs_sub(self, other: Self) -> Self {
+        if self.value > other.value {
+            self - other
+        } else {
+            Var::zero()
+        }
+    }
+
+    fn cbrt(self) -> Self {
+        let value = self.value.cbrt();
+        self.chain(value, (T::constant(3.0) * value * value).recip())
+    }
+
+    fn hypot(self, other: Self) -> Self {
+        (self * self + other * other).sqrt()
+    }
+
+    fn sin(self) -> Self {
+        self.chain(self.value.sin(), self.value.cos())
+    }
+
+    fn cos(self) -> Self {
+        self.chain(self.value.cos(), -self.value.sin())
+    }
+
+    fn tan(self) -> Self {
+        let c = self.value.cos();
+        self.chain(self.value.tan(), (c * c).recip())
+    }
+
+    fn asin(self) -> Self {
+        let derivative = (T::one() - self.value * self.value).sqrt().recip();
+        self.chain(self.value.asin(), derivative)
+    }
+
+    fn acos(self) -> Self {
+        let derivative = -(T::one() - self.value * self.value).sqrt().recip();
+        self.chain(self.value.acos(), derivative)
+    }
+
+    fn atan(self) -> Self {
+        let derivative = (T::one() + self.value * self.value).recip();
+        self.chain(self.value.atan(), derivative)
+    }
+
+    fn atan2(self, other: Self) -> Self {
+        // d atan2(y, x) = (x dy - y dx) / (x^2 + y^2)
+        let (y, x) = (self.value, other.value);
+        let r2 = x * x + y * y;
+        self.chain2(other, y.atan2(x), x / r2, -y / r2)
+    }
+
+    fn sin_cos(self) -> (Self, Self) {
+        (self.sin(), self.cos())
+    }
+
+    fn exp_m1(self) -> Self {
+        self.chain(self.value.exp_m1(), self.value.exp())
+    }
+
+    fn ln_1p(self) -> Self {
+        self.chain(self.value.ln_1p(), (T::one() + self.value).recip())
+    }
+
+    fn sinh(self) -> Self {
+        self.chain(self.value.sinh(), self.value.cosh())
+    }
+
+    fn cosh(self) -> Self {
+        self.chain(self.value.cosh(), self.value.sinh())
+    }
+
+    fn tanh(self) -> Self {
+        let t = self.value.tanh();
+        self.chain(t, T::one() - t * t)
+    }
+
+    fn asinh(self) -> Self {
+        let derivative = (self.value * self.value + T::one()).sqrt().recip();
+        self.chain(self.value.asinh(), derivative)
+    }
+
+    fn acosh(self) -> Self {
+        let derivative = (self.value * self.value - T::one()).sqrt().recip();
+        self.chain(self.value.acosh(), derivative)
+    }
+
+    fn atanh(self) -> Self {
+        let derivative = (T::one() - self.value * self.value).recip();
+        self.chain(self.value.atanh(), derivative)
+    }
+
+    fn integer_decode(self) -> (u64, i16, i8) {
+        self.value.integer_decode()
+    }
+}
@@ -0,0 +1,519 @@
+//! Derivative free solvers
+//!
+//! Nelder-Mead, particle swarm optimization and simulated annealing only
+//! evaluate the cost function, so they can be used on problems without a
+//! gradient. Each of them needs more than an initial parameter vector to get
+//! started: Nelder-Mead an initial simplex, particle swarm optimization an
+//! initial swarm and simulated annealing a way to draw neighbouring points
+//! and a temperature schedule. The options and helpers in this module provide
+//! these.
+
+use crate::backend::Vector;
+use crate::problem::DynProblem;
+use argmin::core::{ArgminError, CostFunction, Error};
+use argmin::solver::particleswarm::Particle;
+use argmin::solver::simulatedannealing::{Anneal, SATempFunc};
+use rand::{Rng, SeedableRng};
+use rand_xoshiro::Xoshiro256PlusPlus;
+use serde::{Deserialize, Serialize};
+use std::marker::PhantomData;
+use std::sync::Mutex;
+
+/// Random number generator used by the stochastic solvers
+pub type SolverRng = Xoshiro256PlusPlus;
+
+/// Random number generator seeded with `seed`, or from entropy if `None`
+pub fn rng(seed: Option<u64>) -> SolverRng {
+    match seed {
+        Some(seed) => SolverRng::seed_from_u64(seed),
+        None => SolverRng::from_entropy(),
+    }
+}
+
+fn invalid(text: String) -> Error {
+    ArgminError::InvalidParameter { text }.into()
+}
+
+/// Construction of the initial Nelder-Mead simplex around the starting point
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum SimplexInit {
+    /// Move each coordinate by 5% of its value (0.00025 if it is zero), as
+    /// done by MATLAB's `fminsearch`
+    Pfeffer,
+    /// Move each coordinate by a fixed step
+    Axis,
+    /// Regular simplex with a fixed edge length
+    Regular,
+}
+
+impl SimplexInit {
+    /// All strategies, in the order in which they are listed
+    pub const ALL: &'static [SimplexInit] =
+        &[SimplexInit::Pfeffer, SimplexInit::Axis, SimplexInit::Regular];
+
+    /// Name used to select the strategy
+    pub fn name(self) -> &'static str {
+        match self {
+            SimplexInit::Pfeffer => "pfeffer",
+            SimplexInit::Axis => "axis",
+            SimplexInit::Regular => "regular",
+        }
+    }
+
+    /// Look up a strategy by its name
+    pub fn from_name(name: &str) -> Option<SimplexInit> {
+        SimplexInit::ALL.iter().copied().find(|s| s.name() == name)
+    }
+}
+
+/// Options of the Nelder-Mead method
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct NelderMeadOptions {
+    /// Construction of the initial simplex
+    pub init: SimplexInit,
+    /// Step (`axis`) or edge length (`regular`) of the initial simplex
+    pub size: f64,
+    /// Stop when the standard deviation of the cost function values at the
+    /// vertices falls below this value
+    pub sd_tolerance: Option<f64>,
+}
+
+impl Default for NelderMeadOptions {
+    fn default() -> Self {
+        NelderMeadOptions {
+            init: SimplexInit::Pfeffer,
+            size: 1.0,
+            sd_tolerance: None,
+        }
+    }
+}
+
+impl NelderMeadOptions {
+    /// Vertices of the initial simplex around `x0`, starting with `x0` itself
+    pub fn simplex(&self, x0: &[f64]) -> Result<Vec<Vec<f64>>, Error> {
+        if self.init != SimplexInit::Pfeffer && !(self.size.is_finite() && self.size > 0.0) {
+            return Err(invalid("Nelder-Mead: simplex size must be positive".to_string()));
+        }
+        let n = x0.len();
+        let mut simplex = vec![x0.to_vec()];
+        match self.init {
+            SimplexInit::Pfeffer => {
+                for i in 0..n {
+                    let mut vertex = x0.to_vec();
+                    vertex[i] += if x0[i] != 0.0 { 0.05 * x0[i] } else { 0.00025 };
+                    simplex.push(vertex);
+                }
+            }
+            SimplexInit::Axis => {
+                for i in 0..n {
+                    let mut vertex = x0.to_vec();
+                    vertex[i] += self.size;
+                    simplex.push(vertex);
+                }
+            }
+            SimplexInit::Regular => {
+                // Spendley, Hext and Himsworth: all edges have length `size`
+                let nf = n as f64;
+                let scale = self.size / (nf * 2f64.sqrt());
+                let p = scale * ((nf + 1.0).sqrt() + nf - 1.0);
+                let q = scale * ((nf + 1.0).sqrt() - 1.0);
+                for i in 0..n {
+                    let vertex = x0
+                        .iter()
+                        .enumerate()
+                        .map(|(j, x)| if i == j { x + p } else { x + q })
+                        .collect();
+                    simplex.push(vertex);
+                }
+            }
+        }
+        Ok(simplex)
+    }
+}
+
+/// Placement of the initial particles of a swarm
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum SwarmInit {
+    /// Uniformly distributed in the bounds of the problem
+    Uniform,
+    /// Uniformly distributed in a box around the starting point (clipped to
+    /// the bounds of the problem); one particle starts at the starting point
+    AroundStart,
+}
+
+impl SwarmInit {
+    /// All strategies, in the order in which they are listed
+    pub const ALL: &'static [SwarmInit] = &[SwarmInit::Uniform, SwarmInit::AroundStart];
+
+    /// Name used to select the strategy
+    pub fn name(self) -> &'static str {
+        match self {
+            SwarmInit::Uniform => "uniform",
+            SwarmInit::AroundStart => "around-start",
+        }
+    }
+
+    /// Look up a strategy by its name
+    pub fn from_name(name: &str) -> Option<SwarmInit> {
+        SwarmInit::ALL.iter().copied().find(|s| s.name() == name)
+    }
+}
+
+/// Options of particle swarm optimization
+///
+/// The weights which are `None` keep argmin's defaults.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SwarmOptions {
+    /// Number of particles
+    pub particles: usize,
+    /// Placement of the initial particles
+    pub init: SwarmInit,
+    /// Half width of the box around the starting point (`around-start`)
+    pub spread: f64,
+    /// Weight of a particle's velocity
+    pub inertia: Option<f64>,
+    /// Attraction to the best position of the particle itself
+    pub cognitive: Option<f64>,
+    /// Attraction to the best position of the swarm
+    pub social: Option<f64>,
+}
+
+impl Default for SwarmOptions {
+    fn default() -> Self {
+        SwarmOptions {
+            particles: 40,
+            init: SwarmInit::Uniform,
+            spread: 1.0,
+            inertia: None,
+            cognitive: None,
+            social: None,
+        }
+    }
+}
+
+impl SwarmOptions {
+    /// Initial swarm for `problem`
+    ///
+    /// Velocities are drawn uniformly from `[-(u - l), u - l]` per coordinate,
+    /// as argmin does for the swarms it creates itself. The cost function is
+    /// evaluated at every initial position.
+    pub fn swarm<P: Vector>(
+        &self,
+        problem: &DynProblem,
+        x0: &[f64],
+        rng: &mut SolverRng,
+    ) -> Result<Vec<Particle<P, f64>>, Error> {
+        if self.particles == 0 {
+            return Err(invalid("Particle swarm: at least one particle is needed".to_string()));
+        }
+        if self.init == SwarmInit::AroundStart && !(self.spread.is_finite() && self.spread > 0.0) {
+            return Err(invalid("Particle swarm: spread must be positive".to_string()));
+        }
+        let bounds = &problem.info().bounds;
+        (0..self.particles)
+            .map(|k| {
+                let position: Vec<f64> = match self.init {
+                    SwarmInit::Uniform => bounds.iter().map(|&(l, u)| rng.gen_range(l..=u)).collect(),
+                    SwarmInit::AroundStart if k == 0 => x0.to_vec(),
+                    SwarmInit::AroundStart => x0
+                        .iter()
+                        .zip(bounds)
+                        .map(|(x, &(l, u))| {
+                            (x + rng.gen_range(-self.spread..=self.spread)).clamp(l, u)
+                        })
+                        .collect(),
+                };
+                let velocity: Vec<f64> = bounds
+                    .iter()
+                    .map(|&(l, u)| {
+                        let width = (u - l).abs();
+                        rng.gen_range(-width..=width)
+                    })
+                    .collect();
+                let cost = problem.cost(&position)?;
+                Ok(Particle::new(P::from_vec(position), cost, P::from_vec(velocity)))
+            })
+            .collect()
+    }
+}
+
+/// Temperature schedules of simulated annealing
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum TemperatureSchedule {
+    /// `t_i = t_0 / i`
+    Fast,
+    /// `t_i = t_0 / ln(i)`
+    Boltzmann,
+    /// `t_i = t_0 * decay^i`
+    Exponential,
+}
+
+impl TemperatureSchedule {
+    /// All schedules, in the order in which they are listed
+    pub const ALL: &'static [TemperatureSchedule] = &[
+        TemperatureSchedule::Fast,
+        TemperatureSchedule::Boltzmann,
+        TemperatureSchedule::Exponential,
+    ];
+
+    /// Name used to select the schedule
+    pub fn name(self) -> &'static str {
+        match self {
+            TemperatureSchedule::Fast => "fast",
+            TemperatureSchedule::Boltzmann => "boltzmann",
+            TemperatureSchedule::Exponential => "exponential",
+        }
+    }
+
+    /// Look up a schedule by its name
+    pub fn from_name(name: &str) -> Option<TemperatureSchedule> {
+        TemperatureSchedule::ALL.iter().copied().find(|s| s.name() == name)
+    }
+}
+
+/// Options of simulated annealing
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AnnealingOptions {
+    /// Initial temperature
+    pub temperature: f64,
+    /// Temperature schedule
+    pub schedule: TemperatureSchedule,
+    /// Factor of the `exponential` schedule, in `(0, 1)`
+    pub decay: f64,
+    /// Half width of the box from which neighbours are drawn at the initial
+    /// temperature; it shrinks proportionally to the temperature
+    pub step: f64,
+    /// Stop if the best point has not improved for this many iterations
+    pub stall_best: Option<u64>,
+    /// Stop if no point has been accepted for this many iterations
+    pub stall_accepted: Option<u64>,
+    /// Reanneal every this many iterations
+    pub reanneal_fixed: Option<u64>,
+    /// Reanneal if no point has been accepted for this many iterations
+    pub reanneal_accepted: Option<u64>,
+    /// Reanneal if the best point has not improved for this many iterations
+    pub reanneal_best: Option<u64>,
+}
+
+impl Default for AnnealingOptions {
+    fn default() -> Self {
+        AnnealingOptions {
+            temperature: 15.0,
+            schedule: TemperatureSchedule::Boltzmann,
+            decay: 0.95,
+            step: 0.5,
+            stall_best: None,
+            stall_accepted: None,
+            reanneal_fixed: None,
+            reanneal_accepted: None,
+            reanneal_best: None,
+        }
+    }
+}
+
+impl AnnealingOptions {
+    /// Temperature schedule in argmin's representation
+    pub fn temp_func(&self) -> Result<SATempFunc<f64>, Error> {
+        Ok(match self.schedule {
+            TemperatureSchedule::Fast => SATempFunc::TemperatureFast,
+            TemperatureSchedule::Boltzmann => SATempFunc::Boltzmann,
+            TemperatureSchedule::Exponential => {
+                if !(self.decay > 0.0 && self.decay < 1.0) {
+                    return Err(invalid(
+                        "Simulated annealing: decay must be in (0, 1)".to_string(),
+                    ));
+                }
+                SATempFunc::Exponential(self.decay)
+            }
+        })
+    }
+}
+
+/// A problem together with the neighbourhood used by simulated annealing
+///
+/// argmin's simulated annealing asks the problem (via the `Anneal` trait) for
+/// a random neighbour of the current point. Neighbours are drawn uniformly
+/// from a box around the point, whose half width is `step` at the initial
+/// temperature and shrinks with the temperature. On bounded problems they
+/// are clipped to the box constraints; otherwise they may leave the search
+/// domain of the problem.
+pub struct Annealing<P = Vec<f64>> {
+    problem: DynProblem,
+    step: f64,
+    temperature: f64,
+    rng: Mutex<SolverRng>,
+    param: PhantomData<fn() -> P>,
+}
+
+impl<P> Annealing<P> {
+    /// Wrap `problem` with the neighbourhood described by `options`
+    pub fn new(problem: DynProblem, options: &AnnealingOptions, rng: SolverRng) -> Result<Self, Error> {
+        if !(options.step.is_finite() && options.step > 0.0) {
+            return Err(invalid("Simulated annealing: step must be positive".to_string()));
+        }
+        if !(options.temperature.is_finite() && options.temperature > 0.0) {
+            return Err(invalid(
+                "Simulated annealing: temperature must be positive".to_string(),
+            ));
+        }
+        Ok(Annealing {
+            problem,
+            step: options.step,
+            temperature: options.temperature,
+            rng: Mutex::new(rng),
+            param: PhantomData,
+        })
+    }
+
+    /// The wrapped problem
+    pub fn problem(&self) -> &DynProblem {
+        &self.problem
+    }
+}
+
+impl<P: Vector> CostFunction for Annealing<P> {
+    type Param = P;
+    type Output = f64;
+
+    fn cost(&self, p: &Self::Param) -> Result<Self::Output, Error> {
+        self.problem.cost(&p.to_vec())
+    }
+}
+
+impl<P: Vector> Anneal for Annealing<P> {
+    type Param = P;
+    type Output = P;
+    type Float = f64;
+
+    /// Draw a neighbour of `param`; `temp` is the current temperature
+    fn anneal(&self, param: &Self::Param, temp: Self::Float) -> Result<Self::Output, Error> {
+        let width = self.step * (temp / self.temperature).min(1.0);
+        let mut rng = self.rng.lock().unwrap();
+        let neighbour: Vec<f64> = param
+            .to_vec()
+            .iter()
+            .map(|x| x + width * rng.gen_range(-1.0..=1.0))
+            .collect();
+        Ok(P::from_vec(self.problem.info().project(&neighbour)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::problem::{self, Rosenbrock};
+    use crate::report::Report;
+    use crate::solver::{solve, Method, SolverOptions};
+    use crate::{RunConfig, StopCriteria};
+
+    fn solve_from_default_start(problem: DynProblem, options: &SolverOptions) -> Report {
+        let config = RunConfig {
+            init_param: problem.info().default_start.clone(),
+            ..RunConfig::default()
+        };
+        solve(problem, options, &config).unwrap()
+    }
+
+    fn seeded(method: Method) -> SolverOptions {
+        SolverOptions {
+            seed: Some(1),
+            ..SolverOptions::new(method)
+        }
+    }
+
+    #[test]
+    fn regular_simplex_has_equal_edges() {
+        let options = NelderMeadOptions {
+            init: SimplexInit::Regular,
+            size: 0.5,
+            ..NelderMeadOptions::default()
+        };
+        let simplex = options.simplex(&[1.0, -2.0, 3.0]).unwrap();
+        assert_eq!(simplex.len(), 4);
+        for (i, a) in simplex.iter().enumerate() {
+            for b in &simplex[i + 1..] {
+                let edge = a
+                    .iter()
+                    .zip(b)
+                    .map(|(x, y)| (x - y).powi(2))
+                    .sum::<f64>()
+                    .sqrt();
+                assert!((edge - 0.5).abs() < 1e-12, "edge {}", edge);
+            }
+        }
+    }
+
+    #[test]
+    fn simplex_size_must_be_positive() {
+        let options = NelderMeadOptions {
+            init: SimplexInit::Axis,
+            size: 0.0,
+            ..NelderMeadOptions::default()
+        };
+        assert!(options.simplex(&[1.0, 2.0]).is_err());
+    }
+
+    #[test]
+    fn nelder_mead_converges() {
+        let report = solve_from_default_start(
+            Rosenbrock::default().into_dyn(),
+            &seeded(Method::NelderMead),
+        );
+        assert!(report.cost < 1e-8, "cost {}", report.cost);
+    }
+
+    #[test]
+    fn particle_swarm_finds_the_minimum() {
+        let sphere = problem::build("sphere", Some(2)).unwrap();
+        let report = solve_from_default_start(sphere, &seeded(Method::ParticleSwarm));
+        assert!(report.cost < 1e-6, "cost {}", report.cost);
+    }
+
+    #[test]
+    fn seeded_annealing_is_reproducible() {
+        let options = seeded(Method::SimulatedAnnealing);
+        let first = solve_from_default_start(Rosenbrock::default().into_dyn(), &options);
+        let second = solve_from_default_start(Rosenbrock::default().into_dyn(), &options);
+        assert_eq!(first.param, second.param);
+        assert!(first.cost < 0.1, "cost {}", first.cost);
+    }
+
+    #[test]
+    fn annealing_leaves_the_search_domain_of_unbounded_problems() {
+        // The minimum (5, 25) lies outside the search domain [-5, 10]^2
+        let rosenbrock = Rosenbrock { a: 5.0, b: 1.0 };
+        assert!(!rosenbrock.info().bounded);
+        let config = RunConfig {
+            init_param: rosenbrock.info().default_start,
+            stop: StopCriteria {
+                max_iters: 100_000,
+                ..StopCriteria::default()
+            },
+            ..RunConfig::default()
+        };
+        let options = seeded(Method::SimulatedAnnealing);
+        let report = solve(rosenbrock.into_dyn(), &options, &config).unwrap();
+        assert!((report.param[0] - 5.0).abs() < 0.05, "{:?}", report.param);
+        assert!((report.param[1] - 25.0).abs() < 0.5, "{:?}", report.param);
+        assert!(report.cost < 1e-3, "cost {}", report.cost);
+    }
+
+    #[test]
+    fn annealing_stays_within_box_constraints() {
+        let config: problem::ProblemConfig = serde_json::from_value(serde_json::json!({
+            "name": "rosenbrock",
+            "parameters": { "a": 5.0, "b": 1.0 },
+            "upper": [10.0],
+        }))
+        .unwrap();
+        let problem = config.build().unwrap();
+        let report = solve_from_default_start(problem, &seeded(Method::SimulatedAnnealing));
+        assert!(report.param.iter().all(|&x| x <= 10.0), "{:?}", report.param);
+    }
+}
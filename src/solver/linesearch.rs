@@ -0,0 +1,335 @@
+//! Line searches which can be selected at runtime
+//!
+//! argmin's line search solvers are distinct types, while the solvers using
+//! them are generic over the line search. [`DynLineSearch`] wraps the
+//! supported line searches in a single type which implements both
+//! `LineSearch` and `Solver` by delegating to the selected variant.
+//!
+//! The solvers run their line search without an iteration limit, and
+//! argmin's line searches never give up on a search direction which is not
+//! a descent direction (for instance a NaN direction of nonlinear CG at the
+//! minimum). [`DynLineSearch`] therefore stops after
+//! [`MAX_LINE_SEARCH_ITERS`] iterations, so that such a run ends instead of
+//! hanging. At a stationary point, where there is no descent direction,
+//! it stays at the current parameter vector instead of failing.
+
+use argmin::core::{
+    ArgminError, Error, IterState, Problem, Solver, State, TerminationReason, KV,
+};
+use argmin::solver::linesearch::condition::{ArmijoCondition, StrongWolfeCondition, WolfeCondition};
+use argmin_math::{ArgminDot, ArgminScaledAdd};
+use argmin::solver::linesearch::{
+    BacktrackingLineSearch, HagerZhangLineSearch, LineSearch, MoreThuenteLineSearch,
+};
+use serde::{Deserialize, Serialize};
+
+/// Iterations after which a line search gives up
+pub const MAX_LINE_SEARCH_ITERS: u64 = 100;
+
+/// State on which the line searches operate
+pub type LineSearchState<P> = IterState<P, P, (), (), f64>;
+
+/// Line searches which can be selected by name
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum LineSearchMethod {
+    MoreThuente,
+    HagerZhang,
+    BacktrackingArmijo,
+    BacktrackingWolfe,
+    BacktrackingStrongWolfe,
+}
+
+impl LineSearchMethod {
+    /// All line searches, in the order in which they are listed
+    pub const ALL: &'static [LineSearchMethod] = &[
+        LineSearchMethod::MoreThuente,
+        LineSearchMethod::HagerZhang,
+        LineSearchMethod::BacktrackingArmijo,
+        LineSearchMethod::BacktrackingWolfe,
+        LineSearchMethod::BacktrackingStrongWolfe,
+    ];
+
+    /// Name used to select the line search
+    pub fn name(self) -> &'static str {
+        match self {
+            LineSearchMethod::MoreThuente => "more-thuente",
+            LineSearchMethod::HagerZhang => "hager-zhang",
+            LineSearchMethod::BacktrackingArmijo => "backtracking-armijo",
+            LineSearchMethod::BacktrackingWolfe => "backtracking-wolfe",
+            LineSearchMethod::BacktrackingStrongWolfe => "backtracking-strong-wolfe",
+        }
+    }
+
+    /// One line description
+    pub fn description(self) -> &'static str {
+        match self {
+            LineSearchMethod::MoreThuente => "More-Thuente, strong Wolfe conditions (c1, c2)",
+            LineSearchMethod::HagerZhang => "Hager-Zhang, approximate Wolfe conditions (c1 = delta, c2 = sigma)",
+            LineSearchMethod::BacktrackingArmijo => "Backtracking, Armijo condition (c1)",
+            LineSearchMethod::BacktrackingWolfe => "Backtracking, Wolfe conditions (c1, c2)",
+            LineSearchMethod::BacktrackingStrongWolfe => "Backtracking, strong Wolfe conditions (c1, c2)",
+        }
+    }
+
+    /// Look up a line search by its name
+    pub fn from_name(name: &str) -> Option<LineSearchMethod> {
+        LineSearchMethod::ALL.iter().copied().find(|m| m.name() == name)
+    }
+}
+
+/// A line search together with its tuning parameters
+///
+/// Parameters which are `None` keep argmin's defaults. Not every parameter
+/// applies to every line search: the contraction factor is only used by the
+/// backtracking variants and the step bounds only by More-Thuente and
+/// Hager-Zhang; they are ignored otherwise.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LineSearchOptions {
+    /// Line search to use
+    pub method: LineSearchMethod,
+    /// Sufficient decrease constant
+    pub c1: Option<f64>,
+    /// Curvature constant
+    pub c2: Option<f64>,
+    /// First step length tried in each line search
+    pub initial_step: Option<f64>,
+    /// Smallest and largest allowed step length
+    pub step_bounds: Option<(f64, f64)>,
+    /// Factor by which backtracking shrinks the step length, in `(0, 1)`
+    pub contraction: Option<f64>,
+}
+
+impl Default for LineSearchOptions {
+    fn default() -> Self {
+        LineSearchOptions::new(LineSearchMethod::MoreThuente)
+    }
+}
+
+impl LineSearchOptions {
+    /// Default options for `method`
+    pub fn new(method: LineSearchMethod) -> Self {
+        LineSearchOptions {
+            method,
+            c1: None,
+            c2: None,
+            initial_step: None,
+            step_bounds: None,
+            contraction: None,
+        }
+    }
+
+    /// Build the line search
+    pub fn build<P>(&self) -> Result<DynLineSearch<P>, Error>
+    where
+        P: ArgminScaledAdd<P, f64, P> + ArgminDot<P, f64>,
+    {
+        let c1 = self.c1.unwrap_or(1e-4);
+        let c2 = self.c2.unwrap_or(0.9);
+        let mut linesearch = match self.method {
+            LineSearchMethod::MoreThuente => {
+                let mut ls = MoreThuenteLineSearch::new();
+                if self.c1.is_some() || self.c2.is_some() {
+                    ls = ls.with_c(c1, c2)?;
+                }
+                if let Some((min, max)) = self.step_bounds {
+                    ls = ls.with_bounds(min, max)?;
+                }
+                DynLineSearch::MoreThuente(ls)
+            }
+            LineSearchMethod::HagerZhang => {
+                let mut ls = HagerZhangLineSearch::new();
+                if self.c1.is_some() || self.c2.is_some() {
+                    ls = ls.with_delta_sigma(self.c1.unwrap_or(0.1), c2)?;
+                }
+                if let Some((min, max)) = self.step_bounds {
+                    ls = ls.with_bounds(min, max)?;
+                }
+                DynLineSearch::HagerZhang(ls)
+            }
+            LineSearchMethod::BacktrackingArmijo => {
+                let ls = BacktrackingLineSearch::new(ArmijoCondition::new(c1)?);
+                DynLineSearch::BacktrackingArmijo(self.contract(ls)?)
+            }
+            LineSearchMethod::BacktrackingWolfe => {
+                let ls = BacktrackingLineSearch::new(WolfeCondition::new(c1, c2)?);
+                DynLineSearch::BacktrackingWolfe(self.contract(ls)?)
+            }
+            LineSearchMethod::BacktrackingStrongWolfe => {
+                let ls = BacktrackingLineSearch::new(StrongWolfeCondition::new(c1, c2)?);
+                DynLineSearch::BacktrackingStrongWolfe(self.contract(ls)?)
+            }
+        };
+        if let Some(alpha) = self.initial_step {
+            if alpha <= 0.0 {
+                return Err(ArgminError::InvalidParameter {
+                    text: "line search: initial step length must be positive".to_string(),
+                }
+                .into());
+            }
+            linesearch.initial_step_length(alpha)?;
+        }
+        Ok(linesearch)
+    }
+
+    /// Apply the contraction factor to a backtracking line search
+    fn contract<P, L>(
+        &self,
+        ls: BacktrackingLineSearch<P, P, L, f64>,
+    ) -> Result<BacktrackingLineSearch<P, P, L, f64>, Error> {
+        match self.contraction {
+            Some(rho) => ls.rho(rho),
+            None => Ok(ls),
+        }
+    }
+}
+
+/// One of the supported line searches, selected at runtime
+#[derive(Clone, Serialize, Deserialize)]
+pub enum DynLineSearch<P> {
+    MoreThuente(MoreThuenteLineSearch<P, P, f64>),
+    HagerZhang(HagerZhangLineSearch<P, P, f64>),
+    BacktrackingArmijo(BacktrackingLineSearch<P, P, ArmijoCondition<f64>, f64>),
+    BacktrackingWolfe(BacktrackingLineSearch<P, P, WolfeCondition<f64>, f64>),
+    BacktrackingStrongWolfe(BacktrackingLineSearch<P, P, StrongWolfeCondition<f64>, f64>),
+}
+
+impl<P> LineSearch<P, f64> for DynLineSearch<P> {
+    fn search_direction(&mut self, direction: P) {
+        match self {
+            DynLineSearch::MoreThuente(ls) => ls.search_direction(direction),
+            DynLineSearch::HagerZhang(ls) => ls.search_direction(direction),
+            DynLineSearch::BacktrackingArmijo(ls) => ls.search_direction(direction),
+            DynLineSearch::BacktrackingWolfe(ls) => ls.search_direction(direction),
+            DynLineSearch::BacktrackingStrongWolfe(ls) => ls.search_direction(direction),
+        }
+    }
+
+    fn initial_step_length(&mut self, step_length: f64) -> Result<(), Error> {
+        match self {
+            DynLineSearch::MoreThuente(ls) => ls.initial_step_length(step_length),
+            DynLineSearch::HagerZhang(ls) => ls.initial_step_length(step_length),
+            DynLineSearch::BacktrackingArmijo(ls) => ls.initial_step_length(step_length),
+            DynLineSearch::BacktrackingWolfe(ls) => ls.initial_step_length(step_length),
+            DynLineSearch::BacktrackingStrongWolfe(ls) => ls.initial_step_length(step_length),
+        }
+    }
+}
+
+impl<O, P> Solver<O, LineSearchState<P>> for DynLineSearch<P>
+where
+    P: Clone + ArgminDot<P, f64>,
+    MoreThuenteLineSearch<P, P, f64>: Solver<O, LineSearchState<P>>,
+    HagerZhangLineSearch<P, P, f64>: Solver<O, LineSearchState<P>>,
+    BacktrackingLineSearch<P, P, ArmijoCondition<f64>, f64>: Solver<O, LineSearchState<P>>,
+    BacktrackingLineSearch<P, P, WolfeCondition<f64>, f64>: Solver<O, LineSearchState<P>>,
+    BacktrackingLineSearch<P, P, StrongWolfeCondition<f64>, f64>: Solver<O, LineSearchState<P>>,
+    Self: Serialize,
+{
+    const NAME: &'static str = "Line search";
+
+    fn init(
+        &mut self,
+        problem: &mut Problem<O>,
+        state: LineSearchState<P>,
+    ) -> Result<(LineSearchState<P>, Option<KV>), Error> {
+        // More-Thuente and Hager-Zhang reject the zero search direction
+        if state.get_gradient().is_some_and(|g| g.dot(g) == 0.0) {
+            return Ok((state.terminate_with(TerminationReason::TargetPrecisionReached), None));
+        }
+        match self {
+            DynLineSearch::MoreThuente(ls) => ls.init(problem, state),
+            DynLineSearch::HagerZhang(ls) => ls.init(problem, state),
+            DynLineSearch::BacktrackingArmijo(ls) => ls.init(problem, state),
+            DynLineSearch::BacktrackingWolfe(ls) => ls.init(problem, state),
+            DynLineSearch::BacktrackingStrongWolfe(ls) => ls.init(problem, state),
+        }
+    }
+
+    fn next_iter(
+        &mut self,
+        problem: &mut Problem<O>,
+        state: LineSearchState<P>,
+    ) -> Result<(LineSearchState<P>, Option<KV>), Error> {
+        match self {
+            DynLineSearch::MoreThuente(ls) => ls.next_iter(problem, state),
+            DynLineSearch::HagerZhang(ls) => ls.next_iter(problem, state),
+            DynLineSearch::BacktrackingArmijo(ls) => ls.next_iter(problem, state),
+            DynLineSearch::BacktrackingWolfe(ls) => ls.next_iter(problem, state),
+            DynLineSearch::BacktrackingStrongWolfe(ls) => ls.next_iter(problem, state),
+        }
+    }
+
+    fn terminate(&mut self, state: &LineSearchState<P>) -> TerminationReason {
+        let reason = match self {
+            DynLineSearch::MoreThuente(ls) => ls.terminate(state),
+            DynLineSearch::HagerZhang(ls) => ls.terminate(state),
+            DynLineSearch::BacktrackingArmijo(ls) => ls.terminate(state),
+            DynLineSearch::BacktrackingWolfe(ls) => ls.terminate(state),
+            DynLineSearch::BacktrackingStrongWolfe(ls) => ls.terminate(state),
+        };
+        if !reason.terminated() && state.get_iter() >= MAX_LINE_SEARCH_ITERS {
+            return TerminationReason::MaxItersReached;
+        }
+        reason
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::problem::Rosenbrock;
+    use crate::solver::{solve, Method, SolverOptions};
+    use crate::RunConfig;
+
+    #[test]
+    fn names_round_trip() {
+        for &method in LineSearchMethod::ALL {
+            assert_eq!(LineSearchMethod::from_name(method.name()), Some(method));
+        }
+        assert_eq!(LineSearchMethod::from_name("golden-section"), None);
+    }
+
+    #[test]
+    fn invalid_parameters_are_rejected() {
+        let invalid = [
+            LineSearchOptions {
+                c1: Some(0.95),
+                ..LineSearchOptions::new(LineSearchMethod::MoreThuente)
+            },
+            LineSearchOptions {
+                step_bounds: Some((1.0, 0.5)),
+                ..LineSearchOptions::new(LineSearchMethod::HagerZhang)
+            },
+            LineSearchOptions {
+                contraction: Some(1.5),
+                ..LineSearchOptions::new(LineSearchMethod::BacktrackingArmijo)
+            },
+            LineSearchOptions {
+                initial_step: Some(-1.0),
+                ..LineSearchOptions::new(LineSearchMethod::BacktrackingWolfe)
+            },
+        ];
+        for options in invalid {
+            assert!(options.build::<Vec<f64>>().is_err(), "{:?}", options);
+        }
+    }
+
+    #[test]
+    fn every_line_search_converges_with_lbfgs() {
+        let problem = Rosenbrock::default().into_dyn();
+        let config = RunConfig {
+            init_param: problem.info().default_start.clone(),
+            ..RunConfig::default()
+        };
+        for &method in LineSearchMethod::ALL {
+            let options = SolverOptions {
+                linesearch: LineSearchOptions::new(method),
+                ..SolverOptions::new(Method::Lbfgs)
+            };
+            let report = solve(problem.clone(), &options, &config).unwrap();
+            assert!(report.cost < 1e-6, "{}: cost {}", method.name(), report.cost);
+        }
+    }
+}
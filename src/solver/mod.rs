@@ -0,0 +1,711 @@
+//! Construction of argmin solvers
+//!
+//! The functions in this module hide the (rather verbose) type annotations
+//! needed to build a solver for problems with `Vec<f64>` parameters, or with
+//! the parameter types of the other [backends](crate::backend).
+//!
+//! [`Method`] enumerates the solvers which can be selected by name and
+//! [`solve`] runs the selected one on a [`DynProblem`]. Since the solvers
+//! differ in the state they operate on (quasi-Newton methods keep an
+//! approximation of the (inverse) Hessian, steepest descent does not, and
+//! particle swarm optimization works on a `PopulationState`), the outcome is
+//! summarized in a [`Report`] rather than argmin's `OptimizationResult`.
+//!
+//! Bounded problems ([`ProblemInfo::constraints`]) can only be solved by the
+//! methods which keep their iterates in the box, see
+//! [`Method::respects_bounds`]: the projected gradient methods of
+//! [`projected`] and the derivative free methods which sample in the box.
+
+pub mod derivative_free;
+pub mod linesearch;
+pub mod newton;
+pub mod projected;
+
+use crate::backend::{Backend, HessianProduct, OnBackend, Vector};
+use crate::problem::{DynProblem, ProblemInfo, Tridiagonal};
+use crate::report::Report;
+use crate::stopping::Stopping;
+use crate::{run_derivative_free, run_swarm, run_with, GradientState, RunConfig};
+use argmin::core::{ArgminError, Error, OptimizationResult, State};
+use argmin::solver::conjugategradient::beta::{
+    FletcherReeves, HestenesStiefel, PolakRibiere, PolakRibierePlus,
+};
+use argmin::solver::conjugategradient::NonlinearConjugateGradient;
+use argmin::solver::gradientdescent::SteepestDescent;
+use argmin::solver::linesearch::MoreThuenteLineSearch;
+use argmin::solver::neldermead::NelderMead;
+use argmin::solver::newton::NewtonCG;
+use argmin::solver::particleswarm::ParticleSwarm;
+use argmin::solver::quasinewton::{SR1TrustRegion, BFGS, DFP, LBFGS};
+use argmin::solver::simulatedannealing::SimulatedAnnealing;
+use argmin::solver::trustregion::Steihaug;
+use argmin_math::{ArgminDot, ArgminEye, ArgminScaledAdd};
+use derivative_free::Annealing;
+use nalgebra::{DMatrix, DVector};
+use ndarray::{Array1, Array2};
+use serde::{Deserialize, Serialize};
+
+pub use derivative_free::{
+    AnnealingOptions, NelderMeadOptions, SimplexInit, SwarmInit, SwarmOptions,
+    TemperatureSchedule,
+};
+pub use linesearch::{DynLineSearch, LineSearchMethod, LineSearchOptions};
+pub use newton::Newton;
+pub use projected::{Backtracking, Lbfgsb, ProjectedGradient};
+
+/// More-Thuente line search on `Vec<f64>` parameters
+pub type MoreThuente = MoreThuenteLineSearch<Vec<f64>, Vec<f64>, f64>;
+
+/// Steepest descent with a More-Thuente line search using default constants
+pub fn steepest_descent() -> SteepestDescent<MoreThuente> {
+    let linesearch: MoreThuente = MoreThuenteLineSearch::new();
+    SteepestDescent::new(linesearch)
+}
+
+/// Solvers which can be selected by name
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Method {
+    SteepestDescent,
+    Newton,
+    NewtonCg,
+    Bfgs,
+    Lbfgs,
+    Dfp,
+    Sr1TrustRegion,
+    CgFletcherReeves,
+    CgPolakRibiere,
+    CgPolakRibierePlus,
+    CgHestenesStiefel,
+    ProjectedGradient,
+    LbfgsB,
+    NelderMead,
+    ParticleSwarm,
+    SimulatedAnnealing,
+}
+
+impl Method {
+    /// All methods, in the order in which they are listed
+    pub const ALL: &'static [Method] = &[
+        Method::SteepestDescent,
+        Method::Newton,
+        Method::NewtonCg,
+        Method::Bfgs,
+        Method::Lbfgs,
+        Method::Dfp,
+        Method::Sr1TrustRegion,
+        Method::CgFletcherReeves,
+        Method::CgPolakRibiere,
+        Method::CgPolakRibierePlus,
+        Method::CgHestenesStiefel,
+        Method::ProjectedGradient,
+        Method::LbfgsB,
+        Method::NelderMead,
+        Method::ParticleSwarm,
+        Method::SimulatedAnnealing,
+    ];
+
+    /// Name used to select the method
+    pub fn name(self) -> &'static str {
+        match self {
+            Method::SteepestDescent => "steepest-descent",
+            Method::Newton => "newton",
+            Method::NewtonCg => "newton-cg",
+            Method::Bfgs => "bfgs",
+            Method::Lbfgs => "lbfgs",
+            Method::Dfp => "dfp",
+            Method::Sr1TrustRegion => "sr1-trust-region",
+            Method::CgFletcherReeves => "cg-fletcher-reeves",
+            Method::CgPolakRibiere => "cg-polak-ribiere",
+            Method::CgPolakRibierePlus => "cg-polak-ribiere-plus",
+            Method::CgHestenesStiefel => "cg-hestenes-stiefel",
+            Method::ProjectedGradient => "projected-gradient",
+            Method::LbfgsB => "lbfgs-b",
+            Method::NelderMead => "nelder-mead",
+            Method::ParticleSwarm => "particle-swarm",
+            Method::SimulatedAnnealing => "simulated-annealing",
+        }
+    }
+
+    /// One line description
+    pub fn description(self) -> &'static str {
+        match self {
+            Method::SteepestDescent => "Steepest descent with line search",
+            Method::Newton => "Newton's method (requires the Hessian)",
+            Method::NewtonCg => "Newton-CG with line search (requires the Hessian)",
+            Method::Bfgs => "BFGS quasi-Newton method with line search",
+            Method::Lbfgs => "Limited memory BFGS with line search",
+            Method::Dfp => "DFP quasi-Newton method with line search",
+            Method::Sr1TrustRegion => "SR1 quasi-Newton method with Steihaug trust region",
+            Method::CgFletcherReeves => "Nonlinear conjugate gradient, Fletcher-Reeves beta",
+            Method::CgPolakRibiere => "Nonlinear conjugate gradient, Polak-Ribiere beta",
+            Method::CgPolakRibierePlus => "Nonlinear conjugate gradient, Polak-Ribiere+ beta",
+            Method::CgHestenesStiefel => "Nonlinear conjugate gradient, Hestenes-Stiefel beta",
+            Method::ProjectedGradient => "Projected gradient with Barzilai-Borwein steps (respects bounds)",
+            Method::LbfgsB => "Projected limited memory BFGS, L-BFGS-B style (respects bounds)",
+            Method::NelderMead => "Nelder-Mead simplex method (derivative free)",
+            Method::ParticleSwarm => "Particle swarm optimization in the problem bounds (derivative free)",
+            Method::SimulatedAnnealing => "Simulated annealing (derivative free)",
+        }
+    }
+
+    /// Whether the method only evaluates the cost function
+    pub fn is_derivative_free(self) -> bool {
+        matches!(
+            self,
+            Method::NelderMead | Method::ParticleSwarm | Method::SimulatedAnnealing
+        )
+    }
+
+    /// Whether the method keeps its iterates within the bounds of bounded
+    /// problems
+    pub fn respects_bounds(self) -> bool {
+        matches!(
+            self,
+            Method::ProjectedGradient
+                | Method::LbfgsB
+                | Method::ParticleSwarm
+                | Method::SimulatedAnnealing
+        )
+    }
+
+    /// Whether the method can run on `backend`
+    ///
+    /// argmin-math lacks the products of vectors and matrices BFGS, DFP and
+    /// SR1 need for nalgebra, and random vectors, which particle swarm
+    /// optimization needs, for ndarray and nalgebra.
+    pub fn supports(self, backend: Backend) -> bool {
+        match self {
+            Method::Bfgs | Method::Dfp | Method::Sr1TrustRegion => backend != Backend::Nalgebra,
+            Method::ParticleSwarm => backend == Backend::Vec,
+            _ => true,
+        }
+    }
+
+    /// Look up a method by its name
+    pub fn from_name(name: &str) -> Option<Method> {
+        Method::ALL.iter().copied().find(|m| m.name() == name)
+    }
+}
+
+/// A method together with its tuning parameters
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SolverOptions {
+    /// Method to use
+    pub method: Method,
+    /// Number of correction pairs stored by L-BFGS and L-BFGS-B
+    pub lbfgs_memory: usize,
+    /// Step length of Newton's method
+    pub newton_gamma: f64,
+    /// Initial trust region radius of SR1
+    pub trust_region_radius: f64,
+    /// Restart nonlinear CG every this many iterations
+    pub cg_restart_iters: Option<u64>,
+    /// Restart nonlinear CG if consecutive gradients are less orthogonal than
+    /// this; [`CG_RESTART_ORTHOGONALITY`] if `None`
+    pub cg_restart_orthogonality: Option<f64>,
+    /// Line search used by the line search based methods
+    pub linesearch: LineSearchOptions,
+    /// Options of Nelder-Mead
+    pub nelder_mead: NelderMeadOptions,
+    /// Options of particle swarm optimization
+    pub swarm: SwarmOptions,
+    /// Options of simulated annealing
+    pub annealing: AnnealingOptions,
+    /// Seed of the random number generator of the stochastic methods
+    /// (drawn from entropy if `None`)
+    pub seed: Option<u64>,
+    /// Math backend the solver works on
+    pub backend: Backend,
+    /// Let Newton-CG multiply by the Hessian through Hessian-vector products
+    /// instead of forming it (see [`HessianProduct`])
+    pub hessian_products: bool,
+}
+
+impl Default for SolverOptions {
+    fn default() -> Self {
+        SolverOptions {
+            method: Method::SteepestDescent,
+            lbfgs_memory: 7,
+            newton_gamma: 1.0,
+            trust_region_radius: 1.0,
+            cg_restart_iters: None,
+            cg_restart_orthogonality: None,
+            linesearch: LineSearchOptions::default(),
+            nelder_mead: NelderMeadOptions::default(),
+            swarm: SwarmOptions::default(),
+            annealing: AnnealingOptions::default(),
+            seed: None,
+            backend: Backend::Vec,
+            hessian_products: false,
+        }
+    }
+}
+
+impl SolverOptions {
+    /// Default options for `method`
+    pub fn new(method: Method) -> Self {
+        SolverOptions {
+            method,
+            ..SolverOptions::default()
+        }
+    }
+}
+
+/// Line search of the projected gradient methods, with `c1` and
+/// `contraction` of the line search options
+///
+/// The other line search options do not apply: the projected methods always
+/// backtrack along the projection arc, see [`projected`].
+fn projected_linesearch(options: &SolverOptions) -> Result<Backtracking, Error> {
+    let defaults = Backtracking::default();
+    let linesearch = &options.linesearch;
+    Backtracking::new(
+        linesearch.c1.unwrap_or(defaults.c1()),
+        linesearch.contraction.unwrap_or(defaults.contraction()),
+    )
+}
+
+/// Restart threshold of nonlinear CG unless configured otherwise
+///
+/// Powell's criterion: restart with the steepest descent direction once
+/// consecutive gradients are far from orthogonal. Without restarts,
+/// Polak-Ribiere and Hestenes-Stiefel may produce search directions which
+/// are not descent directions, which the line searches reject. Pass an
+/// infinite threshold to disable the restarts.
+pub const CG_RESTART_ORTHOGONALITY: f64 = 0.1;
+
+/// Build a nonlinear conjugate gradient solver with the given beta method
+fn nonlinear_cg<P, B>(
+    beta: B,
+    options: &SolverOptions,
+) -> Result<NonlinearConjugateGradient<P, DynLineSearch<P>, B, f64>, Error>
+where
+    P: ArgminScaledAdd<P, f64, P> + ArgminDot<P, f64>,
+{
+    let mut solver = NonlinearConjugateGradient::new(options.linesearch.build()?, beta);
+    if let Some(iters) = options.cg_restart_iters {
+        solver = solver.restart_iters(iters);
+    }
+    let orthogonality = options
+        .cg_restart_orthogonality
+        .unwrap_or(CG_RESTART_ORTHOGONALITY);
+    Ok(solver.restart_orthogonality(orthogonality))
+}
+
+/// Summarize an argmin result as a [`Report`]
+fn summarize<O, S, I>(
+    problem: &str,
+    method: Method,
+    res: OptimizationResult<O, Stopping<S>, I>,
+) -> Report
+where
+    I: State<Float = f64>,
+    I::Param: Vector,
+{
+    stopped_by(Report::from_state(problem, method.name(), &res.state), &res.solver)
+}
+
+/// Replace argmin's termination reason in `report` by the exact one if the
+/// run was stopped by one of the additional stopping criteria
+fn stopped_by<S>(mut report: Report, solver: &Stopping<S>) -> Report {
+    if let Some(reason) = solver.reason() {
+        report.termination = reason.to_string();
+    }
+    report
+}
+
+/// Run the solver described by `options` on `problem`
+///
+/// The solver works on the parameter and matrix types of `options.backend`.
+/// On bounded problems, the initial parameter vector is projected onto the
+/// box, and methods which do not respect bounds are rejected, as are
+/// stopping criteria which the method cannot meet (see
+/// [`StopCriteria::validate_for`](crate::StopCriteria::validate_for)).
+pub fn solve(
+    problem: DynProblem,
+    options: &SolverOptions,
+    config: &RunConfig,
+) -> Result<Report, Error> {
+    config.stop.validate_for(options.method)?;
+    let info = problem.info();
+    if info.bounded && !options.method.respects_bounds() {
+        let methods: Vec<&str> = Method::ALL
+            .iter()
+            .filter(|m| m.respects_bounds())
+            .map(|m| m.name())
+            .collect();
+        return Err(ArgminError::InvalidParameter {
+            text: format!(
+                "{} does not respect the bounds of {}, use one of {}",
+                options.method.name(),
+                info.name,
+                methods.join(", ")
+            ),
+        }
+        .into());
+    }
+    let projected;
+    let config = if info.bounded {
+        projected = RunConfig {
+            init_param: info.project(&config.init_param),
+            ..config.clone()
+        };
+        &projected
+    } else {
+        config
+    };
+    let derivative_free = options.method.is_derivative_free();
+    match options.backend {
+        Backend::Vec if derivative_free => solve_derivative_free_vec(problem, options, config),
+        Backend::Vec => solve_vec(problem, options, config),
+        Backend::Ndarray if derivative_free => {
+            solve_derivative_free_ndarray(problem, options, config)
+        }
+        Backend::Ndarray => solve_ndarray(problem, options, config),
+        Backend::Nalgebra if derivative_free => {
+            solve_derivative_free_nalgebra(problem, options, config)
+        }
+        Backend::Nalgebra => solve_nalgebra(problem, options, config),
+    }
+}
+
+/// Box constraints of the problem described by `info`, if any
+fn bounds(info: &ProblemInfo) -> Option<Vec<(f64, f64)>> {
+    info.constraints().map(<[_]>::to_vec)
+}
+
+/// Expands to `$body` if `$supported` is `true` and to an error otherwise
+///
+/// argmin-math does not implement every math trait for every backend. The
+/// body of an unsupported combination is dropped before type checking, so
+/// that the backend macros below compile for all backends.
+macro_rules! if_supported {
+    (true, $backend:expr, $method:expr, $body:block) => {
+        $body
+    };
+    (false, $backend:expr, $method:expr, $body:block) => {
+        return Err(ArgminError::NotImplemented {
+            text: format!(
+                "{} is not available on the {} backend",
+                $method.name(),
+                $backend.name()
+            ),
+        }
+        .into())
+    };
+}
+
+/// Defines a function running the solver described by the options on one
+/// backend
+///
+/// The body is the same for every backend, but the argmin solvers place
+/// different trait bounds on the math types, which only hold for concrete
+/// types. Expanding the body once per backend lets the compiler check them
+/// there instead of repeating every bound in a generic function.
+///
+/// `quasi_newton` tells whether BFGS, DFP and SR1 are available: they need
+/// products of vectors and matrices which argmin-math lacks for nalgebra.
+macro_rules! solve_on_backend {
+    ($name:ident, $backend:expr, $param:ty, $matrix:ty, quasi_newton: $qn:tt) => {
+        fn $name(
+            problem: DynProblem,
+            options: &SolverOptions,
+            config: &RunConfig,
+        ) -> Result<Report, Error> {
+            type Param = $param;
+            type Matrix = $matrix;
+            type LineSearch = DynLineSearch<Param>;
+
+            let method = options.method;
+            let name = problem.info().name.clone();
+            let linesearch = || options.linesearch.build::<Param>();
+            let problem: OnBackend<Param, Matrix> = OnBackend::new(problem);
+            let report = match method {
+                Method::SteepestDescent => {
+                    let solver = SteepestDescent::new(linesearch()?);
+                    summarize(&name, method, run_with(problem, solver, config, |s| s)?)
+                }
+                Method::Newton if problem.problem().has_tridiagonal_hessian() => {
+                    let problem: OnBackend<Param, Tridiagonal> =
+                        OnBackend::new(problem.problem().clone());
+                    let solver = Newton::new().with_gamma(options.newton_gamma)?;
+                    let res: OptimizationResult<_, _, GradientState<Tridiagonal, Param>> =
+                        run_with(problem, solver, config, |s| s)?;
+                    summarize(&name, method, res)
+                }
+                Method::Newton => {
+                    let solver = Newton::new().with_gamma(options.newton_gamma)?;
+                    let res: OptimizationResult<_, _, GradientState<Matrix, Param>> =
+                        run_with(problem, solver, config, |s| s)?;
+                    summarize(&name, method, res)
+                }
+                Method::NewtonCg if options.hessian_products => {
+                    let problem: OnBackend<Param, HessianProduct> =
+                        OnBackend::new(problem.problem().clone());
+                    let solver: NewtonCG<LineSearch, f64> = NewtonCG::new(linesearch()?);
+                    let res: OptimizationResult<_, _, GradientState<HessianProduct, Param>> =
+                        run_with(problem, solver, config, |s| s)?;
+                    summarize(&name, method, res)
+                }
+                Method::NewtonCg => {
+                    let solver: NewtonCG<LineSearch, f64> = NewtonCG::new(linesearch()?);
+                    let res: OptimizationResult<_, _, GradientState<Matrix, Param>> =
+                        run_with(problem, solver, config, |s| s)?;
+                    summarize(&name, method, res)
+                }
+                Method::Bfgs => if_supported!($qn, $backend, method, {
+                    let solver: BFGS<LineSearch, f64> = BFGS::new(linesearch()?);
+                    // The identity is the initial (inverse) Hessian
+                    let init = <Matrix as ArgminEye>::eye(problem.problem().info().dim);
+                    summarize(&name, method, run_with(problem, solver, config, |s| s.inv_hessian(init))?)
+                }),
+                Method::Lbfgs => {
+                    if options.lbfgs_memory == 0 {
+                        return Err(ArgminError::InvalidParameter {
+                            text: "L-BFGS memory must be at least 1".to_string(),
+                        }
+                        .into());
+                    }
+                    let solver: LBFGS<LineSearch, Param, Param, f64> =
+                        LBFGS::new(linesearch()?, options.lbfgs_memory);
+                    summarize(&name, method, run_with(problem, solver, config, |s| s)?)
+                }
+                Method::Dfp => if_supported!($qn, $backend, method, {
+                    let solver: DFP<LineSearch, f64> = DFP::new(linesearch()?);
+                    // The identity is the initial (inverse) Hessian
+                    let init = <Matrix as ArgminEye>::eye(problem.problem().info().dim);
+                    summarize(&name, method, run_with(problem, solver, config, |s| s.inv_hessian(init))?)
+                }),
+                Method::Sr1TrustRegion => if_supported!($qn, $backend, method, {
+                    let subproblem: Steihaug<Param, f64> = Steihaug::new().with_max_iters(20);
+                    let solver =
+                        SR1TrustRegion::new(subproblem).with_radius(options.trust_region_radius);
+                    // The identity is the initial (inverse) Hessian
+                    let init = <Matrix as ArgminEye>::eye(problem.problem().info().dim);
+                    summarize(&name, method, run_with(problem, solver, config, |s| s.hessian(init))?)
+                }),
+                Method::CgFletcherReeves => {
+                    let solver = nonlinear_cg::<Param, _>(FletcherReeves::new(), options)?;
+                    summarize(&name, method, run_with(problem, solver, config, |s| s)?)
+                }
+                Method::CgPolakRibiere => {
+                    let solver = nonlinear_cg::<Param, _>(PolakRibiere::new(), options)?;
+                    summarize(&name, method, run_with(problem, solver, config, |s| s)?)
+                }
+                Method::CgPolakRibierePlus => {
+                    let solver = nonlinear_cg::<Param, _>(PolakRibierePlus::new(), options)?;
+                    summarize(&name, method, run_with(problem, solver, config, |s| s)?)
+                }
+                Method::CgHestenesStiefel => {
+                    let solver = nonlinear_cg::<Param, _>(HestenesStiefel::new(), options)?;
+                    summarize(&name, method, run_with(problem, solver, config, |s| s)?)
+                }
+                Method::ProjectedGradient => {
+                    let solver = ProjectedGradient::new(
+                        bounds(problem.problem().info()),
+                        projected_linesearch(options)?,
+                    );
+                    let res: OptimizationResult<_, _, GradientState<(), Param>> =
+                        run_with(problem, solver, config, |s| s)?;
+                    summarize(&name, method, res)
+                }
+                Method::LbfgsB => {
+                    let solver = Lbfgsb::new(
+                        bounds(problem.problem().info()),
+                        projected_linesearch(options)?,
+                        options.lbfgs_memory,
+                    )?;
+                    let res: OptimizationResult<_, _, GradientState<(), Param>> =
+                        run_with(problem, solver, config, |s| s)?;
+                    summarize(&name, method, res)
+                }
+                Method::NelderMead | Method::ParticleSwarm | Method::SimulatedAnnealing => {
+                    unreachable!("derivative free methods are run by `solve_derivative_free_*`")
+                }
+            };
+            Ok(report)
+        }
+    };
+}
+
+solve_on_backend!(solve_vec, Backend::Vec, Vec<f64>, Vec<Vec<f64>>, quasi_newton: true);
+solve_on_backend!(solve_ndarray, Backend::Ndarray, Array1<f64>, Array2<f64>, quasi_newton: true);
+solve_on_backend!(solve_nalgebra, Backend::Nalgebra, DVector<f64>, DMatrix<f64>, quasi_newton: false);
+
+/// Defines a function running one of the derivative free methods on one
+/// backend, see `solve_on_backend`
+///
+/// `swarm` tells whether particle swarm optimization is available: it needs
+/// random vectors, which argmin-math only provides for `Vec`.
+macro_rules! solve_derivative_free_on_backend {
+    ($name:ident, $backend:expr, $param:ty, swarm: $swarm:tt) => {
+        fn $name(
+            problem: DynProblem,
+            options: &SolverOptions,
+            config: &RunConfig,
+        ) -> Result<Report, Error> {
+            type Param = $param;
+
+            let method = options.method;
+            let name = problem.info().name.clone();
+            let report = match method {
+                Method::NelderMead => {
+                    let nm = &options.nelder_mead;
+                    let simplex = nm
+                        .simplex(&config.init_param)?
+                        .into_iter()
+                        .map(<Param as Vector>::from_vec)
+                        .collect();
+                    let mut solver: NelderMead<Param, f64> = NelderMead::new(simplex);
+                    if let Some(tol) = nm.sd_tolerance {
+                        solver = solver.with_sd_tolerance(tol)?;
+                    }
+                    let problem: OnBackend<Param, ()> = OnBackend::new(problem);
+                    let res = run_derivative_free(problem, solver, config)?;
+                    stopped_by(Report::from_state(&name, method.name(), &res.state), &res.solver)
+                }
+                Method::ParticleSwarm => if_supported!($swarm, $backend, method, {
+                    let swarm = &options.swarm;
+                    let bounds = &problem.info().bounds;
+                    let lower = <Param as Vector>::from_vec(bounds.iter().map(|b| b.0).collect());
+                    let upper = <Param as Vector>::from_vec(bounds.iter().map(|b| b.1).collect());
+                    let mut solver: ParticleSwarm<Param, f64> =
+                        ParticleSwarm::new((lower, upper), swarm.particles);
+                    if let Some(w) = swarm.inertia {
+                        solver = solver.with_inertia_factor(w)?;
+                    }
+                    if let Some(w) = swarm.cognitive {
+                        solver = solver.with_cognitive_factor(w)?;
+                    }
+                    if let Some(w) = swarm.social {
+                        solver = solver.with_social_factor(w)?;
+                    }
+                    let mut rng = derivative_free::rng(options.seed);
+                    let population = swarm.swarm::<Param>(&problem, &config.init_param, &mut rng)?;
+                    let problem: OnBackend<Param, ()> = OnBackend::new(problem);
+                    let res = run_swarm(problem, solver, config, Some(population))?;
+                    stopped_by(Report::from_swarm_state(&name, method.name(), &res.state), &res.solver)
+                }),
+                Method::SimulatedAnnealing => {
+                    let sa = &options.annealing;
+                    let mut rng = derivative_free::rng(options.seed);
+                    // The solver and the neighbourhood draw from separate streams
+                    let problem: Annealing<Param> = Annealing::new(problem, sa, rng.clone())?;
+                    rng.jump();
+                    let mut solver = SimulatedAnnealing::new_with_rng(sa.temperature, rng)?
+                        .with_temp_func(sa.temp_func()?);
+                    if let Some(n) = sa.stall_best {
+                        solver = solver.with_stall_best(n);
+                    }
+                    if let Some(n) = sa.stall_accepted {
+                        solver = solver.with_stall_accepted(n);
+                    }
+                    if let Some(n) = sa.reanneal_fixed {
+                        solver = solver.with_reannealing_fixed(n);
+                    }
+                    if let Some(n) = sa.reanneal_accepted {
+                        solver = solver.with_reannealing_accepted(n);
+                    }
+                    if let Some(n) = sa.reanneal_best {
+                        solver = solver.with_reannealing_best(n);
+                    }
+                    let res = run_derivative_free(problem, solver, config)?;
+                    stopped_by(Report::from_state(&name, method.name(), &res.state), &res.solver)
+                }
+                _ => unreachable!("only derivative free methods are handled here"),
+            };
+            Ok(report)
+        }
+    };
+}
+
+solve_derivative_free_on_backend!(solve_derivative_free_vec, Backend::Vec, Vec<f64>, swarm: true);
+solve_derivative_free_on_backend!(
+    solve_derivative_free_ndarray,
+    Backend::Ndarray,
+    Array1<f64>,
+    swarm: false
+);
+solve_derivative_free_on_backend!(
+    solve_derivative_free_nalgebra,
+    Backend::Nalgebra,
+    DVector<f64>,
+    swarm: false
+);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::problem::Rosenbrock;
+
+    /// Solve Rosenbrock from its default start, like `opt run`
+    fn solve_rosenbrock(options: &SolverOptions) -> Result<Report, Error> {
+        let problem = Rosenbrock::default().into_dyn();
+        let config = RunConfig {
+            init_param: problem.info().default_start.clone(),
+            ..RunConfig::default()
+        };
+        solve(problem, options, &config)
+    }
+
+    fn assert_converges(methods: &[Method]) {
+        for &method in methods {
+            let report = solve_rosenbrock(&SolverOptions::new(method)).unwrap();
+            assert!(report.cost < 1e-8, "{}: cost {}", method.name(), report.cost);
+            for x in &report.param {
+                assert!((x - 1.0).abs() < 1e-3, "{}: {:?}", method.name(), report.param);
+            }
+        }
+    }
+
+    #[test]
+    fn steepest_descent_decreases_the_cost() {
+        let report = solve_rosenbrock(&SolverOptions::new(Method::SteepestDescent)).unwrap();
+        assert!(report.cost < 1e-3, "cost {}", report.cost);
+    }
+
+    #[test]
+    fn newton_methods_converge() {
+        assert_converges(&[Method::Newton, Method::NewtonCg]);
+    }
+
+    #[test]
+    fn quasi_newton_methods_converge() {
+        assert_converges(&[
+            Method::Bfgs,
+            Method::Lbfgs,
+            Method::Dfp,
+            Method::Sr1TrustRegion,
+        ]);
+    }
+
+    #[test]
+    fn conjugate_gradient_methods_converge() {
+        assert_converges(&[
+            Method::CgFletcherReeves,
+            Method::CgPolakRibiere,
+            Method::CgPolakRibierePlus,
+            Method::CgHestenesStiefel,
+        ]);
+    }
+
+    #[test]
+    fn polak_ribiere_needs_restarts() {
+        let options = SolverOptions {
+            cg_restart_orthogonality: Some(f64::INFINITY),
+            ..SolverOptions::new(Method::CgPolakRibiere)
+        };
+        assert!(solve_rosenbrock(&options).is_err());
+    }
+
+    #[test]
+    fn invalid_lbfgs_memory_is_rejected() {
+        let options = SolverOptions {
+            lbfgs_memory: 0,
+            ..SolverOptions::new(Method::Lbfgs)
+        };
+        assert!(solve_rosenbrock(&options).is_err());
+    }
+}
@@ -0,0 +1,196 @@
+//! Newton's method solving the Newton system
+//!
+//! argmin's `Newton` computes the step as `H^{-1} g` and therefore requires
+//! `ArgminInv` for the Hessian, which argmin-math does not implement for
+//! `Vec<Vec<f64>>`. This variant solves the Newton system `H d = g` instead,
+//! which is also cheaper and more accurate than forming the inverse: with
+//! the nalgebra backend the system is solved by an LU decomposition, and
+//! tridiagonal Hessians ([`Tridiagonal`]) are solved in `O(n)` on every
+//! backend.
+
+use crate::backend::Vector;
+use crate::problem::Tridiagonal;
+use argmin::core::{
+    ArgminError, CostFunction, Error, Gradient, Hessian, IterState, Problem, Solver, KV,
+};
+use argmin_math::ArgminScaledSub;
+use nalgebra::{DMatrix, DVector};
+use ndarray::{Array1, Array2};
+use serde::{Deserialize, Serialize};
+
+/// Solve linear systems with `Self` as system matrix
+pub trait SolveLinear<P> {
+    /// Solve `self * x = rhs` for `x`
+    fn solve(&self, rhs: &P) -> Result<P, Error>;
+}
+
+impl SolveLinear<Vec<f64>> for Vec<Vec<f64>> {
+    /// Gaussian elimination with partial pivoting
+    fn solve(&self, rhs: &Vec<f64>) -> Result<Vec<f64>, Error> {
+        let n = rhs.len();
+        let mut a = self.clone();
+        let mut b = rhs.clone();
+        for k in 0..n {
+            let pivot = (k..n)
+                .max_by(|&i, &j| a[i][k].abs().total_cmp(&a[j][k].abs()))
+                .unwrap_or(k);
+            if a[pivot][k] == 0.0 || !a[pivot][k].is_finite() {
+                return Err(ArgminError::ConditionViolated {
+                    text: "Newton: Hessian is singular".to_string(),
+                }
+                .into());
+            }
+            a.swap(k, pivot);
+            b.swap(k, pivot);
+            for i in (k + 1)..n {
+                let factor = a[i][k] / a[k][k];
+                let (upper, lower) = a.split_at_mut(i);
+                for (aij, akj) in lower[0][k..].iter_mut().zip(&upper[k][k..]) {
+                    *aij -= factor * akj;
+                }
+                b[i] -= factor * b[k];
+            }
+        }
+        let mut x = vec![0.0; n];
+        for i in (0..n).rev() {
+            let s: f64 = ((i + 1)..n).map(|j| a[i][j] * x[j]).sum();
+            x[i] = (b[i] - s) / a[i][i];
+        }
+        Ok(x)
+    }
+}
+
+impl SolveLinear<Array1<f64>> for Array2<f64> {
+    /// Gaussian elimination with partial pivoting, as for `Vec<Vec<f64>>`
+    fn solve(&self, rhs: &Array1<f64>) -> Result<Array1<f64>, Error> {
+        let rows: Vec<Vec<f64>> = self.outer_iter().map(|row| row.to_vec()).collect();
+        rows.solve(&rhs.to_vec()).map(Array1::from)
+    }
+}
+
+impl SolveLinear<DVector<f64>> for DMatrix<f64> {
+    /// LU decomposition with partial pivoting
+    fn solve(&self, rhs: &DVector<f64>) -> Result<DVector<f64>, Error> {
+        self.clone().lu().solve(rhs).ok_or_else(|| {
+            ArgminError::ConditionViolated {
+                text: "Newton: Hessian is singular".to_string(),
+            }
+            .into()
+        })
+    }
+}
+
+impl<P: Vector> SolveLinear<P> for Tridiagonal {
+    /// Thomas algorithm, see [`Tridiagonal::solve`]
+    fn solve(&self, rhs: &P) -> Result<P, Error> {
+        Tridiagonal::solve(self, &rhs.to_vec()).map(P::from_vec)
+    }
+}
+
+/// Newton's method with step length `gamma`
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Newton {
+    gamma: f64,
+}
+
+impl Default for Newton {
+    fn default() -> Self {
+        Newton { gamma: 1.0 }
+    }
+}
+
+impl Newton {
+    /// Construct a new instance with `gamma = 1`
+    pub fn new() -> Self {
+        Newton::default()
+    }
+
+    /// Set step length `gamma`, which must be in `(0, 1]`
+    pub fn with_gamma(mut self, gamma: f64) -> Result<Self, Error> {
+        if gamma <= 0.0 || gamma > 1.0 {
+            return Err(ArgminError::InvalidParameter {
+                text: "Newton: gamma must be in (0, 1]".to_string(),
+            }
+            .into());
+        }
+        self.gamma = gamma;
+        Ok(self)
+    }
+}
+
+impl<O, P, H> Solver<O, IterState<P, P, (), H, f64>> for Newton
+where
+    O: CostFunction<Param = P, Output = f64>
+        + Gradient<Param = P, Gradient = P>
+        + Hessian<Param = P, Hessian = H>,
+    P: Clone + ArgminScaledSub<P, f64, P>,
+    H: SolveLinear<P>,
+{
+    const NAME: &'static str = "Newton method";
+
+    fn next_iter(
+        &mut self,
+        problem: &mut Problem<O>,
+        mut state: IterState<P, P, (), H, f64>,
+    ) -> Result<(IterState<P, P, (), H, f64>, Option<KV>), Error> {
+        let param = state.take_param().ok_or_else(|| ArgminError::NotInitialized {
+            text: "`Newton` requires an initial parameter vector.".to_string(),
+        })?;
+        let grad = problem.gradient(&param)?;
+        let hessian = problem.hessian(&param)?;
+        let new_param = param.scaled_sub(&self.gamma, &hessian.solve(&grad)?);
+        // Unlike argmin's `Newton`, also evaluate the cost function so that
+        // the best parameter vector and the target cost are tracked.
+        let cost = problem.cost(&new_param)?;
+        Ok((state.param(new_param).cost(cost), None))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::Backend;
+    use crate::problem::RosenbrockNd;
+    use crate::solver::{solve, Method, SolverOptions};
+    use crate::{RunConfig, StopCriteria};
+
+    #[test]
+    fn tridiagonal_solve_matches_dense_solve() {
+        let p: Vec<f64> = (0..6).map(|i| 0.3 * i as f64 - 0.5).collect();
+        let hessian = RosenbrockNd::default().hessian(&p).unwrap();
+        let rhs: Vec<f64> = (1..=6).map(f64::from).collect();
+        let banded = SolveLinear::<Vec<f64>>::solve(&hessian, &rhs).unwrap();
+        let dense = hessian.to_dense().solve(&rhs).unwrap();
+        for (a, b) in banded.iter().zip(&dense) {
+            assert!((a - b).abs() <= 1e-9 * b.abs().max(1.0), "{} != {}", a, b);
+        }
+        for (a, b) in hessian.dot(&banded).iter().zip(&rhs) {
+            assert!((a - b).abs() <= 1e-9 * b.abs().max(1.0), "{} != {}", a, b);
+        }
+    }
+
+    #[test]
+    fn newton_uses_the_tridiagonal_hessian() {
+        // The dense Hessian of this size would allocate 10^6 entries per iteration
+        let problem = RosenbrockNd::default().into_dyn(1000);
+        assert!(problem.has_tridiagonal_hessian());
+        // Full Newton steps only converge near the minimum
+        let config = RunConfig {
+            init_param: vec![0.8; 1000],
+            stop: StopCriteria {
+                max_iters: 100,
+                target_cost: Some(1e-12),
+                ..StopCriteria::default()
+            },
+            ..RunConfig::default()
+        };
+        for &backend in Backend::ALL {
+            let options = SolverOptions {
+                backend,
+                ..SolverOptions::new(Method::Newton)
+            };
+            let report = solve(problem.clone(), &options, &config).unwrap();
+            assert!(report.cost <= 1e-12, "{}: {}", backend.name(), report.cost);
+        }
+    }
+}
@@ -0,0 +1,525 @@
+//! Gradient methods respecting box constraints
+//!
+//! Both solvers keep the iterates in the box of a bounded problem
+//! ([`ProblemInfo::constraints`](crate::problem::ProblemInfo::constraints))
+//! by projecting onto it: the starting point is moved to the closest point
+//! of the box, and the line search backtracks along the projection arc
+//! `x(t) = P(x + t d)` of the search direction `d` until the Armijo
+//! condition `f(x(t)) <= f(x) + c1 g^T (x(t) - x)` holds.
+//!
+//! * [`ProjectedGradient`] searches along the negative gradient, starting
+//!   every line search with the Barzilai-Borwein step length.
+//! * [`Lbfgsb`] is a projected limited memory BFGS method in the spirit of
+//!   L-BFGS-B: the variables which sit on a bound and are pushed against it
+//!   by the gradient are fixed, and the L-BFGS direction is computed for the
+//!   free ones. It does not compute the generalized Cauchy point of L-BFGS-B,
+//!   so the active set may take a few more iterations to settle.
+//!
+//! At a constrained minimum the gradient does not vanish, so the state holds
+//! the projected gradient `x - P(x - g)` instead, which does. The gradient
+//! tolerance of the [stopping criteria](crate::stopping) and the gradient
+//! norm in traces therefore measure first-order optimality in the box. On
+//! unconstrained problems the projection is the identity and the solvers
+//! are plain steepest descent and L-BFGS with backtracking.
+
+use crate::backend::Vector;
+use crate::GradientState;
+use argmin::core::{
+    ArgminError, CostFunction, Error, Gradient, Problem, Solver, State, TerminationReason, KV,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+
+/// Step lengths below this fraction of the size of the iterate end the
+/// line search without success
+const MIN_STEP: f64 = 1e-14;
+
+/// Sufficient decrease constant and contraction factor of the projected
+/// backtracking line search
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct Backtracking {
+    c1: f64,
+    contraction: f64,
+}
+
+impl Default for Backtracking {
+    fn default() -> Self {
+        Backtracking {
+            c1: 1e-4,
+            contraction: 0.5,
+        }
+    }
+}
+
+impl Backtracking {
+    /// Line search with sufficient decrease constant `c1` in `(0, 1)` and
+    /// contraction factor `contraction` in `(0, 1)`
+    pub fn new(c1: f64, contraction: f64) -> Result<Self, Error> {
+        if !(c1 > 0.0 && c1 < 1.0) {
+            return Err(invalid("Projected line search: c1 must be in (0, 1)"));
+        }
+        if !(contraction > 0.0 && contraction < 1.0) {
+            return Err(invalid(
+                "Projected line search: contraction must be in (0, 1)",
+            ));
+        }
+        Ok(Backtracking { c1, contraction })
+    }
+
+    /// Sufficient decrease constant
+    pub fn c1(&self) -> f64 {
+        self.c1
+    }
+
+    /// Factor by which the step length shrinks
+    pub fn contraction(&self) -> f64 {
+        self.contraction
+    }
+
+    /// Search along the projection arc of `direction` from `x`, starting
+    /// with step length `step`; the accepted point and its cost, or `None`
+    /// if no step decreases the cost sufficiently
+    #[allow(clippy::too_many_arguments)]
+    fn search<O, P>(
+        &self,
+        problem: &mut Problem<O>,
+        bounds: Option<&[(f64, f64)]>,
+        x: &[f64],
+        cost: f64,
+        gradient: &[f64],
+        direction: &[f64],
+        mut step: f64,
+    ) -> Result<Option<(Vec<f64>, f64)>, Error>
+    where
+        O: CostFunction<Param = P, Output = f64>,
+        P: Vector,
+    {
+        let size = 1.0 + norm(x);
+        loop {
+            let trial: Vec<f64> = x.iter().zip(direction).map(|(x, d)| x + step * d).collect();
+            let trial = project(bounds, &trial);
+            let change: Vec<f64> = trial.iter().zip(x).map(|(t, x)| t - x).collect();
+            if norm(&change) <= MIN_STEP * size {
+                return Ok(None);
+            }
+            let trial_cost = problem.cost(&P::from_vec(trial.clone()))?;
+            if trial_cost <= cost + self.c1 * dot(gradient, &change) {
+                return Ok(Some((trial, trial_cost)));
+            }
+            step *= self.contraction;
+        }
+    }
+}
+
+/// Projected gradient method with Barzilai-Borwein step lengths
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ProjectedGradient {
+    bounds: Option<Vec<(f64, f64)>>,
+    linesearch: Backtracking,
+    /// Gradient at the current iterate (the state holds the projected one)
+    gradient: Vec<f64>,
+    /// Last step and change of the gradient, for the Barzilai-Borwein step
+    last: Option<(Vec<f64>, Vec<f64>)>,
+    stalled: bool,
+}
+
+impl ProjectedGradient {
+    /// Projected gradient method in the box `bounds` (unconstrained if
+    /// `None`)
+    pub fn new(bounds: Option<Vec<(f64, f64)>>, linesearch: Backtracking) -> Self {
+        ProjectedGradient {
+            bounds,
+            linesearch,
+            gradient: Vec::new(),
+            last: None,
+            stalled: false,
+        }
+    }
+}
+
+impl<O, P> Solver<O, GradientState<(), P>> for ProjectedGradient
+where
+    O: CostFunction<Param = P, Output = f64> + Gradient<Param = P, Gradient = P>,
+    P: Vector,
+{
+    const NAME: &'static str = "Projected gradient";
+
+    fn init(
+        &mut self,
+        problem: &mut Problem<O>,
+        state: GradientState<(), P>,
+    ) -> Result<(GradientState<(), P>, Option<KV>), Error> {
+        let (state, gradient) = start(problem, state, self.bounds.as_deref(), "ProjectedGradient")?;
+        self.gradient = gradient;
+        Ok((state, None))
+    }
+
+    fn next_iter(
+        &mut self,
+        problem: &mut Problem<O>,
+        state: GradientState<(), P>,
+    ) -> Result<(GradientState<(), P>, Option<KV>), Error> {
+        let bounds = self.bounds.as_deref();
+        let x = current(&state, "ProjectedGradient")?;
+        let cost = state.get_cost();
+        let direction: Vec<f64> = self.gradient.iter().map(|g| -g).collect();
+        let step = match &self.last {
+            Some((s, y)) if dot(s, y) > 0.0 => (dot(s, s) / dot(s, y)).clamp(1e-10, 1e10),
+            _ => 1.0 / norm(&projected_gradient(bounds, &x, &self.gradient)).max(1.0),
+        };
+        let found =
+            self.linesearch
+                .search(problem, bounds, &x, cost, &self.gradient, &direction, step)?;
+        let Some((next, next_cost)) = found else {
+            self.stalled = true;
+            return Ok((state.param(P::from_vec(x)).cost(cost), None));
+        };
+        let gradient = problem.gradient(&P::from_vec(next.clone()))?.to_vec();
+        self.last = Some((difference(&next, &x), difference(&gradient, &self.gradient)));
+        let projected = projected_gradient(bounds, &next, &gradient);
+        self.gradient = gradient;
+        let state = state
+            .param(P::from_vec(next))
+            .cost(next_cost)
+            .gradient(P::from_vec(projected));
+        Ok((state, None))
+    }
+
+    fn terminate(&mut self, state: &GradientState<(), P>) -> TerminationReason {
+        terminated(state, self.stalled)
+    }
+}
+
+/// Projected limited memory BFGS method (L-BFGS-B style)
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Lbfgsb {
+    bounds: Option<Vec<(f64, f64)>>,
+    linesearch: Backtracking,
+    memory: usize,
+    /// Gradient at the current iterate (the state holds the projected one)
+    gradient: Vec<f64>,
+    /// The last `memory` steps and changes of the gradient, oldest first
+    pairs: VecDeque<(Vec<f64>, Vec<f64>)>,
+    stalled: bool,
+}
+
+impl Lbfgsb {
+    /// Projected L-BFGS in the box `bounds` (unconstrained if `None`),
+    /// keeping `memory` correction pairs
+    pub fn new(
+        bounds: Option<Vec<(f64, f64)>>,
+        linesearch: Backtracking,
+        memory: usize,
+    ) -> Result<Self, Error> {
+        if memory == 0 {
+            return Err(invalid("L-BFGS-B: memory must be at least 1"));
+        }
+        Ok(Lbfgsb {
+            bounds,
+            linesearch,
+            memory,
+            gradient: Vec::new(),
+            pairs: VecDeque::new(),
+            stalled: false,
+        })
+    }
+
+    /// The L-BFGS direction `-H g` for the variables which are not held at
+    /// a bound, zero for the others
+    ///
+    /// `H` is positive definite and so is its restriction to the free
+    /// variables, hence the direction is a descent direction.
+    fn direction(&self, free: &[bool]) -> Vec<f64> {
+        let mut q: Vec<f64> = self
+            .gradient
+            .iter()
+            .zip(free)
+            .map(|(&g, &free)| if free { g } else { 0.0 })
+            .collect();
+        let mut alphas = Vec::with_capacity(self.pairs.len());
+        for (s, y) in self.pairs.iter().rev() {
+            let alpha = dot(s, &q) / dot(s, y);
+            q.iter_mut().zip(y).for_each(|(q, y)| *q -= alpha * y);
+            alphas.push(alpha);
+        }
+        // Scale the initial Hessian approximation by the latest curvature
+        if let Some((s, y)) = self.pairs.back() {
+            let gamma = dot(s, y) / dot(y, y);
+            q.iter_mut().for_each(|q| *q *= gamma);
+        }
+        for ((s, y), alpha) in self.pairs.iter().zip(alphas.iter().rev()) {
+            let beta = dot(y, &q) / dot(s, y);
+            q.iter_mut()
+                .zip(s)
+                .for_each(|(q, s)| *q += (alpha - beta) * s);
+        }
+        q.iter()
+            .zip(free)
+            .map(|(&q, &free)| if free { -q } else { 0.0 })
+            .collect()
+    }
+}
+
+impl<O, P> Solver<O, GradientState<(), P>> for Lbfgsb
+where
+    O: CostFunction<Param = P, Output = f64> + Gradient<Param = P, Gradient = P>,
+    P: Vector,
+{
+    const NAME: &'static str = "L-BFGS-B";
+
+    fn init(
+        &mut self,
+        problem: &mut Problem<O>,
+        state: GradientState<(), P>,
+    ) -> Result<(GradientState<(), P>, Option<KV>), Error> {
+        let (state, gradient) = start(problem, state, self.bounds.as_deref(), "Lbfgsb")?;
+        self.gradient = gradient;
+        Ok((state, None))
+    }
+
+    fn next_iter(
+        &mut self,
+        problem: &mut Problem<O>,
+        state: GradientState<(), P>,
+    ) -> Result<(GradientState<(), P>, Option<KV>), Error> {
+        let bounds = self.bounds.as_deref();
+        let x = current(&state, "Lbfgsb")?;
+        let cost = state.get_cost();
+        let free: Vec<bool> = match bounds {
+            Some(bounds) => x
+                .iter()
+                .zip(&self.gradient)
+                .zip(bounds)
+                .map(|((&x, &g), &(l, u))| !((x <= l && g > 0.0) || (x >= u && g < 0.0)))
+                .collect(),
+            None => vec![true; x.len()],
+        };
+        let direction = self.direction(&free);
+        // Without curvature information the step length is unknown, so the
+        // first step is at most of unit length
+        let step = if self.pairs.is_empty() {
+            1.0 / norm(&direction).max(1.0)
+        } else {
+            1.0
+        };
+        let found =
+            self.linesearch
+                .search(problem, bounds, &x, cost, &self.gradient, &direction, step)?;
+        let Some((next, next_cost)) = found else {
+            // Start over from steepest descent before giving up
+            self.stalled = self.pairs.is_empty();
+            self.pairs.clear();
+            return Ok((state.param(P::from_vec(x)).cost(cost), None));
+        };
+        let gradient = problem.gradient(&P::from_vec(next.clone()))?.to_vec();
+        let (s, y) = (difference(&next, &x), difference(&gradient, &self.gradient));
+        // Only pairs with positive curvature keep `H` positive definite
+        if dot(&s, &y) > f64::EPSILON * dot(&y, &y) {
+            if self.pairs.len() == self.memory {
+                self.pairs.pop_front();
+            }
+            self.pairs.push_back((s, y));
+        }
+        let projected = projected_gradient(bounds, &next, &gradient);
+        self.gradient = gradient;
+        let state = state
+            .param(P::from_vec(next))
+            .cost(next_cost)
+            .gradient(P::from_vec(projected));
+        Ok((state, None))
+    }
+
+    fn terminate(&mut self, state: &GradientState<(), P>) -> TerminationReason {
+        terminated(state, self.stalled)
+    }
+}
+
+/// Project the initial parameter vector of `state` onto the box and
+/// evaluate the problem there; returns the state and the gradient
+fn start<O, P>(
+    problem: &mut Problem<O>,
+    state: GradientState<(), P>,
+    bounds: Option<&[(f64, f64)]>,
+    solver: &str,
+) -> Result<(GradientState<(), P>, Vec<f64>), Error>
+where
+    O: CostFunction<Param = P, Output = f64> + Gradient<Param = P, Gradient = P>,
+    P: Vector,
+{
+    let x = project(bounds, &current(&state, solver)?);
+    let param = P::from_vec(x.clone());
+    let cost = problem.cost(&param)?;
+    let gradient = problem.gradient(&param)?.to_vec();
+    let projected = P::from_vec(projected_gradient(bounds, &x, &gradient));
+    Ok((state.param(param).cost(cost).gradient(projected), gradient))
+}
+
+/// The current parameter vector of `state`
+///
+/// It is left in the state, so that it becomes the previous parameter
+/// vector and traces can show the length of the step.
+fn current<P: Vector>(state: &GradientState<(), P>, solver: &str) -> Result<Vec<f64>, Error> {
+    let param = state
+        .get_param()
+        .ok_or_else(|| ArgminError::NotInitialized {
+            text: format!("`{}` requires an initial parameter vector.", solver),
+        })?;
+    Ok(param.to_vec())
+}
+
+/// Converged if the projected gradient vanishes, stopped if the line
+/// search cannot make progress
+fn terminated<P: Vector>(state: &GradientState<(), P>, stalled: bool) -> TerminationReason {
+    if stalled {
+        return TerminationReason::NoChangeInCost;
+    }
+    match state.get_gradient() {
+        Some(gradient) if gradient.to_vec().iter().all(|&g| g == 0.0) => {
+            TerminationReason::TargetPrecisionReached
+        }
+        _ => TerminationReason::NotTerminated,
+    }
+}
+
+/// The point of the box closest to `x`
+fn project(bounds: Option<&[(f64, f64)]>, x: &[f64]) -> Vec<f64> {
+    match bounds {
+        Some(bounds) => x
+            .iter()
+            .zip(bounds)
+            .map(|(x, &(l, u))| x.clamp(l, u))
+            .collect(),
+        None => x.to_vec(),
+    }
+}
+
+/// `x - P(x - g)`, which vanishes exactly at the stationary points in the box
+fn projected_gradient(bounds: Option<&[(f64, f64)]>, x: &[f64], g: &[f64]) -> Vec<f64> {
+    let descent: Vec<f64> = x.iter().zip(g).map(|(x, g)| x - g).collect();
+    difference(x, &project(bounds, &descent))
+}
+
+fn difference(a: &[f64], b: &[f64]) -> Vec<f64> {
+    a.iter().zip(b).map(|(a, b)| a - b).collect()
+}
+
+fn dot(a: &[f64], b: &[f64]) -> f64 {
+    a.iter().zip(b).map(|(a, b)| a * b).sum()
+}
+
+fn norm(a: &[f64]) -> f64 {
+    dot(a, a).sqrt()
+}
+
+fn invalid(text: &str) -> Error {
+    ArgminError::InvalidParameter {
+        text: text.to_string(),
+    }
+    .into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::problem::{DynProblem, ProblemConfig};
+    use crate::report::Report;
+    use crate::solver::{solve, Method, SolverOptions};
+    use crate::stopping::StopCriteria;
+    use crate::RunConfig;
+    use serde_json::json;
+
+    fn bounded(name: &str, dim: usize, lower: &[f64], upper: &[f64]) -> DynProblem {
+        let config: ProblemConfig = serde_json::from_value(json!({
+            "name": name,
+            "dim": dim,
+            "lower": lower,
+            "upper": upper,
+        }))
+        .unwrap();
+        config.build().unwrap()
+    }
+
+    fn solve_from(problem: DynProblem, method: Method, x0: Vec<f64>) -> Report {
+        let config = RunConfig {
+            init_param: x0,
+            stop: StopCriteria {
+                max_iters: 5000,
+                gradient_tol: Some(1e-10),
+                ..StopCriteria::default()
+            },
+            ..RunConfig::default()
+        };
+        solve(problem, &SolverOptions::new(method), &config).unwrap()
+    }
+
+    fn assert_close(a: &[f64], b: &[f64], tol: f64) {
+        assert!(a.iter().zip(b).all(|(a, b)| (a - b).abs() <= tol), "{:?} != {:?}", a, b);
+    }
+
+    #[test]
+    fn projections_clamp_to_the_box() {
+        let bounds = [(0.0, 1.0), (-1.0, 1.0)];
+        assert_eq!(project(Some(&bounds), &[2.0, -3.0]), [1.0, -1.0]);
+        assert_eq!(project(None, &[2.0, -3.0]), [2.0, -3.0]);
+        // Components pushed against their bound do not count
+        assert_eq!(projected_gradient(Some(&bounds), &[0.0, 0.0], &[1.0, 0.5]), [0.0, 0.5]);
+        assert_eq!(projected_gradient(Some(&bounds), &[1.0, 0.0], &[-1.0, 0.5]), [0.0, 0.5]);
+        assert_eq!(projected_gradient(None, &[0.0, 0.0], &[1.0, 0.5]), [1.0, 0.5]);
+    }
+
+    #[test]
+    fn invalid_parameters_are_rejected() {
+        assert!(Backtracking::new(0.0, 0.5).is_err());
+        assert!(Backtracking::new(1e-4, 1.0).is_err());
+        assert!(Backtracking::new(1e-4, 0.5).is_ok());
+        assert!(Lbfgsb::new(None, Backtracking::default(), 0).is_err());
+        let problem = bounded("sphere", 2, &[-1.0], &[1.0]);
+        let err = solve(problem, &SolverOptions::new(Method::Bfgs), &RunConfig::default());
+        assert!(err.unwrap_err().to_string().contains("does not respect the bounds"));
+    }
+
+    #[test]
+    fn bounds_are_checked() {
+        let config = |lower: &[f64], upper: &[f64]| -> ProblemConfig {
+            serde_json::from_value(json!({ "name": "booth", "lower": lower, "upper": upper }))
+                .unwrap()
+        };
+        assert!(config(&[0.0, 0.0, 0.0], &[1.0]).build().is_err());
+        assert!(config(&[2.0], &[1.0]).build().is_err());
+        let info = config(&[0.0], &[1.0, 2.0]).build().unwrap().info().clone();
+        assert_eq!(info.constraints(), Some(&[(0.0, 1.0), (0.0, 2.0)][..]));
+        assert_eq!(info.project(&[-1.0, 3.0]), [0.0, 2.0]);
+    }
+
+    #[test]
+    fn minimum_on_a_bound_is_found() {
+        // With x <= 0.5 the minimum of Rosenbrock is at (0.5, 0.25)
+        let problem = bounded("rosenbrock", 2, &[-5.0], &[0.5, 10.0]);
+        for method in [Method::ProjectedGradient, Method::LbfgsB] {
+            let report = solve_from(problem.clone(), method, vec![-1.2, 1.0]);
+            assert_close(&report.param, &[0.5, 0.25], 1e-6);
+            assert!((report.cost - 0.25).abs() < 1e-10, "{}", method.name());
+        }
+    }
+
+    #[test]
+    fn starting_points_outside_the_box_are_projected() {
+        // Every coordinate of the sphere is held at its lower bound 1
+        let problem = bounded("sphere", 4, &[1.0], &[5.0]);
+        for method in [Method::ProjectedGradient, Method::LbfgsB] {
+            let report = solve_from(problem.clone(), method, vec![-3.0, 7.0, 2.0, 0.0]);
+            assert_close(&report.param, &[1.0; 4], 1e-12);
+            assert_eq!(report.cost, 4.0);
+        }
+    }
+
+    #[test]
+    fn unbounded_problems_are_solved_like_without_projection() {
+        let config: ProblemConfig =
+            serde_json::from_value(json!({ "name": "rosenbrock" })).unwrap();
+        for method in [Method::ProjectedGradient, Method::LbfgsB] {
+            let report = solve_from(config.build().unwrap(), method, vec![-1.2, 1.0]);
+            assert_close(&report.param, &[1.0, 1.0], 1e-6);
+        }
+    }
+}
@@ -0,0 +1,730 @@
+//! Stopping criteria
+//!
+//! argmin itself stops a run when the maximum number of iterations or the
+//! target cost is reached, or when the solver decides it has converged.
+//! [`StopCriteria`] adds tolerances on the gradient norm, on the change of
+//! the cost function value and on the step length, a stagnation window, a
+//! wall-clock timeout and a limit on the number of cost function
+//! evaluations. They are enforced by [`Stopping`], which wraps a solver and
+//! checks them between iterations, after argmin's own criteria. Like the
+//! timeout, `max_evals` is therefore a soft limit: the last iteration is
+//! completed, and may take the count past the limit.
+//!
+//! When one of these criteria stops a run, argmin only knows the closest of
+//! its fixed [`TerminationReason`]s. The exact [`StopReason`], including the
+//! value which met the criterion, is kept by [`Stopping`] and reported
+//! instead.
+
+use crate::backend::Vector;
+use crate::solver::Method;
+use crate::trace::{StepLength, TraceState};
+use crate::{CostState, GradientState, SwarmState};
+use argmin::core::{ArgminError, Error, Gradient, Problem, Solver, State, TerminationReason, KV};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::fmt;
+
+/// When a run stops
+///
+/// The criteria which are `None` are not checked. Tolerances are inclusive:
+/// a run with `gradient_tol = 1e-8` stops as soon as the gradient norm is at
+/// most `1e-8`.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct StopCriteria {
+    /// Maximum number of iterations
+    pub max_iters: u64,
+    /// Stop when the cost function value is at most this value; problems
+    /// whose minimum is negative would stop early at a fixed target such as
+    /// `0`, so there is none by default
+    pub target_cost: Option<f64>,
+    /// Stop when the Euclidean norm of the gradient is at most this value;
+    /// only used by gradient based solvers, which evaluate the gradient at
+    /// the new parameter vector if they do not keep it themselves
+    pub gradient_tol: Option<f64>,
+    /// Stop when the cost function value changes by at most this value
+    /// between two iterations; not for derivative free methods (see
+    /// [`StopCriteria::validate_for`])
+    pub cost_tol_abs: Option<f64>,
+    /// Stop when the cost function value changes by at most this fraction of
+    /// its previous magnitude between two iterations; not for derivative
+    /// free methods
+    pub cost_tol_rel: Option<f64>,
+    /// Stop when the step between two parameter vectors is at most this
+    /// long; not for derivative free methods
+    pub step_tol: Option<f64>,
+    /// Stop when the best cost function value has not improved within this
+    /// many iterations
+    pub stagnation_iters: Option<u64>,
+    /// Improvements of the best cost function value by at most this fraction
+    /// of its magnitude do not count for `stagnation_iters`
+    pub stagnation_tol: f64,
+    /// Stop after this many seconds of wall-clock time
+    pub timeout: Option<f64>,
+    /// Stop once the cost function has been evaluated this many times; the
+    /// iteration which reaches the limit is completed, so the final count
+    /// can be higher
+    pub max_evals: Option<u64>,
+}
+
+impl Default for StopCriteria {
+    fn default() -> Self {
+        StopCriteria {
+            max_iters: 1000,
+            target_cost: None,
+            gradient_tol: None,
+            cost_tol_abs: None,
+            cost_tol_rel: None,
+            step_tol: None,
+            stagnation_iters: None,
+            stagnation_tol: 0.0,
+            timeout: None,
+            max_evals: None,
+        }
+    }
+}
+
+impl StopCriteria {
+    /// Target cost handed to argmin, which has no target at `-inf`
+    pub fn target(&self) -> f64 {
+        self.target_cost.unwrap_or(f64::NEG_INFINITY)
+    }
+
+    /// Check that the criteria can be met
+    pub fn validate(&self) -> Result<(), Error> {
+        let invalid = |text: String| -> Result<(), Error> {
+            Err(ArgminError::InvalidParameter { text }.into())
+        };
+        if self.max_iters == 0 {
+            return invalid("max_iters must be at least 1".to_string());
+        }
+        if self.target_cost.is_some_and(f64::is_nan) {
+            return invalid("target_cost must not be NaN".to_string());
+        }
+        let tolerances = [
+            ("gradient_tol", self.gradient_tol),
+            ("cost_tol_abs", self.cost_tol_abs),
+            ("cost_tol_rel", self.cost_tol_rel),
+            ("step_tol", self.step_tol),
+            ("stagnation_tol", Some(self.stagnation_tol)),
+        ];
+        for (name, tol) in tolerances {
+            if tol.is_some_and(|tol| !(tol.is_finite() && tol >= 0.0)) {
+                return invalid(format!("{} must be finite and non-negative", name));
+            }
+        }
+        if self.stagnation_iters == Some(0) {
+            return invalid("stagnation_iters must be at least 1".to_string());
+        }
+        if self.timeout.is_some_and(|t| t.is_nan() || t <= 0.0) {
+            return invalid("timeout must be positive".to_string());
+        }
+        if self.max_evals == Some(0) {
+            return invalid("max_evals must be at least 1".to_string());
+        }
+        Ok(())
+    }
+
+    /// Check that the criteria can be met by `method`
+    ///
+    /// Iterations of the derivative free methods often leave the best point
+    /// where it is: Nelder-Mead may shrink the simplex around it, simulated
+    /// annealing rejects moves, and the swarm may not find a better
+    /// position. A cost change or step of zero is thus no sign of
+    /// convergence, so `cost_tol_abs`, `cost_tol_rel` and `step_tol` are
+    /// refused for them; `stagnation_iters` is the criterion to use.
+    pub fn validate_for(&self, method: Method) -> Result<(), Error> {
+        self.validate()?;
+        let tolerances = [
+            ("cost_tol_abs", self.cost_tol_abs),
+            ("cost_tol_rel", self.cost_tol_rel),
+            ("step_tol", self.step_tol),
+        ];
+        match tolerances.iter().find(|(_, tol)| tol.is_some()) {
+            Some((name, _)) if method.is_derivative_free() => {
+                Err(ArgminError::InvalidParameter {
+                    text: format!(
+                        "{} is not supported by {}, whose iterations often keep the best \
+                         point; stop on stagnation instead",
+                        name,
+                        method.name()
+                    ),
+                }
+                .into())
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Which of the [`StopCriteria`] stopped a run, and why
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum StopReason {
+    /// The gradient norm fell to `gradient_tol`
+    Gradient {
+        /// Gradient norm at the last parameter vector
+        norm: f64,
+        /// The tolerance
+        tol: f64,
+    },
+    /// The cost function value changed by at most `cost_tol_abs`
+    CostChange {
+        /// Absolute change in the last iteration
+        change: f64,
+        /// The tolerance
+        tol: f64,
+    },
+    /// The cost function value changed by at most `cost_tol_rel`
+    RelativeCostChange {
+        /// Change in the last iteration relative to the previous value
+        change: f64,
+        /// The tolerance
+        tol: f64,
+    },
+    /// The step fell to `step_tol`
+    Step {
+        /// Length of the last step
+        length: f64,
+        /// The tolerance
+        tol: f64,
+    },
+    /// The best cost function value stagnated for `stagnation_iters`
+    Stagnation {
+        /// Length of the window
+        iters: u64,
+        /// Improvement of the best cost function value within the window
+        improvement: f64,
+    },
+    /// The run took longer than `timeout`
+    Timeout {
+        /// Seconds since the start of the run
+        elapsed: f64,
+        /// The limit
+        limit: f64,
+    },
+    /// The cost function was evaluated `max_evals` times
+    MaxEvals {
+        /// Number of cost function evaluations
+        evals: u64,
+        /// The limit
+        limit: u64,
+    },
+}
+
+impl StopReason {
+    /// Closest of argmin's termination reasons
+    pub fn termination_reason(&self) -> TerminationReason {
+        match self {
+            StopReason::Gradient { .. } => TerminationReason::TargetPrecisionReached,
+            StopReason::CostChange { .. } | StopReason::RelativeCostChange { .. } => {
+                TerminationReason::NoChangeInCost
+            }
+            StopReason::Step { .. } => TerminationReason::TargetToleranceReached,
+            StopReason::Stagnation { .. } => TerminationReason::BestStallIterExceeded,
+            StopReason::Timeout { .. } | StopReason::MaxEvals { .. } => TerminationReason::Aborted,
+        }
+    }
+}
+
+impl fmt::Display for StopReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StopReason::Gradient { norm, tol } => {
+                write!(f, "Gradient norm {:e} reached tolerance {:e}", norm, tol)
+            }
+            StopReason::CostChange { change, tol } => {
+                write!(
+                    f,
+                    "Cost change {:e} within absolute tolerance {:e}",
+                    change, tol
+                )
+            }
+            StopReason::RelativeCostChange { change, tol } => {
+                write!(
+                    f,
+                    "Relative cost change {:e} within tolerance {:e}",
+                    change, tol
+                )
+            }
+            StopReason::Step { length, tol } => {
+                write!(f, "Step length {:e} reached tolerance {:e}", length, tol)
+            }
+            StopReason::Stagnation { iters, improvement } => write!(
+                f,
+                "Best cost improved by only {:e} in the last {} iterations",
+                improvement, iters
+            ),
+            StopReason::Timeout { elapsed, limit } => {
+                write!(f, "Timeout of {}s exceeded after {:.3}s", limit, elapsed)
+            }
+            StopReason::MaxEvals { evals, limit } => write!(
+                f,
+                "Cost function evaluated {} times, limit {}",
+                evals, limit
+            ),
+        }
+    }
+}
+
+/// States on which the [`StopCriteria`] can be checked
+pub trait StopState<O>: TraceState + Sized {
+    /// Store the gradient at the current parameter vector in the state if
+    /// it holds none; returns whether it was evaluated
+    ///
+    /// The evaluation is only counted if `counted` is set.
+    fn ensure_gradient(
+        self,
+        problem: &mut Problem<O>,
+        counted: bool,
+    ) -> Result<(Self, bool), Error>;
+    /// Remove the gradient from the state
+    fn clear_gradient(&mut self);
+}
+
+impl<O, H, P> StopState<O> for GradientState<H, P>
+where
+    O: Gradient<Param = P, Gradient = P>,
+    P: Vector,
+{
+    fn ensure_gradient(
+        self,
+        problem: &mut Problem<O>,
+        counted: bool,
+    ) -> Result<(Self, bool), Error> {
+        match self.get_param() {
+            Some(param) if self.get_gradient().is_none() => {
+                let gradient = match &problem.problem {
+                    Some(inner) if !counted => inner.gradient(param)?,
+                    _ => problem.gradient(param)?,
+                };
+                Ok((self.gradient(gradient), true))
+            }
+            _ => Ok((self, false)),
+        }
+    }
+
+    fn clear_gradient(&mut self) {
+        self.take_gradient();
+    }
+}
+
+impl<O, P: Vector> StopState<O> for CostState<P> {
+    fn ensure_gradient(
+        self,
+        _problem: &mut Problem<O>,
+        _counted: bool,
+    ) -> Result<(Self, bool), Error> {
+        Ok((self, false))
+    }
+
+    fn clear_gradient(&mut self) {}
+}
+
+impl<O, P: Vector> StopState<O> for SwarmState<P> {
+    fn ensure_gradient(
+        self,
+        _problem: &mut Problem<O>,
+        _counted: bool,
+    ) -> Result<(Self, bool), Error> {
+        Ok((self, false))
+    }
+
+    fn clear_gradient(&mut self) {}
+}
+
+/// A solver which additionally stops on the [`StopCriteria`]
+///
+/// Apart from stopping, the wrapped solver behaves exactly as on its own.
+/// If `gradient_tol` is set and the solver does not keep the gradient in
+/// the state (steepest descent, Newton and Newton-CG), it is evaluated after
+/// each iteration and removed again before the next one. The same is done,
+/// without counting the evaluations, for runs which are
+/// [`observed`](Stopping::observed).
+///
+/// An iteration which leaves the cost function value unchanged may have
+/// started at a stationary point, from which no gradient based solver
+/// moves. The gradient is then evaluated as well, and the run stops if it
+/// is zero, even without `gradient_tol`.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Stopping<S> {
+    solver: S,
+    criteria: StopCriteria,
+    /// Cost function value at the previous check
+    previous_cost: Option<f64>,
+    /// Parameter vector at the previous check, if `step_tol` is set
+    #[serde(default)]
+    step: StepLength,
+    /// Best cost function values at the last `stagnation_iters + 1` checks,
+    /// oldest first
+    best_costs: VecDeque<f64>,
+    /// Whether the gradient in the state has been evaluated by `Stopping`
+    own_gradient: bool,
+    /// Whether observers need the gradient in the state
+    #[serde(default)]
+    observed: bool,
+    /// Whether the last iteration ended at a zero gradient without changing
+    /// the cost function value
+    #[serde(default)]
+    stationary: bool,
+    reason: Option<StopReason>,
+}
+
+impl<S> Stopping<S> {
+    /// Stop `solver` on `criteria`
+    pub fn new(solver: S, criteria: StopCriteria) -> Result<Self, Error> {
+        criteria.validate()?;
+        Ok(Stopping {
+            solver,
+            criteria,
+            previous_cost: None,
+            step: StepLength::default(),
+            best_costs: VecDeque::new(),
+            own_gradient: false,
+            observed: false,
+            stationary: false,
+            reason: None,
+        })
+    }
+
+    /// Provide the gradient to observers such as [`Trace`](crate::trace::Trace)
+    /// even if the solver does not keep it in the state
+    ///
+    /// These evaluations are not counted, so that the counts of a run do not
+    /// depend on whether it is observed.
+    pub fn observed(self) -> Self {
+        Stopping {
+            observed: true,
+            ..self
+        }
+    }
+
+    /// The wrapped solver
+    pub fn solver(&self) -> &S {
+        &self.solver
+    }
+
+    /// The criterion which stopped the run, if it was one of the
+    /// [`StopCriteria`] beyond argmin's own
+    pub fn reason(&self) -> Option<&StopReason> {
+        self.reason.as_ref()
+    }
+
+    /// Evaluate the gradient if it is needed and missing
+    fn prepare<O, I: StopState<O>>(
+        &mut self,
+        problem: &mut Problem<O>,
+        state: I,
+    ) -> Result<I, Error> {
+        let unchanged = self.previous_cost == Some(state.get_cost());
+        let counted = self.criteria.gradient_tol.is_some() || unchanged;
+        if !counted && !self.observed {
+            return Ok(state);
+        }
+        let (state, evaluated) = state.ensure_gradient(problem, counted)?;
+        self.own_gradient = evaluated;
+        self.stationary = unchanged && state.gradient_norm() == Some(0.0);
+        Ok(state)
+    }
+
+    /// The first of the criteria which is met by `state`
+    fn check<I: TraceState>(&mut self, state: &I) -> Option<StopReason> {
+        let criteria = &self.criteria;
+        let cost = state.get_cost();
+        let previous_cost = self.previous_cost.replace(cost);
+        // There is no step before the first iteration
+        let step_length = match (criteria.step_tol, state.trace_param()) {
+            (Some(_), Some(param)) => self.step.update(&param),
+            _ => None,
+        };
+        if let Some(window) = criteria.stagnation_iters {
+            self.best_costs.push_back(state.get_best_cost());
+            // The window spans `window` iterations, i.e. `window + 1` checks
+            if self.best_costs.len() as u64 > window + 1 {
+                self.best_costs.pop_front();
+            }
+        }
+
+        if let (Some(tol), Some(norm)) = (criteria.gradient_tol, state.gradient_norm()) {
+            if norm <= tol {
+                return Some(StopReason::Gradient { norm, tol });
+            }
+        }
+        if self.stationary {
+            let tol = criteria.gradient_tol.unwrap_or(0.0);
+            return Some(StopReason::Gradient { norm: 0.0, tol });
+        }
+        if let Some(previous) = previous_cost {
+            let change = (cost - previous).abs();
+            if let Some(tol) = criteria.cost_tol_abs {
+                if change <= tol {
+                    return Some(StopReason::CostChange { change, tol });
+                }
+            }
+            if let Some(tol) = criteria.cost_tol_rel {
+                let change = change / previous.abs();
+                // `0 / 0` if the cost function value stays at zero
+                let change = if change.is_nan() { 0.0 } else { change };
+                if change <= tol {
+                    return Some(StopReason::RelativeCostChange { change, tol });
+                }
+            }
+        }
+        if let (Some(tol), Some(length)) = (criteria.step_tol, step_length) {
+            if length <= tol {
+                return Some(StopReason::Step { length, tol });
+            }
+        }
+        if let Some(window) = criteria.stagnation_iters {
+            if self.best_costs.len() as u64 == window + 1 {
+                let first = self.best_costs[0];
+                let improvement = first - state.get_best_cost();
+                if improvement <= criteria.stagnation_tol * first.abs() {
+                    return Some(StopReason::Stagnation {
+                        iters: window,
+                        improvement,
+                    });
+                }
+            }
+        }
+        if let (Some(limit), Some(elapsed)) = (criteria.timeout, state.get_time()) {
+            let elapsed = elapsed.as_secs_f64();
+            if elapsed >= limit {
+                return Some(StopReason::Timeout { elapsed, limit });
+            }
+        }
+        if let Some(limit) = criteria.max_evals {
+            let evals = state
+                .get_func_counts()
+                .get("cost_count")
+                .copied()
+                .unwrap_or(0);
+            if evals >= limit {
+                return Some(StopReason::MaxEvals { evals, limit });
+            }
+        }
+        None
+    }
+}
+
+impl<O, S, I> Solver<O, I> for Stopping<S>
+where
+    S: Solver<O, I>,
+    I: StopState<O>,
+{
+    const NAME: &'static str = S::NAME;
+
+    fn init(&mut self, problem: &mut Problem<O>, state: I) -> Result<(I, Option<KV>), Error> {
+        let (state, kv) = self.solver.init(problem, state)?;
+        Ok((self.prepare(problem, state)?, kv))
+    }
+
+    fn next_iter(
+        &mut self,
+        problem: &mut Problem<O>,
+        mut state: I,
+    ) -> Result<(I, Option<KV>), Error> {
+        // The solver must not see a gradient it did not compute itself
+        if self.own_gradient {
+            state.clear_gradient();
+            self.own_gradient = false;
+        }
+        let (state, kv) = self.solver.next_iter(problem, state)?;
+        Ok((self.prepare(problem, state)?, kv))
+    }
+
+    fn terminate_internal(&mut self, state: &I) -> TerminationReason {
+        let reason = self.solver.terminate_internal(state);
+        if reason.terminated() {
+            return reason;
+        }
+        match self.check(state) {
+            Some(reason) => {
+                let termination = reason.termination_reason();
+                self.reason = Some(reason);
+                termination
+            }
+            None => TerminationReason::NotTerminated,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::problem::Rosenbrock;
+    use crate::report::Report;
+    use crate::solver::{solve, Method, SolverOptions};
+    use crate::RunConfig;
+
+    fn solve_with(method: Method, stop: StopCriteria) -> Report {
+        let problem = Rosenbrock::default().into_dyn();
+        let config = RunConfig {
+            init_param: problem.info().default_start.clone(),
+            stop,
+            ..RunConfig::default()
+        };
+        solve(problem, &SolverOptions::new(method), &config).unwrap()
+    }
+
+    #[test]
+    fn invalid_criteria_are_rejected() {
+        let invalid = [
+            StopCriteria { max_iters: 0, ..StopCriteria::default() },
+            StopCriteria { target_cost: Some(f64::NAN), ..StopCriteria::default() },
+            StopCriteria { gradient_tol: Some(-1.0), ..StopCriteria::default() },
+            StopCriteria { step_tol: Some(f64::INFINITY), ..StopCriteria::default() },
+            StopCriteria { stagnation_iters: Some(0), ..StopCriteria::default() },
+            StopCriteria { timeout: Some(0.0), ..StopCriteria::default() },
+            StopCriteria { max_evals: Some(0), ..StopCriteria::default() },
+        ];
+        for criteria in invalid {
+            assert!(criteria.validate().is_err(), "{:?}", criteria);
+        }
+        assert!(StopCriteria::default().validate().is_ok());
+    }
+
+    #[test]
+    fn each_criterion_reports_its_reason() {
+        let cases = [
+            (
+                StopCriteria { gradient_tol: Some(1e-2), ..StopCriteria::default() },
+                "Gradient norm",
+            ),
+            (
+                StopCriteria { cost_tol_abs: Some(1e-3), ..StopCriteria::default() },
+                "Cost change",
+            ),
+            (
+                StopCriteria { cost_tol_rel: Some(0.5), ..StopCriteria::default() },
+                "Relative cost change",
+            ),
+            (
+                StopCriteria { step_tol: Some(1e-2), ..StopCriteria::default() },
+                "Step length",
+            ),
+            (
+                StopCriteria {
+                    stagnation_iters: Some(2),
+                    stagnation_tol: 0.9,
+                    ..StopCriteria::default()
+                },
+                "Best cost improved by only",
+            ),
+            (
+                StopCriteria { timeout: Some(1e-9), ..StopCriteria::default() },
+                "Timeout",
+            ),
+            (
+                StopCriteria { max_evals: Some(10), ..StopCriteria::default() },
+                "Cost function evaluated",
+            ),
+        ];
+        for (criteria, reason) in cases {
+            for method in [Method::SteepestDescent, Method::Lbfgs, Method::NelderMead] {
+                // Nelder-Mead has no gradient, and refuses the tolerances
+                // on cost changes and steps
+                let gradient = criteria.gradient_tol.is_some();
+                if method == Method::NelderMead
+                    && (gradient || criteria.validate_for(method).is_err())
+                {
+                    continue;
+                }
+                let report = solve_with(method, criteria.clone());
+                assert!(
+                    report.termination.starts_with(reason),
+                    "{}: expected `{}`, got `{}`",
+                    method.name(),
+                    reason,
+                    report.termination
+                );
+                assert!(report.iterations < 1000, "{}", method.name());
+            }
+        }
+    }
+
+    #[test]
+    fn derivative_free_methods_refuse_cost_and_step_tolerances() {
+        let problem = Rosenbrock::default().into_dyn();
+        for (criteria, name) in [
+            (StopCriteria { cost_tol_abs: Some(1e-12), ..StopCriteria::default() }, "cost_tol_abs"),
+            (StopCriteria { cost_tol_rel: Some(1e-12), ..StopCriteria::default() }, "cost_tol_rel"),
+            (StopCriteria { step_tol: Some(1e-12), ..StopCriteria::default() }, "step_tol"),
+        ] {
+            assert!(criteria.validate_for(Method::Lbfgs).is_ok());
+            // Nelder-Mead's first iteration from the default start keeps the
+            // best vertex, which these tolerances would take for convergence
+            let config = RunConfig {
+                init_param: problem.info().default_start.clone(),
+                stop: criteria,
+                ..RunConfig::default()
+            };
+            let options = SolverOptions::new(Method::NelderMead);
+            let err = solve(problem.clone(), &options, &config).err().unwrap();
+            let expected = format!("{} is not supported by nelder-mead", name);
+            assert!(err.to_string().contains(&expected), "{}", err);
+        }
+        // The stagnation window is the criterion to use instead
+        let stop = StopCriteria { stagnation_iters: Some(20), ..StopCriteria::default() };
+        let report = solve_with(Method::NelderMead, stop);
+        assert!(report.cost < 1e-6, "cost {}", report.cost);
+    }
+
+    #[test]
+    fn step_tol_waits_for_the_first_step() {
+        let stop = StopCriteria { step_tol: Some(1e9), ..StopCriteria::default() };
+        for method in [Method::SteepestDescent, Method::Newton, Method::Lbfgs] {
+            let report = solve_with(method, stop.clone());
+            assert_eq!(report.iterations, 1, "{}", method.name());
+            assert!(report.termination.starts_with("Step length"), "{}", report.termination);
+        }
+    }
+
+    #[test]
+    fn max_evals_is_checked_between_iterations() {
+        let stop = StopCriteria { max_evals: Some(10), ..StopCriteria::default() };
+        let report = solve_with(Method::Lbfgs, stop);
+        let evals = report.counts["cost_count"];
+        assert!(evals >= 10, "{} evaluations", evals);
+        assert!(
+            report.termination.ends_with(&format!("evaluated {} times, limit 10", evals)),
+            "{}",
+            report.termination
+        );
+        let without_last = solve_with(
+            Method::Lbfgs,
+            StopCriteria { max_iters: report.iterations - 1, ..StopCriteria::default() },
+        );
+        assert!(without_last.counts["cost_count"] < 10);
+    }
+
+    #[test]
+    fn there_is_no_default_target() {
+        // The minimum of easom is -1, below any fixed target such as 0
+        let problem = crate::problem::build("easom", None).unwrap();
+        let config = RunConfig {
+            init_param: vec![3.0, 3.0],
+            ..RunConfig::default()
+        };
+        let report = solve(problem, &SolverOptions::new(Method::Lbfgs), &config).unwrap();
+        assert!(report.iterations > 0);
+        assert!((report.cost + 1.0).abs() < 1e-10, "cost {}", report.cost);
+    }
+
+    #[test]
+    fn exact_stationary_points_stop_the_run() {
+        // Both solvers reach the minimum of the sphere in their first
+        // iteration, and cannot move from it in the second
+        let problem = crate::problem::build("sphere", Some(2)).unwrap();
+        let config = RunConfig {
+            init_param: vec![1.0, -2.0],
+            ..RunConfig::default()
+        };
+        for method in [Method::SteepestDescent, Method::Newton] {
+            let report = solve(problem.clone(), &SolverOptions::new(method), &config).unwrap();
+            assert_eq!(report.cost, 0.0, "{}", method.name());
+            assert_eq!(report.iterations, 2, "{}", method.name());
+            assert_eq!(report.termination, "Gradient norm 0e0 reached tolerance 0e0");
+        }
+    }
+}
@@ -0,0 +1,464 @@
+//! Per-iteration trace of a run
+//!
+//! [`Trace`] is an argmin observer which writes one row per (selected)
+//! iteration to a CSV or JSON Lines file. Each row holds
+//!
+//! * `iter`: the iteration number,
+//! * `cost` and `best_cost`: the current and the best cost function value,
+//! * `gradient_norm`: the Euclidean norm of the current gradient (empty for
+//!   derivative free solvers; solvers which do not keep the gradient, such
+//!   as steepest descent, have it evaluated for the trace without counting
+//!   the evaluations),
+//! * `step_length`: the distance between the current and the previous
+//!   parameter vector (empty in the first row),
+//! * `evals`: the number of cost function evaluations during the iteration,
+//!   i.e. the evaluations of the line search for line search based solvers,
+//! * `time_secs`: the time since the start of the run, and
+//! * `param`: the current parameter vector (`param_0`, `param_1`, ... in
+//!   CSV files).
+//!
+//! Traces are requested through [`RunConfig::trace`](crate::RunConfig).
+//!
+//! [`History`] records the same quantities in memory instead, with the
+//! cumulative numbers of evaluations, for benchmarks and plots which
+//! process them after the run ([`RunConfig::history`](crate::RunConfig)).
+
+use crate::backend::Vector;
+use crate::{CostState, GradientState, SwarmState};
+use argmin::core::observers::Observe;
+use argmin::core::{ArgminError, Error, State, KV};
+use serde::{Deserialize, Serialize};
+use std::fs::{File, OpenOptions};
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+/// File format of a trace
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum TraceFormat {
+    /// Comma separated values with a header line
+    Csv,
+    /// One JSON object per line
+    Jsonl,
+}
+
+impl TraceFormat {
+    /// Format implied by the extension of `path`: JSON Lines for `.jsonl`
+    /// and `.json`, CSV otherwise
+    pub fn from_path(path: &Path) -> TraceFormat {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("jsonl") | Some("json") => TraceFormat::Jsonl,
+            _ => TraceFormat::Csv,
+        }
+    }
+}
+
+/// Iterations which are written to a trace
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum TraceFrequency {
+    /// Every `n`-th iteration
+    Every(u64),
+    /// Only iterations which found a new best parameter vector
+    NewBest,
+}
+
+/// Where and how often a trace is written
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct TraceOptions {
+    /// File the trace is written to; it is overwritten if it exists
+    pub path: PathBuf,
+    /// File format
+    pub format: TraceFormat,
+    /// Iterations which are written
+    pub frequency: TraceFrequency,
+    /// Append to an existing file instead of overwriting it, as done when a
+    /// run is resumed from a checkpoint
+    #[serde(default)]
+    pub append: bool,
+}
+
+impl TraceOptions {
+    /// Trace every iteration to `path`, in the format implied by its extension
+    pub fn new<P: Into<PathBuf>>(path: P) -> Self {
+        let path = path.into();
+        TraceOptions {
+            format: TraceFormat::from_path(&path),
+            path,
+            frequency: TraceFrequency::Every(1),
+            append: false,
+        }
+    }
+}
+
+/// States which can be traced
+///
+/// The argmin `State` trait does not give access to the gradient, and the
+/// parameter of a `PopulationState` is a whole particle, so the few
+/// quantities a trace needs beyond `State` are provided by this trait. Step
+/// lengths are tracked by [`StepLength`] instead.
+pub trait TraceState: State<Float = f64> {
+    /// Current parameter vector
+    fn trace_param(&self) -> Option<Vec<f64>>;
+    /// Euclidean norm of the current gradient
+    fn gradient_norm(&self) -> Option<f64>;
+}
+
+fn distance(a: &[f64], b: &[f64]) -> f64 {
+    a.iter().zip(b).map(|(x, y)| (x - y).powi(2)).sum::<f64>().sqrt()
+}
+
+/// Length of the steps between the parameter vectors of consecutive
+/// iterations
+///
+/// argmin's solvers take the parameter vector out of the state before they
+/// store the new one, so the state rarely knows the previous one; observers
+/// remember it themselves.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct StepLength {
+    previous: Option<Vec<f64>>,
+}
+
+impl StepLength {
+    /// Record `param` and return its distance to the previously recorded
+    /// parameter vector
+    pub fn update(&mut self, param: &[f64]) -> Option<f64> {
+        let length = self.previous.as_deref().map(|previous| distance(previous, param));
+        self.previous = Some(param.to_vec());
+        length
+    }
+}
+
+impl<H, P: Vector> TraceState for GradientState<H, P> {
+    fn trace_param(&self) -> Option<Vec<f64>> {
+        self.get_param().map(P::to_vec)
+    }
+
+    fn gradient_norm(&self) -> Option<f64> {
+        let g = self.get_gradient()?.to_vec();
+        Some(g.iter().map(|x| x * x).sum::<f64>().sqrt())
+    }
+}
+
+impl<P: Vector> TraceState for CostState<P> {
+    fn trace_param(&self) -> Option<Vec<f64>> {
+        self.get_param().map(P::to_vec)
+    }
+
+    fn gradient_norm(&self) -> Option<f64> {
+        None
+    }
+}
+
+impl<P: Vector> TraceState for SwarmState<P> {
+    fn trace_param(&self) -> Option<Vec<f64>> {
+        self.get_param().map(|particle| particle.position.to_vec())
+    }
+
+    fn gradient_norm(&self) -> Option<f64> {
+        None
+    }
+}
+
+/// One row of a trace
+#[derive(Serialize)]
+struct Row<'a> {
+    iter: u64,
+    cost: f64,
+    best_cost: f64,
+    gradient_norm: Option<f64>,
+    step_length: Option<f64>,
+    evals: u64,
+    time_secs: f64,
+    param: &'a [f64],
+}
+
+/// Observer writing a trace, see the [module documentation](self)
+pub struct Trace {
+    writer: BufWriter<File>,
+    format: TraceFormat,
+    frequency: TraceFrequency,
+    start: Instant,
+    /// Cost function evaluations up to the previous iteration
+    prev_evals: u64,
+    step: StepLength,
+    header_written: bool,
+}
+
+impl Trace {
+    /// Create (or truncate, or open for appending) the trace file described
+    /// by `options`
+    pub fn create(options: &TraceOptions) -> Result<Self, Error> {
+        if options.frequency == TraceFrequency::Every(0) {
+            return Err(ArgminError::InvalidParameter {
+                text: "trace: frequency must be at least 1".to_string(),
+            }
+            .into());
+        }
+        let file = OpenOptions::new()
+            .create(true)
+            .write(true)
+            .append(options.append)
+            .truncate(!options.append)
+            .open(&options.path)?;
+        // A CSV header is only needed at the start of the file
+        let header_written = file.metadata()?.len() > 0;
+        Ok(Trace {
+            writer: BufWriter::new(file),
+            format: options.format,
+            frequency: options.frequency,
+            start: Instant::now(),
+            prev_evals: 0,
+            step: StepLength::default(),
+            header_written,
+        })
+    }
+
+    fn write_row(&mut self, row: &Row) -> Result<(), Error> {
+        match self.format {
+            TraceFormat::Jsonl => {
+                serde_json::to_writer(&mut self.writer, row)?;
+                writeln!(self.writer)?;
+            }
+            TraceFormat::Csv => {
+                if !self.header_written {
+                    write!(
+                        self.writer,
+                        "iter,cost,best_cost,gradient_norm,step_length,evals,time_secs"
+                    )?;
+                    for i in 0..row.param.len() {
+                        write!(self.writer, ",param_{}", i)?;
+                    }
+                    writeln!(self.writer)?;
+                    self.header_written = true;
+                }
+                let optional = |x: Option<f64>| x.map(|x| x.to_string()).unwrap_or_default();
+                write!(
+                    self.writer,
+                    "{},{},{},{},{},{},{}",
+                    row.iter,
+                    row.cost,
+                    row.best_cost,
+                    optional(row.gradient_norm),
+                    optional(row.step_length),
+                    row.evals,
+                    row.time_secs
+                )?;
+                for x in row.param {
+                    write!(self.writer, ",{}", x)?;
+                }
+                writeln!(self.writer)?;
+            }
+        }
+        // Flush every row, so that the trace of an interrupted run is complete
+        self.writer.flush()?;
+        Ok(())
+    }
+}
+
+impl<I: TraceState> Observe<I> for Trace {
+    fn observe_init(&mut self, _name: &str, _kv: &KV) -> Result<(), Error> {
+        self.start = Instant::now();
+        Ok(())
+    }
+
+    fn observe_iter(&mut self, state: &I, _kv: &KV) -> Result<(), Error> {
+        // The observer sees every iteration so that `evals` covers exactly
+        // one iteration; rows are filtered here instead of via `ObserverMode`.
+        let evals_total = state.get_func_counts().get("cost_count").copied().unwrap_or(0);
+        let evals = evals_total.saturating_sub(self.prev_evals);
+        self.prev_evals = evals_total;
+        let param = state.trace_param().unwrap_or_default();
+        let step_length = self.step.update(&param);
+
+        let iter = state.get_iter();
+        let selected = match self.frequency {
+            TraceFrequency::Every(n) => iter.is_multiple_of(n),
+            TraceFrequency::NewBest => state.get_last_best_iter() == iter,
+        };
+        if !selected {
+            return Ok(());
+        }
+        self.write_row(&Row {
+            iter,
+            cost: state.get_cost(),
+            best_cost: state.get_best_cost(),
+            gradient_norm: state.gradient_norm(),
+            step_length,
+            evals,
+            time_secs: self.start.elapsed().as_secs_f64(),
+            param: &param,
+        })
+    }
+}
+
+/// One iteration recorded by [`History`]
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct TracePoint {
+    /// Iteration number
+    pub iter: u64,
+    /// Current cost function value
+    pub cost: f64,
+    /// Best cost function value so far
+    pub best_cost: f64,
+    /// Euclidean norm of the current gradient
+    pub gradient_norm: Option<f64>,
+    /// Distance between the current and the previous parameter vector
+    pub step_length: Option<f64>,
+    /// Cost function evaluations since the start of the run
+    pub cost_evals: u64,
+    /// Gradient evaluations since the start of the run
+    pub gradient_evals: u64,
+    /// Hessian evaluations since the start of the run
+    pub hessian_evals: u64,
+    /// Time since the start of the run
+    pub time_secs: f64,
+    /// Current parameter vector
+    pub param: Vec<f64>,
+}
+
+/// Observer recording every iteration in memory
+///
+/// Clones share the recorded points, so a clone can be handed to the
+/// executor and the points read from the original after the run.
+#[derive(Clone, Debug)]
+pub struct History {
+    points: Arc<Mutex<Vec<TracePoint>>>,
+    start: Instant,
+    step: StepLength,
+}
+
+impl Default for History {
+    fn default() -> Self {
+        History::new()
+    }
+}
+
+impl History {
+    /// Empty history
+    pub fn new() -> Self {
+        History {
+            points: Arc::new(Mutex::new(Vec::new())),
+            start: Instant::now(),
+            step: StepLength::default(),
+        }
+    }
+
+    /// The points recorded so far
+    pub fn points(&self) -> Vec<TracePoint> {
+        // A poisoned lock only means that a run panicked while recording
+        match self.points.lock() {
+            Ok(points) => points.clone(),
+            Err(poisoned) => poisoned.into_inner().clone(),
+        }
+    }
+}
+
+impl<I: TraceState> Observe<I> for History {
+    fn observe_init(&mut self, _name: &str, _kv: &KV) -> Result<(), Error> {
+        self.start = Instant::now();
+        Ok(())
+    }
+
+    fn observe_iter(&mut self, state: &I, _kv: &KV) -> Result<(), Error> {
+        let counts = state.get_func_counts();
+        let count = |counter: &str| counts.get(counter).copied().unwrap_or(0);
+        let param = state.trace_param().unwrap_or_default();
+        let point = TracePoint {
+            iter: state.get_iter(),
+            cost: state.get_cost(),
+            best_cost: state.get_best_cost(),
+            gradient_norm: state.gradient_norm(),
+            step_length: self.step.update(&param),
+            cost_evals: count("cost_count"),
+            gradient_evals: count("gradient_count"),
+            hessian_evals: count("hessian_count"),
+            time_secs: self.start.elapsed().as_secs_f64(),
+            param,
+        };
+        self.points
+            .lock()
+            .map_err(|_| ArgminError::PotentialBug {
+                text: "history: lock poisoned".to_string(),
+            })?
+            .push(point);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::problem::Rosenbrock;
+    use crate::solver::{solve, Method, SolverOptions};
+    use crate::{RunConfig, StopCriteria};
+
+    fn stop_after(max_iters: u64) -> RunConfig {
+        RunConfig {
+            stop: StopCriteria {
+                max_iters,
+                ..StopCriteria::default()
+            },
+            ..RunConfig::default()
+        }
+    }
+
+    #[test]
+    fn step_length_is_the_distance_to_the_previous_param() {
+        let mut step = StepLength::default();
+        assert_eq!(step.update(&[0.0, 0.0]), None);
+        assert_eq!(step.update(&[3.0, 4.0]), Some(5.0));
+        assert_eq!(step.update(&[3.0, 4.0]), Some(0.0));
+    }
+
+    #[test]
+    fn history_records_gradient_norm_and_step_length() {
+        let history = History::new();
+        let config = RunConfig {
+            history: Some(history.clone()),
+            ..stop_after(5)
+        };
+        let options = SolverOptions::new(Method::SteepestDescent);
+        let observed = solve(Rosenbrock::default().into_dyn(), &options, &config).unwrap();
+        let points = history.points();
+        assert_eq!(points.len(), 5);
+        assert!(points.iter().all(|p| p.gradient_norm.is_some()));
+        assert!(points[1..].iter().all(|p| p.step_length.is_some()));
+
+        // The gradients evaluated for the history are not counted
+        let plain = solve(Rosenbrock::default().into_dyn(), &options, &stop_after(5)).unwrap();
+        assert_eq!(observed.counts, plain.counts);
+    }
+
+    #[test]
+    fn trace_writes_one_row_per_selected_iteration() {
+        let path = std::env::temp_dir().join(format!("opt-trace-{}.csv", std::process::id()));
+        let config = RunConfig {
+            trace: Some(TraceOptions {
+                frequency: TraceFrequency::Every(2),
+                ..TraceOptions::new(&path)
+            }),
+            ..stop_after(6)
+        };
+        let options = SolverOptions::new(Method::Lbfgs);
+        solve(Rosenbrock::default().into_dyn(), &options, &config).unwrap();
+        let csv = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        let mut lines = csv.lines();
+        assert_eq!(
+            lines.next(),
+            Some("iter,cost,best_cost,gradient_norm,step_length,evals,time_secs,param_0,param_1")
+        );
+        let rows: Vec<Vec<&str>> = lines.map(|line| line.split(',').collect()).collect();
+        let iters: Vec<&str> = rows.iter().map(|row| row[0]).collect();
+        assert_eq!(iters, ["0", "2", "4"]);
+        for row in &rows {
+            assert_eq!(row.len(), 9);
+            assert!(row[3].parse::<f64>().is_ok(), "gradient_norm in {:?}", row);
+        }
+        assert!(rows[1..].iter().all(|row| row[4].parse::<f64>().is_ok()));
+    }
+}